@@ -0,0 +1,435 @@
+//! OAuth2 credentials for the Vertex AI backend.
+//!
+//! Vertex AI authenticates with short-lived bearer tokens instead of an API
+//! key. [`Credentials`] obtains those tokens from one of the supported
+//! sources and caches them until shortly before they expire:
+//!
+//! - a service-account key file, exchanged through a signed JWT
+//! - `gcloud auth application-default login` user credentials
+//! - the GCE / Cloud Run / GKE metadata server
+//! - a fixed token, or any custom [`TokenProvider`]
+//!
+//! [`Credentials::application_default`] discovers the right source the same
+//! way the official Google client libraries do.
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! use gemini_rs::auth::Credentials;
+//! use gemini_rs::{Client, Model};
+//!
+//! # fn example() -> Result<(), gemini_rs::Error> {
+//! let credentials = Credentials::service_account_file("service-account.json")?;
+//! let client = Client::vertex_ai("my-project", "us-central1", credentials);
+//! let model = client.model(Model::Gemini25Flash);
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::clock::{Clock, SystemClock};
+use crate::error::{Error, Result, REDACTED};
+use futures::future::BoxFuture;
+use reqwest::Client as HttpClient;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tokio::sync::Mutex;
+
+/// OAuth2 scope required by Vertex AI.
+pub const CLOUD_PLATFORM_SCOPE: &str = "https://www.googleapis.com/auth/cloud-platform";
+
+const DEFAULT_TOKEN_URI: &str = "https://oauth2.googleapis.com/token";
+const METADATA_TOKEN_URL: &str =
+    "http://metadata.google.internal/computeMetadata/v1/instance/service-accounts/default/token";
+
+/// Tokens are refreshed this long before they expire, or after three
+/// quarters of their lifetime for tokens that live less than four times as
+/// long.
+const REFRESH_MARGIN: Duration = Duration::from_secs(60);
+
+/// A bearer token and the moment it stops being valid.
+#[derive(Clone)]
+pub struct AccessToken {
+    /// The bearer token value.
+    pub token: String,
+    /// When the token expires.
+    pub expires_at: Instant,
+}
+
+impl AccessToken {
+    /// Create a token that expires after `expires_in`.
+    pub fn new(token: impl Into<String>, expires_in: Duration) -> Self {
+        Self {
+            token: token.into(),
+            expires_at: Instant::now() + expires_in,
+        }
+    }
+}
+
+/// A cached token and the moment, on the credentials' clock, to refresh it.
+struct CachedToken {
+    token: String,
+    refresh_at: Instant,
+}
+
+impl CachedToken {
+    fn new(token: AccessToken, now: Instant) -> Self {
+        let lifetime = token.expires_at.saturating_duration_since(Instant::now());
+        let margin = REFRESH_MARGIN.min(lifetime / 4);
+        Self {
+            token: token.token,
+            refresh_at: now + (lifetime - margin),
+        }
+    }
+}
+
+impl fmt::Debug for AccessToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AccessToken")
+            .field("token", &REDACTED)
+            .field("expires_at", &self.expires_at)
+            .finish()
+    }
+}
+
+/// A source of OAuth2 access tokens.
+///
+/// Implement this to plug in a custom token source (workload identity
+/// federation, a secrets sidecar, ...). Caching is handled by
+/// [`Credentials`], so implementations should always fetch a new token.
+pub trait TokenProvider: Send + Sync {
+    /// Fetch a new access token.
+    fn fetch_token<'a>(&'a self, http: &'a HttpClient) -> BoxFuture<'a, Result<AccessToken>>;
+}
+
+/// Cached OAuth2 credentials, shared across clones.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use gemini_rs::auth::Credentials;
+///
+/// # fn example() -> Result<(), gemini_rs::Error> {
+/// // Discover credentials like the gcloud SDK does
+/// let credentials = Credentials::application_default()?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct Credentials {
+    provider: Arc<dyn TokenProvider>,
+    cache: Arc<Mutex<Option<CachedToken>>>,
+    clock: Arc<dyn Clock>,
+}
+
+impl Credentials {
+    /// Use a custom token provider.
+    pub fn from_provider(provider: impl TokenProvider + 'static) -> Self {
+        Self {
+            provider: Arc::new(provider),
+            cache: Arc::new(Mutex::new(None)),
+            clock: Arc::new(SystemClock),
+        }
+    }
+
+    /// Read the time from `clock` when deciding whether to refresh the token.
+    ///
+    /// See the [`clock`](crate::clock) module.
+    pub fn with_clock(mut self, clock: impl Clock + 'static) -> Self {
+        self.clock = Arc::new(clock);
+        self
+    }
+
+    /// Use a fixed access token, for example one from `gcloud auth print-access-token`.
+    ///
+    /// The token is never refreshed.
+    pub fn from_token(token: impl Into<String>) -> Self {
+        Self::from_provider(StaticToken(token.into()))
+    }
+
+    /// Load a credentials JSON file (service account or authorized user).
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+        Self::from_json(&read_credentials_file(path.as_ref())?)
+    }
+
+    /// Parse credentials JSON (service account or authorized user).
+    pub fn from_json(json: &str) -> Result<Self> {
+        let file: CredentialsFile = serde_json::from_str(json)
+            .map_err(|e| Error::AuthError(format!("invalid credentials JSON: {}", e)))?;
+        match file {
+            CredentialsFile::ServiceAccount(key) => Ok(Self::from_provider(key)),
+            CredentialsFile::AuthorizedUser(user) => Ok(Self::from_provider(user)),
+        }
+    }
+
+    /// Load a service-account key file.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::AuthError`] if the file cannot be read or is not a
+    /// service-account key.
+    pub fn service_account_file(path: impl AsRef<Path>) -> Result<Self> {
+        let json = read_credentials_file(path.as_ref())?;
+        let key: ServiceAccountKey = serde_json::from_str(&json)
+            .map_err(|e| Error::AuthError(format!("invalid service account key: {}", e)))?;
+        Ok(Self::from_provider(key))
+    }
+
+    /// Use credentials from the GCE / Cloud Run / GKE metadata server.
+    pub fn metadata_server() -> Self {
+        Self::from_provider(MetadataServer {
+            url: METADATA_TOKEN_URL.to_string(),
+        })
+    }
+
+    /// Discover Application Default Credentials.
+    ///
+    /// Looks, in order, at:
+    /// 1. the file named by `GOOGLE_APPLICATION_CREDENTIALS`
+    /// 2. the gcloud well-known file (`application_default_credentials.json`
+    ///    under `CLOUDSDK_CONFIG`, `~/.config/gcloud` or `%APPDATA%\gcloud`)
+    /// 3. the metadata server, when running on Google Cloud
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::AuthError`] if `GOOGLE_APPLICATION_CREDENTIALS` is set
+    /// but cannot be loaded. Metadata server failures surface on first use.
+    pub fn application_default() -> Result<Self> {
+        if let Ok(path) = std::env::var("GOOGLE_APPLICATION_CREDENTIALS") {
+            return Self::from_file(path);
+        }
+        if let Some(path) = well_known_file().filter(|path| path.exists()) {
+            return Self::from_file(path);
+        }
+        Ok(Self::metadata_server())
+    }
+
+    /// Get a valid access token, fetching a new one if the cached token is
+    /// missing or about to expire.
+    pub async fn token(&self, http: &HttpClient) -> Result<String> {
+        let mut cache = self.cache.lock().await;
+        let now = self.clock.now();
+        if let Some(token) = cache.as_ref().filter(|token| now < token.refresh_at) {
+            return Ok(token.token.clone());
+        }
+        let token = CachedToken::new(self.provider.fetch_token(http).await?, now);
+        let value = token.token.clone();
+        *cache = Some(token);
+        Ok(value)
+    }
+}
+
+impl fmt::Debug for Credentials {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Credentials").finish_non_exhaustive()
+    }
+}
+
+fn read_credentials_file(path: &Path) -> Result<String> {
+    std::fs::read_to_string(path).map_err(|e| {
+        Error::AuthError(format!(
+            "failed to read credentials file {}: {}",
+            path.display(),
+            e
+        ))
+    })
+}
+
+fn well_known_file() -> Option<PathBuf> {
+    let dir = match std::env::var_os("CLOUDSDK_CONFIG") {
+        Some(dir) => PathBuf::from(dir),
+        None if cfg!(windows) => PathBuf::from(std::env::var_os("APPDATA")?).join("gcloud"),
+        None => PathBuf::from(std::env::var_os("HOME")?)
+            .join(".config")
+            .join("gcloud"),
+    };
+    Some(dir.join("application_default_credentials.json"))
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum CredentialsFile {
+    ServiceAccount(ServiceAccountKey),
+    AuthorizedUser(AuthorizedUser),
+}
+
+struct StaticToken(String);
+
+impl TokenProvider for StaticToken {
+    fn fetch_token<'a>(&'a self, _http: &'a HttpClient) -> BoxFuture<'a, Result<AccessToken>> {
+        // Far enough in the future to never trigger a refresh
+        let token = AccessToken::new(self.0.clone(), Duration::from_secs(100 * 365 * 24 * 3600));
+        Box::pin(async move { Ok(token) })
+    }
+}
+
+/// A service-account key, as downloaded from the Cloud console.
+#[derive(Clone, Deserialize)]
+pub struct ServiceAccountKey {
+    /// The service account's email address.
+    pub client_email: String,
+    /// PEM-encoded RSA private key.
+    pub private_key: String,
+    /// Identifier of the private key.
+    #[serde(default)]
+    pub private_key_id: Option<String>,
+    /// OAuth2 token endpoint.
+    #[serde(default = "default_token_uri")]
+    pub token_uri: String,
+}
+
+impl fmt::Debug for ServiceAccountKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ServiceAccountKey")
+            .field("client_email", &self.client_email)
+            .field("private_key", &REDACTED)
+            .field("private_key_id", &self.private_key_id)
+            .field("token_uri", &self.token_uri)
+            .finish()
+    }
+}
+
+#[derive(Serialize)]
+struct JwtClaims<'a> {
+    iss: &'a str,
+    scope: &'a str,
+    aud: &'a str,
+    iat: u64,
+    exp: u64,
+}
+
+impl ServiceAccountKey {
+    fn signed_assertion(&self) -> Result<String> {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let claims = JwtClaims {
+            iss: &self.client_email,
+            scope: CLOUD_PLATFORM_SCOPE,
+            aud: &self.token_uri,
+            iat: now,
+            exp: now + 3600,
+        };
+        let mut header = jsonwebtoken::Header::new(jsonwebtoken::Algorithm::RS256);
+        header.kid = self.private_key_id.clone();
+        let key = jsonwebtoken::EncodingKey::from_rsa_pem(self.private_key.as_bytes())
+            .map_err(|e| Error::AuthError(format!("invalid service account key: {}", e)))?;
+        jsonwebtoken::encode(&header, &claims, &key)
+            .map_err(|e| Error::AuthError(format!("failed to sign JWT: {}", e)))
+    }
+}
+
+impl TokenProvider for ServiceAccountKey {
+    fn fetch_token<'a>(&'a self, http: &'a HttpClient) -> BoxFuture<'a, Result<AccessToken>> {
+        Box::pin(async move {
+            let assertion = self.signed_assertion()?;
+            let request = http.post(&self.token_uri).form(&[
+                ("grant_type", "urn:ietf:params:oauth:grant-type:jwt-bearer"),
+                ("assertion", assertion.as_str()),
+            ]);
+            exchange_token(request).await
+        })
+    }
+}
+
+/// User credentials created by `gcloud auth application-default login`.
+#[derive(Clone, Deserialize)]
+pub struct AuthorizedUser {
+    /// OAuth2 client id.
+    pub client_id: String,
+    /// OAuth2 client secret.
+    pub client_secret: String,
+    /// Long-lived refresh token.
+    pub refresh_token: String,
+    /// OAuth2 token endpoint.
+    #[serde(default = "default_token_uri")]
+    pub token_uri: String,
+}
+
+impl fmt::Debug for AuthorizedUser {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AuthorizedUser")
+            .field("client_id", &self.client_id)
+            .field("client_secret", &REDACTED)
+            .field("refresh_token", &REDACTED)
+            .field("token_uri", &self.token_uri)
+            .finish()
+    }
+}
+
+impl TokenProvider for AuthorizedUser {
+    fn fetch_token<'a>(&'a self, http: &'a HttpClient) -> BoxFuture<'a, Result<AccessToken>> {
+        Box::pin(async move {
+            let request = http.post(&self.token_uri).form(&[
+                ("grant_type", "refresh_token"),
+                ("client_id", self.client_id.as_str()),
+                ("client_secret", self.client_secret.as_str()),
+                ("refresh_token", self.refresh_token.as_str()),
+            ]);
+            exchange_token(request).await
+        })
+    }
+}
+
+struct MetadataServer {
+    url: String,
+}
+
+impl TokenProvider for MetadataServer {
+    fn fetch_token<'a>(&'a self, http: &'a HttpClient) -> BoxFuture<'a, Result<AccessToken>> {
+        Box::pin(async move {
+            let request = http.get(&self.url).header("Metadata-Flavor", "Google");
+            exchange_token(request).await.map_err(|e| {
+                Error::AuthError(format!(
+                    "no Application Default Credentials found and the metadata server \
+                     is unavailable ({}); set GOOGLE_APPLICATION_CREDENTIALS or run \
+                     `gcloud auth application-default login`",
+                    e
+                ))
+            })
+        })
+    }
+}
+
+fn default_token_uri() -> String {
+    DEFAULT_TOKEN_URI.to_string()
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    #[serde(default = "default_expires_in")]
+    expires_in: u64,
+}
+
+fn default_expires_in() -> u64 {
+    3600
+}
+
+async fn exchange_token(request: reqwest::RequestBuilder) -> Result<AccessToken> {
+    let response = request
+        .send()
+        .await
+        .map_err(|e| Error::AuthError(format!("token request failed: {}", e.without_url())))?;
+
+    let status = response.status();
+    if !status.is_success() {
+        let body = response.text().await.unwrap_or_default();
+        return Err(Error::AuthError(format!(
+            "token endpoint returned HTTP {}: {}",
+            status, body
+        )));
+    }
+
+    let token: TokenResponse = response
+        .json()
+        .await
+        .map_err(|e| Error::AuthError(format!("invalid token response: {}", e.without_url())))?;
+    Ok(AccessToken::new(
+        token.access_token,
+        Duration::from_secs(token.expires_in),
+    ))
+}
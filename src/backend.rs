@@ -0,0 +1,291 @@
+//! API backends: the Gemini Developer API (AI Studio) and Vertex AI.
+//!
+//! A [`Backend`] knows where requests go and how they are authenticated:
+//!
+//! | Backend | Endpoint | Auth |
+//! |---------|----------|------|
+//! | Gemini Developer API | `generativelanguage.googleapis.com/v1beta/models/{m}:{method}` | API key |
+//! | Vertex AI | `{location}-aiplatform.googleapis.com/v1/projects/{p}/locations/{l}/publishers/google/models/{m}:{method}` | OAuth2 bearer token |
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! use gemini_rs::auth::Credentials;
+//! use gemini_rs::backend::Backend;
+//! use gemini_rs::{Client, Model};
+//!
+//! # fn example() -> Result<(), gemini_rs::Error> {
+//! let backend = Backend::vertex_ai("my-project", "europe-west4", Credentials::application_default()?);
+//! let client = Client::with_backend(backend);
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::auth::Credentials;
+use crate::error::{Error, Result, REDACTED};
+use crate::models::Model;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, AUTHORIZATION};
+use reqwest::Client as HttpClient;
+use std::fmt;
+
+/// Header carrying the API key for the Gemini Developer API.
+pub(crate) const API_KEY_HEADER: &str = "x-goog-api-key";
+
+/// Base URL of the Gemini Developer API.
+pub const GOOGLE_AI_BASE_URL: &str = "https://generativelanguage.googleapis.com/v1beta";
+
+/// Version of the REST API to call.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ApiVersion {
+    /// The stable API (`v1`).
+    V1,
+    /// The beta API (`v1beta`, or `v1beta1` on Vertex AI), which exposes the
+    /// newest features. This is the default.
+    #[default]
+    V1Beta,
+}
+
+impl ApiVersion {
+    fn path(&self, vertex_ai: bool) -> &'static str {
+        match (self, vertex_ai) {
+            (ApiVersion::V1, _) => "v1",
+            (ApiVersion::V1Beta, false) => "v1beta",
+            (ApiVersion::V1Beta, true) => "v1beta1",
+        }
+    }
+}
+
+/// The API a [`Client`](crate::Client) talks to, and its credentials.
+///
+/// The `Debug` output never contains the API key.
+#[derive(Clone)]
+pub struct Backend {
+    kind: BackendKind,
+    base_url: String,
+}
+
+#[derive(Clone)]
+enum BackendKind {
+    GoogleAi {
+        api_key: String,
+    },
+    VertexAi {
+        project: String,
+        location: String,
+        credentials: Credentials,
+    },
+}
+
+impl Backend {
+    /// The Gemini Developer API, authenticated with an API key.
+    pub fn google_ai(api_key: impl Into<String>) -> Self {
+        Self {
+            kind: BackendKind::GoogleAi {
+                api_key: api_key.into(),
+            },
+            base_url: GOOGLE_AI_BASE_URL.to_string(),
+        }
+    }
+
+    /// Vertex AI in the given project and location, authenticated with
+    /// OAuth2 credentials.
+    ///
+    /// Use `"global"` as the location for the global endpoint.
+    pub fn vertex_ai(
+        project: impl Into<String>,
+        location: impl Into<String>,
+        credentials: Credentials,
+    ) -> Self {
+        let location = location.into();
+        let base_url = format!("{}/v1", vertex_host(&location));
+        Self {
+            kind: BackendKind::VertexAi {
+                project: project.into(),
+                location,
+                credentials,
+            },
+            base_url,
+        }
+    }
+
+    /// Override the base URL (including the API version), for example to
+    /// use a private endpoint or a local mock server.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use gemini_rs::backend::Backend;
+    /// use gemini_rs::Model;
+    ///
+    /// let backend = Backend::google_ai("YOUR_API_KEY").with_base_url("http://localhost:8080/v1beta/");
+    /// assert_eq!(
+    ///     backend.model_url(Model::Gemini25Flash, "generateContent"),
+    ///     "http://localhost:8080/v1beta/models/gemini-2.5-flash:generateContent"
+    /// );
+    /// ```
+    pub fn with_base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = base_url.into().trim_end_matches('/').to_string();
+        self
+    }
+
+    /// Select the API version, resetting the base URL to the backend's
+    /// default host for that version.
+    ///
+    /// Vertex AI defaults to `v1` and the Gemini Developer API to `v1beta`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use gemini_rs::backend::{ApiVersion, Backend};
+    ///
+    /// let backend = Backend::google_ai("YOUR_API_KEY").with_api_version(ApiVersion::V1);
+    /// assert_eq!(backend.base_url(), "https://generativelanguage.googleapis.com/v1");
+    /// ```
+    pub fn with_api_version(mut self, version: ApiVersion) -> Self {
+        let host = match &self.kind {
+            BackendKind::GoogleAi { .. } => "https://generativelanguage.googleapis.com".to_string(),
+            BackendKind::VertexAi { location, .. } => vertex_host(location),
+        };
+        self.base_url = format!("{}/{}", host, version.path(self.is_vertex_ai()));
+        self
+    }
+
+    /// Get the base URL requests are sent to.
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    /// Whether this is the Vertex AI backend.
+    pub fn is_vertex_ai(&self) -> bool {
+        matches!(self.kind, BackendKind::VertexAi { .. })
+    }
+
+    /// Build the URL of a model method such as `generateContent`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use gemini_rs::auth::Credentials;
+    /// use gemini_rs::backend::Backend;
+    /// use gemini_rs::Model;
+    ///
+    /// let backend = Backend::vertex_ai("my-project", "us-central1", Credentials::from_token("t"));
+    /// assert_eq!(
+    ///     backend.model_url(Model::Gemini25Flash, "generateContent"),
+    ///     "https://us-central1-aiplatform.googleapis.com/v1/projects/my-project/\
+    ///      locations/us-central1/publishers/google/models/gemini-2.5-flash:generateContent"
+    /// );
+    /// ```
+    pub fn model_url(&self, model: Model, method: &str) -> String {
+        match &self.kind {
+            BackendKind::GoogleAi { .. } => {
+                format!("{}/models/{}:{}", self.base_url, model.as_str(), method)
+            }
+            BackendKind::VertexAi {
+                project, location, ..
+            } => format!(
+                "{}/projects/{}/locations/{}/publishers/google/models/{}:{}",
+                self.base_url,
+                project,
+                location,
+                model.as_str(),
+                method
+            ),
+        }
+    }
+
+    /// Build the URL of a Gemini Developer API resource such as
+    /// `batches/123`. Vertex AI has no such resources and returns a
+    /// [`Error::ConfigError`].
+    pub(crate) fn resource_url(&self, path: &str) -> Result<String> {
+        match self.kind {
+            BackendKind::GoogleAi { .. } => Ok(format!("{}/{}", self.base_url, path)),
+            BackendKind::VertexAi { .. } => Err(Error::ConfigError(format!(
+                "{} requires the Gemini Developer API backend",
+                path.split(['/', ':']).next().unwrap_or(path)
+            ))),
+        }
+    }
+
+    /// Build the URL of a resource on the `upload` or `download` endpoint,
+    /// e.g. `https://host/upload/v1beta/files` for a base URL of
+    /// `https://host/v1beta`.
+    pub(crate) fn media_url(&self, endpoint: &str, path: &str) -> Result<String> {
+        let url = self.resource_url(path)?;
+        let host_end = url
+            .find("://")
+            .and_then(|scheme| url[scheme + 3..].find('/').map(|slash| scheme + 3 + slash))
+            .unwrap_or(url.len());
+        Ok(format!(
+            "{}/{}{}",
+            &url[..host_end],
+            endpoint,
+            &url[host_end..]
+        ))
+    }
+
+    /// Add this backend's credentials to request headers.
+    ///
+    /// `http` is used to fetch Vertex AI access tokens.
+    pub(crate) async fn authorize(&self, headers: &mut HeaderMap, http: &HttpClient) -> Result<()> {
+        let (name, mut value) = match &self.kind {
+            BackendKind::GoogleAi { api_key } => (
+                HeaderName::from_static(API_KEY_HEADER),
+                HeaderValue::from_str(api_key).map_err(|_| Error::InvalidApiKey)?,
+            ),
+            BackendKind::VertexAi { credentials, .. } => {
+                let token = credentials.token(http).await?;
+                let value = HeaderValue::from_str(&format!("Bearer {}", token))
+                    .map_err(|_| Error::AuthError("access token is not a valid header".into()))?;
+                (AUTHORIZATION, value)
+            }
+        };
+        value.set_sensitive(true);
+        headers.insert(name, value);
+        Ok(())
+    }
+
+    /// Replace every occurrence of this backend's API key in `text`.
+    ///
+    /// Used on error bodies before they are surfaced, in case the server
+    /// echoes the request back.
+    pub(crate) fn redact(&self, text: &str) -> String {
+        match &self.kind {
+            BackendKind::GoogleAi { api_key } if !api_key.is_empty() => {
+                text.replace(api_key.as_str(), REDACTED)
+            }
+            _ => text.to_string(),
+        }
+    }
+}
+
+fn vertex_host(location: &str) -> String {
+    if location == "global" {
+        "https://aiplatform.googleapis.com".to_string()
+    } else {
+        format!("https://{}-aiplatform.googleapis.com", location)
+    }
+}
+
+impl fmt::Debug for Backend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.kind {
+            BackendKind::GoogleAi { .. } => f
+                .debug_struct("GoogleAi")
+                .field("api_key", &REDACTED)
+                .field("base_url", &self.base_url)
+                .finish(),
+            BackendKind::VertexAi {
+                project,
+                location,
+                credentials,
+            } => f
+                .debug_struct("VertexAi")
+                .field("project", project)
+                .field("location", location)
+                .field("credentials", credentials)
+                .field("base_url", &self.base_url)
+                .finish(),
+        }
+    }
+}
@@ -0,0 +1,530 @@
+//! Batch API: offline `batchGenerateContent` jobs at discounted pricing.
+//!
+//! A [`BatchClient`], from [`Client::batches`](crate::Client::batches),
+//! submits many [`GenerateContentRequest`]s as one long-running job, either
+//! inline or as a JSONL file uploaded with
+//! [`upload_jsonl`](BatchClient::upload_jsonl). Jobs are polled with
+//! [`get`](BatchClient::get) until [done](BatchJob::done), and their
+//! [`results`](BatchClient::results) are returned keyed by request key.
+//!
+//! Batch jobs are only available on the Gemini Developer API.
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! use gemini_rs::batch::{BatchInput, BatchRequest};
+//! use gemini_rs::{Client, Content, Model};
+//! use std::time::Duration;
+//!
+//! # async fn example() -> Result<(), gemini_rs::Error> {
+//! let client = Client::new("YOUR_API_KEY");
+//! let model = client.model(Model::Gemini25Flash);
+//! let requests = vec![
+//!     BatchRequest::new("q1", model.build_request(vec![Content::text("What is Rust?")])),
+//!     BatchRequest::new("q2", model.build_request(vec![Content::text("What is Go?")])),
+//! ];
+//!
+//! let batches = client.batches();
+//! let mut job = batches
+//!     .create(Model::Gemini25Flash, "questions", BatchInput::Requests(requests))
+//!     .await?;
+//! while !job.done {
+//!     tokio::time::sleep(Duration::from_secs(30)).await;
+//!     job = batches.get(&job.name).await?;
+//! }
+//!
+//! for (key, result) in batches.results(&job).await? {
+//!     match result {
+//!         Ok(response) => println!("{}: {}", key, response.text()),
+//!         Err(e) => eprintln!("{}: {}", key, e),
+//!     }
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::client::Client;
+use crate::error::{Error, Result};
+use crate::models::Model;
+use crate::operation::{Operation, OperationError};
+use crate::transport::HttpRequest;
+use crate::types::{GenerateContentRequest, GenerateContentResponse};
+use bytes::Bytes;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use reqwest::{Method, Url};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::path::Path;
+
+/// One request of a batch job, identified by a caller-chosen key.
+#[derive(Debug, Clone, Serialize)]
+pub struct BatchRequest {
+    /// Key the result is returned under.
+    pub key: String,
+    /// The request to run.
+    pub request: GenerateContentRequest,
+}
+
+impl BatchRequest {
+    /// Create a batch request.
+    pub fn new(key: impl Into<String>, request: GenerateContentRequest) -> Self {
+        Self {
+            key: key.into(),
+            request,
+        }
+    }
+}
+
+/// The requests of a batch job.
+#[derive(Debug, Clone)]
+pub enum BatchInput {
+    /// Requests sent inline with the job, for small batches.
+    Requests(Vec<BatchRequest>),
+    /// An uploaded JSONL file (`files/...`) of `{"key", "request"}` lines.
+    File(String),
+}
+
+/// State of a batch job.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum BatchState {
+    /// Waiting to run.
+    #[serde(rename = "BATCH_STATE_PENDING")]
+    Pending,
+    /// Running.
+    #[serde(rename = "BATCH_STATE_RUNNING")]
+    Running,
+    /// Finished; results are available.
+    #[serde(rename = "BATCH_STATE_SUCCEEDED")]
+    Succeeded,
+    /// Failed as a whole.
+    #[serde(rename = "BATCH_STATE_FAILED")]
+    Failed,
+    /// Cancelled before finishing.
+    #[serde(rename = "BATCH_STATE_CANCELLED")]
+    Cancelled,
+    /// Did not finish in time.
+    #[serde(rename = "BATCH_STATE_EXPIRED")]
+    Expired,
+    /// Unknown or not reported.
+    #[default]
+    #[serde(other)]
+    Unspecified,
+}
+
+impl BatchState {
+    /// Whether the job has stopped running.
+    pub fn is_terminal(&self) -> bool {
+        matches!(
+            self,
+            BatchState::Succeeded
+                | BatchState::Failed
+                | BatchState::Cancelled
+                | BatchState::Expired
+        )
+    }
+}
+
+/// Request counts of a batch job.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchStats {
+    /// Requests in the job.
+    #[serde(default, deserialize_with = "int64")]
+    pub request_count: u64,
+    /// Requests that succeeded.
+    #[serde(default, deserialize_with = "int64")]
+    pub successful_request_count: u64,
+    /// Requests that failed.
+    #[serde(default, deserialize_with = "int64")]
+    pub failed_request_count: u64,
+    /// Requests not run yet.
+    #[serde(default, deserialize_with = "int64")]
+    pub pending_request_count: u64,
+}
+
+/// Parse an `int64`, which the API encodes as a JSON string.
+fn int64<'de, D: Deserializer<'de>>(deserializer: D) -> std::result::Result<u64, D::Error> {
+    match Value::deserialize(deserializer)? {
+        Value::String(s) => s.parse().map_err(serde::de::Error::custom),
+        Value::Number(n) => n
+            .as_u64()
+            .ok_or_else(|| serde::de::Error::custom("expected an unsigned integer")),
+        _ => Ok(0),
+    }
+}
+
+/// Metadata of a batch job.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchMetadata {
+    /// Model the job runs on, e.g. `models/gemini-2.5-flash`.
+    pub model: Option<String>,
+    /// Display name given at creation.
+    pub display_name: Option<String>,
+    /// Current state.
+    #[serde(default)]
+    pub state: BatchState,
+    /// Creation time (RFC 3339).
+    pub create_time: Option<String>,
+    /// Last update time (RFC 3339).
+    pub update_time: Option<String>,
+    /// Completion time (RFC 3339).
+    pub end_time: Option<String>,
+    /// Request counts.
+    #[serde(default)]
+    pub batch_stats: BatchStats,
+    /// Results, once the job has succeeded.
+    pub output: Option<BatchOutput>,
+}
+
+/// Results of a batch job: inline responses or a JSONL results file.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchOutput {
+    /// Name of the results file (`files/...`), for file input.
+    pub responses_file: Option<String>,
+    /// Responses, for inline input.
+    pub inlined_responses: Option<InlinedResponses>,
+}
+
+/// Inline responses of a batch job.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InlinedResponses {
+    /// One entry per request, in request order.
+    #[serde(default)]
+    pub inlined_responses: Vec<InlinedResponse>,
+}
+
+/// The response, or error, of one inline request.
+#[derive(Debug, Clone, Deserialize)]
+pub struct InlinedResponse {
+    /// The response, if the request succeeded.
+    pub response: Option<GenerateContentResponse>,
+    /// The error, if the request failed.
+    pub error: Option<OperationError>,
+    /// Metadata given with the request, including its `key`.
+    pub metadata: Option<Value>,
+}
+
+/// A batch job, as returned by the long-running operation API.
+#[derive(Debug, Clone, Deserialize)]
+pub struct BatchJob {
+    /// Resource name, e.g. `batches/123`.
+    pub name: String,
+    /// Job metadata.
+    #[serde(default)]
+    pub metadata: BatchMetadata,
+    /// Whether the job has finished.
+    #[serde(default)]
+    pub done: bool,
+    /// Results, once the job has succeeded.
+    pub response: Option<BatchOutput>,
+    /// The error, if the job failed.
+    pub error: Option<OperationError>,
+}
+
+impl BatchJob {
+    /// Get the current state.
+    pub fn state(&self) -> BatchState {
+        self.metadata.state
+    }
+
+    /// Get the results, once the job has succeeded.
+    pub fn output(&self) -> Option<&BatchOutput> {
+        self.response.as_ref().or(self.metadata.output.as_ref())
+    }
+}
+
+/// A page of batch jobs from [`BatchClient::list`].
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchPage {
+    /// Jobs on this page.
+    #[serde(default, rename = "operations")]
+    pub jobs: Vec<BatchJob>,
+    /// Token for the next page, if any.
+    pub next_page_token: Option<String>,
+}
+
+/// Client for Batch API jobs.
+///
+/// See the [module documentation](self).
+#[derive(Debug, Clone)]
+pub struct BatchClient {
+    client: Client,
+}
+
+impl BatchClient {
+    pub(crate) fn new(client: Client) -> Self {
+        Self { client }
+    }
+
+    /// Create a batch job running `input` on `model`.
+    pub async fn create(
+        &self,
+        model: Model,
+        display_name: impl Into<String>,
+        input: BatchInput,
+    ) -> Result<BatchJob> {
+        let input_config = match input {
+            BatchInput::Requests(requests) => {
+                let requests: Vec<Value> = requests
+                    .into_iter()
+                    .map(|request| {
+                        json!({
+                            "request": request.request,
+                            "metadata": { "key": request.key },
+                        })
+                    })
+                    .collect();
+                json!({ "requests": { "requests": requests } })
+            }
+            BatchInput::File(name) => json!({ "fileName": name }),
+        };
+        let body = json!({
+            "batch": {
+                "displayName": display_name.into(),
+                "inputConfig": input_config,
+            }
+        });
+        let url = self.url(&format!("models/{}:batchGenerateContent", model.as_str()))?;
+        self.send(HttpRequest::post_json(url, &body)?).await
+    }
+
+    /// Get the current status of a job, by name (`batches/123` or `123`).
+    pub async fn get(&self, name: &str) -> Result<BatchJob> {
+        let url = self.url(&batch_name(name))?;
+        self.send(HttpRequest::new(Method::GET, url)).await
+    }
+
+    /// List jobs, `page_size` at a time, starting at `page_token`.
+    pub async fn list(
+        &self,
+        page_size: Option<u32>,
+        page_token: Option<&str>,
+    ) -> Result<BatchPage> {
+        let mut query = Vec::new();
+        if let Some(size) = page_size {
+            query.push(("pageSize", size.to_string()));
+        }
+        if let Some(token) = page_token {
+            query.push(("pageToken", token.to_string()));
+        }
+        let url = Url::parse_with_params(&self.url("batches")?, &query)
+            .map_err(|e| Error::ConfigError(format!("invalid base URL: {}", e)))?;
+        self.send(HttpRequest::new(Method::GET, url.as_str())).await
+    }
+
+    /// Ask the API to cancel a running job.
+    pub async fn cancel(&self, name: &str) -> Result<()> {
+        let url = self.url(&format!("{}:cancel", batch_name(name)))?;
+        self.client
+            .send_raw(HttpRequest::post_json(url, &json!({}))?)
+            .await?;
+        Ok(())
+    }
+
+    /// Delete a job. Running jobs are not cancelled.
+    pub async fn delete(&self, name: &str) -> Result<()> {
+        let url = self.url(&batch_name(name))?;
+        self.client
+            .send_raw(HttpRequest::new(Method::DELETE, url))
+            .await?;
+        Ok(())
+    }
+
+    /// Upload `requests` as a JSONL input file and return its name, for
+    /// [`BatchInput::File`].
+    pub async fn upload_jsonl(
+        &self,
+        display_name: &str,
+        requests: &[BatchRequest],
+    ) -> Result<String> {
+        let mut body = Vec::new();
+        for request in requests {
+            serde_json::to_writer(&mut body, request)?;
+            body.push(b'\n');
+        }
+        self.upload(display_name, body.into()).await
+    }
+
+    /// Upload a JSONL file of `{"key", "request"}` lines from disk and
+    /// return its name, for [`BatchInput::File`].
+    pub async fn upload_file(&self, path: impl AsRef<Path>) -> Result<String> {
+        let path = path.as_ref();
+        let body = tokio::fs::read(path).await?;
+        let display_name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        self.upload(&display_name, body.into()).await
+    }
+
+    /// Upload `body` with the resumable upload protocol.
+    async fn upload(&self, display_name: &str, body: Bytes) -> Result<String> {
+        let url = self.client.backend().media_url("upload", "files")?;
+        let mut start =
+            HttpRequest::post_json(url, &json!({ "file": { "displayName": display_name } }))?;
+        set_header(&mut start.headers, "x-goog-upload-protocol", "resumable");
+        set_header(&mut start.headers, "x-goog-upload-command", "start");
+        set_header(
+            &mut start.headers,
+            "x-goog-upload-header-content-length",
+            &body.len().to_string(),
+        );
+        set_header(
+            &mut start.headers,
+            "x-goog-upload-header-content-type",
+            "application/jsonl",
+        );
+        let response = self.client.send_raw(start).await?;
+        let upload_url = response
+            .headers
+            .get("x-goog-upload-url")
+            .and_then(|url| url.to_str().ok())
+            .ok_or(Error::NoResponse)?
+            .to_string();
+
+        let mut upload = HttpRequest::new(Method::POST, upload_url);
+        upload.body = body;
+        set_header(&mut upload.headers, "x-goog-upload-offset", "0");
+        set_header(
+            &mut upload.headers,
+            "x-goog-upload-command",
+            "upload, finalize",
+        );
+        let response = self.client.send_raw(upload).await?;
+
+        #[derive(Deserialize)]
+        struct Uploaded {
+            file: File,
+        }
+        #[derive(Deserialize)]
+        struct File {
+            name: String,
+        }
+        let uploaded: Uploaded = serde_json::from_slice(&response.body)?;
+        Ok(uploaded.file.name)
+    }
+
+    /// Track a job as an [`Operation`], e.g. to
+    /// [`wait`](Operation::wait) for it or resume it in another process.
+    pub fn operation(&self, name: &str) -> Operation<BatchOutput, BatchMetadata> {
+        self.client.operation(batch_name(name))
+    }
+
+    /// Get the results of a finished job, keyed by request key.
+    ///
+    /// Inline responses without a key are keyed by their index. A failed
+    /// request maps to an [`Error::ApiError`]; a failed job, or one that
+    /// has not finished, is an error as a whole.
+    pub async fn results(
+        &self,
+        job: &BatchJob,
+    ) -> Result<HashMap<String, Result<GenerateContentResponse>>> {
+        if let Some(error) = &job.error {
+            return Err(error.clone().into());
+        }
+        let output = job.output().ok_or_else(|| {
+            Error::InvalidInput(format!(
+                "batch job {} has no results (state {:?})",
+                job.name,
+                job.state()
+            ))
+        })?;
+        self.output_results(output).await
+    }
+
+    /// Get the results in a job's output, e.g. from
+    /// [`Operation::wait`], keyed by request key.
+    pub async fn output_results(
+        &self,
+        output: &BatchOutput,
+    ) -> Result<HashMap<String, Result<GenerateContentResponse>>> {
+        if let Some(inlined) = &output.inlined_responses {
+            return Ok(inlined
+                .inlined_responses
+                .iter()
+                .enumerate()
+                .map(|(index, item)| {
+                    let key = item
+                        .metadata
+                        .as_ref()
+                        .and_then(|metadata| metadata.get("key"))
+                        .and_then(Value::as_str)
+                        .map_or_else(|| index.to_string(), str::to_string);
+                    (key, item_result(item.response.clone(), item.error.clone()))
+                })
+                .collect());
+        }
+
+        let Some(file) = &output.responses_file else {
+            return Ok(HashMap::new());
+        };
+        let url = self
+            .client
+            .backend()
+            .media_url("download", &format!("{}:download", file))?;
+        let response = self
+            .client
+            .send_raw(HttpRequest::new(Method::GET, format!("{}?alt=media", url)))
+            .await?;
+
+        #[derive(Deserialize)]
+        struct Line {
+            key: Option<String>,
+            response: Option<GenerateContentResponse>,
+            error: Option<OperationError>,
+        }
+        let mut results = HashMap::new();
+        let text = response.text();
+        for (index, line) in text
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .enumerate()
+        {
+            let line: Line = serde_json::from_str(line)?;
+            let key = line.key.unwrap_or_else(|| index.to_string());
+            results.insert(key, item_result(line.response, line.error));
+        }
+        Ok(results)
+    }
+
+    fn url(&self, path: &str) -> Result<String> {
+        self.client.backend().resource_url(path)
+    }
+
+    async fn send<T: DeserializeOwned>(&self, request: HttpRequest) -> Result<T> {
+        let response = self.client.send_raw(request).await?;
+        Ok(serde_json::from_slice(&response.body)?)
+    }
+}
+
+fn item_result(
+    response: Option<GenerateContentResponse>,
+    error: Option<OperationError>,
+) -> Result<GenerateContentResponse> {
+    match (response, error) {
+        (_, Some(error)) => Err(error.into()),
+        (Some(response), None) => Ok(response),
+        (None, None) => Err(Error::NoResponse),
+    }
+}
+
+/// Accept both `batches/123` and `123`.
+fn batch_name(name: &str) -> String {
+    if name.starts_with("batches/") {
+        name.to_string()
+    } else {
+        format!("batches/{}", name)
+    }
+}
+
+fn set_header(headers: &mut HeaderMap, name: &'static str, value: &str) {
+    if let Ok(value) = HeaderValue::from_str(value) {
+        headers.insert(HeaderName::from_static(name), value);
+    }
+}
@@ -0,0 +1,414 @@
+//! Hard token and spend limits.
+//!
+//! A [`Budget`] is checked before every call and fails it with
+//! [`Error::BudgetExceeded`] once a limit would be crossed. It can limit
+//! the tokens of a single request, the tokens used in a rolling hour or
+//! day, and the total estimated spend.
+//!
+//! Before a call, the prompt size is estimated with
+//! [`Content::estimate_tokens`](crate::Content::estimate_tokens), or
+//! counted with `countTokens` when [`Budget::count_tokens`] is enabled, and
+//! reserved. Once the response arrives the reservation is replaced by the
+//! actual usage (for streams, when the stream ends or is dropped). Failed
+//! calls release their reservation.
+//!
+//! Attach a budget to a [`Client`](crate::Client), a
+//! [`ModelClient`](crate::ModelClient) or a
+//! [`ChatSession`](crate::ChatSession) with their `with_budget` methods.
+//! Clones of a budget share its usage, so one budget can cover several
+//! clients, e.g. all the clients of a tenant.
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! use gemini_rs::budget::Budget;
+//! use gemini_rs::{Client, Error, Model};
+//!
+//! # async fn example() -> Result<(), gemini_rs::Error> {
+//! let budget = Budget::new()
+//!     .max_tokens_per_request(20_000)
+//!     .max_tokens_per_hour(500_000)
+//!     .max_spend(5.0);
+//!
+//! let mut agent = Client::new("YOUR_API_KEY")
+//!     .model(Model::Gemini25Flash)
+//!     .start_chat()
+//!     .with_budget(budget.clone());
+//!
+//! loop {
+//!     match agent.send_message("Next step?").await {
+//!         Ok(response) => println!("{}", response.text()),
+//!         Err(Error::BudgetExceeded { limit, used }) => {
+//!             println!("stopping: {} used of {}", used, limit);
+//!             break;
+//!         }
+//!         Err(e) => return Err(e),
+//!     }
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::clock::{Clock, SystemClock};
+use crate::cost::{self, CostCalculator};
+use crate::error::{Error, Result};
+use crate::middleware::{Endpoint, Middleware, ModelRequest, ModelResponse, Next};
+use crate::models::Model;
+use crate::types::{GenerateContentRequest, UsageMetadata};
+use futures::future::BoxFuture;
+use futures::stream::StreamExt;
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+const HOUR: Duration = Duration::from_secs(60 * 60);
+const DAY: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// A limit of a [`Budget`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BudgetLimit {
+    /// Maximum tokens of a single request: prompt plus `max_output_tokens`.
+    TokensPerRequest(u64),
+    /// Maximum tokens in a rolling hour.
+    TokensPerHour(u64),
+    /// Maximum tokens in a rolling day.
+    TokensPerDay(u64),
+    /// Maximum estimated spend in USD.
+    Spend(f64),
+}
+
+impl std::fmt::Display for BudgetLimit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BudgetLimit::TokensPerRequest(max) => write!(f, "{} tokens per request", max),
+            BudgetLimit::TokensPerHour(max) => write!(f, "{} tokens per hour", max),
+            BudgetLimit::TokensPerDay(max) => write!(f, "{} tokens per day", max),
+            BudgetLimit::Spend(max) => write!(f, "${:.2}", max),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct State {
+    /// `(id, time, tokens)` of the calls in the last day.
+    calls: VecDeque<(u64, Instant, u64)>,
+    next_id: u64,
+    spent: f64,
+}
+
+impl State {
+    fn prune(&mut self, now: Instant) {
+        while let Some((_, time, _)) = self.calls.front() {
+            if now.saturating_duration_since(*time) < DAY {
+                break;
+            }
+            self.calls.pop_front();
+        }
+    }
+
+    fn tokens_since(&self, now: Instant, window: Duration) -> u64 {
+        self.calls
+            .iter()
+            .filter(|(_, time, _)| now.saturating_duration_since(*time) < window)
+            .map(|(_, _, tokens)| tokens)
+            .sum()
+    }
+}
+
+/// Token and spend limits shared by every client it is attached to.
+///
+/// Without limits a budget only records usage. See the
+/// [module documentation](self).
+#[derive(Clone)]
+pub struct Budget {
+    per_request: Option<u64>,
+    per_hour: Option<u64>,
+    per_day: Option<u64>,
+    spend: Option<f64>,
+    count_tokens: bool,
+    calculator: Option<Arc<CostCalculator>>,
+    clock: Arc<dyn Clock>,
+    state: Arc<Mutex<State>>,
+}
+
+impl Budget {
+    /// Create a budget without limits.
+    pub fn new() -> Self {
+        Self {
+            per_request: None,
+            per_hour: None,
+            per_day: None,
+            spend: None,
+            count_tokens: false,
+            calculator: None,
+            clock: Arc::new(SystemClock),
+            state: Arc::default(),
+        }
+    }
+
+    /// Limit the tokens of a single request: the prompt plus the
+    /// `max_output_tokens` of its generation config, if set.
+    pub fn max_tokens_per_request(mut self, max: u64) -> Self {
+        self.per_request = Some(max);
+        self
+    }
+
+    /// Limit the tokens used in any rolling hour.
+    pub fn max_tokens_per_hour(mut self, max: u64) -> Self {
+        self.per_hour = Some(max);
+        self
+    }
+
+    /// Limit the tokens used in any rolling day.
+    pub fn max_tokens_per_day(mut self, max: u64) -> Self {
+        self.per_day = Some(max);
+        self
+    }
+
+    /// Limit the total estimated spend, in USD.
+    ///
+    /// Calls to models without prices in the cost calculator are not
+    /// counted.
+    pub fn max_spend(mut self, max: f64) -> Self {
+        self.spend = Some(max);
+        self
+    }
+
+    /// Count prompt tokens with the `countTokens` API before each call
+    /// instead of estimating them locally. Exact, but adds a request per
+    /// call.
+    pub fn count_tokens(mut self, enabled: bool) -> Self {
+        self.count_tokens = enabled;
+        self
+    }
+
+    /// Price calls with `calculator` instead of the built-in prices.
+    pub fn with_calculator(mut self, calculator: CostCalculator) -> Self {
+        self.calculator = Some(Arc::new(calculator));
+        self
+    }
+
+    /// Read the time from `clock` instead of the system clock.
+    pub fn with_clock(mut self, clock: impl Clock + 'static) -> Self {
+        self.clock = Arc::new(clock);
+        self
+    }
+
+    /// Tokens used, or reserved by calls in flight, in the last hour.
+    pub fn tokens_last_hour(&self) -> u64 {
+        let now = self.clock.now();
+        self.state.lock().unwrap().tokens_since(now, HOUR)
+    }
+
+    /// Tokens used, or reserved by calls in flight, in the last day.
+    pub fn tokens_last_day(&self) -> u64 {
+        let now = self.clock.now();
+        self.state.lock().unwrap().tokens_since(now, DAY)
+    }
+
+    /// Estimated spend so far, in USD.
+    pub fn spent(&self) -> f64 {
+        self.state.lock().unwrap().spent
+    }
+
+    /// Forget all usage.
+    pub fn reset(&self) {
+        *self.state.lock().unwrap() = State::default();
+    }
+
+    fn calculator(&self) -> &CostCalculator {
+        self.calculator
+            .as_deref()
+            .unwrap_or_else(|| cost::default_calculator())
+    }
+
+    /// Check the limits and reserve `prompt_tokens` for a call.
+    fn reserve(
+        &self,
+        model: &Model,
+        request: &GenerateContentRequest,
+        prompt_tokens: u64,
+    ) -> Result<u64> {
+        let exceeded = |limit, used: u64| Error::BudgetExceeded {
+            limit,
+            used: used as f64,
+        };
+
+        if let Some(max) = self.per_request {
+            let max_output = request
+                .generation_config
+                .as_ref()
+                .and_then(|config| config.max_output_tokens)
+                .map_or(0, |tokens| tokens.max(0) as u64);
+            let tokens = prompt_tokens + max_output;
+            if tokens > max {
+                return Err(exceeded(BudgetLimit::TokensPerRequest(max), tokens));
+            }
+        }
+
+        let now = self.clock.now();
+        let mut state = self.state.lock().unwrap();
+        state.prune(now);
+        for (max, window, limit) in [
+            (
+                self.per_hour,
+                HOUR,
+                BudgetLimit::TokensPerHour as fn(u64) -> BudgetLimit,
+            ),
+            (self.per_day, DAY, BudgetLimit::TokensPerDay),
+        ] {
+            if let Some(max) = max {
+                let used = state.tokens_since(now, window);
+                if used + prompt_tokens > max {
+                    return Err(exceeded(limit(max), used));
+                }
+            }
+        }
+        if let Some(max) = self.spend {
+            let usage = UsageMetadata {
+                prompt_token_count: prompt_tokens,
+                ..UsageMetadata::default()
+            };
+            let input_cost = self
+                .calculator()
+                .cost(model.as_str(), &usage)
+                .map_or(0.0, |cost| cost.total());
+            if state.spent + input_cost > max {
+                return Err(Error::BudgetExceeded {
+                    limit: BudgetLimit::Spend(max),
+                    used: state.spent,
+                });
+            }
+        }
+
+        let id = state.next_id;
+        state.next_id += 1;
+        state.calls.push_back((id, now, prompt_tokens));
+        Ok(id)
+    }
+
+    /// Replace a reservation with the actual usage, or release it.
+    fn settle(&self, id: u64, model: &Model, usage: Option<&UsageMetadata>, failed: bool) {
+        let mut state = self.state.lock().unwrap();
+        let tokens = match usage {
+            Some(usage) => {
+                if let Some(cost) = self.calculator().cost(model.as_str(), usage) {
+                    state.spent += cost.total();
+                }
+                usage.total_token_count.max(
+                    usage.prompt_token_count
+                        + usage.candidates_token_count
+                        + usage.thoughts_token_count,
+                )
+            }
+            None if failed => 0,
+            // Keep the estimate, e.g. for a stream dropped before it ended
+            None => return,
+        };
+        if let Some(call) = state
+            .calls
+            .iter_mut()
+            .find(|(call_id, _, _)| *call_id == id)
+        {
+            call.2 = tokens;
+        }
+    }
+}
+
+impl Default for Budget {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl std::fmt::Debug for Budget {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Budget")
+            .field("max_tokens_per_request", &self.per_request)
+            .field("max_tokens_per_hour", &self.per_hour)
+            .field("max_tokens_per_day", &self.per_day)
+            .field("max_spend", &self.spend)
+            .field("count_tokens", &self.count_tokens)
+            .field("spent", &self.spent())
+            .finish_non_exhaustive()
+    }
+}
+
+/// A reserved call; settles with the budget when dropped.
+struct Reservation {
+    budget: Budget,
+    id: u64,
+    model: Model,
+    usage: Option<UsageMetadata>,
+    failed: bool,
+}
+
+impl Reservation {
+    fn record(&mut self, usage: Option<&UsageMetadata>) {
+        if let Some(usage) = usage {
+            self.usage = Some(usage.clone());
+        }
+    }
+}
+
+impl Drop for Reservation {
+    fn drop(&mut self) {
+        self.budget
+            .settle(self.id, &self.model, self.usage.as_ref(), self.failed);
+    }
+}
+
+impl Middleware for Budget {
+    fn handle<'a>(
+        &'a self,
+        request: ModelRequest,
+        next: Next<'a>,
+    ) -> BoxFuture<'a, Result<ModelResponse>> {
+        Box::pin(async move {
+            if request.endpoint == Endpoint::CountTokens {
+                return next.run(request).await;
+            }
+
+            let prompt_tokens = if self.count_tokens {
+                let mut count =
+                    ModelRequest::new(request.model, Endpoint::CountTokens, request.body.clone());
+                count.intercepted = request.intercepted;
+                next.run(count).await?.into_count_tokens()?.total_tokens
+            } else {
+                request.body.estimate_tokens()
+            };
+
+            let id = self.reserve(&request.model, &request.body, prompt_tokens)?;
+            let mut reservation = Reservation {
+                budget: self.clone(),
+                id,
+                model: request.model,
+                usage: None,
+                failed: false,
+            };
+
+            match next.run(request).await {
+                Ok(ModelResponse::Generate(response)) => {
+                    reservation.record(response.usage_metadata.as_ref());
+                    Ok(ModelResponse::Generate(response))
+                }
+                Ok(ModelResponse::Stream(mut stream)) => Ok(ModelResponse::Stream(
+                    async_stream::stream! {
+                        while let Some(chunk) = stream.next().await {
+                            if let Ok(chunk) = &chunk {
+                                reservation.record(chunk.usage_metadata.as_ref());
+                            }
+                            yield chunk;
+                        }
+                    }
+                    .boxed(),
+                )),
+                Ok(other) => Ok(other),
+                Err(error) => {
+                    reservation.failed = true;
+                    Err(error)
+                }
+            }
+        })
+    }
+}
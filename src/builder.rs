@@ -0,0 +1,240 @@
+//! Builder for [`Client`] with transport-level options.
+//!
+//! [`Client::new`] covers the common case. Use [`Client::builder`] to set
+//! timeouts, a proxy, custom CA certificates, default headers, the API
+//! version, an alternate endpoint, or a custom [`Transport`].
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! use gemini_rs::backend::ApiVersion;
+//! use gemini_rs::Client;
+//! use std::time::Duration;
+//!
+//! # fn example() -> Result<(), gemini_rs::Error> {
+//! let client = Client::builder()
+//!     .api_key("YOUR_API_KEY")
+//!     .connect_timeout(Duration::from_secs(5))
+//!     .timeout(Duration::from_secs(60))
+//!     .proxy(reqwest::Proxy::https("http://proxy.internal:3128")?)
+//!     .default_header("x-request-source", "billing-service")
+//!     .user_agent_suffix("billing-service/2.3")
+//!     .api_version(ApiVersion::V1)
+//!     .build()?;
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::auth::Credentials;
+use crate::backend::{ApiVersion, Backend};
+use crate::client::Client;
+use crate::error::{Error, Result};
+use crate::transport::{ReqwestTransport, Transport};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use reqwest::{Certificate, Client as HttpClient, Proxy};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Builder for [`Client`], created with [`Client::builder`].
+///
+/// Options that configure the HTTP connection (timeouts, proxy,
+/// certificates, headers, user agent) cannot be combined with
+/// [`http_client`](ClientBuilder::http_client) or
+/// [`transport`](ClientBuilder::transport); configure the injected client
+/// or transport instead.
+#[derive(Default)]
+pub struct ClientBuilder {
+    backend: Option<Backend>,
+    api_version: Option<ApiVersion>,
+    base_url: Option<String>,
+    http_client: Option<HttpClient>,
+    transport: Option<Arc<dyn Transport>>,
+    connect_timeout: Option<Duration>,
+    timeout: Option<Duration>,
+    proxies: Vec<Proxy>,
+    root_certificates: Vec<Certificate>,
+    default_headers: Vec<(String, String)>,
+    user_agent_suffix: Option<String>,
+}
+
+impl ClientBuilder {
+    /// Create a builder with no credentials set.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Use the Gemini Developer API with this API key.
+    pub fn api_key(mut self, api_key: impl Into<String>) -> Self {
+        self.backend = Some(Backend::google_ai(api_key));
+        self
+    }
+
+    /// Use Vertex AI in the given project and location.
+    pub fn vertex_ai(
+        mut self,
+        project: impl Into<String>,
+        location: impl Into<String>,
+        credentials: Credentials,
+    ) -> Self {
+        self.backend = Some(Backend::vertex_ai(project, location, credentials));
+        self
+    }
+
+    /// Use an explicitly configured [`Backend`].
+    pub fn backend(mut self, backend: Backend) -> Self {
+        self.backend = Some(backend);
+        self
+    }
+
+    /// Select the API version (`v1` or `v1beta`).
+    pub fn api_version(mut self, version: ApiVersion) -> Self {
+        self.api_version = Some(version);
+        self
+    }
+
+    /// Override the base URL, including the API version path
+    /// (e.g. `http://localhost:8080/v1beta`).
+    ///
+    /// Takes precedence over [`api_version`](ClientBuilder::api_version).
+    pub fn base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = Some(base_url.into());
+        self
+    }
+
+    /// Use a pre-built `reqwest::Client` for all requests.
+    pub fn http_client(mut self, client: HttpClient) -> Self {
+        self.http_client = Some(client);
+        self
+    }
+
+    /// Send API requests through a custom [`Transport`].
+    ///
+    /// Vertex AI access tokens are still fetched with the `reqwest::Client`
+    /// (the default one, or the one passed to
+    /// [`http_client`](ClientBuilder::http_client)).
+    pub fn transport(mut self, transport: impl Transport + 'static) -> Self {
+        self.transport = Some(Arc::new(transport));
+        self
+    }
+
+    /// Set the timeout for establishing a connection.
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = Some(timeout);
+        self
+    }
+
+    /// Set the total timeout of each request, from sending it until the
+    /// response body has been read.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Route requests through a proxy. Can be called several times.
+    pub fn proxy(mut self, proxy: Proxy) -> Self {
+        self.proxies.push(proxy);
+        self
+    }
+
+    /// Trust an additional root certificate, e.g. a corporate CA.
+    pub fn add_root_certificate(mut self, certificate: Certificate) -> Self {
+        self.root_certificates.push(certificate);
+        self
+    }
+
+    /// Send a header with every request.
+    pub fn default_header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.default_headers.push((name.into(), value.into()));
+        self
+    }
+
+    /// Append an identifier to the `gemini-rs/<version>` user agent.
+    pub fn user_agent_suffix(mut self, suffix: impl Into<String>) -> Self {
+        self.user_agent_suffix = Some(suffix.into());
+        self
+    }
+
+    /// Build the client.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidInput`] if no credentials were configured, a
+    /// header is invalid, or connection options are combined with an
+    /// injected HTTP client or transport; and [`Error::HttpError`] if the HTTP client
+    /// cannot be created.
+    pub fn build(self) -> Result<Client> {
+        let mut backend = self.backend.ok_or_else(|| {
+            Error::InvalidInput(
+                "no credentials configured; call api_key(), vertex_ai() or backend()".to_string(),
+            )
+        })?;
+        if let Some(version) = self.api_version {
+            backend = backend.with_api_version(version);
+        }
+        if let Some(base_url) = self.base_url {
+            backend = backend.with_base_url(base_url);
+        }
+
+        let configures_connection = self.connect_timeout.is_some()
+            || self.timeout.is_some()
+            || !self.proxies.is_empty()
+            || !self.root_certificates.is_empty()
+            || !self.default_headers.is_empty()
+            || self.user_agent_suffix.is_some();
+
+        if configures_connection && (self.http_client.is_some() || self.transport.is_some()) {
+            return Err(Error::InvalidInput(
+                "timeouts, proxies, certificates, headers and user agent cannot be \
+                 combined with http_client() or transport(); configure the injected \
+                 client or transport instead"
+                    .to_string(),
+            ));
+        }
+
+        let http_client = match self.http_client {
+            Some(client) => client,
+            None => {
+                let mut headers = HeaderMap::new();
+                for (name, value) in &self.default_headers {
+                    let name = HeaderName::from_bytes(name.as_bytes()).map_err(|_| {
+                        Error::InvalidInput(format!("invalid header name {:?}", name))
+                    })?;
+                    let value = HeaderValue::from_str(value).map_err(|_| {
+                        Error::InvalidInput(format!("invalid value for header {}", name))
+                    })?;
+                    headers.append(name, value);
+                }
+
+                let mut user_agent = format!("gemini-rs/{}", env!("CARGO_PKG_VERSION"));
+                if let Some(suffix) = &self.user_agent_suffix {
+                    user_agent = format!("{} {}", user_agent, suffix);
+                }
+
+                let mut builder = HttpClient::builder()
+                    .user_agent(user_agent)
+                    .default_headers(headers);
+                if let Some(timeout) = self.connect_timeout {
+                    builder = builder.connect_timeout(timeout);
+                }
+                if let Some(timeout) = self.timeout {
+                    builder = builder.timeout(timeout);
+                }
+                for proxy in self.proxies {
+                    builder = builder.proxy(proxy);
+                }
+                for certificate in self.root_certificates {
+                    builder = builder.add_root_certificate(certificate);
+                }
+                builder.build()?
+            }
+        };
+
+        let transport = match self.transport {
+            Some(transport) => transport,
+            None => Arc::new(ReqwestTransport::new(http_client.clone())),
+        };
+        let mut client = Client::from_parts(http_client, transport, backend);
+        client.set_timeouts(self.connect_timeout, self.timeout);
+        Ok(client)
+    }
+}
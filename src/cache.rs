@@ -0,0 +1,442 @@
+//! Response caching for repeated identical requests.
+//!
+//! A [`ResponseCache`] stores `generateContent` responses under a
+//! [`cache_key`]: a stable hash of the model and the canonicalized request.
+//! Install one with [`Client::with_cache`](crate::Client::with_cache) or
+//! [`ModelClient::with_cache`](crate::ModelClient::with_cache); identical
+//! requests are then answered from the cache without calling the API, and
+//! without counting against [budgets](crate::budget) or
+//! [rate limits](crate::rate_limit). Streaming and `countTokens` calls are
+//! never cached.
+//!
+//! [Interceptors](crate::middleware::Interceptor) run before the lookup, so
+//! requests are keyed as they leave them (e.g. with personal data
+//! scrubbed), and their `after_response` hooks run on cache hits too.
+//!
+//! [`MemoryCache`] is an LRU cache and [`FileCache`] keeps one file per
+//! entry, so cached responses survive restarts. Both expire entries after
+//! an optional TTL. With [`CachePolicy::ZeroTemperature`] only requests with
+//! `temperature` set to 0 are cached. Responses without candidates or with a
+//! blocked prompt are never cached.
+//!
+//! Responses carry their [`CacheStatus`] in
+//! [`GenerateContentResponse::cache_status`], and
+//! [`CallMetrics::cache`](crate::metrics::CallMetrics::cache) reports it to
+//! metrics sinks. Cache hits are reported with no token usage.
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! use gemini_rs::cache::{CachePolicy, CacheStatus, FileCache};
+//! use gemini_rs::{Client, GenerationConfig, Model};
+//! use std::time::Duration;
+//!
+//! # async fn example() -> Result<(), gemini_rs::Error> {
+//! let cache = FileCache::new("./.gemini-cache")?.with_ttl(Duration::from_secs(7 * 24 * 3600));
+//! let model = Client::new("YOUR_API_KEY")
+//!     .with_cache(cache)
+//!     .with_cache_policy(CachePolicy::ZeroTemperature)
+//!     .model(Model::Gemini25Flash)
+//!     .with_config(GenerationConfig::new().temperature(0.0));
+//!
+//! let response = model.generate_content("Classify: coffee $4").await?;
+//! if response.cache_status == Some(CacheStatus::Hit) {
+//!     println!("served from cache");
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::clock::{Clock, SystemClock};
+use crate::error::Result;
+use crate::middleware::{Endpoint, ModelRequest};
+use crate::models::Model;
+use crate::types::{GenerateContentRequest, GenerateContentResponse};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// Whether a response was served from the cache.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CacheStatus {
+    /// Served from the cache, without calling the API.
+    Hit,
+    /// Not in the cache; the API was called and the response stored.
+    Miss,
+}
+
+impl CacheStatus {
+    /// `"hit"` or `"miss"`.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CacheStatus::Hit => "hit",
+            CacheStatus::Miss => "miss",
+        }
+    }
+}
+
+/// Which requests are cached.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CachePolicy {
+    /// Every `generateContent` request.
+    #[default]
+    All,
+    /// Only requests whose generation config sets `temperature` to 0,
+    /// whose responses are (nearly) deterministic.
+    ZeroTemperature,
+}
+
+impl CachePolicy {
+    pub(crate) fn applies(&self, request: &ModelRequest) -> bool {
+        if request.endpoint != Endpoint::GenerateContent {
+            return false;
+        }
+        match self {
+            CachePolicy::All => true,
+            CachePolicy::ZeroTemperature => {
+                request
+                    .body
+                    .generation_config
+                    .as_ref()
+                    .and_then(|config| config.temperature)
+                    == Some(0.0)
+            }
+        }
+    }
+}
+
+/// Storage for cached responses.
+///
+/// Lookup failures are treated as misses, and store failures are ignored,
+/// so a broken cache never fails a call.
+pub trait ResponseCache: Send + Sync {
+    /// Get the response stored under `key`, if present and not expired.
+    fn get(&self, key: &str) -> Result<Option<GenerateContentResponse>>;
+
+    /// Store `response` under `key`.
+    fn put(&self, key: &str, response: &GenerateContentResponse) -> Result<()>;
+}
+
+impl<C: ResponseCache + ?Sized> ResponseCache for Arc<C> {
+    fn get(&self, key: &str) -> Result<Option<GenerateContentResponse>> {
+        (**self).get(key)
+    }
+
+    fn put(&self, key: &str, response: &GenerateContentResponse) -> Result<()> {
+        (**self).put(key, response)
+    }
+}
+
+/// Compute the cache key of a request to `model`: 32 hex digits.
+///
+/// The key depends only on the model and the request's content, not on
+/// the order of JSON object keys, and is stable across processes.
+///
+/// # Example
+///
+/// ```rust
+/// use gemini_rs::cache::cache_key;
+/// use gemini_rs::{Client, Content, Model};
+///
+/// let model = Client::new("key").model(Model::Gemini25Flash);
+/// let request = model.build_request(vec![Content::text("Hello")]);
+/// assert_eq!(cache_key(Model::Gemini25Flash, &request).len(), 32);
+/// assert_ne!(
+///     cache_key(Model::Gemini25Flash, &request),
+///     cache_key(Model::Gemini20Flash, &request),
+/// );
+/// ```
+pub fn cache_key(model: Model, request: &GenerateContentRequest) -> String {
+    let mut canonical = format!("{}\n", model.as_str());
+    // Serializing a request never fails: it only holds strings, numbers
+    // and JSON values
+    let value = serde_json::to_value(request).unwrap_or(Value::Null);
+    write_canonical(&value, &mut canonical);
+    format!("{:032x}", fnv1a_128(canonical.as_bytes()))
+}
+
+/// Whether `response` may be stored: it has candidates and its prompt was
+/// not blocked. Other responses fail the call, and must not keep failing it
+/// until they expire.
+pub(crate) fn is_cacheable(response: &GenerateContentResponse) -> bool {
+    let blocked = response
+        .prompt_feedback
+        .as_ref()
+        .is_some_and(|feedback| feedback.block_reason.is_some());
+    !blocked && response.candidates.as_ref().is_some_and(|c| !c.is_empty())
+}
+
+/// Write `value` as compact JSON with object keys sorted.
+fn write_canonical(value: &Value, out: &mut String) {
+    match value {
+        Value::Object(map) => {
+            let sorted: BTreeMap<&String, &Value> = map.iter().collect();
+            out.push('{');
+            for (i, (key, value)) in sorted.into_iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                out.push_str(&Value::String(key.clone()).to_string());
+                out.push(':');
+                write_canonical(value, out);
+            }
+            out.push('}');
+        }
+        Value::Array(items) => {
+            out.push('[');
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_canonical(item, out);
+            }
+            out.push(']');
+        }
+        other => out.push_str(&other.to_string()),
+    }
+}
+
+/// 128-bit FNV-1a, which unlike `std`'s hashers is specified and stable.
+pub(crate) fn fnv1a_128(data: &[u8]) -> u128 {
+    const OFFSET: u128 = 0x6c62272e07bb014262b821756295c58d;
+    const PRIME: u128 = 0x0000000001000000000000000000013b;
+    data.iter().fold(OFFSET, |hash, &byte| {
+        (hash ^ u128::from(byte)).wrapping_mul(PRIME)
+    })
+}
+
+#[derive(Debug)]
+struct MemoryEntry {
+    response: GenerateContentResponse,
+    stored: Instant,
+    used: u64,
+}
+
+#[derive(Debug, Default)]
+struct Lru {
+    entries: HashMap<String, MemoryEntry>,
+    /// Keys by last use, oldest first.
+    order: BTreeMap<u64, String>,
+    tick: u64,
+}
+
+impl Lru {
+    fn touch(&mut self, key: &str) {
+        self.tick += 1;
+        if let Some(entry) = self.entries.get_mut(key) {
+            self.order.remove(&entry.used);
+            entry.used = self.tick;
+            self.order.insert(self.tick, key.to_string());
+        }
+    }
+
+    fn remove(&mut self, key: &str) {
+        if let Some(entry) = self.entries.remove(key) {
+            self.order.remove(&entry.used);
+        }
+    }
+}
+
+/// In-memory LRU [`ResponseCache`], shared across clones.
+///
+/// Holds up to `capacity` responses, evicting the least recently used.
+#[derive(Clone)]
+pub struct MemoryCache {
+    capacity: usize,
+    ttl: Option<Duration>,
+    clock: Arc<dyn Clock>,
+    lru: Arc<Mutex<Lru>>,
+}
+
+impl MemoryCache {
+    /// Create a cache holding up to `capacity` responses (at least one).
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            ttl: None,
+            clock: Arc::new(SystemClock),
+            lru: Arc::default(),
+        }
+    }
+
+    /// Expire entries `ttl` after they were stored.
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = Some(ttl);
+        self
+    }
+
+    /// Read the time from `clock` instead of the system clock.
+    pub fn with_clock(mut self, clock: impl Clock + 'static) -> Self {
+        self.clock = Arc::new(clock);
+        self
+    }
+
+    /// Number of stored responses, including expired ones not yet evicted.
+    pub fn len(&self) -> usize {
+        self.lru.lock().unwrap().entries.len()
+    }
+
+    /// Whether the cache is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Remove all entries.
+    pub fn clear(&self) {
+        *self.lru.lock().unwrap() = Lru::default();
+    }
+}
+
+impl ResponseCache for MemoryCache {
+    fn get(&self, key: &str) -> Result<Option<GenerateContentResponse>> {
+        let now = self.clock.now();
+        let mut lru = self.lru.lock().unwrap();
+        let Some(entry) = lru.entries.get(key) else {
+            return Ok(None);
+        };
+        if self
+            .ttl
+            .is_some_and(|ttl| now.saturating_duration_since(entry.stored) >= ttl)
+        {
+            lru.remove(key);
+            return Ok(None);
+        }
+        let response = entry.response.clone();
+        lru.touch(key);
+        Ok(Some(response))
+    }
+
+    fn put(&self, key: &str, response: &GenerateContentResponse) -> Result<()> {
+        let now = self.clock.now();
+        let mut lru = self.lru.lock().unwrap();
+        lru.remove(key);
+        while lru.entries.len() >= self.capacity {
+            let Some((_, oldest)) = lru.order.pop_first() else {
+                break;
+            };
+            lru.entries.remove(&oldest);
+        }
+        lru.entries.insert(
+            key.to_string(),
+            MemoryEntry {
+                response: response.clone(),
+                stored: now,
+                used: 0,
+            },
+        );
+        lru.touch(key);
+        Ok(())
+    }
+}
+
+impl std::fmt::Debug for MemoryCache {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MemoryCache")
+            .field("capacity", &self.capacity)
+            .field("ttl", &self.ttl)
+            .field("len", &self.len())
+            .finish_non_exhaustive()
+    }
+}
+
+/// A response stored by [`FileCache`].
+#[derive(Serialize, Deserialize)]
+struct FileEntry {
+    /// Seconds since the Unix epoch when the entry was stored.
+    stored: u64,
+    response: GenerateContentResponse,
+}
+
+/// Filesystem-backed [`ResponseCache`]: one `{key}.json` file per entry.
+///
+/// Entries survive restarts and can be shared between processes, e.g. to
+/// cache an evaluation suite's responses in CI.
+#[derive(Debug, Clone)]
+pub struct FileCache {
+    dir: PathBuf,
+    ttl: Option<Duration>,
+}
+
+impl FileCache {
+    /// Create a cache in `dir`, creating the directory if needed.
+    pub fn new(dir: impl Into<PathBuf>) -> Result<Self> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir)?;
+        Ok(Self { dir, ttl: None })
+    }
+
+    /// Expire entries `ttl` after they were stored.
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = Some(ttl);
+        self
+    }
+
+    /// Get the directory holding the cache files.
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Remove all entries.
+    pub fn clear(&self) -> Result<()> {
+        for entry in fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            if path.extension().and_then(|ext| ext.to_str()) == Some("json") {
+                fs::remove_file(path)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn path(&self, key: &str) -> PathBuf {
+        // Keys are hex digests; anything else is hashed so it cannot
+        // escape the directory
+        let name = if key.bytes().all(|b| b.is_ascii_alphanumeric()) {
+            key.to_string()
+        } else {
+            format!("{:032x}", fnv1a_128(key.as_bytes()))
+        };
+        self.dir.join(format!("{}.json", name))
+    }
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs())
+}
+
+impl ResponseCache for FileCache {
+    fn get(&self, key: &str) -> Result<Option<GenerateContentResponse>> {
+        let path = self.path(key);
+        let data = match fs::read(&path) {
+            Ok(data) => data,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        let entry: FileEntry = serde_json::from_slice(&data)?;
+        let age = Duration::from_secs(unix_now().saturating_sub(entry.stored));
+        if self.ttl.is_some_and(|ttl| age >= ttl) {
+            let _ = fs::remove_file(&path);
+            return Ok(None);
+        }
+        Ok(Some(entry.response))
+    }
+
+    fn put(&self, key: &str, response: &GenerateContentResponse) -> Result<()> {
+        let path = self.path(key);
+        let entry = FileEntry {
+            stored: unix_now(),
+            response: response.clone(),
+        };
+        // Write to a temporary file first so readers never see a
+        // truncated entry
+        let tmp = path.with_extension("json.tmp");
+        fs::write(&tmp, serde_json::to_vec(&entry)?)?;
+        fs::rename(&tmp, &path)?;
+        Ok(())
+    }
+}
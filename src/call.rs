@@ -0,0 +1,239 @@
+//! Per-call timeouts, cancellation and hedging.
+//!
+//! [`CallOptions`] installed with
+//! [`ModelClient::with_call_options`](crate::ModelClient::with_call_options)
+//! apply to each call of that client, including chat messages and the
+//! calls of `generate_many`. Since a `ModelClient` is cheap to clone, options
+//! for a single call are set on a clone:
+//!
+//! - a [`timeout`](CallOptions::timeout) fails the call with
+//!   [`Error::Timeout`] when it has not finished in time, fallbacks
+//!   included,
+//! - a [`CancellationToken`] fails the call, or ends its stream, with
+//!   [`Error::Cancelled`] as soon as it is cancelled,
+//! - [hedging](CallOptions::hedge) sends a duplicate request when the first
+//!   has not answered after a delay, and takes the first success.
+//!
+//! Abandoned requests are dropped, which closes their connection. A hedged
+//! duplicate counts against budgets and rate limits like any other call.
+//! Streams are not hedged, and their timeout covers only the wait for the
+//! response to start.
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! use gemini_rs::call::{CallOptions, CancellationToken};
+//! use gemini_rs::{Client, Error, Model};
+//! use std::time::Duration;
+//!
+//! # async fn example() -> Result<(), gemini_rs::Error> {
+//! let model = Client::new("YOUR_API_KEY").model(Model::Gemini25Flash);
+//! let cancel = CancellationToken::new();
+//!
+//! let options = CallOptions::new()
+//!     .timeout(Duration::from_secs(30))
+//!     .hedge(Duration::from_secs(5))
+//!     .cancel_on(cancel.clone());
+//! match model.with_call_options(options).generate_content("Hello").await {
+//!     Ok(response) => println!("{}", response.text()),
+//!     Err(Error::Timeout(after)) => eprintln!("gave up after {:?}", after),
+//!     Err(Error::Cancelled) => eprintln!("cancelled"),
+//!     Err(err) => return Err(err),
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::error::{Error, Result};
+use futures::stream::{BoxStream, StreamExt};
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Notify;
+
+/// A handle to cancel calls, shared by its clones.
+///
+/// # Example
+///
+/// ```rust
+/// use gemini_rs::call::CancellationToken;
+///
+/// let token = CancellationToken::new();
+/// let handle = token.clone();
+/// handle.cancel();
+/// assert!(token.is_cancelled());
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken {
+    inner: Arc<Cancellation>,
+}
+
+#[derive(Debug, Default)]
+struct Cancellation {
+    cancelled: AtomicBool,
+    notify: Notify,
+}
+
+impl CancellationToken {
+    /// Create a token that is not cancelled.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cancel the calls using this token, now and from now on.
+    pub fn cancel(&self) {
+        self.inner.cancelled.store(true, Ordering::SeqCst);
+        self.inner.notify.notify_waiters();
+    }
+
+    /// Whether the token has been cancelled.
+    pub fn is_cancelled(&self) -> bool {
+        self.inner.cancelled.load(Ordering::SeqCst)
+    }
+
+    /// Wait until the token is cancelled.
+    pub async fn cancelled(&self) {
+        let notified = self.inner.notify.notified();
+        tokio::pin!(notified);
+        // Register before checking, so a cancel in between is not missed
+        notified.as_mut().enable();
+        if !self.is_cancelled() {
+            notified.await;
+        }
+    }
+}
+
+/// Timeout, cancellation and hedging of a model call.
+///
+/// See the [module documentation](self).
+#[derive(Debug, Clone, Default)]
+pub struct CallOptions {
+    timeout: Option<Duration>,
+    cancel: Option<CancellationToken>,
+    hedge: Option<Duration>,
+}
+
+impl CallOptions {
+    /// No timeout, cancellation or hedging.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Fail with [`Error::Timeout`] when the call has not finished after
+    /// `timeout`.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Fail with [`Error::Cancelled`] when `token` is cancelled.
+    pub fn cancel_on(mut self, token: CancellationToken) -> Self {
+        self.cancel = Some(token);
+        self
+    }
+
+    /// Send a duplicate request when the first has not answered after
+    /// `delay`, and take the first success. When both fail, the error of
+    /// the last to finish is returned.
+    pub fn hedge(mut self, delay: Duration) -> Self {
+        self.hedge = Some(delay);
+        self
+    }
+
+    /// Run the call made by `attempt` with these options.
+    pub(crate) async fn run<T, F, Fut>(&self, attempt: F) -> Result<T>
+    where
+        F: Fn() -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        let call = async {
+            let call = self.hedged(attempt);
+            match self.timeout {
+                Some(timeout) => tokio::time::timeout(timeout, call)
+                    .await
+                    .unwrap_or(Err(Error::Timeout(timeout))),
+                None => call.await,
+            }
+        };
+        match &self.cancel {
+            Some(token) => tokio::select! {
+                biased;
+                _ = token.cancelled() => Err(Error::Cancelled),
+                result = call => result,
+            },
+            None => call.await,
+        }
+    }
+
+    /// Run `start`, which starts a stream, with the timeout and
+    /// cancellation but without hedging.
+    pub(crate) async fn start<T, F, Fut>(&self, start: F) -> Result<T>
+    where
+        F: Fn() -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        Self {
+            hedge: None,
+            ..self.clone()
+        }
+        .run(start)
+        .await
+    }
+
+    async fn hedged<T, F, Fut>(&self, attempt: F) -> Result<T>
+    where
+        F: Fn() -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        let Some(delay) = self.hedge else {
+            return attempt().await;
+        };
+        let first = attempt();
+        tokio::pin!(first);
+        tokio::select! {
+            result = &mut first => return result,
+            _ = tokio::time::sleep(delay) => {}
+        }
+
+        let second = attempt();
+        tokio::pin!(second);
+        tokio::select! {
+            result = &mut first => match result {
+                Ok(response) => Ok(response),
+                Err(_) => second.await,
+            },
+            result = &mut second => match result {
+                Ok(response) => Ok(response),
+                Err(_) => first.await,
+            },
+        }
+    }
+
+    /// End `stream` with [`Error::Cancelled`] when the token is cancelled.
+    pub(crate) fn stream<T: Send + 'static>(
+        &self,
+        stream: BoxStream<'static, Result<T>>,
+    ) -> BoxStream<'static, Result<T>> {
+        let Some(token) = self.cancel.clone() else {
+            return stream;
+        };
+        async_stream::stream! {
+            let mut stream = stream;
+            loop {
+                tokio::select! {
+                    biased;
+                    _ = token.cancelled() => {
+                        yield Err(Error::Cancelled);
+                        break;
+                    }
+                    item = stream.next() => match item {
+                        Some(item) => yield item,
+                        None => break,
+                    },
+                }
+            }
+        }
+        .boxed()
+    }
+}
@@ -5,19 +5,55 @@
 //! - [`ModelClient`] - Model-specific client with configuration
 //! - [`ChatSession`] - Stateful chat with message history
 
+use crate::auth::Credentials;
+use crate::backend::Backend;
+use crate::batch::BatchClient;
+use crate::budget::Budget;
+use crate::builder::ClientBuilder;
+use crate::cache::{self, CachePolicy, CacheStatus, ResponseCache};
+use crate::call::CallOptions;
+use crate::concurrent::{self, ManyOptions};
+use crate::config;
+use crate::conversation::Conversation;
+use crate::cost::CostTracker;
 use crate::error::{Error, Result};
+use crate::fallback::FallbackPolicy;
+use crate::history::HistoryStrategy;
+use crate::key_pool::{KeyLease, KeyPool, KeySlot};
+use crate::metrics::{CallRecorder, MetricsSink};
+use crate::middleware::{
+    Endpoint, Interceptor, InterceptorLayer, Middleware, ModelRequest, ModelResponse, Next,
+};
 use crate::models::Model;
+use crate::operation::Operation;
+use crate::rate_limit::RateLimiter;
+use crate::store::ChatSnapshot;
+use crate::telemetry::CallSpan;
+use crate::transport::{self, HttpRequest, HttpResponse, ReqwestTransport, Transport};
 use crate::types::{
-    Content, GenerateContentRequest, GenerateContentResponse, GenerationConfig, SafetySetting,
+    Content, CountTokensResponse, GenerateContentRequest, GenerateContentResponse,
+    GenerationConfig, Role, SafetySetting, Tool,
 };
+use futures::stream::{BoxStream, StreamExt};
+use reqwest::header::HeaderMap;
 use reqwest::Client as HttpClient;
+use reqwest::StatusCode;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::Duration;
 
-const BASE_URL: &str = "https://generativelanguage.googleapis.com/v1beta";
+/// A stream of partial responses from
+/// [`ModelClient::stream_generate_content`].
+pub type ResponseStream = BoxStream<'static, Result<GenerateContentResponse>>;
 
 /// Main Gemini API client.
 ///
-/// The `Client` holds your API key and creates model-specific clients.
+/// The `Client` holds your credentials and creates model-specific clients.
 /// It can be cloned efficiently as it shares the underlying HTTP client.
+/// It talks to the Gemini Developer API by default, or to Vertex AI when
+/// created with [`Client::vertex_ai`].
 ///
 /// # Example
 ///
@@ -27,11 +63,24 @@ const BASE_URL: &str = "https://generativelanguage.googleapis.com/v1beta";
 /// let client = Client::new("YOUR_API_KEY");
 /// let model = client.model(Model::Gemini25Flash);
 /// ```
+///
+/// The API key is sent in the `x-goog-api-key` header, never in the URL,
+/// and is redacted from `Debug` output and error messages.
 #[derive(Clone)]
 pub struct Client {
     http_client: HttpClient,
-    api_key: String,
-    base_url: String,
+    transport: Arc<dyn Transport>,
+    backend: Backend,
+    middleware: Vec<Arc<dyn Middleware>>,
+    interceptors: Vec<Arc<dyn Interceptor>>,
+    trace_content: bool,
+    metrics: Vec<Arc<dyn MetricsSink>>,
+    metric_labels: Vec<(String, String)>,
+    cache: Option<Arc<dyn ResponseCache>>,
+    cache_policy: CachePolicy,
+    key_pool: Option<KeyPool>,
+    connect_timeout: Option<Duration>,
+    timeout: Option<Duration>,
 }
 
 impl Client {
@@ -55,13 +104,628 @@ impl Client {
     /// let client = Client::new(std::env::var("GOOGLE_API_KEY").unwrap());
     /// ```
     pub fn new(api_key: impl Into<String>) -> Self {
+        Self::with_backend(Backend::google_ai(api_key))
+    }
+
+    /// Create a client for Vertex AI.
+    ///
+    /// # Arguments
+    ///
+    /// * `project` - Google Cloud project id
+    /// * `location` - Region such as `us-central1`, or `global`
+    /// * `credentials` - OAuth2 credentials, see [`Credentials`]
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use gemini_rs::auth::Credentials;
+    /// use gemini_rs::Client;
+    ///
+    /// # fn example() -> Result<(), gemini_rs::Error> {
+    /// let client = Client::vertex_ai(
+    ///     "my-project",
+    ///     "us-central1",
+    ///     Credentials::application_default()?,
+    /// );
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn vertex_ai(
+        project: impl Into<String>,
+        location: impl Into<String>,
+        credentials: Credentials,
+    ) -> Self {
+        Self::with_backend(Backend::vertex_ai(project, location, credentials))
+    }
+
+    /// Create a client from the standard environment variables.
+    ///
+    /// Uses `GOOGLE_API_KEY` or, if unset, `GEMINI_API_KEY`. When
+    /// `GOOGLE_GENAI_USE_VERTEXAI` is `true` or `1`, uses Vertex AI in
+    /// `GOOGLE_CLOUD_PROJECT` and `GOOGLE_CLOUD_LOCATION` (default
+    /// `us-central1`) with [`Credentials::application_default`]. See
+    /// [`config`](crate::config) for the full list.
+    ///
+    /// The model is chosen with [`model`](Self::model); to read it from
+    /// `GEMINI_MODEL` as well, use
+    /// [`GeminiConfig::model_client`](crate::GeminiConfig::model_client).
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::ConfigError`] naming the missing variable.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use gemini_rs::{Client, Model};
+    ///
+    /// # fn example() -> Result<(), gemini_rs::Error> {
+    /// let client = Client::from_env()?;
+    /// let model = client.model(Model::Gemini25Flash);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn from_env() -> Result<Self> {
+        Ok(Self::with_backend(config::backend_from_env()?))
+    }
+
+    /// Create a client for the given [`Backend`].
+    pub fn with_backend(backend: Backend) -> Self {
+        let http_client = HttpClient::new();
+        let transport = Arc::new(ReqwestTransport::new(http_client.clone()));
+        Self::from_parts(http_client, transport, backend)
+    }
+
+    /// Create a [`ClientBuilder`] to configure timeouts, proxies, headers,
+    /// the API version or the base URL.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use gemini_rs::Client;
+    /// use std::time::Duration;
+    ///
+    /// # fn example() -> Result<(), gemini_rs::Error> {
+    /// let client = Client::builder()
+    ///     .api_key("YOUR_API_KEY")
+    ///     .timeout(Duration::from_secs(30))
+    ///     .build()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn builder() -> ClientBuilder {
+        ClientBuilder::new()
+    }
+
+    pub(crate) fn from_parts(
+        http_client: HttpClient,
+        transport: Arc<dyn Transport>,
+        backend: Backend,
+    ) -> Self {
         Self {
-            http_client: HttpClient::new(),
-            api_key: api_key.into(),
-            base_url: BASE_URL.to_string(),
+            http_client,
+            transport,
+            backend,
+            middleware: Vec::new(),
+            interceptors: Vec::new(),
+            trace_content: false,
+            metrics: Vec::new(),
+            metric_labels: Vec::new(),
+            cache: None,
+            cache_policy: CachePolicy::All,
+            key_pool: None,
+            connect_timeout: None,
+            timeout: None,
+        }
+    }
+
+    /// Add an [`Interceptor`] to every model call made through this client.
+    ///
+    /// Layers run in the order they are added. See the
+    /// [`middleware`](crate::middleware) module.
+    pub fn with_interceptor(mut self, interceptor: impl Interceptor + 'static) -> Self {
+        let interceptor: Arc<dyn Interceptor> = Arc::new(interceptor);
+        self.interceptors.push(interceptor.clone());
+        self.with_middleware(InterceptorLayer(interceptor))
+    }
+
+    /// Add a [`Middleware`] around every model call made through this client.
+    ///
+    /// Layers run in the order they are added; the first one added is the
+    /// outermost. See the [`middleware`](crate::middleware) module.
+    pub fn with_middleware(mut self, middleware: impl Middleware + 'static) -> Self {
+        self.middleware.push(Arc::new(middleware));
+        self
+    }
+
+    /// Get the backend this client talks to.
+    pub fn backend(&self) -> &Backend {
+        &self.backend
+    }
+
+    /// Record prompts and responses in tracing events.
+    ///
+    /// Off by default, since prompts and responses may contain sensitive
+    /// data. See the [`telemetry`](crate::telemetry) module.
+    #[cfg(feature = "tracing")]
+    pub fn with_content_tracing(mut self, enabled: bool) -> Self {
+        self.trace_content = enabled;
+        self
+    }
+
+    /// Enforce `budget` on every call made through this client.
+    ///
+    /// See the [`budget`](crate::budget) module.
+    pub fn with_budget(self, budget: Budget) -> Self {
+        self.with_middleware(budget)
+    }
+
+    /// Limit the requests and tokens per minute of every call made through
+    /// this client and its clones.
+    ///
+    /// See the [`rate_limit`](crate::rate_limit) module.
+    pub fn with_rate_limiter(self, limiter: RateLimiter) -> Self {
+        self.with_middleware(limiter)
+    }
+
+    /// Report every API call made through this client to `sink`.
+    ///
+    /// Every sink added receives every call. See the
+    /// [`metrics`](crate::metrics) module.
+    pub fn with_metrics(mut self, sink: impl MetricsSink + 'static) -> Self {
+        self.metrics.push(Arc::new(sink));
+        self
+    }
+
+    /// Add a label, such as the tenant, to the metrics of every call made
+    /// through this client.
+    pub fn with_metric_label(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.metric_labels.push((key.into(), value.into()));
+        self
+    }
+
+    /// Answer identical `generateContent` calls made through this client
+    /// from `cache`.
+    ///
+    /// The cache is consulted before any middleware, so cache hits do not
+    /// count against budgets or rate limits. Interceptors still run first,
+    /// so the cache is keyed on the request they leave, and their
+    /// `after_response` hooks see cached responses too. See the
+    /// [`cache`](crate::cache) module.
+    pub fn with_cache(mut self, cache: impl ResponseCache + 'static) -> Self {
+        self.cache = Some(Arc::new(cache));
+        self
+    }
+
+    /// Choose which requests are cached; [`CachePolicy::All`] by default.
+    pub fn with_cache_policy(mut self, policy: CachePolicy) -> Self {
+        self.cache_policy = policy;
+        self
+    }
+
+    /// Spread the calls made through this client and its clones over the
+    /// API keys of `pool`, instead of the backend's key.
+    ///
+    /// Gemini Developer API only; Vertex AI clients ignore the pool. See the
+    /// [`key_pool`](crate::key_pool) module.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use gemini_rs::Client;
+    ///
+    /// let client = Client::new("KEY_A").with_key_pool(["KEY_A", "KEY_B", "KEY_C"]);
+    /// ```
+    pub fn with_key_pool(mut self, pool: impl Into<KeyPool>) -> Self {
+        self.key_pool = Some(pool.into());
+        self
+    }
+
+    /// Run a model call through the response cache and the middleware
+    /// chain, then `finish` its response. With a key pool, every error of
+    /// the call, including those of middleware and of `finish`, is tagged
+    /// with the id of the key it last used.
+    ///
+    /// Each request that reaches the API reports its own metrics; a call
+    /// that sends none, because the cache or middleware answered or failed
+    /// it, is reported here.
+    async fn call<T>(
+        &self,
+        request: ModelRequest,
+        finish: impl FnOnce(ModelResponse) -> Result<T>,
+    ) -> Result<T> {
+        let mut recorder = (!self.metrics.is_empty()).then(|| {
+            CallRecorder::start(&self.metrics, &request, &self.metric_labels)
+                .unless_dispatched(request.dispatched.clone())
+        });
+        let key = request.key.clone();
+        let result = match self.model_key_pool().map(KeyPool::acquire).transpose() {
+            Ok(lease) => {
+                if let Some(lease) = lease {
+                    key.assign(lease);
+                }
+                key.tagged(self.run_cached(request).await)
+            }
+            Err(error) => Err(error),
+        };
+        let response = match result {
+            Ok(ModelResponse::Stream(stream)) => {
+                let key = key.clone();
+                let stream = stream.map(move |chunk| key.tagged(chunk)).boxed();
+                match recorder.take() {
+                    Some(recorder) => ModelResponse::Stream(recorder.stream(stream)),
+                    None => ModelResponse::Stream(stream),
+                }
+            }
+            Ok(response) => {
+                if let Some(recorder) = recorder.as_mut() {
+                    match &response {
+                        ModelResponse::Generate(response)
+                            if response.cache_status == Some(CacheStatus::Hit) =>
+                        {
+                            recorder.cache_hit()
+                        }
+                        ModelResponse::Generate(response) => recorder.response(response),
+                        _ => {}
+                    }
+                    recorder.success();
+                }
+                response
+            }
+            Err(error) => {
+                if let Some(recorder) = recorder.as_mut() {
+                    recorder.error(&error);
+                }
+                return Err(error);
+            }
+        };
+        let result = key.tagged(finish(response));
+        if let (Some(recorder), Err(error)) = (recorder.as_mut(), &result) {
+            recorder.error(error);
+        }
+        result
+    }
+
+    /// Answer a model call from the response cache, if it applies, or run
+    /// it through the middleware chain.
+    async fn run_cached(&self, mut request: ModelRequest) -> Result<ModelResponse> {
+        let Some(cache) = self
+            .cache
+            .as_ref()
+            .filter(|_| self.cache_policy.applies(&request))
+        else {
+            return Next::new(self, &self.middleware).run(request).await;
+        };
+
+        // Interceptors run before the lookup, so the cache is keyed on the
+        // request they leave; their layers skip `before_request` afterwards
+        for interceptor in &self.interceptors {
+            interceptor.before_request(&mut request.body)?;
+        }
+        request.intercepted = true;
+
+        let key = cache::cache_key(request.model, &request.body);
+        if let Ok(Some(mut response)) = cache.get(&key) {
+            response.cache_status = Some(CacheStatus::Hit);
+            // In the order of the chain: the first interceptor sees it last
+            for interceptor in self.interceptors.iter().rev() {
+                interceptor.after_response(&response)?;
+            }
+            return Ok(ModelResponse::Generate(response));
+        }
+
+        request.cache = Some(CacheStatus::Miss);
+        match Next::new(self, &self.middleware).run(request).await? {
+            ModelResponse::Generate(mut response) => {
+                if cache::is_cacheable(&response) {
+                    // A failing cache must not fail the call
+                    let _ = cache.put(&key, &response);
+                }
+                response.cache_status = Some(CacheStatus::Miss);
+                Ok(ModelResponse::Generate(response))
+            }
+            other => Ok(other),
+        }
+    }
+
+    /// Send a model call to the API; the end of the middleware chain.
+    pub(crate) async fn dispatch(&self, request: ModelRequest) -> Result<ModelResponse> {
+        request.dispatched.store(true, Ordering::SeqCst);
+        let span = CallSpan::start(&request, &self.backend, self.trace_content);
+        let mut recorder = (!self.metrics.is_empty())
+            .then(|| CallRecorder::start(&self.metrics, &request, &self.metric_labels));
+        match span.instrument(self.execute(request, &span)).await {
+            Ok(ModelResponse::Stream(mut stream)) => {
+                if let Some(recorder) = recorder {
+                    stream = recorder.stream(stream);
+                }
+                Ok(ModelResponse::Stream(span.stream(stream)))
+            }
+            Ok(response) => {
+                if let Some(recorder) = recorder.as_mut() {
+                    if let ModelResponse::Generate(response) = &response {
+                        recorder.response(response);
+                    }
+                    recorder.success();
+                }
+                Ok(response)
+            }
+            Err(error) => {
+                span.error(&error);
+                if let Some(recorder) = recorder.as_mut() {
+                    recorder.error(&error);
+                }
+                Err(error)
+            }
+        }
+    }
+
+    async fn execute(&self, request: ModelRequest, span: &CallSpan) -> Result<ModelResponse> {
+        let ModelRequest {
+            model,
+            endpoint,
+            body,
+            headers,
+            key,
+            ..
+        } = request;
+
+        match endpoint {
+            Endpoint::GenerateContent => {
+                let response: GenerateContentResponse = self
+                    .post(model, endpoint, &body, headers, &key, span)
+                    .await?;
+                span.response(&response);
+                Ok(ModelResponse::Generate(response))
+            }
+            Endpoint::StreamGenerateContent => {
+                let (request, key) = self
+                    .request(model, endpoint, "?alt=sse", &body, headers, &key)
+                    .await?;
+                let response = self
+                    .transport
+                    .send_streaming(request)
+                    .await
+                    .map_err(|error| self.timeout_error(error));
+                let response = KeyLease::tagged(key.as_ref(), response)?;
+                span.status(response.status);
+                if let Some(key) = &key {
+                    key.record(response.status, &response.headers);
+                }
+                if !response.status.is_success() {
+                    let response = response
+                        .collect()
+                        .await
+                        .map_err(|error| self.timeout_error(error));
+                    let response = KeyLease::tagged(key.as_ref(), response)?;
+                    return KeyLease::tagged(key.as_ref(), Err(self.api_error(&response)));
+                }
+
+                let client = self.clone();
+                Ok(ModelResponse::Stream(
+                    transport::sse_data(response.body)
+                        .map(move |data| {
+                            let chunk = data
+                                .map_err(|error| client.timeout_error(error))
+                                .and_then(|data| parse_stream_chunk(&data, &client));
+                            KeyLease::tagged(key.as_ref(), chunk)
+                        })
+                        .boxed(),
+                ))
+            }
+            Endpoint::CountTokens => {
+                let mut request = serde_json::to_value(body)?;
+                let body = if self.backend.is_vertex_ai() {
+                    // Vertex AI takes the prompt at the top level, without
+                    // safety settings
+                    if let Some(request) = request.as_object_mut() {
+                        request.remove("safetySettings");
+                    }
+                    request
+                } else {
+                    request["model"] = model.full_name().into();
+                    serde_json::json!({ "generateContentRequest": request })
+                };
+                let response: CountTokensResponse = self
+                    .post(model, endpoint, &body, headers, &key, span)
+                    .await?;
+                span.count_tokens(&response);
+                Ok(ModelResponse::CountTokens(response))
+            }
+        }
+    }
+
+    /// Build an authorized JSON request for a model method, and the pooled
+    /// key of the call's `slot` it is authorized with.
+    async fn request(
+        &self,
+        model: Model,
+        endpoint: Endpoint,
+        query: &str,
+        body: &impl Serialize,
+        headers: HeaderMap,
+        slot: &KeySlot,
+    ) -> Result<(HttpRequest, Option<KeyLease>)> {
+        let url = format!(
+            "{}{}",
+            self.backend.model_url(model, endpoint.as_str()),
+            query
+        );
+        let mut request = HttpRequest::post_json(url, body)?;
+        request.headers.extend(headers);
+        let key = self.authorize(&mut request.headers, Some(slot)).await?;
+        Ok((request, key))
+    }
+
+    /// The key pool model calls are authorized with; Vertex AI ignores it.
+    fn model_key_pool(&self) -> Option<&KeyPool> {
+        self.key_pool
+            .as_ref()
+            .filter(|_| !self.backend.is_vertex_ai())
+    }
+
+    /// Add credentials to `headers`: a key from the pool, if there is one,
+    /// otherwise the backend's. Model calls take the next key of their
+    /// `slot`; other requests always get the pool's first key, since the
+    /// resources they touch belong to its project.
+    async fn authorize(
+        &self,
+        headers: &mut HeaderMap,
+        slot: Option<&KeySlot>,
+    ) -> Result<Option<KeyLease>> {
+        let Some(pool) = self.model_key_pool() else {
+            self.backend.authorize(headers, &self.http_client).await?;
+            return Ok(None);
+        };
+        let key = match slot {
+            Some(slot) => slot.lease(pool)?,
+            None => pool.first()?,
+        };
+        key.authorize(headers);
+        Ok(Some(key))
+    }
+
+    /// Send `request`, recording the response against the pooled `key` it
+    /// is authorized with, if any.
+    async fn send(&self, request: HttpRequest, key: Option<&KeyLease>) -> Result<HttpResponse> {
+        let response = self
+            .transport
+            .send(request)
+            .await
+            .map_err(|error| self.timeout_error(error));
+        let response = KeyLease::tagged(key, response)?;
+        if let Some(key) = key {
+            key.record(response.status, &response.headers);
+        }
+        Ok(response)
+    }
+
+    /// POST `body` to `models/{model}:{endpoint}` and parse the JSON reply.
+    async fn post<T: DeserializeOwned>(
+        &self,
+        model: Model,
+        endpoint: Endpoint,
+        body: &impl Serialize,
+        headers: HeaderMap,
+        slot: &KeySlot,
+        span: &CallSpan,
+    ) -> Result<T> {
+        let (request, key) = self
+            .request(model, endpoint, "", body, headers, slot)
+            .await?;
+        let response = self.send(request, key.as_ref()).await?;
+        span.status(response.status);
+        let result = if response.status.is_success() {
+            serde_json::from_slice(&response.body).map_err(Error::from)
+        } else {
+            Err(self.api_error(&response))
+        };
+        KeyLease::tagged(key.as_ref(), result)
+    }
+
+    /// Build the error for a non-success response:
+    /// [`Error::RateLimitExceeded`] for HTTP 429, [`Error::ApiError`]
+    /// otherwise.
+    fn api_error(&self, response: &HttpResponse) -> Error {
+        let text = self.redact(&response.text());
+        if response.status == StatusCode::TOO_MANY_REQUESTS {
+            let body: Option<serde_json::Value> = serde_json::from_str(&text).ok();
+            let error = body.as_ref().map(|body| &body["error"]);
+            let message = error
+                .and_then(|error| error["message"].as_str())
+                .map(str::to_string)
+                .or_else(|| Some(text.trim().to_string()).filter(|text| !text.is_empty()));
+            return Error::RateLimitExceeded {
+                retry_after: error
+                    .and_then(retry_delay)
+                    .or_else(|| transport::retry_after(&response.headers)),
+                message,
+            };
+        }
+        Error::ApiError {
+            message: format!("HTTP {}: {}", response.status, text),
+            code: Some(response.status.as_u16() as i32),
+        }
+    }
+
+    /// Send a request that is not a model call, such as a batch or file
+    /// request. It skips the middleware chain, tracing and metrics.
+    pub(crate) async fn send_raw(&self, mut request: HttpRequest) -> Result<HttpResponse> {
+        let key = self.authorize(&mut request.headers, None).await?;
+        let response = self.send(request, key.as_ref()).await?;
+        if !response.status.is_success() {
+            return KeyLease::tagged(key.as_ref(), Err(self.api_error(&response)));
+        }
+        Ok(response)
+    }
+
+    /// Report a timeout of the HTTP client as [`Error::Timeout`].
+    fn timeout_error(&self, error: Error) -> Error {
+        match error {
+            Error::HttpError(err) if err.is_timeout() => {
+                let timeout = if err.is_connect() {
+                    self.connect_timeout.or(self.timeout)
+                } else {
+                    self.timeout
+                };
+                match timeout {
+                    Some(timeout) => Error::Timeout(timeout),
+                    None => Error::HttpError(err),
+                }
+            }
+            error => error,
+        }
+    }
+
+    /// Set the timeouts the HTTP client was built with.
+    pub(crate) fn set_timeouts(&mut self, connect: Option<Duration>, total: Option<Duration>) {
+        self.connect_timeout = connect;
+        self.timeout = total;
+    }
+
+    /// Replace every API key of this client in `text`.
+    ///
+    /// Used on error bodies before they are surfaced, in case the server
+    /// echoes the request back.
+    fn redact(&self, text: &str) -> String {
+        let text = self.backend.redact(text);
+        match &self.key_pool {
+            Some(pool) => pool.redact(&text),
+            None => text,
         }
     }
 
+    /// Track the long-running [operation](crate::operation) `name`, e.g.
+    /// `batches/123`, whose response parses as `T` and metadata as `M`.
+    ///
+    /// The operation is fetched on the first
+    /// [`poll`](Operation::poll) or [`wait`](Operation::wait).
+    pub fn operation<T, M>(&self, name: impl Into<String>) -> Operation<T, M>
+    where
+        T: DeserializeOwned,
+        M: DeserializeOwned,
+    {
+        Operation::new(self.clone(), name)
+    }
+
+    /// Continue a deserialized [operation](crate::operation) with this
+    /// client's credentials.
+    pub fn resume_operation<T, M>(&self, operation: Operation<T, M>) -> Operation<T, M>
+    where
+        T: DeserializeOwned,
+        M: DeserializeOwned,
+    {
+        operation.attach(self.clone())
+    }
+
+    /// Get a client for [Batch API](crate::batch) jobs.
+    ///
+    /// Batch jobs are only available on the Gemini Developer API; on
+    /// Vertex AI every call fails with [`Error::ConfigError`].
+    pub fn batches(&self) -> BatchClient {
+        BatchClient::new(self.clone())
+    }
+
     /// Get a model-specific client for the specified model.
     ///
     /// The returned [`ModelClient`] can be configured with generation settings,
@@ -91,10 +755,26 @@ impl Client {
             generation_config: None,
             safety_settings: None,
             system_instruction: None,
+            tools: None,
+            fallbacks: Vec::new(),
+            fallback_policy: FallbackPolicy::new(),
+            call_options: CallOptions::new(),
         }
     }
 }
 
+impl std::fmt::Debug for Client {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Client")
+            .field("http_client", &self.http_client)
+            .field("backend", &self.backend)
+            .field("middleware", &self.middleware.len())
+            .field("metric_labels", &self.metric_labels)
+            .field("key_pool", &self.key_pool)
+            .finish_non_exhaustive()
+    }
+}
+
 /// Model-specific client with configuration.
 ///
 /// `ModelClient` wraps a specific Gemini model and allows you to:
@@ -120,12 +800,17 @@ impl Client {
 /// # Ok(())
 /// # }
 /// ```
+#[derive(Debug)]
 pub struct ModelClient {
     client: Client,
     model: Model,
     generation_config: Option<GenerationConfig>,
     safety_settings: Option<Vec<SafetySetting>>,
     system_instruction: Option<Content>,
+    tools: Option<Vec<Tool>>,
+    fallbacks: Vec<Model>,
+    fallback_policy: FallbackPolicy,
+    call_options: CallOptions,
 }
 
 impl ModelClient {
@@ -186,6 +871,107 @@ impl ModelClient {
         self
     }
 
+    /// Set the tools (function declarations) the model may call.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use gemini_rs::{Client, Model};
+    /// use gemini_rs::types::{FunctionDeclaration, Tool};
+    ///
+    /// let client = Client::new("YOUR_API_KEY");
+    /// let model = client
+    ///     .model(Model::Gemini25Flash)
+    ///     .with_tools(vec![Tool::functions(vec![FunctionDeclaration::new(
+    ///         "get_time",
+    ///         "Get the current time",
+    ///     )])]);
+    /// ```
+    pub fn with_tools(mut self, tools: Vec<Tool>) -> Self {
+        self.tools = Some(tools);
+        self
+    }
+
+    /// Try `models`, in order, when a call to this client's model fails.
+    ///
+    /// Applies to generation, JSON, streaming and chat calls, not to
+    /// [`count_tokens`](Self::count_tokens). What counts as a failure is
+    /// set with [`with_fallback_policy`](Self::with_fallback_policy); by
+    /// default any retryable error. See the [`fallback`](crate::fallback)
+    /// module.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use gemini_rs::{Client, Model};
+    ///
+    /// # async fn example() -> Result<(), gemini_rs::Error> {
+    /// let model = Client::new("YOUR_API_KEY")
+    ///     .model(Model::Gemini25Flash)
+    ///     .with_fallbacks([Model::Gemini20Flash, Model::Gemini15Flash]);
+    ///
+    /// let response = model.generate_content("Hello").await?;
+    /// assert!(response.answered_by.is_some());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_fallbacks(mut self, models: impl IntoIterator<Item = Model>) -> Self {
+        self.fallbacks = models.into_iter().collect();
+        self
+    }
+
+    /// Set when to move on to the next of the
+    /// [fallback models](Self::with_fallbacks).
+    pub fn with_fallback_policy(mut self, policy: FallbackPolicy) -> Self {
+        self.fallback_policy = policy;
+        self
+    }
+
+    /// Apply a timeout, cancellation token or hedging to the calls of this
+    /// client. See the [`call`](crate::call) module.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use gemini_rs::call::CallOptions;
+    /// use gemini_rs::{Client, Model};
+    /// use std::time::Duration;
+    ///
+    /// # async fn example() -> Result<(), gemini_rs::Error> {
+    /// let model = Client::new("YOUR_API_KEY").model(Model::Gemini25Flash);
+    ///
+    /// // This call only
+    /// let response = model
+    ///     .clone()
+    ///     .with_call_options(CallOptions::new().timeout(Duration::from_secs(10)))
+    ///     .generate_content("Hello")
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_call_options(mut self, options: CallOptions) -> Self {
+        self.call_options = options;
+        self
+    }
+
+    /// Answer identical `generateContent` calls made through this model
+    /// client, and the chat sessions it starts, from `cache`.
+    ///
+    /// See the [`cache`](crate::cache) module and [`Client::with_cache`].
+    pub fn with_cache(mut self, cache: impl ResponseCache + 'static) -> Self {
+        self.client = self.client.with_cache(cache);
+        self
+    }
+
+    /// Enforce `budget` on every call made through this model client and
+    /// the chat sessions it starts.
+    ///
+    /// See the [`budget`](crate::budget) module.
+    pub fn with_budget(mut self, budget: Budget) -> Self {
+        self.client = self.client.with_budget(budget);
+        self
+    }
+
     /// Generate content from a text prompt.
     ///
     /// This is the primary method for simple text generation.
@@ -224,6 +1010,8 @@ impl ModelClient {
     /// Generate content from multiple content parts.
     ///
     /// Use this for multi-turn conversations or multimodal content.
+    /// The turns are checked with [`Conversation::validate`] before
+    /// anything is sent.
     ///
     /// # Arguments
     ///
@@ -232,48 +1020,200 @@ impl ModelClient {
     /// # Returns
     ///
     /// A [`GenerateContentResponse`] containing the model's output.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidInput`] if the turns do not form a valid
+    /// conversation.
     pub async fn generate_content_from_parts(
         &self,
         contents: Vec<Content>,
     ) -> Result<GenerateContentResponse> {
-        let url = format!(
-            "{}/models/{}:generateContent?key={}",
-            self.client.base_url,
-            self.model.as_str(),
-            self.client.api_key
-        );
+        self.send_request(self.build_request(contents)).await
+    }
 
-        let request = GenerateContentRequest {
-            contents,
-            generation_config: self.generation_config.clone(),
-            safety_settings: self.safety_settings.clone(),
-            system_instruction: self.system_instruction.clone(),
-        };
+    /// Send a fully built request to this client's model.
+    ///
+    /// The request is sent as is: this client's generation config, safety
+    /// settings, system instruction and tools only apply if they were
+    /// copied into it, e.g. with [`build_request`](ModelClient::build_request).
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidInput`] if the turns do not form a valid
+    /// conversation.
+    pub async fn send_request(
+        &self,
+        request: GenerateContentRequest,
+    ) -> Result<GenerateContentResponse> {
+        self.send_attempt(request, 0).await
+    }
 
-        let response = self
-            .client
-            .http_client
-            .post(&url)
-            .json(&request)
-            .send()
-            .await?;
+    /// [`send_request`](Self::send_request) for retry `attempt`.
+    pub(crate) async fn send_attempt(
+        &self,
+        request: GenerateContentRequest,
+        attempt: u32,
+    ) -> Result<GenerateContentResponse> {
+        Conversation::new(&request.contents).validate()?;
 
-        if !response.status().is_success() {
-            let status = response.status();
-            let error_text = response.text().await.unwrap_or_default();
-            return Err(Error::ApiError {
-                message: format!("HTTP {}: {}", status, error_text),
-                code: Some(status.as_u16() as i32),
-            });
-        }
+        self.call_options
+            .run(|| async {
+                if self.fallbacks.is_empty() {
+                    return self
+                        .generate_with(self.model, request.clone(), attempt)
+                        .await;
+                }
+                self.fallback_policy
+                    .run(
+                        self.model,
+                        &self.fallbacks,
+                        |model| self.generate_with(model, request.clone(), attempt),
+                        FallbackPolicy::falls_back,
+                    )
+                    .await
+            })
+            .await
+    }
 
-        let gemini_response: GenerateContentResponse = response.json().await?;
+    /// Send `request` to `model`, without fallbacks.
+    async fn generate_with(
+        &self,
+        model: Model,
+        request: GenerateContentRequest,
+        attempt: u32,
+    ) -> Result<GenerateContentResponse> {
+        let mut model_request = ModelRequest::new(model, Endpoint::GenerateContent, request);
+        model_request.attempt = attempt;
+        self.client
+            .call(model_request, |response| {
+                let mut gemini_response = response.into_generate()?;
+                if gemini_response.candidates.is_none() {
+                    return Err(Error::NoResponse);
+                }
+                gemini_response.answered_by = Some(model);
+                Ok(gemini_response)
+            })
+            .await
+    }
 
-        if gemini_response.candidates.is_none() {
-            return Err(Error::NoResponse);
-        }
+    /// Stream content for a text prompt as it is generated.
+    ///
+    /// Each item is a partial response holding the next chunk of text.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use futures::StreamExt;
+    /// use gemini_rs::{Client, Model};
+    ///
+    /// # async fn example() -> Result<(), gemini_rs::Error> {
+    /// let client = Client::new("YOUR_API_KEY");
+    /// let model = client.model(Model::Gemini25Flash);
+    ///
+    /// let mut stream = model.stream_generate_content("Tell me a story").await?;
+    /// while let Some(chunk) = stream.next().await {
+    ///     print!("{}", chunk?.text());
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn stream_generate_content(
+        &self,
+        prompt: impl Into<String>,
+    ) -> Result<ResponseStream> {
+        self.stream_generate_content_from_parts(vec![Content::text(prompt)])
+            .await
+    }
+
+    /// Stream content for multiple content parts as it is generated.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidInput`] if the turns do not form a valid
+    /// conversation, and [`Error::ApiError`] if the request is rejected.
+    /// Errors while streaming are yielded by the stream.
+    pub async fn stream_generate_content_from_parts(
+        &self,
+        contents: Vec<Content>,
+    ) -> Result<ResponseStream> {
+        Conversation::new(&contents).validate()?;
+
+        let request = self.build_request(contents);
+        let stream = |model: Model| {
+            let request =
+                ModelRequest::new(model, Endpoint::StreamGenerateContent, request.clone());
+            async move {
+                let stream = self
+                    .client
+                    .call(request, ModelResponse::into_stream)
+                    .await?;
+                Ok(stream
+                    .map(move |chunk| {
+                        chunk.map(|mut chunk| {
+                            chunk.answered_by = Some(model);
+                            chunk
+                        })
+                    })
+                    .boxed())
+            }
+        };
+        let stream = self
+            .call_options
+            .start(|| {
+                self.fallback_policy.run(
+                    self.model,
+                    &self.fallbacks,
+                    stream,
+                    |policy, result: &Result<ResponseStream>| {
+                        matches!(result, Err(error) if policy.falls_back_on_error(error))
+                    },
+                )
+            })
+            .await?;
+        Ok(self.call_options.stream(stream))
+    }
+
+    /// Count the tokens the given contents would use as a prompt.
+    ///
+    /// The count includes this client's system instruction and tools.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use gemini_rs::{Client, Content, Model};
+    ///
+    /// # async fn example() -> Result<(), gemini_rs::Error> {
+    /// let client = Client::new("YOUR_API_KEY");
+    /// let model = client.model(Model::Gemini25Flash);
+    ///
+    /// let count = model.count_tokens(vec![Content::text("Hello!")]).await?;
+    /// println!("{} tokens", count.total_tokens);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn count_tokens(&self, contents: Vec<Content>) -> Result<CountTokensResponse> {
+        let request = self.build_request(contents);
+        self.call_options
+            .run(|| async {
+                let request = ModelRequest::new(self.model, Endpoint::CountTokens, request.clone());
+                self.client
+                    .call(request, ModelResponse::into_count_tokens)
+                    .await
+            })
+            .await
+    }
 
-        Ok(gemini_response)
+    /// Build the request [`generate_content_from_parts`](ModelClient::generate_content_from_parts)
+    /// would send: `contents` plus this client's configuration.
+    pub fn build_request(&self, contents: Vec<Content>) -> GenerateContentRequest {
+        GenerateContentRequest {
+            contents,
+            generation_config: self.generation_config.clone(),
+            safety_settings: self.safety_settings.clone(),
+            system_instruction: self.system_instruction.clone(),
+            tools: self.tools.clone(),
+        }
     }
 
     /// Generate structured JSON output and deserialize into a type.
@@ -321,29 +1261,115 @@ impl ModelClient {
         &self,
         prompt: impl Into<String>,
     ) -> Result<T> {
+        let response = self.json_mode().generate_content(prompt).await?;
+        parse_json(&response)
+    }
+
+    /// This client with JSON mode enabled.
+    fn json_mode(&self) -> ModelClient {
         let config = self
             .generation_config
             .clone()
             .unwrap_or_default()
             .json_mode();
 
-        let model_with_json = ModelClient {
+        ModelClient {
             generation_config: Some(config),
             ..self.clone()
-        };
+        }
+    }
+
+    /// Generate content for many prompts, running up to
+    /// `options.concurrency()` calls at once.
+    ///
+    /// Returns one result per prompt, in input order. Retryable errors are
+    /// retried per [`ManyOptions`]; other errors only fail their prompt.
+    /// Pass a `usize` for the concurrency with default options. See the
+    /// [`concurrent`](crate::concurrent) module.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use gemini_rs::{Client, Model};
+    ///
+    /// # async fn example() {
+    /// let model = Client::new("YOUR_API_KEY").model(Model::Gemini25Flash);
+    /// let results = model.generate_many(vec!["Hello", "Bonjour", "Hola"], 2).await;
+    /// for result in results {
+    ///     println!("{:?}", result.map(|response| response.text()));
+    /// }
+    /// # }
+    /// ```
+    pub async fn generate_many(
+        &self,
+        prompts: impl IntoIterator<Item = impl Into<String>>,
+        options: impl Into<ManyOptions>,
+    ) -> Vec<Result<GenerateContentResponse>> {
+        let prompts: Vec<String> = prompts.into_iter().map(Into::into).collect();
+        let len = prompts.len();
+        concurrent::in_order(self.run_many(prompts, options.into()), len).await
+    }
 
-        let response = model_with_json.generate_content(prompt).await?;
-        let text = response.text();
+    /// Like [`generate_many`](Self::generate_many), but yields
+    /// `(index, result)` pairs in completion order.
+    pub fn generate_many_stream(
+        &self,
+        prompts: impl IntoIterator<Item = impl Into<String>>,
+        options: impl Into<ManyOptions>,
+    ) -> BoxStream<'static, (usize, Result<GenerateContentResponse>)> {
+        let prompts = prompts.into_iter().map(Into::into).collect();
+        self.run_many(prompts, options.into())
+    }
 
-        // Clean up markdown code blocks if present
-        let json_text = text
-            .trim()
-            .trim_start_matches("```json")
-            .trim_start_matches("```")
-            .trim_end_matches("```")
-            .trim();
+    /// Like [`generate_many`](Self::generate_many), but parses each
+    /// response as JSON as [`generate_json`](Self::generate_json) does.
+    pub async fn generate_json_many<T>(
+        &self,
+        prompts: impl IntoIterator<Item = impl Into<String>>,
+        options: impl Into<ManyOptions>,
+    ) -> Vec<Result<T>>
+    where
+        T: serde::de::DeserializeOwned + Send + 'static,
+    {
+        let prompts: Vec<String> = prompts.into_iter().map(Into::into).collect();
+        let len = prompts.len();
+        concurrent::in_order(self.generate_json_many_stream(prompts, options), len).await
+    }
+
+    /// Like [`generate_json_many`](Self::generate_json_many), but yields
+    /// `(index, result)` pairs in completion order.
+    pub fn generate_json_many_stream<T>(
+        &self,
+        prompts: impl IntoIterator<Item = impl Into<String>>,
+        options: impl Into<ManyOptions>,
+    ) -> BoxStream<'static, (usize, Result<T>)>
+    where
+        T: serde::de::DeserializeOwned + Send + 'static,
+    {
+        let prompts = prompts.into_iter().map(Into::into).collect();
+        let model = self.json_mode();
+        concurrent::run(prompts, options.into(), move |prompt: String, attempt| {
+            let model = model.clone();
+            async move {
+                let request = model.build_request(vec![Content::text(prompt)]);
+                parse_json(&model.send_attempt(request, attempt).await?)
+            }
+        })
+    }
 
-        serde_json::from_str(json_text).map_err(|e| Error::GenerationFailed(e.to_string()))
+    fn run_many(
+        &self,
+        prompts: Vec<String>,
+        options: ManyOptions,
+    ) -> BoxStream<'static, (usize, Result<GenerateContentResponse>)> {
+        let model = self.clone();
+        concurrent::run(prompts, options, move |prompt: String, attempt| {
+            let model = model.clone();
+            async move {
+                let request = model.build_request(vec![Content::text(prompt)]);
+                model.send_attempt(request, attempt).await
+            }
+        })
     }
 
     /// Start a new chat session.
@@ -376,11 +1402,71 @@ impl ModelClient {
     /// # }
     /// ```
     pub fn start_chat(&self) -> ChatSession {
+        self.start_chat_with_history(Vec::new())
+    }
+
+    /// Start a chat session that continues from an existing history.
+    ///
+    /// The history is validated when the next message is sent, so it
+    /// should alternate between user and model turns.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use gemini_rs::{Client, Content, Model};
+    ///
+    /// let client = Client::new("YOUR_API_KEY");
+    /// let model = client.model(Model::Gemini25Flash);
+    ///
+    /// let chat = model.start_chat_with_history(vec![
+    ///     Content::user("My name is Alice"),
+    ///     Content::model("Nice to meet you, Alice!"),
+    /// ]);
+    /// assert_eq!(chat.history().len(), 2);
+    /// ```
+    pub fn start_chat_with_history(&self, history: Vec<Content>) -> ChatSession {
         ChatSession {
             model: self.clone(),
-            history: Vec::new(),
+            history,
+            pending: None,
+            strategy: None,
+            cost_tracker: None,
         }
     }
+
+    /// Resume a chat session from a [`ChatSnapshot`].
+    ///
+    /// The model, configuration, safety settings, system instruction, tools
+    /// and history all come from the snapshot; only the credentials of this
+    /// client are reused.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use gemini_rs::{ChatSnapshot, Client, Model};
+    ///
+    /// # fn example(json: &str) -> Result<(), gemini_rs::Error> {
+    /// let client = Client::new("YOUR_API_KEY");
+    /// let snapshot: ChatSnapshot = serde_json::from_str(json)?;
+    ///
+    /// let chat = client.model(Model::default()).resume_chat(snapshot);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn resume_chat(&self, snapshot: ChatSnapshot) -> ChatSession {
+        let model = ModelClient {
+            client: self.client.clone(),
+            model: snapshot.model,
+            generation_config: snapshot.generation_config,
+            safety_settings: snapshot.safety_settings,
+            system_instruction: snapshot.system_instruction,
+            tools: snapshot.tools,
+            fallbacks: self.fallbacks.clone(),
+            fallback_policy: self.fallback_policy.clone(),
+            call_options: self.call_options.clone(),
+        };
+        model.start_chat_with_history(snapshot.history)
+    }
 }
 
 impl Clone for ModelClient {
@@ -391,6 +1477,10 @@ impl Clone for ModelClient {
             generation_config: self.generation_config.clone(),
             safety_settings: self.safety_settings.clone(),
             system_instruction: self.system_instruction.clone(),
+            tools: self.tools.clone(),
+            fallbacks: self.fallbacks.clone(),
+            fallback_policy: self.fallback_policy.clone(),
+            call_options: self.call_options.clone(),
         }
     }
 }
@@ -401,6 +1491,10 @@ impl Clone for ModelClient {
 /// messages with each new request. This allows the model to maintain context
 /// across multiple turns.
 ///
+/// Every operation is transactional: the history only changes once the
+/// model has answered. A failed call leaves the history untouched and can
+/// be repeated with [`retry_last`](ChatSession::retry_last).
+///
 /// # Example
 ///
 /// ```rust,no_run
@@ -425,13 +1519,93 @@ impl Clone for ModelClient {
 pub struct ChatSession {
     model: ModelClient,
     history: Vec<Content>,
+    /// The message of the last failed call, and the history it followed.
+    pending: Option<(usize, Content)>,
+    strategy: Option<Arc<dyn HistoryStrategy>>,
+    cost_tracker: Option<CostTracker>,
+}
+
+impl std::fmt::Debug for ChatSession {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ChatSession")
+            .field("model", &self.model)
+            .field("history", &self.history)
+            .field("pending", &self.pending)
+            .finish_non_exhaustive()
+    }
 }
 
 impl ChatSession {
+    /// Compact the history with `strategy` before every send.
+    ///
+    /// The compacted turns replace the session's history once the model
+    /// answers. See the [`history`](crate::history) module for the
+    /// available strategies.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use gemini_rs::{Client, Model};
+    /// use gemini_rs::history::LastTurns;
+    ///
+    /// let client = Client::new("YOUR_API_KEY");
+    /// let chat = client
+    ///     .model(Model::Gemini25Flash)
+    ///     .start_chat()
+    ///     .with_history_strategy(LastTurns::new(20));
+    /// ```
+    pub fn with_history_strategy(mut self, strategy: impl HistoryStrategy + 'static) -> Self {
+        self.strategy = Some(Arc::new(strategy));
+        self
+    }
+
+    /// Enforce `budget` on every message sent in this session.
+    ///
+    /// See the [`budget`](crate::budget) module.
+    pub fn with_budget(mut self, budget: Budget) -> Self {
+        self.model = self.model.with_budget(budget);
+        self
+    }
+
+    /// Add up the cost of every call of this session in `tracker`,
+    /// including those of its history strategy.
+    ///
+    /// See the [`cost`](crate::cost) module.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use gemini_rs::cost::CostTracker;
+    /// use gemini_rs::{Client, Model};
+    ///
+    /// # async fn example() -> Result<(), gemini_rs::Error> {
+    /// let model = Client::new("YOUR_API_KEY").model(Model::Gemini25Flash);
+    /// let mut chat = model.start_chat().with_cost_tracker(CostTracker::new());
+    ///
+    /// chat.send_message("Hello!").await?;
+    /// if let Some(tracker) = chat.cost_tracker() {
+    ///     println!("session: ${:.6}", tracker.total().total());
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_cost_tracker(mut self, tracker: CostTracker) -> Self {
+        self.model.client = self.model.client.clone().with_metrics(tracker.clone());
+        self.cost_tracker = Some(tracker);
+        self
+    }
+
+    /// The tracker set with [`with_cost_tracker`](Self::with_cost_tracker).
+    pub fn cost_tracker(&self) -> Option<&CostTracker> {
+        self.cost_tracker.as_ref()
+    }
+
     /// Send a message in the chat session.
     ///
-    /// The message is added to history, sent along with all previous messages,
-    /// and the model's response is also added to history.
+    /// The message is sent along with all previous messages. Once the model
+    /// answers, both the message and the model's reply are added to history.
+    /// If the call fails, or the response carries no content (for example
+    /// because it was blocked), the history is left unchanged.
     ///
     /// # Arguments
     ///
@@ -460,24 +1634,164 @@ impl ChatSession {
         &mut self,
         message: impl Into<String>,
     ) -> Result<GenerateContentResponse> {
-        let user_content = Content::user(message);
-        self.history.push(user_content.clone());
+        self.send(self.history.len(), Content::user(message)).await
+    }
 
-        let response = self
-            .model
-            .generate_content_from_parts(self.history.clone())
-            .await?;
+    /// Resend the last message whose call failed.
+    ///
+    /// The message is sent at the place it was meant for, so a failed
+    /// [`regenerate`](Self::regenerate) or
+    /// [`edit_last_user_message`](Self::edit_last_user_message) is retried
+    /// as such.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidInput`] if no message is awaiting a retry.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use gemini_rs::{Client, Model};
+    ///
+    /// # async fn example() -> Result<(), gemini_rs::Error> {
+    /// let client = Client::new("YOUR_API_KEY");
+    /// let mut chat = client.model(Model::Gemini25Flash).start_chat();
+    ///
+    /// let response = match chat.send_message("Hello!").await {
+    ///     Ok(response) => response,
+    ///     Err(_) => chat.retry_last().await?,
+    /// };
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn retry_last(&mut self) -> Result<GenerateContentResponse> {
+        let (keep, content) = self
+            .pending
+            .clone()
+            .ok_or_else(|| Error::InvalidInput("no failed message to retry".to_string()))?;
+        self.send(keep, content).await
+    }
 
-        // Add model response to history
-        if let Some(candidate) = response.candidates.as_ref().and_then(|c| c.first()) {
-            if let Some(content) = &candidate.content {
-                self.history.push(content.clone());
+    /// Ask the model for a new answer to the last user message.
+    ///
+    /// The last model turn is replaced once the new answer arrives.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidInput`] if the history does not end with a
+    /// user message followed by a model reply.
+    pub async fn regenerate(&mut self) -> Result<GenerateContentResponse> {
+        let index = self.last_index_of(Role::User).filter(|&index| {
+            index + 2 == self.history.len() && self.history[index + 1].role == Some(Role::Model)
+        });
+        let index = index.ok_or_else(|| {
+            Error::InvalidInput("history does not end with a model reply to regenerate".to_string())
+        })?;
+        let content = self.history[index].clone();
+        self.send(index, content).await
+    }
+
+    /// Replace the text of the last user message and ask the model again.
+    ///
+    /// Everything after the last user message is discarded once the new
+    /// answer arrives.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidInput`] if the history has no user message.
+    pub async fn edit_last_user_message(
+        &mut self,
+        message: impl Into<String>,
+    ) -> Result<GenerateContentResponse> {
+        let index = self.last_index_of(Role::User).ok_or_else(|| {
+            Error::InvalidInput("history has no user message to edit".to_string())
+        })?;
+        self.send(index, Content::user(message)).await
+    }
+
+    /// Remove the last exchange from history.
+    ///
+    /// Drops the last user message and everything that followed it, and
+    /// returns the removed turns (empty if there was no user message).
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use gemini_rs::{Client, Content, Model};
+    ///
+    /// let client = Client::new("YOUR_API_KEY");
+    /// let mut chat = client.model(Model::Gemini25Flash).start_chat_with_history(vec![
+    ///     Content::user("Hi"),
+    ///     Content::model("Hello!"),
+    /// ]);
+    ///
+    /// let removed = chat.undo();
+    /// assert_eq!(removed.len(), 2);
+    /// assert!(chat.history().is_empty());
+    /// ```
+    pub fn undo(&mut self) -> Vec<Content> {
+        self.pending = None;
+        match self.last_index_of(Role::User) {
+            Some(index) => self.history.split_off(index),
+            None => Vec::new(),
+        }
+    }
+
+    /// Send `user_content` after `history[..keep]` and, on success, commit
+    /// the (compacted) turns sent and the model's reply as the new history.
+    async fn send(
+        &mut self,
+        keep: usize,
+        user_content: Content,
+    ) -> Result<GenerateContentResponse> {
+        let mut contents = self.history[..keep].to_vec();
+        contents.push(user_content.clone());
+
+        let result = match self.strategy.clone() {
+            Some(strategy) => strategy.compact(contents, &self.model).await,
+            None => Ok(contents),
+        };
+        let result = match result {
+            Ok(contents) => self
+                .model
+                .generate_content_from_parts(contents.clone())
+                .await
+                .map(|response| (contents, response)),
+            Err(e) => Err(e),
+        };
+        let (mut contents, response) = match result {
+            Ok(sent) => sent,
+            Err(e) => {
+                self.pending = Some((keep, user_content));
+                return Err(e);
             }
+        };
+
+        let reply = response
+            .candidates
+            .as_ref()
+            .and_then(|c| c.first())
+            .and_then(|candidate| candidate.content.clone());
+
+        match reply {
+            Some(mut reply) => {
+                reply.role.get_or_insert(Role::Model);
+                contents.push(reply);
+                self.history = contents;
+                self.pending = None;
+            }
+            None => self.pending = Some((keep, user_content)),
         }
 
         Ok(response)
     }
 
+    fn last_index_of(&self, role: Role) -> Option<usize> {
+        self.history
+            .iter()
+            .rposition(|content| content.role == Some(role))
+    }
+
     /// Get the current chat history.
     ///
     /// Returns a slice of all messages (user and model) in order.
@@ -524,5 +1838,88 @@ impl ChatSession {
     /// ```
     pub fn clear_history(&mut self) {
         self.history.clear();
+        self.pending = None;
     }
+
+    /// Capture the session's model, configuration and history.
+    ///
+    /// The snapshot can be serialized, stored (see [`ChatStore`](crate::store::ChatStore))
+    /// and later passed to [`ModelClient::resume_chat`]. Credentials are not
+    /// part of the snapshot.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use gemini_rs::{Client, Model};
+    ///
+    /// # async fn example() -> Result<(), gemini_rs::Error> {
+    /// let client = Client::new("YOUR_API_KEY");
+    /// let mut chat = client.model(Model::Gemini25Flash).start_chat();
+    /// chat.send_message("Hello").await?;
+    ///
+    /// let json = serde_json::to_string(&chat.snapshot())?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn snapshot(&self) -> ChatSnapshot {
+        ChatSnapshot {
+            model: self.model.model,
+            generation_config: self.model.generation_config.clone(),
+            safety_settings: self.model.safety_settings.clone(),
+            system_instruction: self.model.system_instruction.clone(),
+            tools: self.model.tools.clone(),
+            history: self.history.clone(),
+        }
+    }
+}
+
+/// Parse the text of a JSON-mode response, ignoring Markdown code fences.
+fn parse_json<T: DeserializeOwned>(response: &GenerateContentResponse) -> Result<T> {
+    let text = response.text();
+
+    // Clean up markdown code blocks if present
+    let json_text = text
+        .trim()
+        .trim_start_matches("```json")
+        .trim_start_matches("```")
+        .trim_end_matches("```")
+        .trim();
+
+    serde_json::from_str(json_text).map_err(|e| Error::GenerationFailed(e.to_string()))
+}
+
+/// Parse one server-sent event of a streamed response.
+fn parse_stream_chunk(data: &str, client: &Client) -> Result<GenerateContentResponse> {
+    let value: serde_json::Value = serde_json::from_str(data)?;
+    // Errors after the stream has started arrive as an event
+    if let Some(error) = value.get("error") {
+        return Err(Error::ApiError {
+            message: client.redact(
+                error
+                    .get("message")
+                    .and_then(|message| message.as_str())
+                    .unwrap_or(data),
+            ),
+            code: error
+                .get("code")
+                .and_then(|code| code.as_i64())
+                .map(|code| code as i32),
+        });
+    }
+    Ok(serde_json::from_value(value)?)
+}
+
+/// The delay of the `RetryInfo` detail of an API error, e.g. `"28s"`.
+fn retry_delay(error: &serde_json::Value) -> Option<Duration> {
+    let retry_info = error["details"].as_array()?.iter().find(|detail| {
+        detail["@type"]
+            .as_str()
+            .is_some_and(|kind| kind.ends_with("google.rpc.RetryInfo"))
+    })?;
+    let seconds: f64 = retry_info["retryDelay"]
+        .as_str()?
+        .strip_suffix('s')?
+        .parse()
+        .ok()?;
+    Duration::try_from_secs_f64(seconds).ok()
 }
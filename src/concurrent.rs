@@ -0,0 +1,253 @@
+//! Bounded-concurrency generation over many prompts.
+//!
+//! [`ModelClient::generate_many`](crate::ModelClient::generate_many) and
+//! [`ModelClient::generate_json_many`](crate::ModelClient::generate_json_many)
+//! run up to [`ManyOptions::concurrency`] calls at once and return one
+//! result per prompt, in input order. Their `_stream` variants yield
+//! `(index, result)` pairs as calls complete.
+//!
+//! A failing prompt does not stop the others. Calls that fail with a
+//! [retryable](crate::Error::is_retryable) error are retried after the
+//! delay the API asked for ([`Error::retry_after`](crate::Error::retry_after)),
+//! or with exponential backoff; when the API reports rate limiting, all
+//! workers pause for that delay instead of only the one that hit it.
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! use gemini_rs::concurrent::ManyOptions;
+//! use gemini_rs::{Client, Model};
+//! use serde::Deserialize;
+//!
+//! #[derive(Deserialize)]
+//! struct Category {
+//!     name: String,
+//! }
+//!
+//! # async fn example() -> Result<(), gemini_rs::Error> {
+//! let model = Client::new("YOUR_API_KEY").model(Model::Gemini25Flash);
+//! let prompts: Vec<String> = vec!["Classify: coffee $4".into(), "Classify: rent $1200".into()];
+//!
+//! let options = ManyOptions::new(16)
+//!     .max_retries(5)
+//!     .on_progress(|progress| eprintln!("{}/{}", progress.completed, progress.total));
+//! let results = model.generate_json_many::<Category>(prompts, options).await;
+//!
+//! for result in results {
+//!     match result {
+//!         Ok(category) => println!("{}", category.name),
+//!         Err(e) => eprintln!("failed: {}", e),
+//!     }
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::error::{Error, Result};
+use futures::future::Future;
+use futures::stream::{self, BoxStream, StreamExt};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Progress of a [`generate_many`](crate::ModelClient::generate_many) run.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Progress {
+    /// Number of prompts.
+    pub total: usize,
+    /// Prompts finished, successfully or not.
+    pub completed: usize,
+    /// Prompts that failed after all retries.
+    pub failed: usize,
+    /// Retries made so far.
+    pub retries: usize,
+}
+
+type ProgressCallback = Arc<dyn Fn(&Progress) + Send + Sync>;
+
+/// Options of a [`generate_many`](crate::ModelClient::generate_many) run.
+///
+/// A `usize` converts into options with that concurrency.
+#[derive(Clone)]
+pub struct ManyOptions {
+    concurrency: usize,
+    max_retries: u32,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+    on_progress: Option<ProgressCallback>,
+}
+
+impl ManyOptions {
+    /// Run up to `concurrency` calls at once (at least one), retrying
+    /// retryable errors up to 3 times with a backoff from 500ms to 30s.
+    pub fn new(concurrency: usize) -> Self {
+        Self {
+            concurrency: concurrency.max(1),
+            max_retries: 3,
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(30),
+            on_progress: None,
+        }
+    }
+
+    /// Get the maximum number of concurrent calls.
+    pub fn concurrency(&self) -> usize {
+        self.concurrency
+    }
+
+    /// Retry each prompt up to `max_retries` times.
+    pub fn max_retries(mut self, max_retries: u32) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+
+    /// Set the delay before the first retry and the maximum delay.
+    pub fn with_backoff(mut self, initial: Duration, max: Duration) -> Self {
+        self.initial_backoff = initial;
+        self.max_backoff = max;
+        self
+    }
+
+    /// Call `callback` each time a prompt finishes.
+    pub fn on_progress(mut self, callback: impl Fn(&Progress) + Send + Sync + 'static) -> Self {
+        self.on_progress = Some(Arc::new(callback));
+        self
+    }
+
+    fn backoff(&self, attempt: u32) -> Duration {
+        let factor = 1u32.checked_shl(attempt).unwrap_or(u32::MAX);
+        self.initial_backoff
+            .checked_mul(factor)
+            .map_or(self.max_backoff, |delay| delay.min(self.max_backoff))
+    }
+}
+
+impl From<usize> for ManyOptions {
+    fn from(concurrency: usize) -> Self {
+        Self::new(concurrency)
+    }
+}
+
+impl std::fmt::Debug for ManyOptions {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ManyOptions")
+            .field("concurrency", &self.concurrency)
+            .field("max_retries", &self.max_retries)
+            .field("initial_backoff", &self.initial_backoff)
+            .field("max_backoff", &self.max_backoff)
+            .field("on_progress", &self.on_progress.is_some())
+            .finish()
+    }
+}
+
+/// State shared by the workers of one run.
+struct Shared {
+    options: ManyOptions,
+    progress: Mutex<Progress>,
+    /// Set when the API reports rate limiting; no call starts before it.
+    paused_until: Mutex<Option<Instant>>,
+}
+
+impl Shared {
+    async fn wait_for_pause(&self) {
+        let paused_until = *self.paused_until.lock().unwrap();
+        if let Some(until) = paused_until {
+            let remaining = until.saturating_duration_since(Instant::now());
+            if !remaining.is_zero() {
+                tokio::time::sleep(remaining).await;
+            }
+        }
+    }
+
+    fn pause(&self, delay: Duration) {
+        let until = Instant::now() + delay;
+        let mut paused_until = self.paused_until.lock().unwrap();
+        if paused_until.map_or(true, |current| current < until) {
+            *paused_until = Some(until);
+        }
+    }
+
+    fn update(&self, update: impl FnOnce(&mut Progress)) {
+        let progress = {
+            let mut progress = self.progress.lock().unwrap();
+            update(&mut progress);
+            *progress
+        };
+        if let Some(callback) = &self.options.on_progress {
+            callback(&progress);
+        }
+    }
+}
+
+/// Run `call` for every input with bounded concurrency, yielding
+/// `(index, result)` in completion order. `call` receives the retry
+/// attempt, 0 for the first try.
+pub(crate) fn run<I, T, F, Fut>(
+    inputs: Vec<I>,
+    options: ManyOptions,
+    call: F,
+) -> BoxStream<'static, (usize, Result<T>)>
+where
+    I: Clone + Send + 'static,
+    T: Send + 'static,
+    F: Fn(I, u32) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = Result<T>> + Send + 'static,
+{
+    let concurrency = options.concurrency;
+    let shared = Arc::new(Shared {
+        progress: Mutex::new(Progress {
+            total: inputs.len(),
+            ..Progress::default()
+        }),
+        paused_until: Mutex::new(None),
+        options,
+    });
+    let call = Arc::new(call);
+
+    stream::iter(inputs.into_iter().enumerate())
+        .map(move |(index, input)| {
+            let shared = shared.clone();
+            let call = call.clone();
+            async move {
+                let mut attempt = 0;
+                let result = loop {
+                    shared.wait_for_pause().await;
+                    match call(input.clone(), attempt).await {
+                        Err(err) if err.is_retryable() && attempt < shared.options.max_retries => {
+                            let delay = err
+                                .retry_after()
+                                .unwrap_or_else(|| shared.options.backoff(attempt));
+                            if matches!(err.without_key(), Error::RateLimitExceeded { .. }) {
+                                shared.pause(delay);
+                            }
+                            shared.update(|progress| progress.retries += 1);
+                            tokio::time::sleep(delay).await;
+                            attempt += 1;
+                        }
+                        result => break result,
+                    }
+                };
+                shared.update(|progress| {
+                    progress.completed += 1;
+                    if result.is_err() {
+                        progress.failed += 1;
+                    }
+                });
+                (index, result)
+            }
+        })
+        .buffer_unordered(concurrency)
+        .boxed()
+}
+
+/// Collect the results of [`run`] in input order.
+pub(crate) async fn in_order<T>(
+    results: BoxStream<'static, (usize, Result<T>)>,
+    len: usize,
+) -> Vec<Result<T>> {
+    let mut ordered: Vec<Option<Result<T>>> = (0..len).map(|_| None).collect();
+    let mut results = results;
+    while let Some((index, result)) = results.next().await {
+        ordered[index] = Some(result);
+    }
+    ordered.into_iter().flatten().collect()
+}
@@ -0,0 +1,321 @@
+//! Environment- and file-based configuration.
+//!
+//! [`Client::from_env`](crate::Client::from_env) reads the same environment
+//! variables as the official Google SDKs:
+//!
+//! | Variable | Purpose |
+//! |----------|---------|
+//! | `GOOGLE_API_KEY` | Gemini Developer API key (takes precedence) |
+//! | `GEMINI_API_KEY` | Gemini Developer API key |
+//! | `GOOGLE_GENAI_USE_VERTEXAI` | `true` or `1` to use Vertex AI instead |
+//! | `GOOGLE_CLOUD_PROJECT` | Vertex AI project (required with Vertex AI) |
+//! | `GOOGLE_CLOUD_LOCATION` | Vertex AI location (default `us-central1`) |
+//! | `GOOGLE_APPLICATION_CREDENTIALS` | Vertex AI credentials file (see [`Credentials::application_default`]) |
+//! | `GEMINI_MODEL` | Model used by [`GeminiConfig::model_client`] when the configuration names none |
+//!
+//! [`GeminiConfig`] adds the model and its defaults, loaded from JSON or
+//! (with the `toml` feature) TOML. A `Client` serves every model, so
+//! `Client::from_env` does not read `GEMINI_MODEL`; use
+//! `GeminiConfig::default().model_client()` to take both the credentials
+//! and the model from the environment.
+//!
+//! # Example
+//!
+//! ```toml
+//! model = "gemini-2.5-flash"
+//! system_instruction = "You are a concise assistant."
+//!
+//! [generation_config]
+//! temperature = 0.2
+//! max_output_tokens = 1024
+//!
+//! [[safety_settings]]
+//! category = "HARM_CATEGORY_HARASSMENT"
+//! threshold = "BLOCK_ONLY_HIGH"
+//!
+//! [client]
+//! timeout_secs = 60
+//! ```
+//!
+//! ```rust,no_run
+//! use gemini_rs::config::GeminiConfig;
+//!
+//! # async fn example() -> Result<(), gemini_rs::Error> {
+//! let model = GeminiConfig::from_file("gemini.toml")?.model_client()?;
+//! let response = model.generate_content("Hello").await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::auth::Credentials;
+use crate::backend::{ApiVersion, Backend};
+use crate::client::{Client, ModelClient};
+use crate::error::{Error, Result};
+use crate::models::Model;
+use crate::types::{GenerationConfig, SafetySetting};
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Environment variable holding a Gemini Developer API key.
+pub const GOOGLE_API_KEY: &str = "GOOGLE_API_KEY";
+/// Alternative environment variable holding a Gemini Developer API key.
+pub const GEMINI_API_KEY: &str = "GEMINI_API_KEY";
+/// Environment variable selecting the Vertex AI backend.
+pub const GOOGLE_GENAI_USE_VERTEXAI: &str = "GOOGLE_GENAI_USE_VERTEXAI";
+/// Environment variable holding the Vertex AI project.
+pub const GOOGLE_CLOUD_PROJECT: &str = "GOOGLE_CLOUD_PROJECT";
+/// Environment variable holding the Vertex AI location.
+pub const GOOGLE_CLOUD_LOCATION: &str = "GOOGLE_CLOUD_LOCATION";
+/// Environment variable holding the default model, e.g. `gemini-2.5-flash`.
+pub const GEMINI_MODEL: &str = "GEMINI_MODEL";
+
+const DEFAULT_VERTEX_LOCATION: &str = "us-central1";
+
+/// Read a non-empty environment variable.
+fn env_var(name: &str) -> Option<String> {
+    std::env::var(name)
+        .ok()
+        .map(|value| value.trim().to_string())
+        .filter(|value| !value.is_empty())
+}
+
+fn config_error(message: impl Into<String>) -> Error {
+    Error::ConfigError(message.into())
+}
+
+/// Build the backend described by the environment.
+pub(crate) fn backend_from_env() -> Result<Backend> {
+    let use_vertex = env_var(GOOGLE_GENAI_USE_VERTEXAI)
+        .map(|value| matches!(value.to_ascii_lowercase().as_str(), "true" | "1"))
+        .unwrap_or(false);
+
+    if use_vertex {
+        let project = env_var(GOOGLE_CLOUD_PROJECT).ok_or_else(|| {
+            config_error(format!(
+                "{} is set but {} is not",
+                GOOGLE_GENAI_USE_VERTEXAI, GOOGLE_CLOUD_PROJECT
+            ))
+        })?;
+        let location =
+            env_var(GOOGLE_CLOUD_LOCATION).unwrap_or_else(|| DEFAULT_VERTEX_LOCATION.to_string());
+        return Ok(Backend::vertex_ai(
+            project,
+            location,
+            Credentials::application_default()?,
+        ));
+    }
+
+    let api_key = env_var(GOOGLE_API_KEY)
+        .or_else(|| env_var(GEMINI_API_KEY))
+        .ok_or_else(|| {
+            config_error(format!(
+                "neither {} nor {} is set",
+                GOOGLE_API_KEY, GEMINI_API_KEY
+            ))
+        })?;
+    Ok(Backend::google_ai(api_key))
+}
+
+/// Read the model named by `GEMINI_MODEL`, if set.
+fn model_from_env() -> Result<Option<Model>> {
+    env_var(GEMINI_MODEL)
+        .map(|name| {
+            name.parse().map_err(|_| {
+                config_error(format!("{} names an unknown model: {}", GEMINI_MODEL, name))
+            })
+        })
+        .transpose()
+}
+
+/// Configuration of a [`ModelClient`], loadable from JSON or TOML.
+///
+/// Every field is optional. Credentials not given in the file come from the
+/// environment, as with [`Client::from_env`].
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct GeminiConfig {
+    /// The model to use. Defaults to the model named by `GEMINI_MODEL`,
+    /// then [`Model::default`].
+    #[serde(default)]
+    pub model: Option<Model>,
+    /// Gemini Developer API key.
+    #[serde(default)]
+    pub api_key: Option<String>,
+    /// Name of an environment variable holding the API key.
+    #[serde(default)]
+    pub api_key_env: Option<String>,
+    /// Use Vertex AI instead of the Gemini Developer API.
+    #[serde(default)]
+    pub vertex_ai: Option<VertexAiConfig>,
+    /// Default generation configuration.
+    #[serde(default)]
+    pub generation_config: Option<GenerationConfig>,
+    /// Default safety settings.
+    #[serde(default)]
+    pub safety_settings: Option<Vec<SafetySetting>>,
+    /// Default system instruction.
+    #[serde(default)]
+    pub system_instruction: Option<String>,
+    /// HTTP client options.
+    #[serde(default)]
+    pub client: ClientOptions,
+}
+
+/// Vertex AI section of a [`GeminiConfig`].
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct VertexAiConfig {
+    /// Google Cloud project id.
+    pub project: String,
+    /// Location, e.g. `us-central1` or `global`. Defaults to `us-central1`.
+    #[serde(default)]
+    pub location: Option<String>,
+    /// Credentials JSON file. Defaults to Application Default Credentials.
+    #[serde(default)]
+    pub credentials_file: Option<PathBuf>,
+}
+
+/// HTTP options of a [`GeminiConfig`], applied through [`ClientBuilder`](crate::ClientBuilder).
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ClientOptions {
+    /// Total request timeout, in seconds.
+    #[serde(default)]
+    pub timeout_secs: Option<u64>,
+    /// Connection timeout, in seconds.
+    #[serde(default)]
+    pub connect_timeout_secs: Option<u64>,
+    /// Base URL override, including the API version path.
+    #[serde(default)]
+    pub base_url: Option<String>,
+    /// `"v1"` or `"v1beta"`.
+    #[serde(default)]
+    pub api_version: Option<String>,
+    /// Suffix appended to the user agent.
+    #[serde(default)]
+    pub user_agent_suffix: Option<String>,
+}
+
+impl GeminiConfig {
+    /// Parse a JSON configuration.
+    pub fn from_json_str(json: &str) -> Result<Self> {
+        serde_json::from_str(json).map_err(|e| config_error(format!("invalid JSON config: {}", e)))
+    }
+
+    /// Parse a TOML configuration. Requires the `toml` feature.
+    #[cfg(feature = "toml")]
+    pub fn from_toml_str(toml: &str) -> Result<Self> {
+        toml::from_str(toml).map_err(|e| config_error(format!("invalid TOML config: {}", e)))
+    }
+
+    /// Load a configuration file, choosing the format from its extension
+    /// (`.json`, or `.toml` with the `toml` feature).
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let data = std::fs::read_to_string(path)
+            .map_err(|e| config_error(format!("failed to read {}: {}", path.display(), e)))?;
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("json") => Self::from_json_str(&data),
+            #[cfg(feature = "toml")]
+            Some("toml") => Self::from_toml_str(&data),
+            _ => Err(config_error(format!(
+                "unsupported config file {}: expected a .json{} file",
+                path.display(),
+                if cfg!(feature = "toml") {
+                    " or .toml"
+                } else {
+                    ""
+                }
+            ))),
+        }
+    }
+
+    /// Build a [`Client`] from this configuration.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::ConfigError`] naming the missing setting or
+    /// environment variable if no credentials can be found.
+    pub fn client(&self) -> Result<Client> {
+        let mut builder = Client::builder().backend(self.backend()?);
+
+        let options = &self.client;
+        if let Some(secs) = options.timeout_secs {
+            builder = builder.timeout(Duration::from_secs(secs));
+        }
+        if let Some(secs) = options.connect_timeout_secs {
+            builder = builder.connect_timeout(Duration::from_secs(secs));
+        }
+        if let Some(version) = &options.api_version {
+            builder = builder.api_version(match version.as_str() {
+                "v1" => ApiVersion::V1,
+                "v1beta" | "v1beta1" => ApiVersion::V1Beta,
+                other => {
+                    return Err(config_error(format!(
+                        "invalid client.api_version {:?}: expected \"v1\" or \"v1beta\"",
+                        other
+                    )))
+                }
+            });
+        }
+        if let Some(base_url) = &options.base_url {
+            builder = builder.base_url(base_url);
+        }
+        if let Some(suffix) = &options.user_agent_suffix {
+            builder = builder.user_agent_suffix(suffix);
+        }
+        builder.build()
+    }
+
+    /// Build a [`ModelClient`] with this configuration's model and defaults.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::ConfigError`] if no credentials can be found, or if
+    /// the model comes from `GEMINI_MODEL` and is unknown.
+    pub fn model_client(&self) -> Result<ModelClient> {
+        let model = match self.model {
+            Some(model) => model,
+            None => model_from_env()?.unwrap_or_default(),
+        };
+        let mut model = self.client()?.model(model);
+        if let Some(config) = &self.generation_config {
+            model = model.with_config(config.clone());
+        }
+        if let Some(settings) = &self.safety_settings {
+            model = model.with_safety(settings.clone());
+        }
+        if let Some(instruction) = &self.system_instruction {
+            model = model.with_system_instruction(instruction);
+        }
+        Ok(model)
+    }
+
+    fn backend(&self) -> Result<Backend> {
+        if let Some(vertex) = &self.vertex_ai {
+            let credentials = match &vertex.credentials_file {
+                Some(path) => Credentials::from_file(path)?,
+                None => Credentials::application_default()?,
+            };
+            let location = vertex
+                .location
+                .as_deref()
+                .unwrap_or(DEFAULT_VERTEX_LOCATION);
+            return Ok(Backend::vertex_ai(&vertex.project, location, credentials));
+        }
+        if let Some(api_key) = &self.api_key {
+            return Ok(Backend::google_ai(api_key));
+        }
+        if let Some(name) = &self.api_key_env {
+            let api_key = env_var(name).ok_or_else(|| {
+                config_error(format!(
+                    "environment variable {} (api_key_env) is not set",
+                    name
+                ))
+            })?;
+            return Ok(Backend::google_ai(api_key));
+        }
+        backend_from_env()
+    }
+}
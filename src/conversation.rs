@@ -0,0 +1,151 @@
+//! Client-side validation of multi-turn conversations.
+//!
+//! The Gemini API rejects malformed histories with an opaque `400 Bad Request`.
+//! [`Conversation`] checks the turns before they are sent and reports exactly
+//! which turn is wrong and why.
+//!
+//! # Example
+//!
+//! ```rust
+//! use gemini_rs::{Content, Conversation};
+//!
+//! let turns = vec![
+//!     Content::user("Hi"),
+//!     Content::model("Hello! How can I help?"),
+//!     Content::user("Tell me a joke"),
+//! ];
+//! assert!(Conversation::new(&turns).validate().is_ok());
+//!
+//! // Two user turns in a row are rejected
+//! let turns = vec![Content::user("Hi"), Content::user("Anyone there?")];
+//! assert!(Conversation::new(&turns).validate().is_err());
+//! ```
+
+use crate::error::{Error, Result};
+use crate::types::{Content, Role};
+
+/// A borrowed view over conversation turns that can be validated.
+///
+/// The following rules are enforced:
+/// - the conversation contains at least one turn, and every turn has parts
+/// - role-less content (see [`Content::text`]) is only allowed as a single turn
+/// - the [`Role::System`] role never appears in the history
+/// - the first turn comes from the user
+/// - two consecutive turns never share the same role
+/// - every function response answers a function call of the same name in
+///   the preceding model turn
+/// - the history does not end on a model turn
+#[derive(Debug, Clone, Copy)]
+pub struct Conversation<'a> {
+    turns: &'a [Content],
+}
+
+impl<'a> Conversation<'a> {
+    /// Create a view over the given turns.
+    pub fn new(turns: &'a [Content]) -> Self {
+        Self { turns }
+    }
+
+    /// Get the turns of this conversation.
+    pub fn turns(&self) -> &'a [Content] {
+        self.turns
+    }
+
+    /// Validate the conversation.
+    ///
+    /// Returns [`Error::InvalidInput`] describing the first offending turn.
+    /// Turns are referred to by their index in the `contents` array.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use gemini_rs::{Content, Conversation, Error};
+    ///
+    /// let turns = vec![Content::user("Hi"), Content::model("Hello!")];
+    /// match Conversation::new(&turns).validate() {
+    ///     Err(Error::InvalidInput(message)) => assert!(message.contains("contents[1]")),
+    ///     _ => unreachable!(),
+    /// }
+    /// ```
+    pub fn validate(&self) -> Result<()> {
+        let Some(last) = self.turns.last() else {
+            return Err(invalid("conversation must contain at least one turn"));
+        };
+
+        let single_turn = self.turns.len() == 1;
+        let mut previous: Option<(usize, &Content)> = None;
+
+        for (index, turn) in self.turns.iter().enumerate() {
+            if turn.parts.is_empty() {
+                return Err(invalid(format!("contents[{}] has no parts", index)));
+            }
+
+            let role = match turn.role {
+                Some(role) => role,
+                None if single_turn => continue,
+                None => {
+                    return Err(invalid(format!(
+                        "contents[{}] has no role; every turn of a multi-turn \
+                         conversation needs a `user`, `model` or `function` role",
+                        index
+                    )))
+                }
+            };
+
+            if role == Role::System {
+                return Err(invalid(format!(
+                    "contents[{}] uses the `system` role; pass it as a system \
+                     instruction instead",
+                    index
+                )));
+            }
+
+            match previous {
+                None if role != Role::User => {
+                    return Err(invalid(format!(
+                        "contents[{}] is a `{}` turn; a conversation must start \
+                         with a `user` turn",
+                        index, role
+                    )));
+                }
+                Some((prev_index, prev)) if prev.role == Some(role) => {
+                    return Err(invalid(format!(
+                        "contents[{}] and contents[{}] are consecutive `{}` turns",
+                        prev_index, index, role
+                    )));
+                }
+                _ => {}
+            }
+
+            for response in turn.function_responses() {
+                let answered = previous.is_some_and(|(_, prev)| {
+                    prev.role == Some(Role::Model)
+                        && prev.function_calls().any(|call| call.name == response.name)
+                });
+                if !answered {
+                    return Err(invalid(format!(
+                        "contents[{}] contains a response for function `{}` \
+                         without a matching call in the preceding model turn",
+                        index, response.name
+                    )));
+                }
+            }
+
+            previous = Some((index, turn));
+        }
+
+        if last.role == Some(Role::Model) {
+            return Err(invalid(format!(
+                "contents[{}] is a `model` turn; the conversation must end \
+                 with a `user` or `function` turn",
+                self.turns.len() - 1
+            )));
+        }
+
+        Ok(())
+    }
+}
+
+fn invalid(message: impl Into<String>) -> Error {
+    Error::InvalidInput(message.into())
+}
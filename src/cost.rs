@@ -0,0 +1,422 @@
+//! Cost estimates from token usage.
+//!
+//! A [`PriceTable`] holds the per-token prices of each model, a
+//! [`CostCalculator`] turns the [`UsageMetadata`] of a response into a
+//! [`Cost`], and a [`CostTracker`] adds up the cost of every call made
+//! through a [`Client`](crate::Client) or a
+//! [`ChatSession`](crate::ChatSession).
+//!
+//! The built-in table ([`PriceTable::gemini`]) holds the list prices of the
+//! paid tier in USD. Prices change; set your own with [`PriceTable::set`]
+//! when the estimates must match your bill.
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! use gemini_rs::cost::CostTracker;
+//! use gemini_rs::{Client, Model};
+//!
+//! # async fn example() -> Result<(), gemini_rs::Error> {
+//! let tracker = CostTracker::new();
+//! let client = Client::new("YOUR_API_KEY").with_metrics(tracker.clone());
+//!
+//! // Attribute spend to a feature with a metric label
+//! let summaries = client
+//!     .clone()
+//!     .with_metric_label("feature", "summaries")
+//!     .model(Model::Gemini25Flash);
+//! let response = summaries.generate_content("Summarize: ...").await?;
+//! println!("this call: ${:.6}", response.estimated_cost().unwrap_or_default().total());
+//!
+//! println!("total: ${:.6}", tracker.total().total());
+//! for (feature, cost) in tracker.by_label("feature") {
+//!     println!("{}: ${:.6}", feature, cost.total());
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::metrics::{CallMetrics, MetricsSink};
+use crate::types::{GenerateContentResponse, UsageMetadata};
+use std::collections::HashMap;
+use std::ops::{Add, AddAssign};
+use std::sync::{Arc, Mutex, OnceLock};
+
+/// Prices in USD per million tokens.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct TokenPrices {
+    /// Text, image and video prompt tokens.
+    pub input: f64,
+    /// Audio prompt tokens; `None` to charge them as `input`.
+    pub audio_input: Option<f64>,
+    /// Prompt tokens served from the context cache.
+    pub cached_input: f64,
+    /// Generated tokens.
+    pub output: f64,
+    /// Thinking tokens; `None` to charge them as `output`.
+    pub thinking: Option<f64>,
+}
+
+impl TokenPrices {
+    /// Prices for input and output tokens; cached input is free and audio
+    /// and thinking tokens cost the same as other input and output tokens.
+    pub fn new(input: f64, output: f64) -> Self {
+        Self {
+            input,
+            output,
+            ..Self::default()
+        }
+    }
+
+    /// Set the price of cached prompt tokens.
+    pub fn cached_input(mut self, price: f64) -> Self {
+        self.cached_input = price;
+        self
+    }
+
+    /// Set the price of audio prompt tokens.
+    pub fn audio_input(mut self, price: f64) -> Self {
+        self.audio_input = Some(price);
+        self
+    }
+
+    /// Set the price of thinking tokens.
+    pub fn thinking(mut self, price: f64) -> Self {
+        self.thinking = Some(price);
+        self
+    }
+}
+
+/// The prices of one model, optionally higher for long prompts.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ModelPricing {
+    /// `(min_prompt_tokens, prices)`, sorted by threshold.
+    tiers: Vec<(u64, TokenPrices)>,
+}
+
+impl ModelPricing {
+    /// Charge `prices` for every call.
+    pub fn new(prices: TokenPrices) -> Self {
+        Self {
+            tiers: vec![(0, prices)],
+        }
+    }
+
+    /// Charge `prices` for calls whose prompt has more than `threshold`
+    /// tokens.
+    pub fn above(mut self, threshold: u64, prices: TokenPrices) -> Self {
+        self.tiers.push((threshold.saturating_add(1), prices));
+        self.tiers.sort_by_key(|(min, _)| *min);
+        self
+    }
+
+    /// Get the prices for a prompt of `prompt_tokens` tokens.
+    pub fn prices(&self, prompt_tokens: u64) -> &TokenPrices {
+        self.tiers
+            .iter()
+            .rev()
+            .find(|(min, _)| prompt_tokens >= *min)
+            .map(|(_, prices)| prices)
+            .unwrap_or(&self.tiers[0].1)
+    }
+
+    /// Compute the cost of a call.
+    pub fn cost(&self, usage: &UsageMetadata) -> Cost {
+        let prices = self.prices(usage.prompt_token_count);
+        let per_token = |price: f64, tokens: u64| price * tokens as f64 / 1_000_000.0;
+
+        let cached = usage
+            .cached_content_token_count
+            .min(usage.prompt_token_count);
+        let audio = usage
+            .prompt_tokens_of("AUDIO")
+            .saturating_sub(usage.cached_tokens_of("AUDIO"));
+        let uncached = usage.prompt_token_count - cached;
+        let audio = audio.min(uncached);
+
+        Cost {
+            input: per_token(prices.input, uncached - audio)
+                + per_token(prices.audio_input.unwrap_or(prices.input), audio),
+            cached_input: per_token(prices.cached_input, cached),
+            output: per_token(prices.output, usage.candidates_token_count),
+            thinking: per_token(
+                prices.thinking.unwrap_or(prices.output),
+                usage.thoughts_token_count,
+            ),
+        }
+    }
+}
+
+/// Per-model prices, keyed by model id.
+#[derive(Debug, Clone, Default)]
+pub struct PriceTable {
+    models: HashMap<String, ModelPricing>,
+}
+
+impl PriceTable {
+    /// Create an empty table.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// List prices of the Gemini Developer API paid tier, in USD.
+    pub fn gemini() -> Self {
+        let flat = ModelPricing::new;
+        Self::new()
+            .with(
+                "gemini-2.5-pro",
+                ModelPricing::new(TokenPrices::new(1.25, 10.0).cached_input(0.125))
+                    .above(200_000, TokenPrices::new(2.5, 15.0).cached_input(0.25)),
+            )
+            .with(
+                "gemini-2.5-flash",
+                flat(
+                    TokenPrices::new(0.3, 2.5)
+                        .audio_input(1.0)
+                        .cached_input(0.03),
+                ),
+            )
+            .with(
+                "gemini-2.5-flash-lite",
+                flat(
+                    TokenPrices::new(0.1, 0.4)
+                        .audio_input(0.3)
+                        .cached_input(0.01),
+                ),
+            )
+            .with(
+                "gemini-2.0-flash",
+                flat(
+                    TokenPrices::new(0.1, 0.4)
+                        .audio_input(0.7)
+                        .cached_input(0.025),
+                ),
+            )
+            .with("gemini-2.0-flash-lite", flat(TokenPrices::new(0.075, 0.3)))
+            .with(
+                "gemini-1.5-pro",
+                ModelPricing::new(TokenPrices::new(1.25, 5.0).cached_input(0.3125))
+                    .above(128_000, TokenPrices::new(2.5, 10.0).cached_input(0.625)),
+            )
+            .with(
+                "gemini-1.5-flash",
+                ModelPricing::new(TokenPrices::new(0.075, 0.3).cached_input(0.01875))
+                    .above(128_000, TokenPrices::new(0.15, 0.6).cached_input(0.0375)),
+            )
+            .with(
+                "gemini-1.5-flash-8b",
+                ModelPricing::new(TokenPrices::new(0.0375, 0.15).cached_input(0.01))
+                    .above(128_000, TokenPrices::new(0.075, 0.3).cached_input(0.02)),
+            )
+            .with("gemini-1.0-pro", flat(TokenPrices::new(0.5, 1.5)))
+    }
+
+    /// Set the prices of a model, replacing any previous ones.
+    pub fn set(&mut self, model: impl Into<String>, pricing: ModelPricing) {
+        self.models.insert(model.into(), pricing);
+    }
+
+    /// Builder-style [`set`](Self::set).
+    pub fn with(mut self, model: impl Into<String>, pricing: ModelPricing) -> Self {
+        self.set(model, pricing);
+        self
+    }
+
+    /// Get the prices of a model.
+    ///
+    /// Versioned ids such as `gemini-2.0-flash-001` fall back to the longest
+    /// model id they start with.
+    pub fn get(&self, model: &str) -> Option<&ModelPricing> {
+        let model = model.strip_prefix("models/").unwrap_or(model);
+        self.models.get(model).or_else(|| {
+            self.models
+                .iter()
+                .filter(|(id, _)| model.starts_with(id.as_str()))
+                .max_by_key(|(id, _)| id.len())
+                .map(|(_, pricing)| pricing)
+        })
+    }
+}
+
+/// Estimated cost of one or more calls, in USD.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Cost {
+    /// Uncached prompt tokens.
+    pub input: f64,
+    /// Cached prompt tokens.
+    pub cached_input: f64,
+    /// Generated tokens.
+    pub output: f64,
+    /// Thinking tokens.
+    pub thinking: f64,
+}
+
+impl Cost {
+    /// Total cost.
+    pub fn total(&self) -> f64 {
+        self.input + self.cached_input + self.output + self.thinking
+    }
+}
+
+impl Add for Cost {
+    type Output = Cost;
+
+    fn add(mut self, other: Cost) -> Cost {
+        self += other;
+        self
+    }
+}
+
+impl AddAssign for Cost {
+    fn add_assign(&mut self, other: Cost) {
+        self.input += other.input;
+        self.cached_input += other.cached_input;
+        self.output += other.output;
+        self.thinking += other.thinking;
+    }
+}
+
+/// Computes the cost of calls from a [`PriceTable`].
+#[derive(Debug, Clone)]
+pub struct CostCalculator {
+    prices: PriceTable,
+}
+
+impl CostCalculator {
+    /// Use the prices in `prices`.
+    pub fn new(prices: PriceTable) -> Self {
+        Self { prices }
+    }
+
+    /// Get the price table.
+    pub fn prices(&self) -> &PriceTable {
+        &self.prices
+    }
+
+    /// Compute the cost of a call to `model`, or `None` if the model has
+    /// no prices.
+    pub fn cost(&self, model: &str, usage: &UsageMetadata) -> Option<Cost> {
+        self.prices.get(model).map(|pricing| pricing.cost(usage))
+    }
+
+    /// Compute the cost of a response from its model version, or the
+    /// model that answered it, and its usage.
+    pub fn response_cost(&self, response: &GenerateContentResponse) -> Option<Cost> {
+        let usage = response.usage_metadata.as_ref()?;
+        self.cost(response_model(response)?, usage)
+    }
+}
+
+impl Default for CostCalculator {
+    /// Use [`PriceTable::gemini`].
+    fn default() -> Self {
+        Self::new(PriceTable::gemini())
+    }
+}
+
+/// The model of `response`: the version the API reported, or else the
+/// model the client sent it to.
+fn response_model(response: &GenerateContentResponse) -> Option<&str> {
+    response
+        .model_version
+        .as_deref()
+        .or(response.answered_by.map(|model| model.as_str()))
+}
+
+/// The default calculator, built once.
+pub(crate) fn default_calculator() -> &'static CostCalculator {
+    static CALCULATOR: OnceLock<CostCalculator> = OnceLock::new();
+    CALCULATOR.get_or_init(CostCalculator::default)
+}
+
+#[derive(Debug, Default)]
+struct Totals {
+    total: Cost,
+    by_model: HashMap<String, Cost>,
+    by_label: HashMap<(String, String), Cost>,
+    unpriced_calls: usize,
+}
+
+/// Adds up the cost of calls.
+///
+/// Install it with [`Client::with_metrics`](crate::Client::with_metrics) to
+/// track every call made through a client, and through the
+/// [`ModelClient`](crate::ModelClient)s and
+/// [`ChatSession`](crate::ChatSession)s created from it, or with
+/// [`ChatSession::with_cost_tracker`](crate::ChatSession::with_cost_tracker)
+/// to track one chat session. Clones of a tracker share the same totals.
+#[derive(Debug, Clone, Default)]
+pub struct CostTracker {
+    calculator: CostCalculator,
+    totals: Arc<Mutex<Totals>>,
+}
+
+impl CostTracker {
+    /// Track costs with the built-in prices.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Track costs with the prices of `calculator`.
+    pub fn with_calculator(calculator: CostCalculator) -> Self {
+        Self {
+            calculator,
+            totals: Arc::default(),
+        }
+    }
+
+    /// Add the cost of a call to `model`.
+    pub fn add(&self, model: &str, usage: &UsageMetadata, labels: &[(String, String)]) {
+        let mut totals = self.totals.lock().unwrap();
+        let Some(cost) = self.calculator.cost(model, usage) else {
+            totals.unpriced_calls += 1;
+            return;
+        };
+        totals.total += cost;
+        *totals.by_model.entry(model.to_string()).or_default() += cost;
+        for label in labels {
+            *totals.by_label.entry(label.clone()).or_default() += cost;
+        }
+    }
+
+    /// Total cost so far.
+    pub fn total(&self) -> Cost {
+        self.totals.lock().unwrap().total
+    }
+
+    /// Cost so far by model id.
+    pub fn by_model(&self) -> HashMap<String, Cost> {
+        self.totals.lock().unwrap().by_model.clone()
+    }
+
+    /// Cost so far by value of the metric label `key`, e.g. the feature
+    /// or tenant.
+    pub fn by_label(&self, key: &str) -> HashMap<String, Cost> {
+        self.totals
+            .lock()
+            .unwrap()
+            .by_label
+            .iter()
+            .filter(|((k, _), _)| k == key)
+            .map(|((_, value), cost)| (value.clone(), *cost))
+            .collect()
+    }
+
+    /// Number of calls to models without prices.
+    pub fn unpriced_calls(&self) -> usize {
+        self.totals.lock().unwrap().unpriced_calls
+    }
+
+    /// Reset all totals.
+    pub fn reset(&self) {
+        *self.totals.lock().unwrap() = Totals::default();
+    }
+}
+
+impl MetricsSink for CostTracker {
+    fn record(&self, call: &CallMetrics) {
+        if call.usage.total_token_count > 0 || call.usage.prompt_token_count > 0 {
+            self.add(&call.model, &call.usage, &call.labels);
+        }
+    }
+}
@@ -14,19 +14,27 @@
 //!
 //! match model.generate_content("Hello").await {
 //!     Ok(response) => println!("{}", response.text()),
-//!     Err(Error::RateLimitExceeded) => {
-//!         eprintln!("Rate limited! Wait before retrying.");
-//!     }
-//!     Err(Error::ApiError { message, code }) => {
-//!         eprintln!("API error (code {:?}): {}", code, message);
-//!     }
-//!     Err(e) => eprintln!("Error: {}", e),
+//!     // Calls made with a pooled API key wrap errors in `Error::WithKey`
+//!     Err(e) => match e.without_key() {
+//!         Error::RateLimitExceeded { retry_after, .. } => {
+//!             eprintln!("Rate limited! Wait {:?} before retrying.", retry_after);
+//!         }
+//!         Error::ApiError { message, code } => {
+//!             eprintln!("API error (code {:?}): {}", code, message);
+//!         }
+//!         _ => eprintln!("Error: {}", e),
+//!     },
 //! }
 //! # }
 //! ```
 
+use crate::budget::BudgetLimit;
 use thiserror::Error;
 
+/// Placeholder that replaces credentials in error messages, `Debug` output
+/// and recorded cassettes.
+pub const REDACTED: &str = "<redacted>";
+
 /// A `Result` type alias using the [`Error`](enum@Error) enum as the error type.
 pub type Result<T> = std::result::Result<T, Error>;
 
@@ -39,9 +47,18 @@ pub enum Error {
     /// HTTP request failed (network error, timeout, etc.)
     ///
     /// This typically indicates connectivity issues. Consider retrying
-    /// with exponential backoff.
+    /// with exponential backoff. Credentials passed as `key` query
+    /// parameters are removed from the error's URL. Timeouts set on the
+    /// [`ClientBuilder`](crate::ClientBuilder) are reported as
+    /// [`Error::Timeout`] instead; those of an injected `reqwest::Client`
+    /// are not.
     #[error("HTTP request failed: {0}")]
-    HttpError(#[from] reqwest::Error),
+    HttpError(reqwest::Error),
+
+    /// A custom [`Transport`](crate::transport::Transport) failed to send
+    /// a request or read its response.
+    #[error("Transport failed: {0}")]
+    TransportError(String),
 
     /// Failed to parse JSON response.
     ///
@@ -50,14 +67,23 @@ pub enum Error {
     #[error("Failed to parse JSON: {0}")]
     JsonError(#[from] serde_json::Error),
 
+    /// I/O operation failed.
+    ///
+    /// Raised by file-backed components such as
+    /// [`FileChatStore`](crate::store::FileChatStore).
+    #[error("I/O error: {0}")]
+    IoError(#[from] std::io::Error),
+
     /// API returned an error response.
     ///
     /// Check the message and code for details. Common codes:
     /// - 400: Bad request (invalid parameters)
     /// - 401: Unauthorized (invalid API key)
     /// - 403: Forbidden (quota exceeded or region restricted)
-    /// - 429: Too many requests (rate limited)
     /// - 500: Server error (retry later)
+    ///
+    /// HTTP 429 is reported as [`Error::RateLimitExceeded`] instead, with
+    /// the same message.
     #[error("API error: {message}")]
     ApiError {
         /// Error message from the API.
@@ -80,12 +106,39 @@ pub enum Error {
     #[error("Invalid API key")]
     InvalidApiKey,
 
+    /// Authentication failed.
+    ///
+    /// Raised when OAuth2 credentials (used by the Vertex AI backend) cannot
+    /// be loaded or exchanged for an access token.
+    #[error("Authentication failed: {0}")]
+    AuthError(String),
+
+    /// Client configuration is missing or invalid.
+    ///
+    /// Raised by [`Client::from_env`](crate::Client::from_env) and
+    /// [`GeminiConfig`](crate::config::GeminiConfig); the message names the
+    /// missing environment variable or setting.
+    #[error("Configuration error: {0}")]
+    ConfigError(String),
+
     /// Rate limit exceeded.
     ///
-    /// You've made too many requests. Wait before retrying.
-    /// Consider implementing exponential backoff.
-    #[error("Rate limit exceeded")]
-    RateLimitExceeded,
+    /// The API answered HTTP 429, or a
+    /// [`RateLimiter`](crate::rate_limit::RateLimiter) configured to fail
+    /// fast found the quota used up. Wait `retry_after`, when given, before
+    /// retrying; otherwise consider exponential backoff.
+    #[error(
+        "Rate limit exceeded{}",
+        message.as_ref().map(|message| format!(": {}", message)).unwrap_or_default()
+    )]
+    RateLimitExceeded {
+        /// How long to wait before retrying: the `Retry-After` header or
+        /// `RetryInfo` detail of the response, or the time until the
+        /// client-side quota refills.
+        retry_after: Option<std::time::Duration>,
+        /// Error message from the API, or which client-side quota is used up.
+        message: Option<String>,
+    },
 
     /// Invalid model name.
     ///
@@ -107,4 +160,193 @@ pub enum Error {
     /// for details.
     #[error("Invalid input: {0}")]
     InvalidInput(String),
+
+    /// Gave up waiting after the given duration.
+    ///
+    /// Raised when a request outlives the
+    /// [`ClientBuilder::timeout`](crate::ClientBuilder::timeout) or
+    /// [`connect_timeout`](crate::ClientBuilder::connect_timeout), a call its
+    /// [`CallOptions::timeout`](crate::call::CallOptions::timeout), a model
+    /// its [`FallbackPolicy::timeout`](crate::fallback::FallbackPolicy::timeout),
+    /// or by [`Operation::wait`](crate::operation::Operation::wait) when a
+    /// long-running operation is not done in time.
+    #[error("Timed out after {0:?}")]
+    Timeout(std::time::Duration),
+
+    /// The call was cancelled with a
+    /// [`CancellationToken`](crate::call::CancellationToken).
+    #[error("Call cancelled")]
+    Cancelled,
+
+    /// A [`Budget`](crate::budget::Budget) limit was reached.
+    ///
+    /// `used` is the number of tokens already used, or the spend in USD
+    /// for [`BudgetLimit::Spend`].
+    #[error("Budget exceeded: {used} used of {limit}")]
+    BudgetExceeded {
+        /// The limit that was reached.
+        limit: BudgetLimit,
+        /// Tokens or USD used so far.
+        used: f64,
+    },
+
+    /// A call made with a key from a [`KeyPool`](crate::key_pool::KeyPool)
+    /// failed with `error`.
+    ///
+    /// Every error raised once the call has taken its key is wrapped,
+    /// including those of middleware and [`Error::NoResponse`]. Errors raised
+    /// around it are not: [`Error::InvalidInput`] for turns that do not form
+    /// a conversation, which is checked before any key is taken, and the
+    /// [`Error::Timeout`] and [`Error::Cancelled`] of
+    /// [`CallOptions`](crate::call::CallOptions), which abandon the call.
+    /// `key_id` names the key by its id, never by its value. Use
+    /// [`without_key`](Error::without_key) to match on the failure itself.
+    #[error("{error} (API key {key_id})")]
+    WithKey {
+        /// Id of the key the call was made with.
+        key_id: String,
+        /// The failure.
+        error: Box<Error>,
+    },
+}
+
+impl Error {
+    /// Whether the failed call may succeed if retried.
+    ///
+    /// True for rate limiting, server errors (HTTP 500, 502, 503, 504),
+    /// timeouts and connection failures; false for errors caused by
+    /// the request itself, such as invalid input or credentials.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use gemini_rs::Error;
+    ///
+    /// let overloaded = Error::ApiError { message: "overloaded".into(), code: Some(503) };
+    /// assert!(overloaded.is_retryable());
+    /// assert!(!Error::InvalidApiKey.is_retryable());
+    /// ```
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            Error::WithKey { error, .. } => error.is_retryable(),
+            Error::HttpError(err) => err.is_timeout() || err.is_connect(),
+            Error::TransportError(_) | Error::RateLimitExceeded { .. } | Error::Timeout(_) => true,
+            Error::ApiError {
+                code: Some(code), ..
+            } => matches!(code, 429 | 500 | 502 | 503 | 504),
+            _ => false,
+        }
+    }
+
+    /// Id of the pooled API key the failed call was made with.
+    ///
+    /// See the [`key_pool`](crate::key_pool) module.
+    pub fn key_id(&self) -> Option<&str> {
+        match self {
+            Error::WithKey { key_id, .. } => Some(key_id),
+            _ => None,
+        }
+    }
+
+    /// The failure, without the pooled API key it happened with.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use gemini_rs::Error;
+    ///
+    /// let err = Error::WithKey { key_id: "key-1".into(), error: Box::new(Error::InvalidApiKey) };
+    /// assert!(matches!(err.without_key(), Error::InvalidApiKey));
+    /// assert_eq!(err.to_string(), "Invalid API key (API key key-1)");
+    /// ```
+    pub fn without_key(&self) -> &Error {
+        match self {
+            Error::WithKey { error, .. } => error,
+            error => error,
+        }
+    }
+
+    /// How long the server, or the client-side rate limiter, asked to wait
+    /// before retrying a [`RateLimitExceeded`](Error::RateLimitExceeded)
+    /// failure.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use gemini_rs::Error;
+    /// use std::time::Duration;
+    ///
+    /// let err = Error::RateLimitExceeded {
+    ///     retry_after: Some(Duration::from_secs(30)),
+    ///     message: Some("Resource has been exhausted".into()),
+    /// };
+    /// assert_eq!(err.retry_after(), Some(Duration::from_secs(30)));
+    /// assert_eq!(err.to_string(), "Rate limit exceeded: Resource has been exhausted");
+    /// ```
+    pub fn retry_after(&self) -> Option<std::time::Duration> {
+        match self.without_key() {
+            Error::RateLimitExceeded { retry_after, .. } => *retry_after,
+            _ => None,
+        }
+    }
+
+    /// Short description of the failure for telemetry: the HTTP status code
+    /// of API errors, otherwise the kind of error (e.g. `timeout`, `json`).
+    pub(crate) fn error_type(&self) -> String {
+        let kind = match self {
+            Error::ApiError {
+                code: Some(code), ..
+            } => return code.to_string(),
+            Error::HttpError(err) if err.is_timeout() => "timeout",
+            Error::HttpError(_) => "http",
+            Error::TransportError(_) => "transport",
+            Error::JsonError(_) => "json",
+            Error::IoError(_) => "io",
+            Error::ApiError { .. } => "api",
+            Error::NoResponse => "no_response",
+            Error::InvalidApiKey => "invalid_api_key",
+            Error::AuthError(_) => "auth",
+            Error::ConfigError(_) => "config",
+            Error::RateLimitExceeded { .. } => "rate_limit_exceeded",
+            Error::InvalidModel(_) => "invalid_model",
+            Error::GenerationFailed(_) => "generation_failed",
+            Error::InvalidInput(_) => "invalid_input",
+            Error::Timeout(_) => "timeout",
+            Error::Cancelled => "cancelled",
+            Error::BudgetExceeded { .. } => "budget_exceeded",
+            Error::WithKey { error, .. } => return error.error_type(),
+        };
+        kind.to_string()
+    }
+}
+
+impl From<reqwest::Error> for Error {
+    fn from(err: reqwest::Error) -> Self {
+        Error::HttpError(redact_url(err))
+    }
+}
+
+/// Remove credential query parameters from the URL carried by `err`.
+fn redact_url(mut err: reqwest::Error) -> reqwest::Error {
+    if let Some(url) = err.url_mut() {
+        if url.query_pairs().any(|(name, _)| is_secret_param(&name)) {
+            let pairs: Vec<(String, String)> = url
+                .query_pairs()
+                .map(|(name, value)| {
+                    let value = if is_secret_param(&name) {
+                        REDACTED.to_string()
+                    } else {
+                        value.into_owned()
+                    };
+                    (name.into_owned(), value)
+                })
+                .collect();
+            url.query_pairs_mut().clear().extend_pairs(pairs);
+        }
+    }
+    err
+}
+
+fn is_secret_param(name: &str) -> bool {
+    matches!(name, "key" | "access_token")
 }
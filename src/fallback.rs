@@ -0,0 +1,197 @@
+//! Model fallback chains.
+//!
+//! [`ModelClient::with_fallbacks`](crate::ModelClient::with_fallbacks) lists
+//! models to try, in order, when a call to the primary model fails. A
+//! [`FallbackPolicy`] decides what counts as a failure:
+//!
+//! - an error it accepts, by default any [retryable](Error::is_retryable)
+//!   one (HTTP 429 and 5xx, transport errors, timeouts, and a
+//!   [`RateLimiter`](crate::rate_limit::RateLimiter) out of quota),
+//! - a finish reason, e.g. `SAFETY`, or any safety block with
+//!   [`on_blocked`](FallbackPolicy::on_blocked),
+//! - taking longer than a [`timeout`](FallbackPolicy::timeout).
+//!
+//! Every model gets the same request: contents, generation config, safety
+//! settings, system instruction and tools. In a chat session the fallback's
+//! reply joins the history like any other, and the next message starts
+//! again with the primary model. The last model's result is returned as is;
+//! it is not timed out. [`GenerateContentResponse::answered_by`] names the
+//! model that answered.
+//!
+//! Streaming calls fall back on errors and timeouts until the stream
+//! starts; finish reasons are not checked.
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! use gemini_rs::fallback::FallbackPolicy;
+//! use gemini_rs::{Client, Model};
+//! use std::time::Duration;
+//!
+//! # async fn example() -> Result<(), gemini_rs::Error> {
+//! let model = Client::new("YOUR_API_KEY")
+//!     .model(Model::Gemini25Flash)
+//!     .with_fallbacks([Model::Gemini20Flash])
+//!     .with_fallback_policy(
+//!         FallbackPolicy::new()
+//!             .on_blocked()
+//!             .timeout(Duration::from_secs(20)),
+//!     );
+//!
+//! let response = model.generate_content("Hello").await?;
+//! println!("{:?} answered: {}", response.answered_by, response.text());
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::error::{Error, Result};
+use crate::models::Model;
+use crate::types::GenerateContentResponse;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Finish reasons of candidates stopped by safety filters.
+const BLOCK_REASONS: &[&str] = &[
+    "SAFETY",
+    "RECITATION",
+    "BLOCKLIST",
+    "PROHIBITED_CONTENT",
+    "SPII",
+    "IMAGE_SAFETY",
+];
+
+/// When a [`ModelClient`](crate::ModelClient) moves on to its next
+/// fallback model.
+///
+/// See the [module documentation](self).
+#[derive(Clone)]
+pub struct FallbackPolicy {
+    errors: Arc<dyn Fn(&Error) -> bool + Send + Sync>,
+    blocked: bool,
+    finish_reasons: Vec<String>,
+    timeout: Option<Duration>,
+}
+
+impl FallbackPolicy {
+    /// Fall back on retryable errors only.
+    pub fn new() -> Self {
+        Self {
+            errors: Arc::new(Error::is_retryable),
+            blocked: false,
+            finish_reasons: Vec::new(),
+            timeout: None,
+        }
+    }
+
+    /// Fall back on the errors `predicate` accepts, instead of retryable
+    /// ones. Errors of calls made with a pooled API key are passed
+    /// [without the key](Error::without_key).
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use gemini_rs::fallback::FallbackPolicy;
+    /// use gemini_rs::Error;
+    ///
+    /// // Only when overloaded or out of quota
+    /// let policy = FallbackPolicy::new().on_error(|err| {
+    ///     matches!(err, Error::RateLimitExceeded { .. } | Error::ApiError { code: Some(503), .. })
+    /// });
+    /// ```
+    pub fn on_error(mut self, predicate: impl Fn(&Error) -> bool + Send + Sync + 'static) -> Self {
+        self.errors = Arc::new(predicate);
+        self
+    }
+
+    /// Also fall back when a candidate finishes with `reason`, e.g.
+    /// `MAX_TOKENS`.
+    pub fn on_finish_reason(mut self, reason: impl Into<String>) -> Self {
+        self.finish_reasons.push(reason.into());
+        self
+    }
+
+    /// Also fall back when safety filters block the prompt
+    /// ([`Error::NoResponse`]) or stop a candidate (finish reasons such as
+    /// `SAFETY`, `RECITATION` or `PROHIBITED_CONTENT`).
+    pub fn on_blocked(mut self) -> Self {
+        self.blocked = true;
+        self
+    }
+
+    /// Fall back when a model has not answered within `timeout`. The
+    /// abandoned call fails with [`Error::Timeout`].
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Whether `error` moves the call on to the next model.
+    pub(crate) fn falls_back_on_error(&self, error: &Error) -> bool {
+        let error = error.without_key();
+        (self.blocked && matches!(error, Error::NoResponse)) || (self.errors)(error)
+    }
+
+    /// Whether `result` moves the call on to the next model.
+    pub(crate) fn falls_back(&self, result: &Result<GenerateContentResponse>) -> bool {
+        let response = match result {
+            Ok(response) => response,
+            Err(error) => return self.falls_back_on_error(error),
+        };
+        response
+            .candidates
+            .iter()
+            .flatten()
+            .filter_map(|candidate| candidate.finish_reason.as_deref())
+            .any(|reason| {
+                self.finish_reasons.iter().any(|wanted| wanted == reason)
+                    || (self.blocked && BLOCK_REASONS.contains(&reason))
+            })
+    }
+
+    /// Call `attempt` with `primary`, then with each of `fallbacks` while
+    /// `falls_back` accepts the result. Every attempt but the last is
+    /// bounded by the timeout.
+    pub(crate) async fn run<T, F, Fut>(
+        &self,
+        primary: Model,
+        fallbacks: &[Model],
+        mut attempt: F,
+        falls_back: impl Fn(&Self, &Result<T>) -> bool,
+    ) -> Result<T>
+    where
+        F: FnMut(Model) -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        let mut model = primary;
+        for &next in fallbacks {
+            let result = match self.timeout {
+                Some(timeout) => tokio::time::timeout(timeout, attempt(model))
+                    .await
+                    .unwrap_or(Err(Error::Timeout(timeout))),
+                None => attempt(model).await,
+            };
+            if !falls_back(self, &result) {
+                return result;
+            }
+            model = next;
+        }
+        attempt(model).await
+    }
+}
+
+impl Default for FallbackPolicy {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl std::fmt::Debug for FallbackPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FallbackPolicy")
+            .field("blocked", &self.blocked)
+            .field("finish_reasons", &self.finish_reasons)
+            .field("timeout", &self.timeout)
+            .finish_non_exhaustive()
+    }
+}
@@ -0,0 +1,318 @@
+//! History windowing strategies for chat sessions.
+//!
+//! By default a [`ChatSession`](crate::ChatSession) sends its whole history
+//! with every message, which eventually exceeds the model's context window.
+//! A [`HistoryStrategy`] compacts the turns before each send; the compacted
+//! turns then replace the session's history, so memory stays bounded too.
+//!
+//! | Strategy | Keeps |
+//! |----------|-------|
+//! | [`LastTurns`] | The most recent N turns |
+//! | [`TokenBudget`] | The most recent turns that fit a token budget |
+//! | [`PinFirst`] | The first turns verbatim, the rest handled by another strategy |
+//! | [`Summarize`] | Recent turns, with older ones replaced by a model-written summary |
+//!
+//! Every strategy cuts the history at the start of a user turn, so the
+//! compacted conversation stays valid (see [`Conversation`](crate::Conversation)).
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! use gemini_rs::{Client, Model};
+//! use gemini_rs::history::{PinFirst, TokenBudget};
+//!
+//! # async fn example() -> Result<(), gemini_rs::Error> {
+//! let client = Client::new("YOUR_API_KEY");
+//! let model = client.model(Model::Gemini25Flash);
+//!
+//! // Always keep the opening exchange, then as much as fits in 8k tokens
+//! let mut chat = model
+//!     .start_chat()
+//!     .with_history_strategy(PinFirst::new(2, TokenBudget::estimated(8_000)));
+//!
+//! chat.send_message("Hello!").await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::client::ModelClient;
+use crate::error::Result;
+use crate::types::{Content, Part, Role};
+use futures::future::BoxFuture;
+
+/// Compacts chat history before it is sent.
+///
+/// `turns` holds the committed history followed by the message about to be
+/// sent. Implementations must keep that last turn and return a valid
+/// conversation; `model` is the session's model, available for token
+/// counting or summarization.
+pub trait HistoryStrategy: Send + Sync {
+    /// Return the turns to send (and keep) in place of `turns`.
+    fn compact<'a>(
+        &'a self,
+        turns: Vec<Content>,
+        model: &'a ModelClient,
+    ) -> BoxFuture<'a, Result<Vec<Content>>>;
+}
+
+/// Keeps the most recent `max_turns` turns.
+///
+/// The window is shrunk further if needed so that it starts with a user turn.
+#[derive(Debug, Clone, Copy)]
+pub struct LastTurns {
+    max_turns: usize,
+}
+
+impl LastTurns {
+    /// Keep at most `max_turns` turns (at least the message being sent).
+    pub fn new(max_turns: usize) -> Self {
+        Self { max_turns }
+    }
+}
+
+impl HistoryStrategy for LastTurns {
+    fn compact<'a>(
+        &'a self,
+        mut turns: Vec<Content>,
+        _model: &'a ModelClient,
+    ) -> BoxFuture<'a, Result<Vec<Content>>> {
+        let start = next_user_turn(&turns, turns.len().saturating_sub(self.max_turns));
+        turns.drain(..start);
+        Box::pin(async move { Ok(turns) })
+    }
+}
+
+/// How [`TokenBudget`] counts tokens.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenCounter {
+    /// Local estimate ([`Content::estimate_tokens`]); no API calls.
+    Estimate,
+    /// One countTokens call per send, used to calibrate the local estimate.
+    /// Includes the system instruction and tools in the count.
+    CountTokens,
+}
+
+/// Keeps the most recent turns that fit within a token budget.
+///
+/// The oldest turns are dropped first. The message being sent is always
+/// kept, even if it alone exceeds the budget.
+#[derive(Debug, Clone, Copy)]
+pub struct TokenBudget {
+    max_tokens: u64,
+    counter: TokenCounter,
+}
+
+impl TokenBudget {
+    /// Create a budget using the given counting method.
+    pub fn new(max_tokens: u64, counter: TokenCounter) -> Self {
+        Self {
+            max_tokens,
+            counter,
+        }
+    }
+
+    /// Create a budget counted with the local estimate.
+    pub fn estimated(max_tokens: u64) -> Self {
+        Self::new(max_tokens, TokenCounter::Estimate)
+    }
+
+    /// Create a budget counted with the countTokens API.
+    pub fn counted(max_tokens: u64) -> Self {
+        Self::new(max_tokens, TokenCounter::CountTokens)
+    }
+}
+
+impl HistoryStrategy for TokenBudget {
+    fn compact<'a>(
+        &'a self,
+        mut turns: Vec<Content>,
+        model: &'a ModelClient,
+    ) -> BoxFuture<'a, Result<Vec<Content>>> {
+        Box::pin(async move {
+            let estimates: Vec<u64> = turns.iter().map(Content::estimate_tokens).collect();
+            let estimated_total: u64 = estimates.iter().sum();
+
+            // Scale the per-turn estimates so they add up to the real count
+            let scale = match self.counter {
+                TokenCounter::Estimate => 1.0,
+                TokenCounter::CountTokens => {
+                    let counted = model.count_tokens(turns.clone()).await?.total_tokens;
+                    if counted <= self.max_tokens {
+                        return Ok(turns);
+                    }
+                    counted as f64 / estimated_total.max(1) as f64
+                }
+            };
+
+            let mut total = estimated_total as f64 * scale;
+            let mut start = 0;
+            while total > self.max_tokens as f64 && start + 1 < turns.len() {
+                let next = next_user_turn(&turns, start + 1);
+                total -= estimates[start..next].iter().sum::<u64>() as f64 * scale;
+                start = next;
+            }
+            turns.drain(..start);
+            Ok(turns)
+        })
+    }
+}
+
+/// Keeps the first turns verbatim and applies another strategy to the rest.
+///
+/// Useful to preserve an opening exchange that sets up the conversation.
+/// The pinned range is rounded down so it ends on a model turn.
+pub struct PinFirst<S> {
+    pinned: usize,
+    inner: S,
+}
+
+impl<S: HistoryStrategy> PinFirst<S> {
+    /// Pin the first `pinned` turns and compact the rest with `inner`.
+    pub fn new(pinned: usize, inner: S) -> Self {
+        Self { pinned, inner }
+    }
+}
+
+impl<S: HistoryStrategy> HistoryStrategy for PinFirst<S> {
+    fn compact<'a>(
+        &'a self,
+        mut turns: Vec<Content>,
+        model: &'a ModelClient,
+    ) -> BoxFuture<'a, Result<Vec<Content>>> {
+        Box::pin(async move {
+            // Never pin the message being sent
+            let limit = self.pinned.min(turns.len().saturating_sub(1));
+            let pinned = turns[..limit]
+                .iter()
+                .rposition(|turn| turn.role == Some(Role::Model))
+                .map_or(0, |index| index + 1);
+
+            let rest = turns.split_off(pinned);
+            let rest = self.inner.compact(rest, model).await?;
+            turns.extend(rest);
+            Ok(turns)
+        })
+    }
+}
+
+/// Replaces older turns with a summary written by a (cheaper) model.
+///
+/// Once the history grows beyond `max_turns`, everything but the last
+/// `keep_last` turns is summarized into a user turn holding the summary,
+/// acknowledged by a model turn. Earlier summaries are folded into the new
+/// one.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use gemini_rs::{Client, Model};
+/// use gemini_rs::history::Summarize;
+///
+/// let client = Client::new("YOUR_API_KEY");
+/// let summarizer = client.model(Model::Gemini15Flash8B);
+///
+/// let chat = client
+///     .model(Model::Gemini25Flash)
+///     .start_chat()
+///     .with_history_strategy(Summarize::new(summarizer, 40, 10));
+/// ```
+pub struct Summarize {
+    summarizer: ModelClient,
+    max_turns: usize,
+    keep_last: usize,
+    prompt: String,
+}
+
+/// Prefix of the user turn that carries a summary.
+pub const SUMMARY_PREFIX: &str = "Summary of the conversation so far:";
+
+const DEFAULT_SUMMARY_PROMPT: &str = "Summarize the following conversation between a user and \
+an assistant. Keep every fact, name, number, decision and open question that later turns may \
+rely on. Reply with the summary only.";
+
+impl Summarize {
+    /// Summarize with `summarizer` once the history exceeds `max_turns`,
+    /// keeping the last `keep_last` turns verbatim.
+    pub fn new(summarizer: ModelClient, max_turns: usize, keep_last: usize) -> Self {
+        Self {
+            summarizer,
+            max_turns,
+            keep_last,
+            prompt: DEFAULT_SUMMARY_PROMPT.to_string(),
+        }
+    }
+
+    /// Replace the instruction given to the summarizer.
+    pub fn with_prompt(mut self, prompt: impl Into<String>) -> Self {
+        self.prompt = prompt.into();
+        self
+    }
+}
+
+impl HistoryStrategy for Summarize {
+    fn compact<'a>(
+        &'a self,
+        mut turns: Vec<Content>,
+        _model: &'a ModelClient,
+    ) -> BoxFuture<'a, Result<Vec<Content>>> {
+        Box::pin(async move {
+            if turns.len() <= self.max_turns {
+                return Ok(turns);
+            }
+
+            let split = next_user_turn(&turns, turns.len().saturating_sub(self.keep_last.max(1)));
+            if split == 0 {
+                return Ok(turns);
+            }
+
+            let transcript: Vec<String> = turns[..split].iter().map(transcript_line).collect();
+            let prompt = format!("{}\n\n{}", self.prompt, transcript.join("\n"));
+            let summary = self.summarizer.generate_content(prompt).await?.text();
+
+            let mut compacted = vec![
+                Content::user(format!("{}\n{}", SUMMARY_PREFIX, summary.trim())),
+                Content::model("Understood. I'll keep that context in mind."),
+            ];
+            compacted.extend(turns.drain(split..));
+            Ok(compacted)
+        })
+    }
+}
+
+/// Index of the first turn at or after `from` that can open a conversation:
+/// a user turn that is not a function response. Falls back to the last turn.
+fn next_user_turn(turns: &[Content], from: usize) -> usize {
+    turns
+        .iter()
+        .enumerate()
+        .skip(from)
+        .find(|(_, turn)| {
+            turn.role == Some(Role::User) && turn.function_responses().next().is_none()
+        })
+        .map_or(turns.len().saturating_sub(1), |(index, _)| index)
+}
+
+fn transcript_line(turn: &Content) -> String {
+    let speaker = match turn.role {
+        Some(Role::Model) => "Assistant",
+        Some(Role::Function) => "Function",
+        _ => "User",
+    };
+    let parts: Vec<String> = turn
+        .parts
+        .iter()
+        .map(|part| match part {
+            Part::Text { text } => text.clone(),
+            #[cfg(feature = "multimodal")]
+            Part::InlineData { inline_data } => format!("[{} attachment]", inline_data.mime_type),
+            Part::FunctionCall { function_call } => {
+                format!("[called {}({})]", function_call.name, function_call.args)
+            }
+            Part::FunctionResponse { function_response } => format!(
+                "[{} returned {}]",
+                function_response.name, function_response.response
+            ),
+        })
+        .collect();
+    format!("{}: {}", speaker, parts.join(" "))
+}
@@ -0,0 +1,348 @@
+//! Spreading calls over several API keys.
+//!
+//! A [`KeyPool`] holds API keys of the Gemini Developer API, for example
+//! from several projects, and picks one for each call made through
+//! [`Client::with_key_pool`](crate::Client::with_key_pool):
+//!
+//! - [`KeySelection::RoundRobin`] (the default) takes the keys in turn,
+//! - [`KeySelection::LeastRecentlyThrottled`] prefers the key that has gone
+//!   longest without being throttled.
+//!
+//! A key answered with HTTP 429 or 403 is quarantined: it is skipped for the
+//! `Retry-After` delay, or the [quarantine](KeyPool::quarantine) period (one
+//! minute by default). When every key is quarantined, the one released
+//! first is used. Clones of the pool, and of clients using it, share the
+//! keys' health.
+//!
+//! Keys are identified by an id, `key-0`, `key-1`, ... or the names given
+//! to [`KeyPool::named`]. Errors of calls made with a pooled key are
+//! [`Error::WithKey`], which names the key by its id; the key itself never
+//! appears in errors or `Debug` output.
+//!
+//! Files, batches and operations belong to a project, so requests other
+//! than model calls always use the first key.
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! use gemini_rs::key_pool::{KeyPool, KeySelection};
+//! use gemini_rs::{Client, Model};
+//!
+//! # async fn example() -> Result<(), gemini_rs::Error> {
+//! let pool = KeyPool::named([("team-a", "KEY_A"), ("team-b", "KEY_B")])
+//!     .selection(KeySelection::LeastRecentlyThrottled);
+//! let client = Client::new("UNUSED").with_key_pool(pool.clone());
+//!
+//! if let Err(err) = client.model(Model::Gemini25Flash).generate_content("Hi").await {
+//!     eprintln!("{} failed: {}", err.key_id().unwrap_or("no key"), err);
+//! }
+//! for key in pool.health() {
+//!     println!("{}: {} requests, {} throttled", key.id, key.requests, key.throttled);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::backend::API_KEY_HEADER;
+use crate::clock::{Clock, SystemClock};
+use crate::error::{Error, Result, REDACTED};
+use crate::transport;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use reqwest::StatusCode;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// How a [`KeyPool`] picks the key for a call.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum KeySelection {
+    /// Take the keys in turn.
+    #[default]
+    RoundRobin,
+    /// Take the key throttled longest ago, or never; ties are taken in turn.
+    LeastRecentlyThrottled,
+}
+
+/// Health of one key of a [`KeyPool`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyHealth {
+    /// The key's id.
+    pub id: String,
+    /// Requests sent with the key.
+    pub requests: u64,
+    /// Calls answered with HTTP 429 or 403.
+    pub throttled: u64,
+    /// Time left in quarantine, if the key is quarantined.
+    pub quarantined_for: Option<Duration>,
+}
+
+struct Key {
+    id: String,
+    value: String,
+    requests: u64,
+    throttled: u64,
+    last_throttled: Option<Instant>,
+    quarantined_until: Option<Instant>,
+}
+
+/// A set of API keys shared by the calls of a client.
+///
+/// See the [module documentation](self).
+#[derive(Clone)]
+pub struct KeyPool {
+    keys: Arc<Mutex<Vec<Key>>>,
+    next: Arc<Mutex<usize>>,
+    selection: KeySelection,
+    quarantine: Duration,
+    clock: Arc<dyn Clock>,
+}
+
+impl KeyPool {
+    /// Create a pool of `keys`, with ids `key-0`, `key-1`, ... in order.
+    pub fn new(keys: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        Self::named(
+            keys.into_iter()
+                .enumerate()
+                .map(|(index, key)| (format!("key-{}", index), key)),
+        )
+    }
+
+    /// Create a pool of `(id, key)` pairs.
+    pub fn named(keys: impl IntoIterator<Item = (impl Into<String>, impl Into<String>)>) -> Self {
+        let keys = keys
+            .into_iter()
+            .map(|(id, value)| Key {
+                id: id.into(),
+                value: value.into(),
+                requests: 0,
+                throttled: 0,
+                last_throttled: None,
+                quarantined_until: None,
+            })
+            .collect();
+        Self {
+            keys: Arc::new(Mutex::new(keys)),
+            next: Arc::default(),
+            selection: KeySelection::default(),
+            quarantine: Duration::from_secs(60),
+            clock: Arc::new(SystemClock),
+        }
+    }
+
+    /// Choose how keys are picked; [`KeySelection::RoundRobin`] by default.
+    pub fn selection(mut self, selection: KeySelection) -> Self {
+        self.selection = selection;
+        self
+    }
+
+    /// Skip throttled keys for `duration` when the response has no
+    /// `Retry-After` header; one minute by default.
+    pub fn quarantine(mut self, duration: Duration) -> Self {
+        self.quarantine = duration;
+        self
+    }
+
+    /// Read the time from `clock` instead of the system clock.
+    pub fn with_clock(mut self, clock: impl Clock + 'static) -> Self {
+        self.clock = Arc::new(clock);
+        self
+    }
+
+    /// The ids of the keys, in order.
+    pub fn ids(&self) -> Vec<String> {
+        let keys = self.keys.lock().unwrap();
+        keys.iter().map(|key| key.id.clone()).collect()
+    }
+
+    /// The health of each key, in order.
+    pub fn health(&self) -> Vec<KeyHealth> {
+        let now = self.clock.now();
+        let keys = self.keys.lock().unwrap();
+        keys.iter()
+            .map(|key| KeyHealth {
+                id: key.id.clone(),
+                requests: key.requests,
+                throttled: key.throttled,
+                quarantined_for: key
+                    .quarantined_until
+                    .filter(|until| *until > now)
+                    .map(|until| until - now),
+            })
+            .collect()
+    }
+
+    /// Pick the key for a model call.
+    pub(crate) fn acquire(&self) -> Result<KeyLease> {
+        let now = self.clock.now();
+        let keys = self.keys.lock().unwrap();
+        if keys.is_empty() {
+            return Err(Error::ConfigError("the API key pool is empty".to_string()));
+        }
+        let mut next = self.next.lock().unwrap();
+        let available = |key: &Key| key.quarantined_until.map_or(true, |until| until <= now);
+        // Candidates in turn, starting after the last key taken
+        let order = (0..keys.len()).map(|offset| (*next + offset) % keys.len());
+        let index = match self.selection {
+            KeySelection::RoundRobin => order.clone().find(|&index| available(&keys[index])),
+            KeySelection::LeastRecentlyThrottled => order
+                .clone()
+                .filter(|&index| available(&keys[index]))
+                .min_by_key(|&index| keys[index].last_throttled),
+        }
+        .or_else(|| order.min_by_key(|&index| keys[index].quarantined_until))
+        .unwrap_or_default();
+        *next = index + 1;
+        self.lease(&keys, index)
+    }
+
+    /// Take the first key, used for requests on project resources.
+    pub(crate) fn first(&self) -> Result<KeyLease> {
+        let keys = self.keys.lock().unwrap();
+        if keys.is_empty() {
+            return Err(Error::ConfigError("the API key pool is empty".to_string()));
+        }
+        self.lease(&keys, 0)
+    }
+
+    fn lease(&self, keys: &[Key], index: usize) -> Result<KeyLease> {
+        let key = &keys[index];
+        let mut value = HeaderValue::from_str(&key.value).map_err(|_| Error::WithKey {
+            key_id: key.id.clone(),
+            error: Box::new(Error::InvalidApiKey),
+        })?;
+        value.set_sensitive(true);
+        Ok(KeyLease {
+            pool: self.clone(),
+            index,
+            id: key.id.clone(),
+            value,
+        })
+    }
+
+    /// Replace every key of the pool in `text`.
+    pub(crate) fn redact(&self, text: &str) -> String {
+        let keys = self.keys.lock().unwrap();
+        keys.iter()
+            .filter(|key| !key.value.is_empty())
+            .fold(text.to_string(), |text, key| {
+                text.replace(key.value.as_str(), REDACTED)
+            })
+    }
+}
+
+impl<S: Into<String>> From<Vec<S>> for KeyPool {
+    fn from(keys: Vec<S>) -> Self {
+        Self::new(keys)
+    }
+}
+
+impl<S: Into<String>, const N: usize> From<[S; N]> for KeyPool {
+    fn from(keys: [S; N]) -> Self {
+        Self::new(keys)
+    }
+}
+
+impl std::fmt::Debug for KeyPool {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("KeyPool")
+            .field("keys", &self.ids())
+            .field("selection", &self.selection)
+            .field("quarantine", &self.quarantine)
+            .finish()
+    }
+}
+
+/// The key picked for one call, which records how the call went.
+#[derive(Clone)]
+pub(crate) struct KeyLease {
+    pool: KeyPool,
+    index: usize,
+    id: String,
+    value: HeaderValue,
+}
+
+impl KeyLease {
+    /// Add the key to request headers, counting the request against it.
+    pub(crate) fn authorize(&self, headers: &mut HeaderMap) {
+        self.pool.keys.lock().unwrap()[self.index].requests += 1;
+        headers.insert(HeaderName::from_static(API_KEY_HEADER), self.value.clone());
+    }
+
+    /// Record the response to the call, quarantining the key if it was
+    /// throttled.
+    pub(crate) fn record(&self, status: StatusCode, headers: &HeaderMap) {
+        if !matches!(
+            status,
+            StatusCode::TOO_MANY_REQUESTS | StatusCode::FORBIDDEN
+        ) {
+            return;
+        }
+        let retry_after = transport::retry_after(headers);
+        let now = self.pool.clock.now();
+        let mut keys = self.pool.keys.lock().unwrap();
+        let key = &mut keys[self.index];
+        key.throttled += 1;
+        key.last_throttled = Some(now);
+        key.quarantined_until = Some(now + retry_after.unwrap_or(self.pool.quarantine));
+    }
+
+    /// Attach the key's id to `error`.
+    pub(crate) fn tag(&self, error: Error) -> Error {
+        match error {
+            Error::WithKey { .. } => error,
+            error => Error::WithKey {
+                key_id: self.id.clone(),
+                error: Box::new(error),
+            },
+        }
+    }
+
+    /// Attach the id of `key`, if any, to the error of `result`.
+    pub(crate) fn tagged<T>(key: Option<&KeyLease>, result: Result<T>) -> Result<T> {
+        match key {
+            Some(key) => result.map_err(|error| key.tag(error)),
+            None => result,
+        }
+    }
+}
+
+/// The pooled key of one model call, shared by the requests the call makes
+/// through the middleware chain. The first request uses the key assigned
+/// when the call starts; requests after it, such as retries, take a fresh
+/// key each.
+#[derive(Clone, Default)]
+pub(crate) struct KeySlot(Arc<Mutex<Option<(KeyLease, bool)>>>);
+
+impl KeySlot {
+    /// Assign the key of the call's first request.
+    pub(crate) fn assign(&self, key: KeyLease) {
+        *self.0.lock().unwrap() = Some((key, false));
+    }
+
+    /// Take the key for the next request of the call.
+    pub(crate) fn lease(&self, pool: &KeyPool) -> Result<KeyLease> {
+        let mut slot = self.0.lock().unwrap();
+        if let Some((key, used)) = slot.as_mut().filter(|(_, used)| !*used) {
+            *used = true;
+            return Ok(key.clone());
+        }
+        let key = pool.acquire()?;
+        *slot = Some((key.clone(), true));
+        Ok(key)
+    }
+
+    /// Attach the id of the call's latest key, if any, to the error of
+    /// `result`.
+    pub(crate) fn tagged<T>(&self, result: Result<T>) -> Result<T> {
+        let key = self.0.lock().unwrap().as_ref().map(|(key, _)| key.clone());
+        KeyLease::tagged(key.as_ref(), result)
+    }
+}
+
+impl std::fmt::Debug for KeySlot {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let slot = self.0.lock().unwrap();
+        f.debug_tuple("KeySlot")
+            .field(&slot.as_ref().map(|(key, _)| &key.id))
+            .finish()
+    }
+}
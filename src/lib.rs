@@ -8,8 +8,13 @@
 //! - **Type-safe** - Full type safety with Rust's type system
 //! - **Async/await** - Built on tokio for async operations
 //! - **JSON mode** - Generate structured JSON outputs with automatic parsing
-//! - **Chat sessions** - Maintain conversation history
+//! - **Chat sessions** - Maintain conversation history, persist and resume it
 //! - **Multiple models** - Support for all Gemini models
+//! - **Vertex AI** - Service-account and Application Default Credentials
+//! - **Configuration** - Load clients from environment variables or TOML/JSON files
+//! - **Streaming** - Receive responses chunk by chunk as they are generated
+//! - **Pluggable transport** - Swap the HTTP stack or answer requests in-process
+//! - **Observability** - `tracing` spans following the OpenTelemetry GenAI conventions, and per-call metrics
 //!
 //! ## Quick Start
 //!
@@ -109,18 +114,54 @@
 //!
 //! match model.generate_content("Hello").await {
 //!     Ok(response) => println!("{}", response.text()),
-//!     Err(Error::RateLimitExceeded) => eprintln!("Rate limited"),
-//!     Err(e) => eprintln!("Error: {}", e),
+//!     // Calls made with a pooled API key wrap errors in `Error::WithKey`
+//!     Err(e) => match e.without_key() {
+//!         Error::RateLimitExceeded { .. } => eprintln!("Rate limited"),
+//!         _ => eprintln!("Error: {}", e),
+//!     },
 //! }
 //! # }
 //! ```
 
+pub mod auth;
+pub mod backend;
+pub mod batch;
+pub mod budget;
+pub mod builder;
+pub mod cache;
+pub mod call;
 pub mod client;
+pub mod clock;
+pub mod concurrent;
+pub mod config;
+pub mod conversation;
+pub mod cost;
 pub mod error;
+pub mod fallback;
+pub mod history;
+pub mod key_pool;
+pub mod metrics;
+pub mod middleware;
 pub mod models;
+pub mod operation;
+pub mod rate_limit;
+#[cfg(feature = "tower")]
+pub mod service;
+pub mod store;
+pub mod telemetry;
+#[cfg(feature = "testing")]
+pub mod testing;
+pub mod transport;
 pub mod types;
 
-pub use client::{ChatSession, Client, ModelClient};
-pub use error::{Error, Result};
+pub use builder::ClientBuilder;
+pub use client::{ChatSession, Client, ModelClient, ResponseStream};
+pub use config::GeminiConfig;
+pub use conversation::Conversation;
+pub use error::{Error, Result, REDACTED};
 pub use models::Model;
-pub use types::{Content, GenerateContentResponse, GenerationConfig, Part, SafetySettings};
+pub use store::{ChatSnapshot, ChatStore};
+pub use types::{
+    Content, FunctionCall, FunctionResponse, GenerateContentResponse, GenerationConfig, Part, Role,
+    SafetySettings,
+};
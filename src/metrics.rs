@@ -0,0 +1,314 @@
+//! Metrics for every API call.
+//!
+//! Install [`MetricsSink`]s with [`Client::with_metrics`](crate::Client::with_metrics)
+//! and each receives one [`CallMetrics`] per API call: model, endpoint,
+//! outcome, latency, time to first chunk for streams, token usage, cache
+//! status and the labels set with
+//! [`Client::with_metric_label`](crate::Client::with_metric_label).
+//! Responses served from the [response cache](crate::cache) are reported
+//! too, with no token usage, and so are calls that middleware answers or
+//! fails without calling the API, e.g. with [`Error::BudgetExceeded`] or a
+//! fail-fast [`Error::RateLimitExceeded`]. Calls abandoned before they
+//! finish, e.g. by a [`CallOptions`](crate::call::CallOptions) timeout or
+//! cancellation, are reported with the `cancelled` error type.
+//! A sink turns these into counters and histograms for the metrics backend
+//! in use. [`MemoryMetrics`] keeps them in memory, e.g. for tests.
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! use gemini_rs::metrics::{CallMetrics, MetricsSink};
+//! use gemini_rs::Client;
+//!
+//! /// Logs every call.
+//! struct LogMetrics;
+//!
+//! impl MetricsSink for LogMetrics {
+//!     fn record(&self, call: &CallMetrics) {
+//!         println!(
+//!             "{} {} {} in {:?}, {} output tokens",
+//!             call.model,
+//!             call.endpoint,
+//!             call.outcome,
+//!             call.latency,
+//!             call.usage.candidates_token_count
+//!         );
+//!     }
+//! }
+//!
+//! let client = Client::new("YOUR_API_KEY")
+//!     .with_metrics(LogMetrics)
+//!     .with_metric_label("tenant", "acme");
+//! ```
+
+use crate::cache::CacheStatus;
+use crate::client::ResponseStream;
+use crate::error::Error;
+use crate::middleware::{Endpoint, ModelRequest};
+use crate::types::{GenerateContentResponse, UsageMetadata};
+use futures::stream::StreamExt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Receives the metrics of every API call.
+pub trait MetricsSink: Send + Sync {
+    /// Record a finished call. Called once per call, after the response was
+    /// read (for streams, when the stream ends or is dropped).
+    fn record(&self, call: &CallMetrics);
+}
+
+impl<S: MetricsSink + ?Sized> MetricsSink for Arc<S> {
+    fn record(&self, call: &CallMetrics) {
+        (**self).record(call)
+    }
+}
+
+/// How an API call ended.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Outcome {
+    /// The call succeeded.
+    Success,
+    /// The call failed. `error_type` is the HTTP status code for API
+    /// errors, otherwise the kind of error, e.g. `timeout` or `json`.
+    Error {
+        /// Status code or error kind.
+        error_type: String,
+    },
+}
+
+impl Outcome {
+    /// `success` or `error`, for use as a metric label.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Outcome::Success => "success",
+            Outcome::Error { .. } => "error",
+        }
+    }
+
+    /// Whether the call succeeded.
+    pub fn is_success(&self) -> bool {
+        matches!(self, Outcome::Success)
+    }
+}
+
+impl std::fmt::Display for Outcome {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// The metrics of one API call.
+#[derive(Debug, Clone)]
+pub struct CallMetrics {
+    /// Model id, e.g. `gemini-2.5-flash`.
+    pub model: String,
+    /// The API method called.
+    pub endpoint: Endpoint,
+    /// How the call ended.
+    pub outcome: Outcome,
+    /// Time until the response (for streams, the last chunk) was read.
+    pub latency: Duration,
+    /// Time until the first chunk of a stream was read.
+    pub time_to_first_token: Option<Duration>,
+    /// Token usage reported by the API; zero for `countTokens` and for
+    /// calls that failed before a response was read.
+    pub usage: UsageMetadata,
+    /// Retry attempt, 0 for the first try.
+    pub attempt: u32,
+    /// Whether the [response cache](crate::cache) answered the call; `None`
+    /// when no cache applied.
+    pub cache: Option<CacheStatus>,
+    /// Labels set with [`Client::with_metric_label`](crate::Client::with_metric_label).
+    pub labels: Vec<(String, String)>,
+}
+
+impl CallMetrics {
+    /// Get the value of a label.
+    pub fn label(&self, key: &str) -> Option<&str> {
+        self.labels
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+/// A [`MetricsSink`] that keeps every call in memory.
+///
+/// Clones share the same records, so a clone can be handed to the client
+/// and the original inspected afterwards.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use gemini_rs::metrics::MemoryMetrics;
+/// use gemini_rs::{Client, Model};
+///
+/// # async fn example() -> Result<(), gemini_rs::Error> {
+/// let metrics = MemoryMetrics::new();
+/// let model = Client::new("YOUR_API_KEY")
+///     .with_metrics(metrics.clone())
+///     .model(Model::Gemini25Flash);
+///
+/// model.generate_content("Hello").await?;
+/// assert_eq!(metrics.calls().len(), 1);
+/// println!("{} tokens", metrics.total_usage().total_token_count);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct MemoryMetrics {
+    calls: Arc<Mutex<Vec<CallMetrics>>>,
+}
+
+impl MemoryMetrics {
+    /// Create an empty sink.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Get every recorded call, oldest first.
+    pub fn calls(&self) -> Vec<CallMetrics> {
+        self.calls.lock().unwrap().clone()
+    }
+
+    /// Count the calls matching `filter`.
+    pub fn count(&self, filter: impl Fn(&CallMetrics) -> bool) -> usize {
+        self.calls
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|c| filter(c))
+            .count()
+    }
+
+    /// Sum the token usage of all calls.
+    pub fn total_usage(&self) -> UsageMetadata {
+        let calls = self.calls.lock().unwrap();
+        let mut total = UsageMetadata::default();
+        for call in calls.iter() {
+            total.prompt_token_count += call.usage.prompt_token_count;
+            total.candidates_token_count += call.usage.candidates_token_count;
+            total.thoughts_token_count += call.usage.thoughts_token_count;
+            total.cached_content_token_count += call.usage.cached_content_token_count;
+            total.total_token_count += call.usage.total_token_count;
+        }
+        total
+    }
+
+    /// Remove all records.
+    pub fn clear(&self) {
+        self.calls.lock().unwrap().clear();
+    }
+}
+
+impl MetricsSink for MemoryMetrics {
+    fn record(&self, call: &CallMetrics) {
+        self.calls.lock().unwrap().push(call.clone());
+    }
+}
+
+/// Measures one call and reports it to the sink when dropped. A call
+/// dropped before it finished, e.g. by a timeout, is reported as
+/// cancelled.
+pub(crate) struct CallRecorder {
+    sinks: Vec<Arc<dyn MetricsSink>>,
+    started: Instant,
+    metrics: CallMetrics,
+    dispatched: Option<Arc<AtomicBool>>,
+}
+
+impl CallRecorder {
+    pub(crate) fn start(
+        sinks: &[Arc<dyn MetricsSink>],
+        request: &ModelRequest,
+        labels: &[(String, String)],
+    ) -> Self {
+        Self {
+            sinks: sinks.to_vec(),
+            started: Instant::now(),
+            metrics: CallMetrics {
+                model: request.model.as_str().to_string(),
+                endpoint: request.endpoint,
+                outcome: Outcome::Error {
+                    error_type: Error::Cancelled.error_type(),
+                },
+                latency: Duration::ZERO,
+                time_to_first_token: None,
+                usage: UsageMetadata::default(),
+                attempt: request.attempt,
+                cache: request.cache,
+                labels: labels.to_vec(),
+            },
+            dispatched: None,
+        }
+    }
+
+    /// Report the call only if none of its requests reached the API, which
+    /// report it otherwise; e.g. when middleware failed it or the cache
+    /// answered it.
+    pub(crate) fn unless_dispatched(mut self, dispatched: Arc<AtomicBool>) -> Self {
+        self.dispatched = Some(dispatched);
+        self
+    }
+
+    pub(crate) fn success(&mut self) {
+        self.metrics.outcome = Outcome::Success;
+    }
+
+    pub(crate) fn response(&mut self, response: &GenerateContentResponse) {
+        if let Some(usage) = &response.usage_metadata {
+            self.metrics.usage = usage.clone();
+        }
+    }
+
+    /// Report a response served from the cache: no tokens were used.
+    pub(crate) fn cache_hit(&mut self) {
+        self.metrics.cache = Some(CacheStatus::Hit);
+    }
+
+    pub(crate) fn error(&mut self, error: &Error) {
+        self.metrics.outcome = Outcome::Error {
+            error_type: error.error_type(),
+        };
+    }
+
+    /// Report the call when the stream ends or is dropped, recording the
+    /// time to the first chunk and the usage of the last one.
+    pub(crate) fn stream(mut self, mut stream: ResponseStream) -> ResponseStream {
+        async_stream::stream! {
+            let mut failed = false;
+            while let Some(item) = stream.next().await {
+                if self.metrics.time_to_first_token.is_none() {
+                    self.metrics.time_to_first_token = Some(self.started.elapsed());
+                }
+                match &item {
+                    Ok(chunk) => self.response(chunk),
+                    Err(error) => {
+                        self.error(error);
+                        failed = true;
+                    }
+                }
+                yield item;
+            }
+            if !failed {
+                self.success();
+            }
+        }
+        .boxed()
+    }
+}
+
+impl Drop for CallRecorder {
+    fn drop(&mut self) {
+        if let Some(dispatched) = &self.dispatched {
+            if dispatched.load(Ordering::SeqCst) {
+                return;
+            }
+        }
+        self.metrics.latency = self.started.elapsed();
+        for sink in &self.sinks {
+            sink.record(&self.metrics);
+        }
+    }
+}
@@ -0,0 +1,314 @@
+//! Interceptors and middleware around every model call.
+//!
+//! Layers are installed on a [`Client`] and run, in the order they were
+//! added, for every call made through its [`ModelClient`](crate::ModelClient)s
+//! and [`ChatSession`](crate::ChatSession)s: `generateContent`,
+//! `streamGenerateContent` and `countTokens`.
+//!
+//! - An [`Interceptor`] has synchronous hooks to inspect or rewrite the
+//!   request before it is sent and to inspect each response (every chunk,
+//!   when streaming). Returning an error aborts the call.
+//! - A [`Middleware`] wraps the rest of the chain asynchronously. It can
+//!   add headers, time or retry the call, or answer without calling
+//!   [`Next::run`] at all (e.g. from a cache).
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! use futures::future::BoxFuture;
+//! use gemini_rs::middleware::{Interceptor, Middleware, ModelRequest, ModelResponse, Next};
+//! use gemini_rs::types::{GenerateContentRequest, Part};
+//! use gemini_rs::{Client, Result};
+//!
+//! /// Masks e-mail addresses in prompts.
+//! struct ScrubEmails;
+//!
+//! impl Interceptor for ScrubEmails {
+//!     fn before_request(&self, request: &mut GenerateContentRequest) -> Result<()> {
+//!         for part in request.contents.iter_mut().flat_map(|c| c.parts.iter_mut()) {
+//!             if let Part::Text { text } = part {
+//!                 *text = text.replace("alice@example.com", "<email>");
+//!             }
+//!         }
+//!         Ok(())
+//!     }
+//! }
+//!
+//! /// Adds a trace header to every call.
+//! struct TraceHeader;
+//!
+//! impl Middleware for TraceHeader {
+//!     fn handle<'a>(
+//!         &'a self,
+//!         mut request: ModelRequest,
+//!         next: Next<'a>,
+//!     ) -> BoxFuture<'a, Result<ModelResponse>> {
+//!         request.headers.insert("x-trace-id", "4bf92f3577b34da6".parse().unwrap());
+//!         next.run(request)
+//!     }
+//! }
+//!
+//! let client = Client::new("YOUR_API_KEY")
+//!     .with_interceptor(ScrubEmails)
+//!     .with_middleware(TraceHeader);
+//! ```
+
+use crate::cache::CacheStatus;
+use crate::client::{Client, ResponseStream};
+use crate::error::{Error, Result};
+use crate::key_pool::KeySlot;
+use crate::models::Model;
+use crate::types::{CountTokensResponse, GenerateContentRequest, GenerateContentResponse};
+use futures::future::BoxFuture;
+use futures::stream::StreamExt;
+use reqwest::header::HeaderMap;
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
+
+/// The API method a [`ModelRequest`] calls.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Endpoint {
+    /// `generateContent`
+    GenerateContent,
+    /// `streamGenerateContent`
+    StreamGenerateContent,
+    /// `countTokens`
+    CountTokens,
+}
+
+impl Endpoint {
+    /// Get the REST method name, e.g. `generateContent`.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Endpoint::GenerateContent => "generateContent",
+            Endpoint::StreamGenerateContent => "streamGenerateContent",
+            Endpoint::CountTokens => "countTokens",
+        }
+    }
+}
+
+impl std::fmt::Display for Endpoint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// A model call passing through the middleware chain.
+#[derive(Debug, Clone)]
+pub struct ModelRequest {
+    /// The model being called.
+    pub model: Model,
+    /// The API method being called.
+    pub endpoint: Endpoint,
+    /// The request body.
+    pub body: GenerateContentRequest,
+    /// Extra headers sent with the request, in addition to credentials.
+    pub headers: HeaderMap,
+    /// Retry attempt, 0 for the first try. Middleware that retries a call
+    /// should increment it; it is reported in tracing spans.
+    pub attempt: u32,
+    /// Set to [`CacheStatus::Miss`] when the [response cache](crate::cache)
+    /// was consulted and had no response; reported in metrics.
+    pub cache: Option<CacheStatus>,
+    /// The pooled API key of the call, shared with clones of the request.
+    pub(crate) key: KeySlot,
+    /// Set when interceptors already ran `before_request` on the body, for
+    /// the response cache lookup.
+    pub(crate) intercepted: bool,
+    /// Set once a request of the call reaches the API, which then reports
+    /// the call's metrics; shared with clones of the request.
+    pub(crate) dispatched: Arc<AtomicBool>,
+}
+
+impl ModelRequest {
+    pub(crate) fn new(model: Model, endpoint: Endpoint, body: GenerateContentRequest) -> Self {
+        Self {
+            model,
+            endpoint,
+            body,
+            headers: HeaderMap::new(),
+            attempt: 0,
+            cache: None,
+            key: KeySlot::default(),
+            intercepted: false,
+            dispatched: Arc::default(),
+        }
+    }
+}
+
+/// The result of a model call; the variant matches the request's
+/// [`Endpoint`].
+pub enum ModelResponse {
+    /// Response of `generateContent`.
+    Generate(GenerateContentResponse),
+    /// Response of `streamGenerateContent`.
+    Stream(ResponseStream),
+    /// Response of `countTokens`.
+    CountTokens(CountTokensResponse),
+}
+
+impl ModelResponse {
+    fn endpoint(&self) -> Endpoint {
+        match self {
+            ModelResponse::Generate(_) => Endpoint::GenerateContent,
+            ModelResponse::Stream(_) => Endpoint::StreamGenerateContent,
+            ModelResponse::CountTokens(_) => Endpoint::CountTokens,
+        }
+    }
+
+    fn mismatch(self, expected: Endpoint) -> Error {
+        Error::GenerationFailed(format!(
+            "middleware returned a {} response to a {} request",
+            self.endpoint(),
+            expected
+        ))
+    }
+
+    pub(crate) fn into_generate(self) -> Result<GenerateContentResponse> {
+        match self {
+            ModelResponse::Generate(response) => Ok(response),
+            other => Err(other.mismatch(Endpoint::GenerateContent)),
+        }
+    }
+
+    pub(crate) fn into_stream(self) -> Result<ResponseStream> {
+        match self {
+            ModelResponse::Stream(stream) => Ok(stream),
+            other => Err(other.mismatch(Endpoint::StreamGenerateContent)),
+        }
+    }
+
+    pub(crate) fn into_count_tokens(self) -> Result<CountTokensResponse> {
+        match self {
+            ModelResponse::CountTokens(response) => Ok(response),
+            other => Err(other.mismatch(Endpoint::CountTokens)),
+        }
+    }
+}
+
+impl std::fmt::Debug for ModelResponse {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ModelResponse::Generate(response) => f.debug_tuple("Generate").field(response).finish(),
+            ModelResponse::Stream(_) => f.write_str("Stream(..)"),
+            ModelResponse::CountTokens(response) => {
+                f.debug_tuple("CountTokens").field(response).finish()
+            }
+        }
+    }
+}
+
+/// Synchronous hooks run before a request is sent and after each response.
+///
+/// Both hooks default to doing nothing. Returning an error aborts the call
+/// with that error.
+pub trait Interceptor: Send + Sync {
+    /// Inspect or modify the request before it is sent.
+    fn before_request(&self, request: &mut GenerateContentRequest) -> Result<()> {
+        let _ = request;
+        Ok(())
+    }
+
+    /// Inspect a response. Called once per chunk when streaming, and not
+    /// called for `countTokens`.
+    fn after_response(&self, response: &GenerateContentResponse) -> Result<()> {
+        let _ = response;
+        Ok(())
+    }
+}
+
+impl<I: Interceptor + ?Sized> Interceptor for Arc<I> {
+    fn before_request(&self, request: &mut GenerateContentRequest) -> Result<()> {
+        (**self).before_request(request)
+    }
+
+    fn after_response(&self, response: &GenerateContentResponse) -> Result<()> {
+        (**self).after_response(response)
+    }
+}
+
+/// Asynchronous middleware wrapping the rest of the chain.
+pub trait Middleware: Send + Sync {
+    /// Handle `request`, usually by passing it on with [`Next::run`].
+    fn handle<'a>(
+        &'a self,
+        request: ModelRequest,
+        next: Next<'a>,
+    ) -> BoxFuture<'a, Result<ModelResponse>>;
+}
+
+impl<M: Middleware + ?Sized> Middleware for Arc<M> {
+    fn handle<'a>(
+        &'a self,
+        request: ModelRequest,
+        next: Next<'a>,
+    ) -> BoxFuture<'a, Result<ModelResponse>> {
+        (**self).handle(request, next)
+    }
+}
+
+/// The remainder of the middleware chain, ending with the API call.
+///
+/// `Next` is `Copy`, so a middleware can run the rest of the chain more
+/// than once, e.g. to retry a call or to count tokens first.
+#[derive(Clone, Copy)]
+pub struct Next<'a> {
+    client: &'a Client,
+    layers: &'a [Arc<dyn Middleware>],
+}
+
+impl<'a> Next<'a> {
+    pub(crate) fn new(client: &'a Client, layers: &'a [Arc<dyn Middleware>]) -> Self {
+        Self { client, layers }
+    }
+
+    /// Run the remaining layers and then the API call.
+    pub fn run(self, request: ModelRequest) -> BoxFuture<'a, Result<ModelResponse>> {
+        match self.layers.split_first() {
+            Some((layer, rest)) => layer.handle(
+                request,
+                Next {
+                    client: self.client,
+                    layers: rest,
+                },
+            ),
+            None => Box::pin(self.client.dispatch(request)),
+        }
+    }
+}
+
+/// Runs an [`Interceptor`] as a [`Middleware`] layer.
+pub(crate) struct InterceptorLayer(pub(crate) Arc<dyn Interceptor>);
+
+impl Middleware for InterceptorLayer {
+    fn handle<'a>(
+        &'a self,
+        mut request: ModelRequest,
+        next: Next<'a>,
+    ) -> BoxFuture<'a, Result<ModelResponse>> {
+        Box::pin(async move {
+            if !request.intercepted {
+                self.0.before_request(&mut request.body)?;
+            }
+            match next.run(request).await? {
+                ModelResponse::Generate(response) => {
+                    self.0.after_response(&response)?;
+                    Ok(ModelResponse::Generate(response))
+                }
+                ModelResponse::Stream(stream) => {
+                    let interceptor = self.0.clone();
+                    Ok(ModelResponse::Stream(
+                        stream
+                            .map(move |chunk| {
+                                let chunk = chunk?;
+                                interceptor.after_response(&chunk)?;
+                                Ok(chunk)
+                            })
+                            .boxed(),
+                    ))
+                }
+                other => Ok(other),
+            }
+        })
+    }
+}
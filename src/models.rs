@@ -2,7 +2,10 @@
 //!
 //! This module defines the available Gemini models and their API identifiers.
 
+use crate::error::Error;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use std::fmt;
+use std::str::FromStr;
 
 /// Available Google Gemini models.
 ///
@@ -146,3 +149,44 @@ impl From<&str> for Model {
         }
     }
 }
+
+impl FromStr for Model {
+    type Err = Error;
+
+    /// Parse a model from its API identifier, rejecting unknown names.
+    ///
+    /// Unlike [`From<&str>`](Model#impl-From%3C%26str%3E-for-Model), unknown
+    /// identifiers return [`Error::InvalidModel`] instead of a default.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use gemini_rs::Model;
+    ///
+    /// assert_eq!("gemini-1.5-pro".parse::<Model>().unwrap(), Model::Gemini15Pro);
+    /// assert!("unknown".parse::<Model>().is_err());
+    /// ```
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let name = s.strip_prefix("models/").unwrap_or(s);
+        let model = Model::from(name);
+        if model.as_str() == name {
+            Ok(model)
+        } else {
+            Err(Error::InvalidModel(s.to_string()))
+        }
+    }
+}
+
+impl Serialize for Model {
+    /// Serializes as the API identifier (e.g. `"gemini-2.5-flash"`).
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for Model {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let name = String::deserialize(deserializer)?;
+        name.parse().map_err(serde::de::Error::custom)
+    }
+}
@@ -0,0 +1,250 @@
+//! Long-running operations.
+//!
+//! Batch jobs, tuning, video generation and file processing return a
+//! long-running operation (`operations/*`, `batches/*`, ...) instead of a
+//! result. An [`Operation<T, M>`] tracks one: [`poll`](Operation::poll)
+//! refreshes it, [`wait`](Operation::wait) polls with backoff until it is
+//! done, and [`result`](Operation::result) parses the response as `T`. The
+//! metadata is parsed as `M`, raw JSON by default.
+//!
+//! Operations are serde types without credentials. Store one, and continue
+//! it in another process with
+//! [`Client::resume_operation`](crate::Client::resume_operation).
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! use gemini_rs::batch::{BatchMetadata, BatchOutput};
+//! use gemini_rs::operation::Operation;
+//! use gemini_rs::Client;
+//! use std::time::Duration;
+//!
+//! # async fn example() -> Result<(), gemini_rs::Error> {
+//! let client = Client::new("YOUR_API_KEY");
+//! let mut operation = client.operation::<BatchOutput, BatchMetadata>("batches/123");
+//! operation.poll().await?;
+//! let saved = serde_json::to_string(&operation)?;
+//!
+//! // ... in another process
+//! let operation: Operation<BatchOutput, BatchMetadata> = serde_json::from_str(&saved)?;
+//! let mut operation = client.resume_operation(operation);
+//! let output = operation
+//!     .wait(Duration::from_secs(10), Duration::from_secs(3600))
+//!     .await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::client::Client;
+use crate::clock::{Clock, SystemClock};
+use crate::error::{Error, Result};
+use crate::transport::HttpRequest;
+use reqwest::Method;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::marker::PhantomData;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// An error reported by a long-running operation.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OperationError {
+    /// `google.rpc.Code` value.
+    #[serde(default)]
+    pub code: i32,
+    /// Error message.
+    #[serde(default)]
+    pub message: String,
+}
+
+impl From<OperationError> for Error {
+    fn from(error: OperationError) -> Self {
+        Error::ApiError {
+            message: error.message,
+            code: Some(error.code),
+        }
+    }
+}
+
+/// A long-running operation whose response parses as `T` and metadata as
+/// `M`.
+///
+/// See the [module documentation](self).
+#[derive(Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct Operation<T, M = Value> {
+    name: String,
+    #[serde(default)]
+    done: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    metadata: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    response: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    error: Option<OperationError>,
+    #[serde(skip)]
+    client: Option<Client>,
+    #[serde(skip, default = "system_clock")]
+    clock: Arc<dyn Clock>,
+    #[serde(skip)]
+    types: PhantomData<fn() -> (T, M)>,
+}
+
+fn system_clock() -> Arc<dyn Clock> {
+    Arc::new(SystemClock)
+}
+
+impl<T: DeserializeOwned, M: DeserializeOwned> Operation<T, M> {
+    /// Track the operation `name` (e.g. `batches/123`), not fetched yet.
+    pub(crate) fn new(client: Client, name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            done: false,
+            metadata: None,
+            response: None,
+            error: None,
+            client: Some(client),
+            clock: system_clock(),
+            types: PhantomData,
+        }
+    }
+
+    /// Attach the client used to poll and cancel.
+    pub(crate) fn attach(mut self, client: Client) -> Self {
+        self.client = Some(client);
+        self
+    }
+
+    /// Read the time from, and wait with, `clock` instead of the system
+    /// clock.
+    pub fn with_clock(mut self, clock: impl Clock + 'static) -> Self {
+        self.clock = Arc::new(clock);
+        self
+    }
+
+    /// Get the resource name.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Whether the operation has finished, successfully or not, as of the
+    /// last poll.
+    pub fn is_done(&self) -> bool {
+        self.done
+    }
+
+    /// Get the metadata as of the last poll, if the API reported any.
+    pub fn metadata(&self) -> Result<Option<M>> {
+        self.metadata
+            .clone()
+            .map(serde_json::from_value)
+            .transpose()
+            .map_err(Error::from)
+    }
+
+    /// Get the outcome, once done: the parsed response, or the operation's
+    /// error as an [`Error::ApiError`].
+    pub fn result(&self) -> Option<Result<T>> {
+        if !self.done {
+            return None;
+        }
+        Some(match (&self.error, &self.response) {
+            (Some(error), _) => Err(error.clone().into()),
+            (None, Some(response)) => serde_json::from_value(response.clone()).map_err(Error::from),
+            (None, None) => Err(Error::NoResponse),
+        })
+    }
+
+    /// Fetch the current state and return whether the operation is done.
+    pub async fn poll(&mut self) -> Result<bool> {
+        let client = self.client()?;
+        let url = client.backend().resource_url(&self.name)?;
+        let response = client.send_raw(HttpRequest::new(Method::GET, url)).await?;
+        let mut update: Operation<T, M> = serde_json::from_slice(&response.body)?;
+        update.client = self.client.take();
+        update.clock = self.clock.clone();
+        if update.name.is_empty() {
+            update.name = std::mem::take(&mut self.name);
+        }
+        *self = update;
+        Ok(self.done)
+    }
+
+    /// Poll until the operation is done and return its
+    /// [`result`](Operation::result).
+    ///
+    /// The first poll is immediate. The interval then starts at
+    /// `poll_interval` and grows by half after every poll, up to ten times
+    /// `poll_interval`. [Retryable](Error::is_retryable) poll failures are
+    /// retried; fails with [`Error::Timeout`] if the operation is not done
+    /// within `timeout`.
+    pub async fn wait(&mut self, poll_interval: Duration, timeout: Duration) -> Result<T> {
+        let deadline = self.clock.now() + timeout;
+        let max_interval = poll_interval.saturating_mul(10);
+        let mut interval = poll_interval;
+        loop {
+            match self.poll().await {
+                Ok(true) => return self.result().unwrap_or(Err(Error::NoResponse)),
+                Ok(false) => {}
+                Err(err) if err.is_retryable() => {}
+                Err(err) => return Err(err),
+            }
+            let remaining = deadline.saturating_duration_since(self.clock.now());
+            if remaining.is_zero() {
+                return Err(Error::Timeout(timeout));
+            }
+            self.clock.sleep(interval.min(remaining)).await;
+            interval = interval
+                .checked_mul(3)
+                .map_or(max_interval, |grown| (grown / 2).min(max_interval));
+        }
+    }
+
+    /// Ask the API to cancel the operation. Poll to see whether it stopped.
+    pub async fn cancel(&self) -> Result<()> {
+        let client = self.client()?;
+        let url = client
+            .backend()
+            .resource_url(&format!("{}:cancel", self.name))?;
+        client
+            .send_raw(HttpRequest::post_json(url, &serde_json::json!({}))?)
+            .await?;
+        Ok(())
+    }
+
+    fn client(&self) -> Result<&Client> {
+        self.client.as_ref().ok_or_else(|| {
+            Error::ConfigError(format!(
+                "operation {} has no client; resume it with Client::resume_operation",
+                self.name
+            ))
+        })
+    }
+}
+
+impl<T, M> Clone for Operation<T, M> {
+    fn clone(&self) -> Self {
+        Self {
+            name: self.name.clone(),
+            done: self.done,
+            metadata: self.metadata.clone(),
+            response: self.response.clone(),
+            error: self.error.clone(),
+            client: self.client.clone(),
+            clock: self.clock.clone(),
+            types: PhantomData,
+        }
+    }
+}
+
+impl<T, M> std::fmt::Debug for Operation<T, M> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Operation")
+            .field("name", &self.name)
+            .field("done", &self.done)
+            .field("metadata", &self.metadata)
+            .field("error", &self.error)
+            .finish_non_exhaustive()
+    }
+}
@@ -0,0 +1,356 @@
+//! Client-side rate limiting for requests-per-minute and tokens-per-minute
+//! quotas.
+//!
+//! A [`RateLimiter`] holds a token bucket per [`Model`] for each
+//! [`Quota`]. Before a call it takes one request and the estimated prompt
+//! tokens from the model's buckets, waiting until they refill, or failing
+//! with [`Error::RateLimitExceeded`] when configured to
+//! [fail fast](RateLimiter::fail_fast). Once the response arrives the
+//! estimate is replaced by the actual token usage. When the server answers
+//! with a 429 that says how long to wait, the model's buckets are emptied
+//! and no call to it goes out until then.
+//!
+//! Install it with [`Client::with_rate_limiter`](crate::Client::with_rate_limiter).
+//! Clones of the client, and of the limiter, share the same buckets.
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! use gemini_rs::rate_limit::{Quota, RateLimiter};
+//! use gemini_rs::{Client, Model};
+//!
+//! let limiter = RateLimiter::new()
+//!     .limit(Model::Gemini25Flash, Quota::requests_per_minute(10).tokens_per_minute(250_000))
+//!     .default_quota(Quota::requests_per_minute(5));
+//!
+//! let client = Client::new("YOUR_API_KEY").with_rate_limiter(limiter);
+//! ```
+
+use crate::clock::{Clock, SystemClock};
+use crate::error::{Error, Result};
+use crate::middleware::{Endpoint, Middleware, ModelRequest, ModelResponse, Next};
+use crate::models::Model;
+use crate::types::UsageMetadata;
+use futures::future::BoxFuture;
+use futures::stream::StreamExt;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Per-minute quotas of a model.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Quota {
+    requests_per_minute: Option<u64>,
+    tokens_per_minute: Option<u64>,
+}
+
+impl Quota {
+    /// Allow `max` requests per minute.
+    pub fn requests_per_minute(max: u64) -> Self {
+        Self {
+            requests_per_minute: Some(max),
+            tokens_per_minute: None,
+        }
+    }
+
+    /// Also allow at most `max` tokens per minute.
+    pub fn tokens_per_minute(mut self, max: u64) -> Self {
+        self.tokens_per_minute = Some(max);
+        self
+    }
+
+    /// Allow `max` tokens per minute, with no request limit.
+    pub fn tokens_only(max: u64) -> Self {
+        Self::default().tokens_per_minute(max)
+    }
+}
+
+/// A token bucket refilled continuously over one minute.
+///
+/// `available` may go negative when a call used more tokens than
+/// estimated; later calls then wait until the debt is refilled.
+#[derive(Debug)]
+struct Bucket {
+    capacity: f64,
+    available: f64,
+    updated: Instant,
+}
+
+impl Bucket {
+    fn new(capacity: u64, now: Instant) -> Self {
+        Self {
+            capacity: capacity as f64,
+            available: capacity as f64,
+            updated: now,
+        }
+    }
+
+    fn per_sec(&self) -> f64 {
+        self.capacity / 60.0
+    }
+
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+        self.available = (self.available + elapsed * self.per_sec()).min(self.capacity);
+        self.updated = now;
+    }
+
+    /// Time until `amount` can be taken. Amounts above the capacity only
+    /// wait for a full bucket.
+    fn wait(&self, amount: f64) -> Duration {
+        let needed = amount.min(self.capacity) - self.available;
+        if needed <= 0.0 || self.capacity == 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(needed / self.per_sec())
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Buckets {
+    requests: Option<Bucket>,
+    tokens: Option<Bucket>,
+    paused_until: Option<Instant>,
+}
+
+impl Buckets {
+    fn new(quota: &Quota, now: Instant) -> Self {
+        Self {
+            requests: quota.requests_per_minute.map(|max| Bucket::new(max, now)),
+            tokens: quota.tokens_per_minute.map(|max| Bucket::new(max, now)),
+            paused_until: None,
+        }
+    }
+
+    /// Empty the buckets and refuse to take anything before `until`.
+    fn pause(&mut self, now: Instant, until: Instant) {
+        for bucket in [&mut self.requests, &mut self.tokens].into_iter().flatten() {
+            bucket.refill(now);
+            bucket.available = bucket.available.min(0.0);
+        }
+        self.paused_until = self.paused_until.max(Some(until));
+    }
+
+    /// Take a request and `tokens`, or return how long to wait first.
+    fn try_take(&mut self, now: Instant, tokens: u64) -> Option<Duration> {
+        if let Some(until) = self.paused_until.filter(|until| *until > now) {
+            return Some(until - now);
+        }
+        let mut wait = Duration::ZERO;
+        for (bucket, amount) in [(&mut self.requests, 1.0), (&mut self.tokens, tokens as f64)] {
+            if let Some(bucket) = bucket {
+                bucket.refill(now);
+                wait = wait.max(bucket.wait(amount));
+            }
+        }
+        if wait > Duration::ZERO {
+            return Some(wait);
+        }
+        if let Some(bucket) = &mut self.requests {
+            bucket.available -= 1.0;
+        }
+        if let Some(bucket) = &mut self.tokens {
+            bucket.available -= tokens as f64;
+        }
+        None
+    }
+}
+
+/// Token-bucket rate limiter with per-model quotas.
+///
+/// See the [module documentation](self).
+#[derive(Clone)]
+pub struct RateLimiter {
+    quotas: HashMap<Model, Quota>,
+    default: Option<Quota>,
+    fail_fast: bool,
+    clock: Arc<dyn Clock>,
+    buckets: Arc<Mutex<HashMap<Model, Buckets>>>,
+}
+
+impl RateLimiter {
+    /// Create a limiter without quotas.
+    pub fn new() -> Self {
+        Self {
+            quotas: HashMap::new(),
+            default: None,
+            fail_fast: false,
+            clock: Arc::new(SystemClock),
+            buckets: Arc::default(),
+        }
+    }
+
+    /// Set the quota of `model`.
+    pub fn limit(mut self, model: Model, quota: Quota) -> Self {
+        self.quotas.insert(model, quota);
+        self
+    }
+
+    /// Set the quota of models without their own.
+    pub fn default_quota(mut self, quota: Quota) -> Self {
+        self.default = Some(quota);
+        self
+    }
+
+    /// Fail with [`Error::RateLimitExceeded`] instead of waiting when a
+    /// quota is used up.
+    pub fn fail_fast(mut self, enabled: bool) -> Self {
+        self.fail_fast = enabled;
+        self
+    }
+
+    /// Read the time from, and wait with, `clock` instead of the system
+    /// clock.
+    pub fn with_clock(mut self, clock: impl Clock + 'static) -> Self {
+        self.clock = Arc::new(clock);
+        self
+    }
+
+    fn quota(&self, model: &Model) -> Option<&Quota> {
+        self.quotas.get(model).or(self.default.as_ref())
+    }
+
+    /// Take a request and `tokens` from the quota of `model`, waiting
+    /// until they are available.
+    pub async fn acquire(&self, model: Model, tokens: u64) -> Result<()> {
+        let Some(quota) = self.quota(&model) else {
+            return Ok(());
+        };
+        loop {
+            let wait = {
+                let now = self.clock.now();
+                let mut buckets = self.buckets.lock().unwrap();
+                buckets
+                    .entry(model)
+                    .or_insert_with(|| Buckets::new(quota, now))
+                    .try_take(now, tokens)
+            };
+            match wait {
+                None => return Ok(()),
+                Some(wait) if self.fail_fast => {
+                    return Err(Error::RateLimitExceeded {
+                        retry_after: Some(wait),
+                        message: Some(format!("client-side quota of {} used up", model)),
+                    })
+                }
+                Some(wait) => self.clock.sleep(wait).await,
+            }
+        }
+    }
+
+    /// Correct the tokens taken for a call to `model` from `estimated` to
+    /// `actual`.
+    pub fn reconcile(&self, model: Model, estimated: u64, actual: u64) {
+        let mut buckets = self.buckets.lock().unwrap();
+        if let Some(bucket) = buckets.get_mut(&model).and_then(|b| b.tokens.as_mut()) {
+            bucket.refill(self.clock.now());
+            bucket.available =
+                (bucket.available + estimated as f64 - actual as f64).min(bucket.capacity);
+        }
+    }
+
+    /// Empty the quota of `model` and hold its calls for `duration`, as
+    /// when the server answers with a 429.
+    pub fn pause(&self, model: Model, duration: Duration) {
+        let Some(quota) = self.quota(&model) else {
+            return;
+        };
+        let now = self.clock.now();
+        let mut buckets = self.buckets.lock().unwrap();
+        buckets
+            .entry(model)
+            .or_insert_with(|| Buckets::new(quota, now))
+            .pause(now, now + duration);
+    }
+}
+
+impl Default for RateLimiter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl std::fmt::Debug for RateLimiter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RateLimiter")
+            .field("quotas", &self.quotas)
+            .field("default", &self.default)
+            .field("fail_fast", &self.fail_fast)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Tokens taken for a call; reconciled with the limiter when dropped.
+struct Permit {
+    limiter: RateLimiter,
+    model: Model,
+    estimated: u64,
+    actual: Option<u64>,
+}
+
+impl Permit {
+    fn record(&mut self, usage: Option<&UsageMetadata>) {
+        if let Some(usage) = usage {
+            self.actual = Some(usage.total_token_count);
+        }
+    }
+}
+
+impl Drop for Permit {
+    fn drop(&mut self) {
+        if let Some(actual) = self.actual {
+            self.limiter.reconcile(self.model, self.estimated, actual);
+        }
+    }
+}
+
+impl Middleware for RateLimiter {
+    fn handle<'a>(
+        &'a self,
+        request: ModelRequest,
+        next: Next<'a>,
+    ) -> BoxFuture<'a, Result<ModelResponse>> {
+        Box::pin(async move {
+            // countTokens has its own, much larger quota
+            if request.endpoint == Endpoint::CountTokens || self.quota(&request.model).is_none() {
+                return next.run(request).await;
+            }
+
+            let estimated = request.body.estimate_tokens();
+            self.acquire(request.model, estimated).await?;
+            let mut permit = Permit {
+                limiter: self.clone(),
+                model: request.model,
+                estimated,
+                actual: None,
+            };
+
+            match next.run(request).await {
+                Ok(ModelResponse::Generate(response)) => {
+                    permit.record(response.usage_metadata.as_ref());
+                    Ok(ModelResponse::Generate(response))
+                }
+                Ok(ModelResponse::Stream(mut stream)) => Ok(ModelResponse::Stream(
+                    async_stream::stream! {
+                        while let Some(chunk) = stream.next().await {
+                            if let Ok(chunk) = &chunk {
+                                permit.record(chunk.usage_metadata.as_ref());
+                            }
+                            yield chunk;
+                        }
+                    }
+                    .boxed(),
+                )),
+                Err(err) => {
+                    // A server 429 holds every caller sharing this limiter
+                    if let Some(retry_after) = err.retry_after() {
+                        self.pause(permit.model, retry_after);
+                    }
+                    Err(err)
+                }
+                other => other,
+            }
+        })
+    }
+}
@@ -0,0 +1,222 @@
+//! `tower` integration (requires the `tower` feature).
+//!
+//! [`ModelClient`] implements
+//! `Service<GenerateContentRequest, Response = GenerateContentResponse, Error = Error>`,
+//! so it can be wrapped in any `tower` layer. [`RetryPolicy`] is a
+//! `tower::retry::Policy` that retries the errors reported by
+//! [`Error::is_retryable`], waiting for the server's
+//! [`retry_after`](Error::retry_after) delay if it gave one, otherwise with
+//! exponential backoff.
+//!
+//! A bare `GenerateContentRequest` cannot say which retry it is. To have
+//! retries reported in tracing spans and metrics, wrap the client in a
+//! [`ModelService`], which serves [`ServiceRequest`]s carrying the attempt
+//! that `RetryPolicy` increments.
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! use gemini_rs::service::RetryPolicy;
+//! use gemini_rs::{Client, Content, Model};
+//! use tower::{Service, ServiceBuilder, ServiceExt};
+//!
+//! # async fn example() -> Result<(), gemini_rs::Error> {
+//! let model = Client::new("YOUR_API_KEY").model(Model::Gemini25Flash);
+//! let request = model.build_request(vec![Content::text("Hello")]);
+//!
+//! let mut service = ServiceBuilder::new()
+//!     .retry(RetryPolicy::new(3))
+//!     .service(model);
+//!
+//! let response = service.ready().await?.call(request).await?;
+//! println!("{}", response.text());
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::client::ModelClient;
+use crate::error::{Error, Result};
+use crate::types::{GenerateContentRequest, GenerateContentResponse};
+use futures::future::BoxFuture;
+use std::task::{Context, Poll};
+use std::time::Duration;
+use tower::retry::Policy;
+use tower::Service;
+
+impl Service<GenerateContentRequest> for ModelClient {
+    type Response = GenerateContentResponse;
+    type Error = Error;
+    type Future = BoxFuture<'static, Result<GenerateContentResponse>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: GenerateContentRequest) -> Self::Future {
+        let model = self.clone();
+        Box::pin(async move { model.send_request(request).await })
+    }
+}
+
+/// A request for a [`ModelService`], with its retry attempt.
+#[derive(Debug, Clone)]
+pub struct ServiceRequest {
+    /// The request to send.
+    pub request: GenerateContentRequest,
+    /// Retry attempt, 0 for the first try; set by [`RetryPolicy`].
+    pub attempt: u32,
+}
+
+impl From<GenerateContentRequest> for ServiceRequest {
+    fn from(request: GenerateContentRequest) -> Self {
+        Self {
+            request,
+            attempt: 0,
+        }
+    }
+}
+
+/// A [`ModelClient`] serving [`ServiceRequest`]s, so that the retry attempt
+/// set by [`RetryPolicy`] is reported in tracing spans and metrics.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use gemini_rs::service::{ModelService, RetryPolicy, ServiceRequest};
+/// use gemini_rs::{Client, Content, Model};
+/// use tower::{ServiceBuilder, ServiceExt};
+///
+/// # async fn example() -> Result<(), gemini_rs::Error> {
+/// let model = Client::new("YOUR_API_KEY").model(Model::Gemini25Flash);
+/// let request = ServiceRequest::from(model.build_request(vec![Content::text("Hello")]));
+///
+/// let service = ServiceBuilder::new()
+///     .retry(RetryPolicy::new(3))
+///     .service(ModelService::new(model));
+/// let response = service.oneshot(request).await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct ModelService {
+    model: ModelClient,
+}
+
+impl ModelService {
+    /// Serve `model`.
+    pub fn new(model: ModelClient) -> Self {
+        Self { model }
+    }
+}
+
+impl Service<ServiceRequest> for ModelService {
+    type Response = GenerateContentResponse;
+    type Error = Error;
+    type Future = BoxFuture<'static, Result<GenerateContentResponse>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: ServiceRequest) -> Self::Future {
+        let model = self.model.clone();
+        Box::pin(async move { model.send_attempt(request.request, request.attempt).await })
+    }
+}
+
+/// A request [`RetryPolicy`] can retry, recording the retry attempt.
+pub trait RetryRequest: Clone {
+    /// Mark the request as retry `attempt`.
+    fn set_attempt(&mut self, attempt: u32);
+}
+
+impl RetryRequest for ServiceRequest {
+    fn set_attempt(&mut self, attempt: u32) {
+        self.attempt = attempt;
+    }
+}
+
+impl RetryRequest for GenerateContentRequest {
+    /// A bare request has no attempt to record; send [`ServiceRequest`]s
+    /// to a [`ModelService`] to have retries reported.
+    fn set_attempt(&mut self, _attempt: u32) {}
+}
+
+/// Retries calls that failed with a retryable [`Error`].
+///
+/// Waits the server's [`retry_after`](Error::retry_after) delay when it sent
+/// one. Otherwise waits `initial_backoff` before the first retry and
+/// doubles the delay for each further one, up to `max_backoff`.
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    max_retries: usize,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+    attempt: usize,
+}
+
+impl RetryPolicy {
+    /// Retry up to `max_retries` times, starting with a 500ms backoff
+    /// capped at 30s.
+    pub fn new(max_retries: usize) -> Self {
+        Self {
+            max_retries,
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(30),
+            attempt: 0,
+        }
+    }
+
+    /// Set the delay before the first retry and the maximum delay.
+    pub fn with_backoff(mut self, initial: Duration, max: Duration) -> Self {
+        self.initial_backoff = initial;
+        self.max_backoff = max;
+        self
+    }
+
+    fn backoff(&self) -> Duration {
+        let factor = 1u32.checked_shl(self.attempt as u32).unwrap_or(u32::MAX);
+        self.initial_backoff
+            .checked_mul(factor)
+            .map_or(self.max_backoff, |delay| delay.min(self.max_backoff))
+    }
+}
+
+impl Default for RetryPolicy {
+    /// Retry up to 3 times.
+    fn default() -> Self {
+        Self::new(3)
+    }
+}
+
+impl<Req: RetryRequest, Res> Policy<Req, Res, Error> for RetryPolicy {
+    type Future = BoxFuture<'static, ()>;
+
+    fn retry(
+        &mut self,
+        request: &mut Req,
+        result: &mut std::result::Result<Res, Error>,
+    ) -> Option<Self::Future> {
+        match result {
+            Err(err) if err.is_retryable() && self.attempt < self.max_retries => {
+                let delay = err.retry_after().unwrap_or_else(|| self.backoff());
+                self.attempt += 1;
+                request.set_attempt(self.attempt as u32);
+                #[cfg(feature = "tracing")]
+                tracing::info!(
+                    target: "gemini_rs",
+                    attempt = self.attempt,
+                    delay_ms = delay.as_millis() as u64,
+                    error = %err,
+                    "retrying model call"
+                );
+                Some(Box::pin(tokio::time::sleep(delay)))
+            }
+            _ => None,
+        }
+    }
+
+    fn clone_request(&mut self, request: &Req) -> Option<Req> {
+        Some(request.clone())
+    }
+}
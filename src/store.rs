@@ -0,0 +1,275 @@
+//! Persistence for chat sessions.
+//!
+//! A [`ChatSnapshot`] captures everything needed to resume a
+//! [`ChatSession`](crate::ChatSession) except credentials. Snapshots are
+//! plain serde types, so they can be stored anywhere; the [`ChatStore`]
+//! trait and its in-memory and filesystem implementations cover the
+//! common cases.
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! use gemini_rs::{Client, Model};
+//! use gemini_rs::store::{ChatStore, FileChatStore};
+//!
+//! # async fn example() -> Result<(), gemini_rs::Error> {
+//! let client = Client::new("YOUR_API_KEY");
+//! let model = client.model(Model::Gemini25Flash);
+//! let store = FileChatStore::json("./sessions")?;
+//!
+//! let mut chat = model.start_chat();
+//! chat.send_message("My name is Alice").await?;
+//! store.save("session-42", &chat.snapshot())?;
+//!
+//! // ... after a restart
+//! if let Some(snapshot) = store.load("session-42")? {
+//!     let mut chat = model.resume_chat(snapshot);
+//!     chat.send_message("What's my name?").await?;
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::error::{Error, Result};
+use crate::models::Model;
+use crate::types::{Content, GenerationConfig, SafetySetting, Tool};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// Serializable state of a chat session.
+///
+/// Created with [`ChatSession::snapshot`](crate::ChatSession::snapshot) and
+/// restored with [`ModelClient::resume_chat`](crate::ModelClient::resume_chat).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChatSnapshot {
+    /// The model the session talks to.
+    pub model: Model,
+    /// Generation configuration.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub generation_config: Option<GenerationConfig>,
+    /// Safety settings.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub safety_settings: Option<Vec<SafetySetting>>,
+    /// System instruction.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub system_instruction: Option<Content>,
+    /// Tools the model may call.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tools: Option<Vec<Tool>>,
+    /// The conversation so far.
+    #[serde(default)]
+    pub history: Vec<Content>,
+}
+
+/// Storage for chat snapshots, keyed by session id.
+///
+/// Session ids may contain ASCII letters, digits, `-`, `_` and `.`, and
+/// must not start with a `.`.
+pub trait ChatStore: Send + Sync {
+    /// Store a snapshot, replacing any previous one for the same id.
+    fn save(&self, session_id: &str, snapshot: &ChatSnapshot) -> Result<()>;
+
+    /// Load the latest snapshot for a session, if any.
+    fn load(&self, session_id: &str) -> Result<Option<ChatSnapshot>>;
+
+    /// Delete a session. Deleting an unknown session is not an error.
+    fn delete(&self, session_id: &str) -> Result<()>;
+
+    /// List the ids of all stored sessions, sorted.
+    fn list(&self) -> Result<Vec<String>>;
+}
+
+/// In-memory [`ChatStore`], shared across clones.
+///
+/// Useful for tests and single-process deployments.
+#[derive(Debug, Clone, Default)]
+pub struct MemoryChatStore {
+    sessions: Arc<Mutex<HashMap<String, ChatSnapshot>>>,
+}
+
+impl MemoryChatStore {
+    /// Create an empty store.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl ChatStore for MemoryChatStore {
+    fn save(&self, session_id: &str, snapshot: &ChatSnapshot) -> Result<()> {
+        validate_session_id(session_id)?;
+        self.sessions
+            .lock()
+            .unwrap()
+            .insert(session_id.to_string(), snapshot.clone());
+        Ok(())
+    }
+
+    fn load(&self, session_id: &str) -> Result<Option<ChatSnapshot>> {
+        validate_session_id(session_id)?;
+        Ok(self.sessions.lock().unwrap().get(session_id).cloned())
+    }
+
+    fn delete(&self, session_id: &str) -> Result<()> {
+        validate_session_id(session_id)?;
+        self.sessions.lock().unwrap().remove(session_id);
+        Ok(())
+    }
+
+    fn list(&self) -> Result<Vec<String>> {
+        let mut ids: Vec<String> = self.sessions.lock().unwrap().keys().cloned().collect();
+        ids.sort();
+        Ok(ids)
+    }
+}
+
+/// On-disk layout used by [`FileChatStore`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileFormat {
+    /// One pretty-printed `{id}.json` file per session, overwritten on save.
+    Json,
+    /// One `{id}.jsonl` file per session. Every save appends a line, so the
+    /// file keeps the full revision trail; loading returns the last line.
+    Jsonl,
+}
+
+impl FileFormat {
+    fn extension(&self) -> &'static str {
+        match self {
+            FileFormat::Json => "json",
+            FileFormat::Jsonl => "jsonl",
+        }
+    }
+}
+
+/// Filesystem-backed [`ChatStore`].
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use gemini_rs::store::{FileChatStore, FileFormat};
+///
+/// # fn example() -> Result<(), gemini_rs::Error> {
+/// let store = FileChatStore::new("./sessions", FileFormat::Jsonl)?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct FileChatStore {
+    dir: PathBuf,
+    format: FileFormat,
+}
+
+impl FileChatStore {
+    /// Create a store in `dir`, creating the directory if needed.
+    pub fn new(dir: impl Into<PathBuf>, format: FileFormat) -> Result<Self> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir)?;
+        Ok(Self { dir, format })
+    }
+
+    /// Create a store writing one JSON file per session.
+    pub fn json(dir: impl Into<PathBuf>) -> Result<Self> {
+        Self::new(dir, FileFormat::Json)
+    }
+
+    /// Create a store appending to one JSONL file per session.
+    pub fn jsonl(dir: impl Into<PathBuf>) -> Result<Self> {
+        Self::new(dir, FileFormat::Jsonl)
+    }
+
+    /// Get the directory holding the session files.
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    fn path(&self, session_id: &str) -> Result<PathBuf> {
+        validate_session_id(session_id)?;
+        Ok(self
+            .dir
+            .join(format!("{}.{}", session_id, self.format.extension())))
+    }
+}
+
+impl ChatStore for FileChatStore {
+    fn save(&self, session_id: &str, snapshot: &ChatSnapshot) -> Result<()> {
+        let path = self.path(session_id)?;
+        match self.format {
+            FileFormat::Json => {
+                // Write to a temporary file first so a crash never leaves a
+                // truncated session behind
+                let tmp = path.with_extension("json.tmp");
+                fs::write(&tmp, serde_json::to_vec_pretty(snapshot)?)?;
+                fs::rename(&tmp, &path)?;
+            }
+            FileFormat::Jsonl => {
+                let mut line = serde_json::to_vec(snapshot)?;
+                line.push(b'\n');
+                fs::OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(&path)?
+                    .write_all(&line)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn load(&self, session_id: &str) -> Result<Option<ChatSnapshot>> {
+        let path = self.path(session_id)?;
+        let data = match fs::read_to_string(&path) {
+            Ok(data) => data,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        let json = match self.format {
+            FileFormat::Json => data.as_str(),
+            FileFormat::Jsonl => match data.lines().rev().find(|line| !line.trim().is_empty()) {
+                Some(line) => line,
+                None => return Ok(None),
+            },
+        };
+        Ok(Some(serde_json::from_str(json)?))
+    }
+
+    fn delete(&self, session_id: &str) -> Result<()> {
+        match fs::remove_file(self.path(session_id)?) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+
+    fn list(&self) -> Result<Vec<String>> {
+        let mut ids = Vec::new();
+        for entry in fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            if path.extension().and_then(|ext| ext.to_str()) != Some(self.format.extension()) {
+                continue;
+            }
+            if let Some(id) = path.file_stem().and_then(|stem| stem.to_str()) {
+                ids.push(id.to_string());
+            }
+        }
+        ids.sort();
+        Ok(ids)
+    }
+}
+
+fn validate_session_id(session_id: &str) -> Result<()> {
+    let valid = !session_id.is_empty()
+        && !session_id.starts_with('.')
+        && session_id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+    if valid {
+        Ok(())
+    } else {
+        Err(Error::InvalidInput(format!(
+            "invalid session id {:?}: use ASCII letters, digits, '-', '_' or '.'",
+            session_id
+        )))
+    }
+}
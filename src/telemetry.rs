@@ -0,0 +1,242 @@
+//! Tracing instrumentation (emits spans with the `tracing` feature).
+//!
+//! Every API call runs in a `gemini_rs` span whose fields follow the
+//! OpenTelemetry GenAI semantic conventions:
+//!
+//! | Field | Value |
+//! |-------|-------|
+//! | `otel.name` | `{gen_ai.operation.name} {gen_ai.request.model}` |
+//! | `gen_ai.operation.name` | `generate_content` or `count_tokens` |
+//! | `gen_ai.provider.name` | `gcp.gemini` or `gcp.vertex_ai` |
+//! | `gen_ai.request.model` | Model id, e.g. `gemini-2.5-flash` |
+//! | `gen_ai.request.temperature`, `.top_p`, `.top_k`, `.max_tokens` | Generation config |
+//! | `gen_ai.response.model`, `gen_ai.response.id` | Model version and response id |
+//! | `gen_ai.response.finish_reasons` | Finish reason of each candidate |
+//! | `gen_ai.usage.input_tokens`, `gen_ai.usage.output_tokens` | Token usage |
+//! | `gemini.endpoint` | `generateContent`, `streamGenerateContent` or `countTokens` |
+//! | `server.address` | API host |
+//! | `http.response.status_code` | HTTP status |
+//! | `http.request.resend_count` | Retry attempt (0 for the first try) |
+//! | `latency_ms` | Time until the response (or the last chunk) was read |
+//! | `error.type` | HTTP status code or error kind, on failure |
+//!
+//! Prompts and responses may contain sensitive data. They are only recorded,
+//! in the `content` field of `gen_ai.content.prompt` and
+//! `gen_ai.content.completion` debug events, after opting in with
+//! `Client::with_content_tracing(true)`.
+
+#[cfg(feature = "tracing")]
+pub(crate) use enabled::CallSpan;
+
+#[cfg(not(feature = "tracing"))]
+pub(crate) use disabled::CallSpan;
+
+#[cfg(feature = "tracing")]
+mod enabled {
+    use crate::backend::Backend;
+    use crate::client::ResponseStream;
+    use crate::error::Error;
+    use crate::middleware::{Endpoint, ModelRequest};
+    use crate::types::{CountTokensResponse, GenerateContentResponse};
+    use futures::stream::StreamExt;
+    use reqwest::StatusCode;
+    use std::future::Future;
+    use std::time::Instant;
+    use tracing::field::Empty;
+    use tracing::{Instrument, Span};
+
+    /// The span of one API call.
+    pub(crate) struct CallSpan {
+        span: Span,
+        started: Instant,
+        record_content: bool,
+    }
+
+    impl CallSpan {
+        pub(crate) fn start(
+            request: &ModelRequest,
+            backend: &Backend,
+            record_content: bool,
+        ) -> Self {
+            let operation = match request.endpoint {
+                Endpoint::GenerateContent | Endpoint::StreamGenerateContent => "generate_content",
+                Endpoint::CountTokens => "count_tokens",
+            };
+            let provider = if backend.is_vertex_ai() {
+                "gcp.vertex_ai"
+            } else {
+                "gcp.gemini"
+            };
+            let server = backend
+                .base_url()
+                .split("://")
+                .nth(1)
+                .and_then(|rest| rest.split(['/', ':']).next())
+                .unwrap_or_default();
+            let config = request.body.generation_config.as_ref();
+
+            let span = tracing::info_span!(
+                target: "gemini_rs",
+                "gen_ai",
+                otel.name = %format!("{} {}", operation, request.model.as_str()),
+                otel.kind = "client",
+                otel.status_code = Empty,
+                gen_ai.operation.name = operation,
+                gen_ai.provider.name = provider,
+                gen_ai.request.model = request.model.as_str(),
+                gen_ai.request.temperature = config.and_then(|c| c.temperature),
+                gen_ai.request.top_p = config.and_then(|c| c.top_p),
+                gen_ai.request.top_k = config.and_then(|c| c.top_k),
+                gen_ai.request.max_tokens = config.and_then(|c| c.max_output_tokens),
+                gen_ai.response.model = Empty,
+                gen_ai.response.id = Empty,
+                gen_ai.response.finish_reasons = Empty,
+                gen_ai.usage.input_tokens = Empty,
+                gen_ai.usage.output_tokens = Empty,
+                gemini.endpoint = request.endpoint.as_str(),
+                server.address = server,
+                http.response.status_code = Empty,
+                http.request.resend_count = request.attempt,
+                latency_ms = Empty,
+                error.type = Empty,
+            );
+
+            if record_content {
+                if let Ok(prompt) = serde_json::to_string(&request.body.contents) {
+                    tracing::debug!(
+                        target: "gemini_rs",
+                        parent: &span,
+                        content = %prompt,
+                        "gen_ai.content.prompt"
+                    );
+                }
+            }
+
+            Self {
+                span,
+                started: Instant::now(),
+                record_content,
+            }
+        }
+
+        /// Run `future` inside the span.
+        pub(crate) fn instrument<F: Future>(&self, future: F) -> impl Future<Output = F::Output> {
+            future.instrument(self.span.clone())
+        }
+
+        pub(crate) fn status(&self, status: StatusCode) {
+            self.span
+                .record("http.response.status_code", status.as_u16());
+        }
+
+        pub(crate) fn response(&self, response: &GenerateContentResponse) {
+            let span = &self.span;
+            if let Some(model) = &response.model_version {
+                span.record("gen_ai.response.model", model.as_str());
+            }
+            if let Some(id) = &response.response_id {
+                span.record("gen_ai.response.id", id.as_str());
+            }
+            let finish_reasons: Vec<&str> = response
+                .candidates
+                .iter()
+                .flatten()
+                .filter_map(|candidate| candidate.finish_reason.as_deref())
+                .collect();
+            if !finish_reasons.is_empty() {
+                span.record(
+                    "gen_ai.response.finish_reasons",
+                    tracing::field::debug(&finish_reasons),
+                );
+            }
+            if let Some(usage) = &response.usage_metadata {
+                span.record("gen_ai.usage.input_tokens", usage.prompt_token_count);
+                span.record(
+                    "gen_ai.usage.output_tokens",
+                    usage.candidates_token_count + usage.thoughts_token_count,
+                );
+            }
+
+            if self.record_content {
+                tracing::debug!(
+                    target: "gemini_rs",
+                    parent: span,
+                    content = %response.text(),
+                    "gen_ai.content.completion"
+                );
+            }
+        }
+
+        pub(crate) fn count_tokens(&self, response: &CountTokensResponse) {
+            self.span
+                .record("gen_ai.usage.input_tokens", response.total_tokens);
+        }
+
+        pub(crate) fn error(&self, error: &Error) {
+            self.span.record("error.type", error.error_type().as_str());
+            self.span.record("otel.status_code", "ERROR");
+        }
+
+        /// Keep the span open until the stream ends or is dropped, recording
+        /// every chunk.
+        pub(crate) fn stream(self, mut stream: ResponseStream) -> ResponseStream {
+            async_stream::stream! {
+                while let Some(item) = stream.next().await {
+                    match &item {
+                        Ok(chunk) => self.response(chunk),
+                        Err(error) => self.error(error),
+                    }
+                    yield item;
+                }
+            }
+            .boxed()
+        }
+    }
+
+    impl Drop for CallSpan {
+        fn drop(&mut self) {
+            self.span
+                .record("latency_ms", self.started.elapsed().as_millis() as u64);
+        }
+    }
+}
+
+#[cfg(not(feature = "tracing"))]
+mod disabled {
+    use crate::backend::Backend;
+    use crate::client::ResponseStream;
+    use crate::error::Error;
+    use crate::middleware::ModelRequest;
+    use crate::types::{CountTokensResponse, GenerateContentResponse};
+    use reqwest::StatusCode;
+    use std::future::Future;
+
+    /// No-op stand-in used without the `tracing` feature.
+    pub(crate) struct CallSpan;
+
+    impl CallSpan {
+        pub(crate) fn start(
+            _request: &ModelRequest,
+            _backend: &Backend,
+            _record_content: bool,
+        ) -> Self {
+            CallSpan
+        }
+
+        pub(crate) fn instrument<F: Future>(&self, future: F) -> F {
+            future
+        }
+
+        pub(crate) fn status(&self, _status: StatusCode) {}
+
+        pub(crate) fn response(&self, _response: &GenerateContentResponse) {}
+
+        pub(crate) fn count_tokens(&self, _response: &CountTokensResponse) {}
+
+        pub(crate) fn error(&self, _error: &Error) {}
+
+        pub(crate) fn stream(self, stream: ResponseStream) -> ResponseStream {
+            stream
+        }
+    }
+}
@@ -0,0 +1,573 @@
+//! Record/replay transport.
+//!
+//! A [`CassetteTransport`] in record mode forwards requests to a real
+//! [`Transport`] and appends every request/response pair to a cassette, a
+//! pretty-printed JSON file meant to be committed next to the tests. In
+//! replay mode it answers requests from the cassette without touching the
+//! network, so CI runs offline and without an API key.
+//!
+//! Streaming responses are recorded as their list of server-sent events and
+//! replayed one event per chunk.
+//!
+//! # Redaction
+//!
+//! Credentials never reach the cassette: the `x-goog-api-key` and
+//! `Authorization` headers (and any other header marked sensitive) are
+//! dropped, and their values, `key` query parameters and any string passed
+//! to [`redact`](CassetteTransport::redact) are replaced with [`REDACTED`]
+//! wherever they appear in URLs, headers and bodies. Incoming requests are
+//! redacted the same way before matching, so replay works with any key.
+//!
+//! # Matching
+//!
+//! A request matches a recorded one with the same method, path, query
+//! parameters (in any order) and, unless disabled with
+//! [`match_body`](CassetteTransport::match_body), JSON body. Each recorded
+//! interaction is used once, in order; when all matches are used up the
+//! last one is repeated, so polling loops keep working. A request without
+//! a match fails with [`Error::TransportError`].
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! use gemini_rs::testing::CassetteTransport;
+//! use gemini_rs::transport::ReqwestTransport;
+//! use gemini_rs::{Client, Model};
+//!
+//! # async fn example() -> Result<(), gemini_rs::Error> {
+//! // Records on the first run (with a real key), replays afterwards
+//! let transport = CassetteTransport::once(
+//!     "tests/cassettes/greeting.json",
+//!     ReqwestTransport::default(),
+//! )?;
+//! let api_key = std::env::var("GEMINI_API_KEY").unwrap_or_else(|_| "replay".into());
+//! let client = Client::builder().api_key(api_key).transport(transport).build()?;
+//!
+//! let response = client.model(Model::Gemini25Flash).generate_content("Hello").await?;
+//! println!("{}", response.text());
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::error::{Error, Result, REDACTED};
+use crate::transport::{sse_data, HttpRequest, HttpResponse, StreamingResponse, Transport};
+use bytes::Bytes;
+use futures::future::BoxFuture;
+use futures::stream::{self, StreamExt};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// Request headers that carry credentials.
+const SECRET_HEADERS: &[&str] = &["x-goog-api-key", "authorization", "cookie"];
+
+/// Response headers that are not worth recording.
+const SKIPPED_RESPONSE_HEADERS: &[&str] =
+    &["content-length", "date", "set-cookie", "transfer-encoding"];
+
+/// Recorded API traffic.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Cassette {
+    /// Request/response pairs in the order they completed.
+    #[serde(default)]
+    pub interactions: Vec<Interaction>,
+}
+
+impl Cassette {
+    /// Create an empty cassette.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Read a cassette file.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        Ok(serde_json::from_slice(&fs::read(path)?)?)
+    }
+
+    /// Write the cassette to `path`, creating parent directories.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            fs::create_dir_all(dir)?;
+        }
+        let mut json = serde_json::to_vec_pretty(self)?;
+        json.push(b'\n');
+        let tmp = path.with_extension("json.tmp");
+        fs::write(&tmp, json)?;
+        fs::rename(&tmp, path)?;
+        Ok(())
+    }
+}
+
+/// One recorded request and the response it got.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Interaction {
+    /// The request, redacted.
+    pub request: RecordedRequest,
+    /// The response, redacted.
+    pub response: RecordedResponse,
+}
+
+/// A recorded request.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RecordedRequest {
+    /// HTTP method.
+    pub method: String,
+    /// Path and query string, without scheme and host.
+    pub url: String,
+    /// Request headers, without credentials.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub headers: BTreeMap<String, String>,
+    /// JSON body, the body as a string if it is not JSON, or null if empty.
+    #[serde(default, skip_serializing_if = "Value::is_null")]
+    pub body: Value,
+}
+
+/// A recorded response.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RecordedResponse {
+    /// Status code.
+    pub status: u16,
+    /// Response headers.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub headers: BTreeMap<String, String>,
+    /// JSON body, the body as a string if it is not JSON, or null if empty
+    /// or streamed.
+    #[serde(default, skip_serializing_if = "Value::is_null")]
+    pub body: Value,
+    /// The `data` of each server-sent event, for successful streaming
+    /// responses.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub events: Option<Vec<Value>>,
+}
+
+/// A [`Transport`] that records traffic to, or replays it from, a
+/// cassette file.
+///
+/// See the [module documentation](crate::testing::cassette).
+#[derive(Clone)]
+pub struct CassetteTransport {
+    path: PathBuf,
+    /// The transport recorded from; `None` when replaying.
+    inner: Option<Arc<dyn Transport>>,
+    state: Arc<Mutex<State>>,
+    redactor: Redactor,
+    match_body: bool,
+}
+
+#[derive(Default)]
+struct State {
+    cassette: Cassette,
+    /// Whether each interaction has been replayed.
+    used: Vec<bool>,
+}
+
+impl CassetteTransport {
+    /// Send requests through `inner` and record them to `path`, replacing
+    /// any existing cassette. The file is written after every interaction.
+    pub fn record(path: impl Into<PathBuf>, inner: impl Transport + 'static) -> Self {
+        Self {
+            path: path.into(),
+            inner: Some(Arc::new(inner)),
+            state: Arc::default(),
+            redactor: Redactor::default(),
+            match_body: true,
+        }
+    }
+
+    /// Answer requests from the cassette at `path`.
+    pub fn replay(path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+        let cassette = Cassette::load(&path)?;
+        let state = State {
+            used: vec![false; cassette.interactions.len()],
+            cassette,
+        };
+        Ok(Self {
+            path,
+            inner: None,
+            state: Arc::new(Mutex::new(state)),
+            redactor: Redactor::default(),
+            match_body: true,
+        })
+    }
+
+    /// Replay the cassette at `path` if it exists, otherwise record it
+    /// through `inner`.
+    ///
+    /// Delete the file to re-record.
+    pub fn once(path: impl Into<PathBuf>, inner: impl Transport + 'static) -> Result<Self> {
+        let path = path.into();
+        if path.exists() {
+            Self::replay(path)
+        } else {
+            Ok(Self::record(path, inner))
+        }
+    }
+
+    /// Replace `secret` with [`REDACTED`] in everything recorded, e.g. a
+    /// project id or personal data in prompts.
+    pub fn redact(mut self, secret: impl Into<String>) -> Self {
+        self.redactor.add(secret.into());
+        self
+    }
+
+    /// Whether request bodies must match when replaying. On by default;
+    /// turn off for prompts that change between runs, such as ones with
+    /// timestamps.
+    pub fn match_body(mut self, enabled: bool) -> Self {
+        self.match_body = enabled;
+        self
+    }
+
+    /// Whether requests are sent and recorded rather than replayed.
+    pub fn is_recording(&self) -> bool {
+        self.inner.is_some()
+    }
+
+    /// Get the cassette file path.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Get the interactions recorded or loaded so far.
+    pub fn cassette(&self) -> Cassette {
+        self.state.lock().unwrap().cassette.clone()
+    }
+
+    /// Append an interaction and write the cassette.
+    fn push(&self, interaction: Interaction) -> Result<()> {
+        let mut state = self.state.lock().unwrap();
+        state.cassette.interactions.push(interaction);
+        state.used.push(true);
+        state.cassette.save(&self.path)
+    }
+
+    /// Find the response to replay for `request`.
+    fn find(&self, request: &HttpRequest) -> Result<RecordedResponse> {
+        let wanted = record_request(request, &self.redactor.with_request(request));
+        let mut state = self.state.lock().unwrap();
+        let matches: Vec<usize> = state
+            .cassette
+            .interactions
+            .iter()
+            .enumerate()
+            .filter(|(_, interaction)| self.matches(&interaction.request, &wanted))
+            .map(|(index, _)| index)
+            .collect();
+        let index = matches
+            .iter()
+            .copied()
+            .find(|&index| !state.used[index])
+            .or_else(|| matches.last().copied())
+            .ok_or_else(|| {
+                Error::TransportError(format!(
+                    "no interaction in cassette {} matches {} {}",
+                    self.path.display(),
+                    wanted.method,
+                    wanted.url
+                ))
+            })?;
+        state.used[index] = true;
+        Ok(state.cassette.interactions[index].response.clone())
+    }
+
+    fn matches(&self, recorded: &RecordedRequest, wanted: &RecordedRequest) -> bool {
+        recorded.method == wanted.method
+            && normalize_url(&recorded.url) == normalize_url(&wanted.url)
+            && (!self.match_body || recorded.body == wanted.body)
+    }
+}
+
+impl Transport for CassetteTransport {
+    fn send<'a>(&'a self, request: HttpRequest) -> BoxFuture<'a, Result<HttpResponse>> {
+        Box::pin(async move {
+            let Some(inner) = &self.inner else {
+                return replay_response(&self.find(&request)?);
+            };
+            let redactor = self.redactor.with_request(&request);
+            let recorded = record_request(&request, &redactor);
+            let response = inner.send(request).await?;
+            self.push(Interaction {
+                request: recorded,
+                response: record_response(
+                    response.status,
+                    &response.headers,
+                    &response.body,
+                    false,
+                    &redactor,
+                ),
+            })?;
+            Ok(response)
+        })
+    }
+
+    fn send_streaming<'a>(
+        &'a self,
+        request: HttpRequest,
+    ) -> BoxFuture<'a, Result<StreamingResponse>> {
+        Box::pin(async move {
+            let Some(inner) = &self.inner else {
+                return replay_streaming(&self.find(&request)?);
+            };
+            let redactor = self.redactor.with_request(&request);
+            let recorded = record_request(&request, &redactor);
+            let response = inner.send_streaming(request).await?;
+
+            let mut recorder = StreamRecorder {
+                transport: self.clone(),
+                pending: Some((recorded, response.status, response.headers.clone())),
+                body: Vec::new(),
+                redactor,
+            };
+            let mut chunks = response.body;
+            let body = async_stream::stream! {
+                while let Some(chunk) = chunks.next().await {
+                    if let Ok(chunk) = &chunk {
+                        recorder.body.extend_from_slice(chunk);
+                    }
+                    yield chunk;
+                }
+                if let Err(err) = recorder.finish() {
+                    yield Err(err);
+                }
+            };
+            Ok(StreamingResponse {
+                status: response.status,
+                headers: response.headers,
+                body: body.boxed(),
+            })
+        })
+    }
+}
+
+impl std::fmt::Debug for CassetteTransport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CassetteTransport")
+            .field("path", &self.path)
+            .field("recording", &self.is_recording())
+            .field("match_body", &self.match_body)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Records a streamed response once it ends or is dropped.
+struct StreamRecorder {
+    transport: CassetteTransport,
+    pending: Option<(RecordedRequest, StatusCode, HeaderMap)>,
+    body: Vec<u8>,
+    redactor: Redactor,
+}
+
+impl StreamRecorder {
+    fn finish(&mut self) -> Result<()> {
+        let Some((request, status, headers)) = self.pending.take() else {
+            return Ok(());
+        };
+        let response = record_response(status, &headers, &self.body, true, &self.redactor);
+        self.transport.push(Interaction { request, response })
+    }
+}
+
+impl Drop for StreamRecorder {
+    fn drop(&mut self) {
+        // A stream dropped early is recorded with the events read so far
+        let _ = self.finish();
+    }
+}
+
+/// Secrets to replace with [`REDACTED`].
+#[derive(Debug, Clone, Default)]
+struct Redactor {
+    secrets: Vec<String>,
+}
+
+impl Redactor {
+    fn add(&mut self, secret: String) {
+        if !secret.is_empty() && !self.secrets.contains(&secret) {
+            self.secrets.push(secret);
+            // Longer secrets first, so a bearer token inside a header value
+            // does not leave the rest of the value behind
+            self.secrets
+                .sort_by_key(|secret| std::cmp::Reverse(secret.len()));
+        }
+    }
+
+    /// Add the credentials carried by `request`.
+    fn with_request(&self, request: &HttpRequest) -> Redactor {
+        let mut redactor = self.clone();
+        for (name, value) in &request.headers {
+            if !is_secret_header(name, value) {
+                continue;
+            }
+            if let Ok(value) = value.to_str() {
+                redactor.add(value.to_string());
+                if let Some(token) = value.strip_prefix("Bearer ") {
+                    redactor.add(token.to_string());
+                }
+            }
+        }
+        if let Ok(url) = reqwest::Url::parse(&request.url) {
+            for (name, value) in url.query_pairs() {
+                if name == "key" {
+                    redactor.add(value.into_owned());
+                }
+            }
+        }
+        redactor
+    }
+
+    fn text(&self, text: &str) -> String {
+        self.secrets.iter().fold(text.to_string(), |text, secret| {
+            text.replace(secret, REDACTED)
+        })
+    }
+}
+
+fn is_secret_header(name: &HeaderName, value: &HeaderValue) -> bool {
+    value.is_sensitive() || SECRET_HEADERS.contains(&name.as_str())
+}
+
+fn record_request(request: &HttpRequest, redactor: &Redactor) -> RecordedRequest {
+    RecordedRequest {
+        method: request.method.to_string(),
+        url: redactor.text(path_and_query(&request.url)),
+        headers: request
+            .headers
+            .iter()
+            .filter(|(name, value)| !is_secret_header(name, value))
+            .map(|(name, value)| (name.to_string(), header_text(value, redactor)))
+            .collect(),
+        body: body_value(&request.body, redactor),
+    }
+}
+
+fn record_response(
+    status: StatusCode,
+    headers: &HeaderMap,
+    body: &[u8],
+    streamed: bool,
+    redactor: &Redactor,
+) -> RecordedResponse {
+    let headers = headers
+        .iter()
+        .filter(|(name, value)| {
+            !value.is_sensitive() && !SKIPPED_RESPONSE_HEADERS.contains(&name.as_str())
+        })
+        .map(|(name, value)| (name.to_string(), header_text(value, redactor)))
+        .collect();
+    if streamed && status.is_success() {
+        let body = Bytes::copy_from_slice(body);
+        let events: Vec<String> = futures::executor::block_on(
+            sse_data(stream::once(async move { Ok(body) }).boxed())
+                .filter_map(|event| async move { event.ok() })
+                .collect(),
+        );
+        RecordedResponse {
+            status: status.as_u16(),
+            headers,
+            body: Value::Null,
+            events: Some(
+                events
+                    .iter()
+                    .map(|event| body_value(event.as_bytes(), redactor))
+                    .collect(),
+            ),
+        }
+    } else {
+        RecordedResponse {
+            status: status.as_u16(),
+            headers,
+            body: body_value(body, redactor),
+            events: None,
+        }
+    }
+}
+
+fn replay_response(recorded: &RecordedResponse) -> Result<HttpResponse> {
+    let mut headers = HeaderMap::new();
+    for (name, value) in &recorded.headers {
+        let name = HeaderName::from_bytes(name.as_bytes())
+            .map_err(|err| Error::TransportError(format!("invalid recorded header: {}", err)))?;
+        let value = HeaderValue::from_str(value)
+            .map_err(|err| Error::TransportError(format!("invalid recorded header: {}", err)))?;
+        headers.append(name, value);
+    }
+    let status = StatusCode::from_u16(recorded.status)
+        .map_err(|err| Error::TransportError(format!("invalid recorded status: {}", err)))?;
+    Ok(HttpResponse {
+        status,
+        headers,
+        body: body_bytes(&recorded.body),
+    })
+}
+
+fn replay_streaming(recorded: &RecordedResponse) -> Result<StreamingResponse> {
+    let response = replay_response(recorded)?;
+    let Some(events) = &recorded.events else {
+        return Ok(response.into());
+    };
+    let chunks: Vec<Result<Bytes>> = events
+        .iter()
+        .map(|event| {
+            let mut chunk = b"data: ".to_vec();
+            chunk.extend_from_slice(&body_bytes(event));
+            chunk.extend_from_slice(b"\r\n\r\n");
+            Ok(Bytes::from(chunk))
+        })
+        .collect();
+    Ok(StreamingResponse {
+        status: response.status,
+        headers: response.headers,
+        body: stream::iter(chunks).boxed(),
+    })
+}
+
+/// Strip the scheme and host from a URL.
+fn path_and_query(url: &str) -> &str {
+    url.split_once("://")
+        .and_then(|(_, rest)| rest.find('/').map(|start| &rest[start..]))
+        .unwrap_or(url)
+}
+
+/// Sort the query parameters so their order does not matter.
+fn normalize_url(url: &str) -> (String, Vec<String>) {
+    let (path, query) = url.split_once('?').unwrap_or((url, ""));
+    let mut params: Vec<String> = query
+        .split('&')
+        .filter(|param| !param.is_empty())
+        .map(str::to_string)
+        .collect();
+    params.sort();
+    (path.to_string(), params)
+}
+
+fn header_text(value: &HeaderValue, redactor: &Redactor) -> String {
+    redactor.text(&String::from_utf8_lossy(value.as_bytes()))
+}
+
+/// Store a body as JSON when it is a JSON object or array, and as a string
+/// otherwise.
+fn body_value(body: &[u8], redactor: &Redactor) -> Value {
+    if body.is_empty() {
+        return Value::Null;
+    }
+    let text = redactor.text(&String::from_utf8_lossy(body));
+    match serde_json::from_str::<Value>(&text) {
+        Ok(json) if json.is_object() || json.is_array() => json,
+        _ => Value::String(text),
+    }
+}
+
+fn body_bytes(body: &Value) -> Bytes {
+    match body {
+        Value::Null => Bytes::new(),
+        Value::String(text) => Bytes::from(text.clone()),
+        json => Bytes::from(json.to_string()),
+    }
+}
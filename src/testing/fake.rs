@@ -0,0 +1,933 @@
+//! Scriptable in-process fake of the Gemini API.
+//!
+//! [`FakeGemini`] is a [`Transport`] that answers requests itself, without
+//! a socket. It implements `generateContent`, `streamGenerateContent`,
+//! `countTokens`, `embedContent`, `batchEmbedContents` and the files
+//! endpoints (resumable uploads, get, list, delete and download). Point a
+//! client at it with [`FakeGemini::client`], or pass a clone to
+//! [`ClientBuilder::transport`](crate::ClientBuilder::transport) to keep
+//! other settings such as the Vertex AI backend.
+//!
+//! Model calls answer with a [`Reply`]: the next one
+//! [queued](FakeGemini::enqueue), else the [default](FakeGemini::set_default).
+//! A closure set with [`respond_with`](FakeGemini::respond_with) sees every
+//! request first and may answer any of them. Replies can be errors (HTTP 429
+//! with `Retry-After`, 500, ...), safety blocks, or delayed by a latency.
+//!
+//! Every request is captured; inspect them with
+//! [`requests`](FakeGemini::requests).
+//!
+//! Token counts are estimated with [`Content::estimate_tokens`], and
+//! embeddings are unit vectors derived from a hash of the text, so equal
+//! texts get equal embeddings.
+//!
+//! # Example
+//!
+//! ```rust
+//! use gemini_rs::testing::{FakeGemini, Reply};
+//! use gemini_rs::{Error, Model};
+//! use std::time::Duration;
+//!
+//! # async fn example() -> Result<(), gemini_rs::Error> {
+//! let fake = FakeGemini::new();
+//! fake.enqueue(Reply::rate_limited(Duration::from_secs(30)));
+//! fake.enqueue(Reply::text("Bonjour"));
+//!
+//! let model = fake.client().model(Model::Gemini25Flash);
+//! let err = model.generate_content("Hello").await.unwrap_err();
+//! assert!(matches!(err, Error::RateLimitExceeded { .. }));
+//! assert_eq!(err.retry_after(), Some(Duration::from_secs(30)));
+//! assert_eq!(model.generate_content("Hello").await?.text(), "Bonjour");
+//! assert_eq!(fake.requests()[1].prompt(), "Hello");
+//! # Ok(())
+//! # }
+//! # tokio::runtime::Runtime::new().unwrap().block_on(example()).unwrap();
+//! ```
+
+use crate::cache::fnv1a_128;
+use crate::client::Client;
+use crate::clock::{Clock, SystemClock};
+use crate::error::Result;
+use crate::transport::{HttpRequest, HttpResponse, StreamingResponse, Transport};
+use crate::types::{Content, GenerateContentResponse, Part};
+use bytes::Bytes;
+use futures::future::BoxFuture;
+use futures::stream::{self, StreamExt};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, CONTENT_TYPE};
+use reqwest::{Method, StatusCode};
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Dimensions of fake embeddings unless the request sets
+/// `outputDimensionality`.
+const EMBEDDING_DIMENSIONS: usize = 768;
+
+/// The API method a [`FakeRequest`] was routed to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Route {
+    /// `models/*:generateContent`.
+    GenerateContent,
+    /// `models/*:streamGenerateContent`.
+    StreamGenerateContent,
+    /// `models/*:countTokens`.
+    CountTokens,
+    /// `models/*:embedContent`.
+    EmbedContent,
+    /// `models/*:batchEmbedContents`.
+    BatchEmbedContents,
+    /// `POST upload/.../files`, including resumable upload chunks.
+    UploadFile,
+    /// `GET files/*`.
+    GetFile,
+    /// `GET files`.
+    ListFiles,
+    /// `DELETE files/*`.
+    DeleteFile,
+    /// `GET download/.../files/*:download`.
+    DownloadFile,
+    /// Anything else; answered with HTTP 404 unless a
+    /// [`respond_with`](FakeGemini::respond_with) closure handles it.
+    Other,
+}
+
+impl Route {
+    /// Whether this is a content generation call.
+    pub fn is_generate(self) -> bool {
+        matches!(self, Route::GenerateContent | Route::StreamGenerateContent)
+    }
+}
+
+/// A request received by a [`FakeGemini`].
+#[derive(Debug, Clone)]
+pub struct FakeRequest {
+    /// HTTP method.
+    pub method: Method,
+    /// Full URL, including the query string.
+    pub url: String,
+    /// The API method the request was routed to.
+    pub route: Route,
+    /// Model id for model methods, e.g. `gemini-2.5-flash`.
+    pub model: Option<String>,
+    /// Request headers, including credentials.
+    pub headers: HeaderMap,
+    /// Request body.
+    pub body: Bytes,
+}
+
+impl FakeRequest {
+    /// Parse the body as JSON.
+    pub fn json(&self) -> Option<Value> {
+        serde_json::from_slice(&self.body).ok()
+    }
+
+    /// Get the text of the last content sent, usually the newest user
+    /// message, or an empty string.
+    pub fn prompt(&self) -> String {
+        self.contents()
+            .last()
+            .map(|content| {
+                content
+                    .parts
+                    .iter()
+                    .filter_map(|part| match part {
+                        Part::Text { text } => Some(text.as_str()),
+                        _ => None,
+                    })
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Get the contents of a generation or `countTokens` request.
+    pub fn contents(&self) -> Vec<Content> {
+        let Some(json) = self.json() else {
+            return Vec::new();
+        };
+        let request = json.get("generateContentRequest").unwrap_or(&json);
+        request
+            .get("contents")
+            .cloned()
+            .and_then(|contents| serde_json::from_value(contents).ok())
+            .unwrap_or_default()
+    }
+
+    /// Estimate the prompt tokens of the contents and system instruction.
+    fn prompt_tokens(&self) -> u64 {
+        let system: Option<Content> = self
+            .json()
+            .and_then(|json| json.get("systemInstruction").cloned())
+            .and_then(|instruction| serde_json::from_value(instruction).ok());
+        self.contents()
+            .iter()
+            .chain(&system)
+            .map(Content::estimate_tokens)
+            .sum()
+    }
+
+    fn header(&self, name: &str) -> Option<&str> {
+        self.headers.get(name).and_then(|value| value.to_str().ok())
+    }
+
+    fn query(&self, name: &str) -> Option<String> {
+        reqwest::Url::parse(&self.url).ok().and_then(|url| {
+            url.query_pairs()
+                .find(|(key, _)| key == name)
+                .map(|(_, value)| value.into_owned())
+        })
+    }
+}
+
+/// A scripted answer of a [`FakeGemini`].
+#[derive(Debug, Clone)]
+pub struct Reply {
+    status: u16,
+    headers: Vec<(String, String)>,
+    body: ReplyBody,
+    latency: Duration,
+}
+
+#[derive(Debug, Clone)]
+enum ReplyBody {
+    /// Model text, streamed one chunk per event.
+    Text {
+        chunks: Vec<String>,
+        finish_reason: String,
+    },
+    Json(Value),
+    Raw {
+        data: Bytes,
+        content_type: String,
+    },
+}
+
+impl Reply {
+    /// Answer with model text, finishing with `STOP` and usage metadata.
+    pub fn text(text: impl Into<String>) -> Self {
+        Self::chunks([text.into()])
+    }
+
+    /// Answer with model text that streams as one event per chunk. Non-
+    /// streaming calls get the chunks joined.
+    pub fn chunks<I, S>(chunks: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        Self::from_body(
+            200,
+            ReplyBody::Text {
+                chunks: chunks.into_iter().map(Into::into).collect(),
+                finish_reason: "STOP".into(),
+            },
+        )
+    }
+
+    /// Answer with a complete response.
+    pub fn response(response: &GenerateContentResponse) -> Self {
+        Self::json(
+            200,
+            serde_json::to_value(response).expect("responses serialize to JSON"),
+        )
+    }
+
+    /// Answer with a raw JSON body. Streaming calls get it as one event if
+    /// `status` is a success.
+    pub fn json(status: u16, body: Value) -> Self {
+        Self::from_body(status, ReplyBody::Json(body))
+    }
+
+    /// Answer with an API error in the Google error format.
+    pub fn error(status: u16, message: impl Into<String>) -> Self {
+        Self::json(
+            status,
+            json!({
+                "error": {
+                    "code": status,
+                    "message": message.into(),
+                    "status": status_name(status),
+                }
+            }),
+        )
+    }
+
+    /// Answer HTTP 429 with a `Retry-After` header and a `RetryInfo`
+    /// detail.
+    pub fn rate_limited(retry_after: Duration) -> Self {
+        let seconds = retry_after.as_secs() + u64::from(retry_after.subsec_nanos() > 0);
+        let mut reply = Self::error(429, "Resource has been exhausted (e.g. check quota).")
+            .with_header("retry-after", seconds.to_string());
+        if let ReplyBody::Json(body) = &mut reply.body {
+            body["error"]["details"] = json!([{
+                "@type": "type.googleapis.com/google.rpc.RetryInfo",
+                "retryDelay": format!("{}s", seconds),
+            }]);
+        }
+        reply
+    }
+
+    /// Answer HTTP 500.
+    pub fn server_error() -> Self {
+        Self::error(500, "An internal error has occurred.")
+    }
+
+    /// Answer with the prompt blocked by safety filters: no candidates and
+    /// a `SAFETY` block reason, which
+    /// [`generate_content`](crate::ModelClient::generate_content) reports as
+    /// [`Error::NoResponse`](crate::Error::NoResponse).
+    pub fn prompt_blocked() -> Self {
+        Self::json(
+            200,
+            json!({
+                "promptFeedback": {
+                    "blockReason": "SAFETY",
+                    "safetyRatings": [
+                        { "category": "HARM_CATEGORY_DANGEROUS_CONTENT", "probability": "HIGH" }
+                    ]
+                }
+            }),
+        )
+    }
+
+    /// Answer with a candidate stopped by safety filters: finish reason
+    /// `SAFETY` and no content.
+    pub fn safety_stop() -> Self {
+        Self::json(
+            200,
+            json!({
+                "candidates": [{
+                    "finishReason": "SAFETY",
+                    "index": 0,
+                    "safetyRatings": [
+                        { "category": "HARM_CATEGORY_DANGEROUS_CONTENT", "probability": "HIGH" }
+                    ]
+                }]
+            }),
+        )
+    }
+
+    /// Delay the answer by `latency`, on top of the fake's
+    /// [latency](FakeGemini::set_latency).
+    pub fn with_latency(mut self, latency: Duration) -> Self {
+        self.latency = latency;
+        self
+    }
+
+    /// Add a response header.
+    pub fn with_header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.push((name.into(), value.into()));
+        self
+    }
+
+    /// Set the finish reason of a text reply, e.g. `MAX_TOKENS`.
+    pub fn with_finish_reason(mut self, reason: impl Into<String>) -> Self {
+        if let ReplyBody::Text { finish_reason, .. } = &mut self.body {
+            *finish_reason = reason.into();
+        }
+        self
+    }
+
+    fn from_body(status: u16, body: ReplyBody) -> Self {
+        Self {
+            status,
+            headers: Vec::new(),
+            body,
+            latency: Duration::ZERO,
+        }
+    }
+
+    fn raw(data: Bytes, content_type: impl Into<String>) -> Self {
+        Self::from_body(
+            200,
+            ReplyBody::Raw {
+                data,
+                content_type: content_type.into(),
+            },
+        )
+    }
+
+    /// Render the reply as a status, headers and body chunks.
+    fn render(self, request: &FakeRequest) -> (StatusCode, HeaderMap, Vec<Bytes>) {
+        let status = StatusCode::from_u16(self.status).unwrap_or(StatusCode::OK);
+        let streaming = request.route == Route::StreamGenerateContent && status.is_success();
+        let mut headers = HeaderMap::new();
+        let chunks = match self.body {
+            ReplyBody::Text {
+                chunks,
+                finish_reason,
+            } => {
+                let prompt_tokens = request.prompt_tokens();
+                let text: String = chunks.concat();
+                let candidates_tokens = Content::text(text.as_str()).estimate_tokens();
+                let usage = json!({
+                    "promptTokenCount": prompt_tokens,
+                    "candidatesTokenCount": candidates_tokens,
+                    "totalTokenCount": prompt_tokens + candidates_tokens,
+                });
+                let chunk = |text: &str, last: bool| {
+                    let mut candidate = json!({
+                        "content": { "role": "model", "parts": [{ "text": text }] },
+                        "index": 0,
+                    });
+                    let mut response = json!({ "modelVersion": request.model });
+                    if last {
+                        candidate["finishReason"] = finish_reason.as_str().into();
+                        response["usageMetadata"] = usage.clone();
+                    }
+                    response["candidates"] = json!([candidate]);
+                    response
+                };
+                if streaming {
+                    let last = chunks.len().saturating_sub(1);
+                    let events: Vec<Value> = if chunks.is_empty() {
+                        vec![chunk("", true)]
+                    } else {
+                        chunks
+                            .iter()
+                            .enumerate()
+                            .map(|(index, text)| chunk(text, index == last))
+                            .collect()
+                    };
+                    sse(&mut headers, &events)
+                } else {
+                    json_body(&mut headers, &chunk(&text, true))
+                }
+            }
+            ReplyBody::Json(body) if streaming => sse(&mut headers, &[body]),
+            ReplyBody::Json(body) => json_body(&mut headers, &body),
+            ReplyBody::Raw { data, content_type } => {
+                if let Ok(value) = HeaderValue::from_str(&content_type) {
+                    headers.insert(CONTENT_TYPE, value);
+                }
+                vec![data]
+            }
+        };
+        for (name, value) in self.headers {
+            if let (Ok(name), Ok(value)) = (
+                HeaderName::from_bytes(name.as_bytes()),
+                HeaderValue::from_str(&value),
+            ) {
+                headers.append(name, value);
+            }
+        }
+        (status, headers, chunks)
+    }
+}
+
+fn json_body(headers: &mut HeaderMap, body: &Value) -> Vec<Bytes> {
+    headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+    vec![Bytes::from(body.to_string())]
+}
+
+fn sse(headers: &mut HeaderMap, events: &[Value]) -> Vec<Bytes> {
+    headers.insert(CONTENT_TYPE, HeaderValue::from_static("text/event-stream"));
+    events
+        .iter()
+        .map(|event| Bytes::from(format!("data: {}\r\n\r\n", event)))
+        .collect()
+}
+
+fn status_name(status: u16) -> &'static str {
+    match status {
+        400 => "INVALID_ARGUMENT",
+        401 => "UNAUTHENTICATED",
+        403 => "PERMISSION_DENIED",
+        404 => "NOT_FOUND",
+        409 => "ALREADY_EXISTS",
+        429 => "RESOURCE_EXHAUSTED",
+        499 => "CANCELLED",
+        500 => "INTERNAL",
+        503 => "UNAVAILABLE",
+        504 => "DEADLINE_EXCEEDED",
+        _ => "UNKNOWN",
+    }
+}
+
+type Handler = Arc<dyn Fn(&FakeRequest) -> Option<Reply> + Send + Sync>;
+
+/// A scriptable, in-process fake of the Gemini API.
+///
+/// Clones share the script, captured requests and files. See the
+/// [module documentation](crate::testing::fake).
+#[derive(Clone)]
+pub struct FakeGemini {
+    state: Arc<Mutex<FakeState>>,
+    clock: Arc<dyn Clock>,
+}
+
+struct FakeState {
+    /// Queued replies; `None` targets generation calls.
+    queue: VecDeque<(Option<Route>, Reply)>,
+    default: Reply,
+    handler: Option<Handler>,
+    latency: Duration,
+    requests: Vec<FakeRequest>,
+    files: BTreeMap<String, FakeFile>,
+    uploads: HashMap<u64, Upload>,
+    next_id: u64,
+}
+
+struct FakeFile {
+    resource: Value,
+    data: Bytes,
+}
+
+struct Upload {
+    display_name: Option<String>,
+    mime_type: String,
+    data: Vec<u8>,
+}
+
+impl FakeGemini {
+    /// Create a fake that answers generation calls with the text `OK`.
+    pub fn new() -> Self {
+        Self {
+            state: Arc::new(Mutex::new(FakeState {
+                queue: VecDeque::new(),
+                default: Reply::text("OK"),
+                handler: None,
+                latency: Duration::ZERO,
+                requests: Vec::new(),
+                files: BTreeMap::new(),
+                uploads: HashMap::new(),
+                next_id: 1,
+            })),
+            clock: Arc::new(SystemClock),
+        }
+    }
+
+    /// Wait out latencies with `clock` instead of `tokio::time::sleep`.
+    pub fn with_clock(mut self, clock: impl Clock + 'static) -> Self {
+        self.clock = Arc::new(clock);
+        self
+    }
+
+    /// Create a client for the Gemini Developer API that sends every
+    /// request to this fake.
+    pub fn client(&self) -> Client {
+        Client::builder()
+            .api_key("fake-api-key")
+            .transport(self.clone())
+            .build()
+            .expect("a client with only an API key and a transport is valid")
+    }
+
+    /// Answer the next generation call that finds no earlier queued reply
+    /// with `reply`.
+    pub fn enqueue(&self, reply: Reply) {
+        self.state.lock().unwrap().queue.push_back((None, reply));
+    }
+
+    /// Answer the next request to `route` with `reply`, e.g. an error for
+    /// [`Route::CountTokens`].
+    pub fn enqueue_for(&self, route: Route, reply: Reply) {
+        self.state
+            .lock()
+            .unwrap()
+            .queue
+            .push_back((Some(route), reply));
+    }
+
+    /// Answer generation calls with `reply` once the queue is empty.
+    pub fn set_default(&self, reply: Reply) {
+        self.state.lock().unwrap().default = reply;
+    }
+
+    /// Answer requests with `handler` before anything else. Returning
+    /// `None` falls back to the queue and built-in behavior.
+    pub fn respond_with(
+        &self,
+        handler: impl Fn(&FakeRequest) -> Option<Reply> + Send + Sync + 'static,
+    ) {
+        self.state.lock().unwrap().handler = Some(Arc::new(handler));
+    }
+
+    /// Delay every answer by `latency`.
+    pub fn set_latency(&self, latency: Duration) {
+        self.state.lock().unwrap().latency = latency;
+    }
+
+    /// Get the requests received so far, oldest first.
+    pub fn requests(&self) -> Vec<FakeRequest> {
+        self.state.lock().unwrap().requests.clone()
+    }
+
+    /// Get the requests received for `route`, oldest first.
+    pub fn requests_to(&self, route: Route) -> Vec<FakeRequest> {
+        self.state
+            .lock()
+            .unwrap()
+            .requests
+            .iter()
+            .filter(|request| request.route == route)
+            .cloned()
+            .collect()
+    }
+
+    /// Forget the captured requests.
+    pub fn clear_requests(&self) {
+        self.state.lock().unwrap().requests.clear();
+    }
+
+    /// Store a file, e.g. batch results to download, and return its
+    /// resource name.
+    pub fn add_file(
+        &self,
+        display_name: impl Into<String>,
+        mime_type: impl Into<String>,
+        data: impl Into<Bytes>,
+    ) -> String {
+        let mut state = self.state.lock().unwrap();
+        state.store_file(
+            Some(display_name.into()),
+            mime_type.into(),
+            data.into(),
+            "https://generativelanguage.googleapis.com/v1beta",
+        )["name"]
+            .as_str()
+            .unwrap_or_default()
+            .to_string()
+    }
+
+    /// Get the contents of a stored or uploaded file.
+    pub fn file_data(&self, name: &str) -> Option<Bytes> {
+        self.state
+            .lock()
+            .unwrap()
+            .files
+            .get(name)
+            .map(|file| file.data.clone())
+    }
+
+    /// Capture `request` and work out the reply and its total latency.
+    fn handle(&self, request: HttpRequest) -> (FakeRequest, Reply, Duration) {
+        let request = parse(request);
+        let (handler, latency) = {
+            let mut state = self.state.lock().unwrap();
+            state.requests.push(request.clone());
+            (state.handler.clone(), state.latency)
+        };
+        // Called without the lock, so the handler may use the fake
+        let reply = handler
+            .and_then(|handler| handler(&request))
+            .unwrap_or_else(|| self.state.lock().unwrap().reply(&request));
+        let latency = latency + reply.latency;
+        (request, reply, latency)
+    }
+}
+
+impl Default for FakeGemini {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl std::fmt::Debug for FakeGemini {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let state = self.state.lock().unwrap();
+        f.debug_struct("FakeGemini")
+            .field("queued", &state.queue.len())
+            .field("requests", &state.requests.len())
+            .field("files", &state.files.len())
+            .finish_non_exhaustive()
+    }
+}
+
+impl FakeState {
+    /// The queued or built-in reply to `request`.
+    fn reply(&mut self, request: &FakeRequest) -> Reply {
+        let queued = self.queue.iter().position(|(route, _)| match route {
+            Some(route) => *route == request.route,
+            None => request.route.is_generate(),
+        });
+        if let Some((_, reply)) = queued.and_then(|index| self.queue.remove(index)) {
+            return reply;
+        }
+
+        match request.route {
+            Route::GenerateContent | Route::StreamGenerateContent => self.default.clone(),
+            Route::CountTokens => {
+                Reply::json(200, json!({ "totalTokens": request.prompt_tokens() }))
+            }
+            Route::EmbedContent => {
+                let body = request.json().unwrap_or_default();
+                Reply::json(200, json!({ "embedding": embedding(&body) }))
+            }
+            Route::BatchEmbedContents => {
+                let body = request.json().unwrap_or_default();
+                let embeddings: Vec<Value> = body["requests"]
+                    .as_array()
+                    .map(|requests| requests.iter().map(embedding).collect())
+                    .unwrap_or_default();
+                Reply::json(200, json!({ "embeddings": embeddings }))
+            }
+            Route::UploadFile => self.upload(request),
+            Route::GetFile => match self.files.get(&file_name(request)) {
+                Some(file) => Reply::json(200, file.resource.clone()),
+                None => not_found(request),
+            },
+            Route::ListFiles => {
+                let files: Vec<Value> = self
+                    .files
+                    .values()
+                    .map(|file| file.resource.clone())
+                    .collect();
+                Reply::json(200, json!({ "files": files }))
+            }
+            Route::DeleteFile => match self.files.remove(&file_name(request)) {
+                Some(_) => Reply::json(200, json!({})),
+                None => not_found(request),
+            },
+            Route::DownloadFile => match self.files.get(&file_name(request)) {
+                Some(file) => Reply::raw(
+                    file.data.clone(),
+                    file.resource["mimeType"].as_str().unwrap_or_default(),
+                ),
+                None => not_found(request),
+            },
+            Route::Other => Reply::error(
+                404,
+                format!(
+                    "FakeGemini does not implement {} {}",
+                    request.method, request.url
+                ),
+            ),
+        }
+    }
+
+    /// Handle the start and the chunks of a resumable upload, or a single
+    /// request upload.
+    fn upload(&mut self, request: &FakeRequest) -> Reply {
+        let (origin, path) = split_url(&request.url);
+        let api_root = format!(
+            "{}{}",
+            origin,
+            path.trim_start_matches("/upload")
+                .trim_end_matches("/files")
+        );
+
+        if let Some(id) = request.query("upload_id") {
+            let Some(upload) = id.parse().ok().and_then(|id| self.uploads.get_mut(&id)) else {
+                return Reply::error(404, format!("Unknown upload session {}", id));
+            };
+            upload.data.extend_from_slice(&request.body);
+            let command = request.header("x-goog-upload-command").unwrap_or_default();
+            if !command.split(',').any(|part| part.trim() == "finalize") {
+                return Reply::json(200, json!({})).with_header("x-goog-upload-status", "active");
+            }
+            let id = id.parse().expect("upload session id is a number");
+            let upload = self.uploads.remove(&id).expect("upload session exists");
+            let file = self.store_file(
+                upload.display_name,
+                upload.mime_type,
+                upload.data.into(),
+                &api_root,
+            );
+            return Reply::json(200, json!({ "file": file }))
+                .with_header("x-goog-upload-status", "final");
+        }
+
+        let metadata = request.json().unwrap_or_default();
+        let display_name = metadata["file"]["displayName"].as_str().map(str::to_string);
+        if request.header("x-goog-upload-protocol") == Some("resumable") {
+            let id = self.next_id;
+            self.next_id += 1;
+            let mime_type = request
+                .header("x-goog-upload-header-content-type")
+                .unwrap_or("application/octet-stream")
+                .to_string();
+            self.uploads.insert(
+                id,
+                Upload {
+                    display_name,
+                    mime_type,
+                    data: Vec::new(),
+                },
+            );
+            let session = format!(
+                "{}{}?upload_id={}&upload_protocol=resumable",
+                origin, path, id
+            );
+            return Reply::json(200, json!({}))
+                .with_header("x-goog-upload-url", session)
+                .with_header("x-goog-upload-status", "active");
+        }
+
+        let mime_type = request
+            .header("content-type")
+            .unwrap_or("application/octet-stream")
+            .to_string();
+        let file = self.store_file(None, mime_type, request.body.clone(), &api_root);
+        Reply::json(200, json!({ "file": file }))
+    }
+
+    fn store_file(
+        &mut self,
+        display_name: Option<String>,
+        mime_type: String,
+        data: Bytes,
+        api_root: &str,
+    ) -> Value {
+        let name = format!("files/fake-{}", self.next_id);
+        self.next_id += 1;
+        let mut resource = json!({
+            "name": name,
+            "mimeType": mime_type,
+            "sizeBytes": data.len().to_string(),
+            "uri": format!("{}/{}", api_root, name),
+            "state": "ACTIVE",
+        });
+        if let Some(display_name) = display_name {
+            resource["displayName"] = display_name.into();
+        }
+        self.files.insert(
+            name,
+            FakeFile {
+                resource: resource.clone(),
+                data,
+            },
+        );
+        resource
+    }
+}
+
+impl Transport for FakeGemini {
+    fn send<'a>(&'a self, request: HttpRequest) -> BoxFuture<'a, Result<HttpResponse>> {
+        Box::pin(async move {
+            let (request, reply, latency) = self.handle(request);
+            if !latency.is_zero() {
+                self.clock.sleep(latency).await;
+            }
+            let (status, headers, chunks) = reply.render(&request);
+            Ok(HttpResponse {
+                status,
+                headers,
+                body: chunks.concat().into(),
+            })
+        })
+    }
+
+    fn send_streaming<'a>(
+        &'a self,
+        request: HttpRequest,
+    ) -> BoxFuture<'a, Result<StreamingResponse>> {
+        Box::pin(async move {
+            let (request, reply, latency) = self.handle(request);
+            if !latency.is_zero() {
+                self.clock.sleep(latency).await;
+            }
+            let (status, headers, chunks) = reply.render(&request);
+            Ok(StreamingResponse {
+                status,
+                headers,
+                body: stream::iter(chunks.into_iter().map(Ok)).boxed(),
+            })
+        })
+    }
+}
+
+/// Route an HTTP request to an API method.
+fn parse(request: HttpRequest) -> FakeRequest {
+    let (_, path) = split_url(&request.url);
+    let path = path.split('?').next().unwrap_or_default();
+    let post = request.method == Method::POST;
+    let get = request.method == Method::GET;
+    let (route, model) = match path.rsplit_once("/models/") {
+        Some((_, method)) => {
+            let (model, method) = method.split_once(':').unwrap_or((method, ""));
+            let route = match method {
+                "generateContent" if post => Route::GenerateContent,
+                "streamGenerateContent" if post => Route::StreamGenerateContent,
+                "countTokens" if post => Route::CountTokens,
+                "embedContent" if post => Route::EmbedContent,
+                "batchEmbedContents" if post => Route::BatchEmbedContents,
+                _ => Route::Other,
+            };
+            (route, Some(model.to_string()))
+        }
+        None => {
+            let route = if path.starts_with("/upload/") {
+                if post && path.ends_with("/files") {
+                    Route::UploadFile
+                } else {
+                    Route::Other
+                }
+            } else if path.starts_with("/download/") {
+                if get && path.ends_with(":download") {
+                    Route::DownloadFile
+                } else {
+                    Route::Other
+                }
+            } else if get && path.ends_with("/files") {
+                Route::ListFiles
+            } else if get && path.contains("/files/") {
+                Route::GetFile
+            } else if request.method == Method::DELETE && path.contains("/files/") {
+                Route::DeleteFile
+            } else {
+                Route::Other
+            };
+            (route, None)
+        }
+    };
+    FakeRequest {
+        method: request.method,
+        url: request.url,
+        route,
+        model,
+        headers: request.headers,
+        body: request.body,
+    }
+}
+
+/// Split a URL into its origin and the rest.
+fn split_url(url: &str) -> (&str, &str) {
+    let start = url
+        .find("://")
+        .and_then(|scheme| url[scheme + 3..].find('/').map(|host| scheme + 3 + host))
+        .unwrap_or(url.len());
+    url.split_at(start)
+}
+
+/// The `files/*` name in a file request path.
+fn file_name(request: &FakeRequest) -> String {
+    let (_, path) = split_url(&request.url);
+    let path = path.split('?').next().unwrap_or_default();
+    let id = path.rsplit_once("/files/").map_or("", |(_, id)| id);
+    format!("files/{}", id.trim_end_matches(":download"))
+}
+
+fn not_found(request: &FakeRequest) -> Reply {
+    Reply::error(404, format!("File {} not found", file_name(request)))
+}
+
+/// A deterministic unit vector for the text of an embed request.
+fn embedding(request: &Value) -> Value {
+    let content: Option<Content> = serde_json::from_value(request["content"].clone()).ok();
+    let text: String = content
+        .iter()
+        .flat_map(|content| &content.parts)
+        .filter_map(|part| match part {
+            Part::Text { text } => Some(text.as_str()),
+            _ => None,
+        })
+        .collect();
+    let dimensions = request["outputDimensionality"]
+        .as_u64()
+        .map_or(EMBEDDING_DIMENSIONS, |dimensions| dimensions as usize);
+
+    let values: Vec<f64> = (0..dimensions)
+        .map(|index| {
+            let hash = fnv1a_128(format!("{}\n{}", index, text).as_bytes());
+            (hash >> 75) as f64 / (1u64 << 53) as f64 * 2.0 - 1.0
+        })
+        .collect();
+    let norm = values.iter().map(|value| value * value).sum::<f64>().sqrt();
+    let values: Vec<f64> = values
+        .iter()
+        .map(|value| if norm > 0.0 { value / norm } else { 0.0 })
+        .collect();
+    json!({ "values": values })
+}
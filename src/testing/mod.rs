@@ -0,0 +1,19 @@
+//! Test utilities for code built on this crate.
+//!
+//! Enabled with the `testing` feature, usually as a dev-dependency:
+//!
+//! ```toml
+//! [dev-dependencies]
+//! gemini-rs = { version = "*", features = ["testing"] }
+//! ```
+//!
+//! - [`CassetteTransport`] records real API traffic to a cassette file, with
+//!   secrets redacted, and replays it offline.
+//! - [`FakeGemini`] is a scriptable in-process fake of the API, for
+//!   canned replies, injected errors and latency, and request assertions.
+
+pub mod cassette;
+pub mod fake;
+
+pub use cassette::{Cassette, CassetteTransport, Interaction, RecordedRequest, RecordedResponse};
+pub use fake::{FakeGemini, FakeRequest, Reply, Route};
@@ -0,0 +1,292 @@
+//! Pluggable HTTP transport.
+//!
+//! Every API call made by a [`ModelClient`](crate::ModelClient) goes through a
+//! [`Transport`]: the client builds an [`HttpRequest`] (URL, headers including
+//! credentials, JSON body) and the transport returns an [`HttpResponse`], or a
+//! [`StreamingResponse`] whose body arrives as a stream of byte chunks.
+//!
+//! [`ReqwestTransport`] is the default. Provide your own to use another HTTP
+//! stack, wrap requests, or answer them in-process in tests.
+//!
+//! Vertex AI access tokens are still fetched with the client's
+//! `reqwest::Client`; only Gemini API calls (including batch and file
+//! requests) go through the transport.
+//!
+//! # Example
+//!
+//! ```rust
+//! use futures::future::BoxFuture;
+//! use gemini_rs::transport::{HttpRequest, HttpResponse, Transport};
+//! use gemini_rs::{Client, Model};
+//!
+//! /// Answers every request with the same text, without opening a socket.
+//! struct Canned;
+//!
+//! impl Transport for Canned {
+//!     fn send<'a>(&'a self, _request: HttpRequest) -> BoxFuture<'a, gemini_rs::Result<HttpResponse>> {
+//!         let body = r#"{"candidates":[{"content":{"role":"model","parts":[{"text":"hi"}]}}]}"#;
+//!         Box::pin(async move { Ok(HttpResponse::new(200, body)) })
+//!     }
+//! }
+//!
+//! # async fn example() -> Result<(), gemini_rs::Error> {
+//! let client = Client::builder().api_key("test").transport(Canned).build()?;
+//! let response = client.model(Model::Gemini25Flash).generate_content("Hello").await?;
+//! assert_eq!(response.text(), "hi");
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::error::{Error, Result};
+use bytes::Bytes;
+use futures::future::BoxFuture;
+use futures::stream::{self, BoxStream, Stream, StreamExt};
+use reqwest::header::{HeaderMap, HeaderValue, CONTENT_TYPE, RETRY_AFTER};
+use reqwest::{Client as HttpClient, Method, StatusCode};
+use serde::Serialize;
+use std::time::Duration;
+
+/// A stream of response body chunks.
+pub type ByteStream = BoxStream<'static, Result<Bytes>>;
+
+/// An HTTP request to the Gemini API.
+#[derive(Debug, Clone)]
+pub struct HttpRequest {
+    /// HTTP method (`POST` for model methods).
+    pub method: Method,
+    /// Full URL, including any query string.
+    pub url: String,
+    /// Request headers, including credentials (marked sensitive).
+    pub headers: HeaderMap,
+    /// Request body.
+    pub body: Bytes,
+}
+
+impl HttpRequest {
+    /// Create a request without a body.
+    pub fn new(method: Method, url: impl Into<String>) -> Self {
+        Self {
+            method,
+            url: url.into(),
+            headers: HeaderMap::new(),
+            body: Bytes::new(),
+        }
+    }
+
+    /// Create a `POST` request with a JSON body.
+    pub fn post_json(url: impl Into<String>, body: &impl Serialize) -> Result<Self> {
+        let mut headers = HeaderMap::new();
+        headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+        Ok(Self {
+            method: Method::POST,
+            url: url.into(),
+            headers,
+            body: Bytes::from(serde_json::to_vec(body)?),
+        })
+    }
+}
+
+/// A fully-read HTTP response.
+#[derive(Debug, Clone)]
+pub struct HttpResponse {
+    /// Status code.
+    pub status: StatusCode,
+    /// Response headers.
+    pub headers: HeaderMap,
+    /// Response body.
+    pub body: Bytes,
+}
+
+impl HttpResponse {
+    /// Create a response with the given status and body and no headers.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `status` is not a valid HTTP status code.
+    pub fn new(status: u16, body: impl Into<Bytes>) -> Self {
+        Self {
+            status: StatusCode::from_u16(status).expect("invalid HTTP status code"),
+            headers: HeaderMap::new(),
+            body: body.into(),
+        }
+    }
+
+    /// Get the body as (lossily decoded) text.
+    pub fn text(&self) -> String {
+        String::from_utf8_lossy(&self.body).into_owned()
+    }
+}
+
+/// An HTTP response whose body is streamed.
+pub struct StreamingResponse {
+    /// Status code.
+    pub status: StatusCode,
+    /// Response headers.
+    pub headers: HeaderMap,
+    /// Response body chunks.
+    pub body: ByteStream,
+}
+
+impl StreamingResponse {
+    /// Read the whole body into an [`HttpResponse`].
+    pub async fn collect(self) -> Result<HttpResponse> {
+        let mut body = Vec::new();
+        let mut chunks = self.body;
+        while let Some(chunk) = chunks.next().await {
+            body.extend_from_slice(&chunk?);
+        }
+        Ok(HttpResponse {
+            status: self.status,
+            headers: self.headers,
+            body: body.into(),
+        })
+    }
+}
+
+impl From<HttpResponse> for StreamingResponse {
+    fn from(response: HttpResponse) -> Self {
+        Self {
+            status: response.status,
+            headers: response.headers,
+            body: stream::once(async move { Ok(response.body) }).boxed(),
+        }
+    }
+}
+
+impl std::fmt::Debug for StreamingResponse {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("StreamingResponse")
+            .field("status", &self.status)
+            .field("headers", &self.headers)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Sends HTTP requests for a [`Client`](crate::Client).
+///
+/// Implementations report failures to reach the server as
+/// [`Error::TransportError`] (or [`Error::HttpError`]); non-2xx responses
+/// are returned as responses and turned into [`Error::ApiError`] by the
+/// client.
+pub trait Transport: Send + Sync {
+    /// Send a request and read the whole response.
+    fn send<'a>(&'a self, request: HttpRequest) -> BoxFuture<'a, Result<HttpResponse>>;
+
+    /// Send a request and stream the response body.
+    ///
+    /// Used by streaming endpoints. The default implementation reads the
+    /// whole response with [`send`](Transport::send) and yields it as a
+    /// single chunk.
+    fn send_streaming<'a>(
+        &'a self,
+        request: HttpRequest,
+    ) -> BoxFuture<'a, Result<StreamingResponse>> {
+        Box::pin(async move { Ok(self.send(request).await?.into()) })
+    }
+}
+
+/// The default [`Transport`], backed by `reqwest`.
+#[derive(Debug, Clone, Default)]
+pub struct ReqwestTransport {
+    client: HttpClient,
+}
+
+impl ReqwestTransport {
+    /// Send requests with the given `reqwest::Client`.
+    pub fn new(client: HttpClient) -> Self {
+        Self { client }
+    }
+
+    async fn execute(&self, request: HttpRequest) -> Result<reqwest::Response> {
+        Ok(self
+            .client
+            .request(request.method, request.url)
+            .headers(request.headers)
+            .body(request.body)
+            .send()
+            .await?)
+    }
+}
+
+impl Transport for ReqwestTransport {
+    fn send<'a>(&'a self, request: HttpRequest) -> BoxFuture<'a, Result<HttpResponse>> {
+        Box::pin(async move {
+            let response = self.execute(request).await?;
+            let status = response.status();
+            let headers = response.headers().clone();
+            let body = response.bytes().await?;
+            Ok(HttpResponse {
+                status,
+                headers,
+                body,
+            })
+        })
+    }
+
+    fn send_streaming<'a>(
+        &'a self,
+        request: HttpRequest,
+    ) -> BoxFuture<'a, Result<StreamingResponse>> {
+        Box::pin(async move {
+            let response = self.execute(request).await?;
+            Ok(StreamingResponse {
+                status: response.status(),
+                headers: response.headers().clone(),
+                body: response
+                    .bytes_stream()
+                    .map(|chunk| chunk.map_err(Error::from))
+                    .boxed(),
+            })
+        })
+    }
+}
+
+/// Split a server-sent events body into the `data` payload of each event.
+pub(crate) fn sse_data(mut body: ByteStream) -> impl Stream<Item = Result<String>> + Send {
+    async_stream::try_stream! {
+        let mut buffer: Vec<u8> = Vec::new();
+        let mut data = String::new();
+        loop {
+            let chunk = body.next().await.transpose()?;
+            let done = chunk.is_none();
+            match chunk {
+                Some(chunk) => buffer.extend_from_slice(&chunk),
+                // Terminate a last line without a newline
+                None if !buffer.is_empty() => buffer.push(b'\n'),
+                None => {}
+            }
+
+            while let Some(end) = buffer.iter().position(|&byte| byte == b'\n') {
+                let line: Vec<u8> = buffer.drain(..=end).collect();
+                let line = String::from_utf8_lossy(&line);
+                let line = line.trim_end_matches(['\n', '\r']);
+                if line.is_empty() {
+                    if !data.is_empty() {
+                        yield std::mem::take(&mut data);
+                    }
+                } else if let Some(value) = line.strip_prefix("data:") {
+                    if !data.is_empty() {
+                        data.push('\n');
+                    }
+                    data.push_str(value.strip_prefix(' ').unwrap_or(value));
+                }
+            }
+
+            if done {
+                if !data.is_empty() {
+                    yield data;
+                }
+                break;
+            }
+        }
+    }
+}
+
+/// The delay of a `Retry-After` header given in seconds.
+pub(crate) fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    headers
+        .get(RETRY_AFTER)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.trim().parse().ok())
+        .map(Duration::from_secs)
+}
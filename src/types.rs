@@ -3,7 +3,55 @@
 //! This module contains all the data structures used to communicate
 //! with the Gemini API, including content types, configuration, and responses.
 
+use crate::cache::CacheStatus;
+use crate::models::Model;
 use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// The author of a [`Content`] turn.
+///
+/// Serialized in lowercase (`"user"`, `"model"`, ...) as expected by the API.
+///
+/// # Example
+///
+/// ```rust
+/// use gemini_rs::{Content, Role};
+///
+/// let content = Content::user("Hi!");
+/// assert_eq!(content.role, Some(Role::User));
+/// assert_eq!(Role::Model.as_str(), "model");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    /// A message written by the end user.
+    User,
+    /// A message generated by the model.
+    Model,
+    /// The result of a function call, sent back to the model.
+    Function,
+    /// System-level guidance. Only valid as a system instruction, never
+    /// inside the conversation history.
+    System,
+}
+
+impl Role {
+    /// Get the API identifier for this role.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Role::User => "user",
+            Role::Model => "model",
+            Role::Function => "function",
+            Role::System => "system",
+        }
+    }
+}
+
+impl fmt::Display for Role {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
 
 /// Content for generation requests and responses.
 ///
@@ -30,13 +78,15 @@ pub struct Content {
     pub parts: Vec<Part>,
     /// The role of this content (user, model, or none for simple prompts)
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub role: Option<String>,
+    pub role: Option<Role>,
 }
 
 impl Content {
     /// Create content from text.
     ///
-    /// Use this for simple text prompts without a specific role.
+    /// Use this for simple text prompts without a specific role. Role-less
+    /// content is only valid as a single-turn prompt; use [`Content::user`]
+    /// and [`Content::model`] for multi-turn history.
     ///
     /// # Example
     ///
@@ -59,15 +109,15 @@ impl Content {
     /// # Example
     ///
     /// ```rust
-    /// use gemini_rs::Content;
+    /// use gemini_rs::{Content, Role};
     ///
     /// let user_message = Content::user("Hello!");
-    /// assert_eq!(user_message.role, Some("user".to_string()));
+    /// assert_eq!(user_message.role, Some(Role::User));
     /// ```
     pub fn user(text: impl Into<String>) -> Self {
         Self {
             parts: vec![Part::Text { text: text.into() }],
-            role: Some("user".to_string()),
+            role: Some(Role::User),
         }
     }
 
@@ -78,23 +128,97 @@ impl Content {
     /// # Example
     ///
     /// ```rust
-    /// use gemini_rs::Content;
+    /// use gemini_rs::{Content, Role};
     ///
     /// let model_message = Content::model("Hello! How can I help?");
-    /// assert_eq!(model_message.role, Some("model".to_string()));
+    /// assert_eq!(model_message.role, Some(Role::Model));
     /// ```
     pub fn model(text: impl Into<String>) -> Self {
         Self {
             parts: vec![Part::Text { text: text.into() }],
-            role: Some("model".to_string()),
+            role: Some(Role::Model),
         }
     }
+
+    /// Create function-role content carrying the result of a function call.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use gemini_rs::{Content, Role};
+    /// use serde_json::json;
+    ///
+    /// let result = Content::function_response("get_weather", json!({ "temp": 21 }));
+    /// assert_eq!(result.role, Some(Role::Function));
+    /// ```
+    pub fn function_response(name: impl Into<String>, response: serde_json::Value) -> Self {
+        Self {
+            parts: vec![Part::FunctionResponse {
+                function_response: FunctionResponse {
+                    name: name.into(),
+                    response,
+                },
+            }],
+            role: Some(Role::Function),
+        }
+    }
+
+    /// Roughly estimate the number of tokens in this content.
+    ///
+    /// Uses about four characters per token for text and serialized
+    /// function data, and a flat 258 tokens per inline image. Use
+    /// [`ModelClient::count_tokens`](crate::ModelClient::count_tokens)
+    /// for exact numbers.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use gemini_rs::Content;
+    ///
+    /// assert_eq!(Content::user("12345678").estimate_tokens(), 2);
+    /// ```
+    pub fn estimate_tokens(&self) -> u64 {
+        fn chars_to_tokens(chars: usize) -> u64 {
+            (chars as u64 + 3) / 4
+        }
+
+        self.parts
+            .iter()
+            .map(|part| match part {
+                Part::Text { text } => chars_to_tokens(text.chars().count()),
+                #[cfg(feature = "multimodal")]
+                Part::InlineData { .. } => 258,
+                Part::FunctionCall { function_call } => {
+                    chars_to_tokens(function_call.name.len() + function_call.args.to_string().len())
+                }
+                Part::FunctionResponse { function_response } => chars_to_tokens(
+                    function_response.name.len() + function_response.response.to_string().len(),
+                ),
+            })
+            .sum()
+    }
+
+    /// Iterate over the function calls contained in this content.
+    pub fn function_calls(&self) -> impl Iterator<Item = &FunctionCall> {
+        self.parts.iter().filter_map(|part| match part {
+            Part::FunctionCall { function_call } => Some(function_call),
+            _ => None,
+        })
+    }
+
+    /// Iterate over the function responses contained in this content.
+    pub fn function_responses(&self) -> impl Iterator<Item = &FunctionResponse> {
+        self.parts.iter().filter_map(|part| match part {
+            Part::FunctionResponse { function_response } => Some(function_response),
+            _ => None,
+        })
+    }
 }
 
 /// A part of content (text, image, etc.)
 ///
-/// Currently supports text and (with the `multimodal` feature) inline data
-/// for images.
+/// Supports text, function calls and responses, and (with the `multimodal`
+/// feature) inline data for images.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(untagged)]
 pub enum Part {
@@ -109,6 +233,37 @@ pub enum Part {
         /// The inline data with MIME type and base64-encoded content.
         inline_data: InlineData,
     },
+    /// A function call requested by the model.
+    FunctionCall {
+        /// The function name and arguments.
+        #[serde(rename = "functionCall", alias = "function_call")]
+        function_call: FunctionCall,
+    },
+    /// The result of a function call, sent back to the model.
+    FunctionResponse {
+        /// The function name and its result.
+        #[serde(rename = "functionResponse", alias = "function_response")]
+        function_response: FunctionResponse,
+    },
+}
+
+/// A function call requested by the model.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FunctionCall {
+    /// The name of the function to call.
+    pub name: String,
+    /// The arguments as a JSON object.
+    #[serde(default)]
+    pub args: serde_json::Value,
+}
+
+/// The result of a function call, answering a [`FunctionCall`] by name.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FunctionResponse {
+    /// The name of the function that was called.
+    pub name: String,
+    /// The function's result as a JSON object.
+    pub response: serde_json::Value,
 }
 
 /// Inline data for multimodal content.
@@ -124,8 +279,11 @@ pub struct InlineData {
     pub data: String,
 }
 
-/// Internal request structure for the generateContent API.
-#[derive(Debug, Serialize)]
+/// Request body of the generateContent API.
+///
+/// Built by [`ModelClient`](crate::ModelClient); visible to
+/// [middleware](crate::middleware) before it is sent.
+#[derive(Debug, Clone, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct GenerateContentRequest {
     /// The content to send to the model.
@@ -139,12 +297,92 @@ pub struct GenerateContentRequest {
     /// Optional system instruction.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub system_instruction: Option<Content>,
+    /// Optional tools the model may call.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tools: Option<Vec<Tool>>,
+}
+
+impl GenerateContentRequest {
+    /// Estimate the prompt tokens of the contents and system instruction
+    /// with [`Content::estimate_tokens`].
+    pub fn estimate_tokens(&self) -> u64 {
+        self.contents
+            .iter()
+            .chain(&self.system_instruction)
+            .map(Content::estimate_tokens)
+            .sum()
+    }
+}
+
+/// A set of functions the model may call.
+///
+/// # Example
+///
+/// ```rust
+/// use gemini_rs::types::{FunctionDeclaration, Tool};
+/// use serde_json::json;
+///
+/// let tool = Tool::functions(vec![FunctionDeclaration::new(
+///     "get_weather",
+///     "Get the current weather for a city",
+/// )
+/// .parameters(json!({
+///     "type": "object",
+///     "properties": { "city": { "type": "string" } },
+///     "required": ["city"]
+/// }))]);
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Tool {
+    /// Functions the model may call.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub function_declarations: Option<Vec<FunctionDeclaration>>,
+}
+
+impl Tool {
+    /// Create a tool from function declarations.
+    pub fn functions(declarations: Vec<FunctionDeclaration>) -> Self {
+        Self {
+            function_declarations: Some(declarations),
+        }
+    }
+}
+
+/// Declaration of a function the model may call.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FunctionDeclaration {
+    /// The function name.
+    pub name: String,
+    /// What the function does, used by the model to decide when to call it.
+    pub description: String,
+    /// JSON schema (OpenAPI subset) of the function's arguments.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub parameters: Option<serde_json::Value>,
+}
+
+impl FunctionDeclaration {
+    /// Create a declaration for a function without parameters.
+    pub fn new(name: impl Into<String>, description: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            description: description.into(),
+            parameters: None,
+        }
+    }
+
+    /// Set the JSON schema of the function's arguments.
+    pub fn parameters(mut self, schema: serde_json::Value) -> Self {
+        self.parameters = Some(schema);
+        self
+    }
 }
 
 /// Configuration for content generation.
 ///
 /// Use `GenerationConfig` to control how the model generates responses.
-/// All fields are optional; unset fields use model defaults.
+/// All fields are optional; unset fields use model defaults. Fields
+/// serialize in camelCase and also accept snake_case when deserialized.
 ///
 /// # Example
 ///
@@ -176,23 +414,23 @@ pub struct GenerationConfig {
 
     /// Nucleus sampling threshold. Range: 0.0 to 1.0.
     /// Only tokens with cumulative probability <= top_p are considered.
-    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(skip_serializing_if = "Option::is_none", alias = "top_p")]
     pub top_p: Option<f32>,
 
     /// Top-k sampling. Only the top k tokens are considered.
-    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(skip_serializing_if = "Option::is_none", alias = "top_k")]
     pub top_k: Option<i32>,
 
     /// Maximum number of tokens to generate.
-    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(skip_serializing_if = "Option::is_none", alias = "max_output_tokens")]
     pub max_output_tokens: Option<i32>,
 
     /// Sequences that stop generation when encountered.
-    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(skip_serializing_if = "Option::is_none", alias = "stop_sequences")]
     pub stop_sequences: Option<Vec<String>>,
 
     /// Response MIME type. Set to "application/json" for JSON mode.
-    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(skip_serializing_if = "Option::is_none", alias = "response_mime_type")]
     pub response_mime_type: Option<String>,
 }
 
@@ -387,13 +625,32 @@ impl SafetySettings {
 /// # Ok(())
 /// # }
 /// ```
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct GenerateContentResponse {
     /// The generated candidates (usually one).
     pub candidates: Option<Vec<Candidate>>,
     /// Feedback about the prompt (e.g., if it was blocked).
     pub prompt_feedback: Option<PromptFeedback>,
+    /// Token usage of the call. When streaming, each chunk carries the
+    /// running totals.
+    #[serde(default)]
+    pub usage_metadata: Option<UsageMetadata>,
+    /// The exact model version that produced the response.
+    #[serde(default)]
+    pub model_version: Option<String>,
+    /// Identifier of the response.
+    #[serde(default)]
+    pub response_id: Option<String>,
+    /// Whether the response came from the
+    /// [response cache](crate::cache); `None` when no cache applied.
+    #[serde(skip)]
+    pub cache_status: Option<CacheStatus>,
+    /// The model that answered, which differs from the requested one when a
+    /// [fallback](crate::fallback) answered. Set by
+    /// [`ModelClient`](crate::ModelClient) generation calls.
+    #[serde(skip)]
+    pub answered_by: Option<Model>,
 }
 
 impl GenerateContentResponse {
@@ -425,7 +682,6 @@ impl GenerateContentResponse {
             .and_then(|content| content.parts.first())
             .and_then(|part| match part {
                 Part::Text { text } => Some(text.clone()),
-                #[cfg(feature = "multimodal")]
                 _ => None,
             })
             .unwrap_or_default()
@@ -465,10 +721,91 @@ impl GenerateContentResponse {
         let text = self.text();
         serde_json::from_str(&text)
     }
+
+    /// Estimate the cost of this response in USD from its token usage, with
+    /// the built-in prices of [`PriceTable::gemini`](crate::cost::PriceTable::gemini).
+    ///
+    /// The model is the reported `model_version`, or else
+    /// [`answered_by`](Self::answered_by). Returns `None` if the response
+    /// has no usage or model, or the model has no built-in prices. Use a
+    /// [`CostCalculator`](crate::cost::CostCalculator) for other prices.
+    pub fn estimated_cost(&self) -> Option<crate::cost::Cost> {
+        crate::cost::default_calculator().response_cost(self)
+    }
+}
+
+/// Token usage reported with a response.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UsageMetadata {
+    /// Tokens in the prompt, including cached tokens.
+    #[serde(default)]
+    pub prompt_token_count: u64,
+    /// Tokens in the generated candidates.
+    #[serde(default)]
+    pub candidates_token_count: u64,
+    /// Tokens spent on thinking, for thinking models.
+    #[serde(default)]
+    pub thoughts_token_count: u64,
+    /// Prompt tokens served from the context cache.
+    #[serde(default)]
+    pub cached_content_token_count: u64,
+    /// Total tokens of the call.
+    #[serde(default)]
+    pub total_token_count: u64,
+    /// Prompt tokens by modality (text, image, audio, video).
+    #[serde(default)]
+    pub prompt_tokens_details: Vec<ModalityTokenCount>,
+    /// Cached prompt tokens by modality.
+    #[serde(default)]
+    pub cache_tokens_details: Vec<ModalityTokenCount>,
+}
+
+impl UsageMetadata {
+    /// Prompt tokens of `modality` (e.g. `"AUDIO"`).
+    pub fn prompt_tokens_of(&self, modality: &str) -> u64 {
+        ModalityTokenCount::sum(&self.prompt_tokens_details, modality)
+    }
+
+    /// Cached prompt tokens of `modality` (e.g. `"AUDIO"`).
+    pub fn cached_tokens_of(&self, modality: &str) -> u64 {
+        ModalityTokenCount::sum(&self.cache_tokens_details, modality)
+    }
+}
+
+/// Token count of one modality.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ModalityTokenCount {
+    /// `TEXT`, `IMAGE`, `AUDIO` or `VIDEO`.
+    #[serde(default)]
+    pub modality: String,
+    /// Number of tokens.
+    #[serde(default)]
+    pub token_count: u64,
+}
+
+impl ModalityTokenCount {
+    fn sum(counts: &[ModalityTokenCount], modality: &str) -> u64 {
+        counts
+            .iter()
+            .filter(|count| count.modality.eq_ignore_ascii_case(modality))
+            .map(|count| count.token_count)
+            .sum()
+    }
+}
+
+/// Response from the countTokens API.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CountTokensResponse {
+    /// Total number of tokens in the prompt.
+    #[serde(default)]
+    pub total_tokens: u64,
 }
 
 /// A single candidate response from the model.
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Candidate {
     /// The generated content.
@@ -480,7 +817,7 @@ pub struct Candidate {
 }
 
 /// Safety rating for a response.
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SafetyRating {
     /// The harm category that was rated.
     pub category: String,
@@ -489,7 +826,7 @@ pub struct SafetyRating {
 }
 
 /// Feedback about the prompt.
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct PromptFeedback {
     /// Why the prompt was blocked (if applicable).
@@ -0,0 +1,342 @@
+//! Batch API tests against a mock server
+//! These tests don't require API keys
+
+use gemini_rs::auth::Credentials;
+use gemini_rs::batch::{BatchInput, BatchRequest, BatchState};
+use gemini_rs::{Client, Content, Error, Model};
+use serde_json::{json, Value};
+use wiremock::matchers::{body_string, header, headers, method, path, query_param};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+fn client(server: &MockServer) -> Client {
+    Client::builder()
+        .api_key("test-key")
+        .base_url(format!("{}/v1beta", server.uri()))
+        .build()
+        .unwrap()
+}
+
+fn requests(client: &Client) -> Vec<BatchRequest> {
+    let model = client.model(Model::Gemini25Flash);
+    vec![
+        BatchRequest::new("a", model.build_request(vec![Content::text("one")])),
+        BatchRequest::new("b", model.build_request(vec![Content::text("two")])),
+    ]
+}
+
+fn response(text: &str) -> Value {
+    json!({ "candidates": [{ "content": { "role": "model", "parts": [{ "text": text }] } }] })
+}
+
+#[tokio::test]
+async fn test_inline_batch_end_to_end() {
+    let server = MockServer::start().await;
+    let client = client(&server);
+
+    Mock::given(method("POST"))
+        .and(path("/v1beta/models/gemini-2.5-flash:batchGenerateContent"))
+        .and(header("x-goog-api-key", "test-key"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "name": "batches/123",
+            "metadata": { "state": "BATCH_STATE_PENDING", "displayName": "job" }
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/v1beta/batches/123"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "name": "batches/123",
+            "done": true,
+            "metadata": {
+                "model": "models/gemini-2.5-flash",
+                "state": "BATCH_STATE_SUCCEEDED",
+                "batchStats": { "requestCount": "2", "successfulRequestCount": "1", "failedRequestCount": "1" }
+            },
+            "response": {
+                "inlinedResponses": { "inlinedResponses": [
+                    { "response": response("first"), "metadata": { "key": "a" } },
+                    { "error": { "code": 400, "message": "bad request" }, "metadata": { "key": "b" } }
+                ] }
+            }
+        })))
+        .mount(&server)
+        .await;
+
+    let batches = client.batches();
+    let job = batches
+        .create(
+            Model::Gemini25Flash,
+            "job",
+            BatchInput::Requests(requests(&client)),
+        )
+        .await
+        .unwrap();
+    assert_eq!(job.name, "batches/123");
+    assert_eq!(job.state(), BatchState::Pending);
+    assert!(!job.done);
+
+    let sent: Value = server.received_requests().await.unwrap()[0]
+        .body_json()
+        .unwrap();
+    assert_eq!(sent["batch"]["displayName"], "job");
+    let inline = &sent["batch"]["inputConfig"]["requests"]["requests"];
+    assert_eq!(inline[1]["metadata"]["key"], "b");
+    assert_eq!(
+        inline[1]["request"]["contents"][0]["parts"][0]["text"],
+        "two"
+    );
+
+    let job = batches.get("123").await.unwrap();
+    assert!(job.done);
+    assert_eq!(job.state(), BatchState::Succeeded);
+    assert!(job.state().is_terminal());
+    assert_eq!(job.metadata.batch_stats.request_count, 2);
+    assert_eq!(job.metadata.batch_stats.failed_request_count, 1);
+
+    let results = batches.results(&job).await.unwrap();
+    assert_eq!(results.len(), 2);
+    assert_eq!(results["a"].as_ref().unwrap().text(), "first");
+    match &results["b"] {
+        Err(Error::ApiError { code, message }) => {
+            assert_eq!(*code, Some(400));
+            assert_eq!(message, "bad request");
+        }
+        other => panic!("expected an API error, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn test_file_batch_end_to_end() {
+    let server = MockServer::start().await;
+    let client = client(&server);
+    let upload_url = format!("{}/upload/session/1", server.uri());
+
+    Mock::given(method("POST"))
+        .and(path("/upload/v1beta/files"))
+        .and(header("x-goog-upload-protocol", "resumable"))
+        .and(header("x-goog-upload-command", "start"))
+        .and(header(
+            "x-goog-upload-header-content-type",
+            "application/jsonl",
+        ))
+        .respond_with(
+            ResponseTemplate::new(200).insert_header("x-goog-upload-url", upload_url.as_str()),
+        )
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/upload/session/1"))
+        .and(headers("x-goog-upload-command", vec!["upload", "finalize"]))
+        .respond_with(
+            ResponseTemplate::new(200).set_body_json(json!({ "file": { "name": "files/input" } })),
+        )
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/v1beta/models/gemini-2.5-flash:batchGenerateContent"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "name": "batches/456",
+            "done": true,
+            "metadata": {
+                "state": "BATCH_STATE_SUCCEEDED",
+                "output": { "responsesFile": "files/output" }
+            }
+        })))
+        .mount(&server)
+        .await;
+    let lines = format!(
+        "{}\n{}\n",
+        json!({ "key": "a", "response": response("first") }),
+        json!({ "key": "b", "error": { "code": 500, "message": "internal" } }),
+    );
+    Mock::given(method("GET"))
+        .and(path("/download/v1beta/files/output:download"))
+        .and(query_param("alt", "media"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(lines))
+        .mount(&server)
+        .await;
+
+    let batches = client.batches();
+    let file = batches
+        .upload_jsonl("input", &requests(&client))
+        .await
+        .unwrap();
+    assert_eq!(file, "files/input");
+
+    let received = server.received_requests().await.unwrap();
+    let uploaded = String::from_utf8(received[1].body.clone()).unwrap();
+    let uploaded: Vec<Value> = uploaded
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(uploaded.len(), 2);
+    assert_eq!(uploaded[0]["key"], "a");
+    assert_eq!(
+        uploaded[0]["request"]["contents"][0]["parts"][0]["text"],
+        "one"
+    );
+
+    let job = batches
+        .create(Model::Gemini25Flash, "job", BatchInput::File(file))
+        .await
+        .unwrap();
+    let sent: Value = server.received_requests().await.unwrap()[2]
+        .body_json()
+        .unwrap();
+    assert_eq!(sent["batch"]["inputConfig"]["fileName"], "files/input");
+
+    let results = batches.results(&job).await.unwrap();
+    assert_eq!(results["a"].as_ref().unwrap().text(), "first");
+    assert!(matches!(
+        results["b"],
+        Err(Error::ApiError {
+            code: Some(500),
+            ..
+        })
+    ));
+}
+
+#[tokio::test]
+async fn test_upload_file_from_disk() {
+    let server = MockServer::start().await;
+    let client = client(&server);
+    let upload_url = format!("{}/upload/session/2", server.uri());
+    let dir = tempfile::tempdir().unwrap();
+    let file = dir.path().join("requests.jsonl");
+    std::fs::write(&file, "{\"key\":\"a\",\"request\":{\"contents\":[]}}\n").unwrap();
+
+    Mock::given(method("POST"))
+        .and(path("/upload/v1beta/files"))
+        .and(header("x-goog-upload-header-content-length", "38"))
+        .and(body_string(
+            json!({ "file": { "displayName": "requests.jsonl" } }).to_string(),
+        ))
+        .respond_with(
+            ResponseTemplate::new(200).insert_header("x-goog-upload-url", upload_url.as_str()),
+        )
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/upload/session/2"))
+        .and(body_string(
+            "{\"key\":\"a\",\"request\":{\"contents\":[]}}\n",
+        ))
+        .respond_with(
+            ResponseTemplate::new(200).set_body_json(json!({ "file": { "name": "files/disk" } })),
+        )
+        .mount(&server)
+        .await;
+
+    let file = client.batches().upload_file(&file).await.unwrap();
+    assert_eq!(file, "files/disk");
+}
+
+#[tokio::test]
+async fn test_list_cancel_and_delete() {
+    let server = MockServer::start().await;
+    let client = client(&server);
+
+    Mock::given(method("GET"))
+        .and(path("/v1beta/batches"))
+        .and(query_param("pageSize", "2"))
+        .and(query_param("pageToken", "next/page"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "operations": [
+                { "name": "batches/1", "metadata": { "state": "BATCH_STATE_RUNNING" } },
+                { "name": "batches/2", "metadata": { "state": "BATCH_STATE_SOMETHING_NEW" } }
+            ],
+            "nextPageToken": "more"
+        })))
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/v1beta/batches/1:cancel"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({})))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("DELETE"))
+        .and(path("/v1beta/batches/1"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({})))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let batches = client.batches();
+    let page = batches.list(Some(2), Some("next/page")).await.unwrap();
+    assert_eq!(page.jobs.len(), 2);
+    assert_eq!(page.jobs[0].state(), BatchState::Running);
+    assert_eq!(page.jobs[1].state(), BatchState::Unspecified);
+    assert_eq!(page.next_page_token.as_deref(), Some("more"));
+
+    batches.cancel("batches/1").await.unwrap();
+    batches.delete("1").await.unwrap();
+}
+
+#[tokio::test]
+async fn test_results_of_unfinished_job_and_api_errors() {
+    let server = MockServer::start().await;
+    let client = client(&server);
+
+    Mock::given(method("GET"))
+        .and(path("/v1beta/batches/running"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "name": "batches/running",
+            "metadata": { "state": "BATCH_STATE_RUNNING" }
+        })))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/v1beta/batches/failed"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "name": "batches/failed",
+            "done": true,
+            "metadata": { "state": "BATCH_STATE_FAILED" },
+            "error": { "code": 3, "message": "invalid input file" }
+        })))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/v1beta/batches/missing"))
+        .respond_with(ResponseTemplate::new(404).set_body_string("not found"))
+        .mount(&server)
+        .await;
+
+    let batches = client.batches();
+    let running = batches.get("running").await.unwrap();
+    assert!(matches!(
+        batches.results(&running).await,
+        Err(Error::InvalidInput(_))
+    ));
+
+    let failed = batches.get("failed").await.unwrap();
+    assert!(matches!(
+        batches.results(&failed).await,
+        Err(Error::ApiError { code: Some(3), .. })
+    ));
+
+    assert!(matches!(
+        batches.get("missing").await,
+        Err(Error::ApiError {
+            code: Some(404),
+            ..
+        })
+    ));
+}
+
+#[tokio::test]
+async fn test_batches_require_gemini_developer_api() {
+    let client = Client::vertex_ai("project", "us-central1", Credentials::from_token("t"));
+    let batches = client.batches();
+    assert!(matches!(
+        batches.get("123").await,
+        Err(Error::ConfigError(_))
+    ));
+    assert!(matches!(
+        batches.upload_jsonl("input", &[]).await,
+        Err(Error::ConfigError(_))
+    ));
+}
@@ -0,0 +1,215 @@
+//! Budget enforcement tests against a local mock server
+//! These tests don't require API keys
+
+use futures::StreamExt;
+use gemini_rs::budget::{Budget, BudgetLimit};
+use gemini_rs::clock::ManualClock;
+use gemini_rs::cost::{CostCalculator, ModelPricing, PriceTable, TokenPrices};
+use gemini_rs::{Client, Error, GenerationConfig, Model};
+use serde_json::json;
+use std::time::Duration;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+fn client(server: &MockServer) -> Client {
+    Client::builder()
+        .api_key("test_api_key")
+        .base_url(format!("{}/v1beta", server.uri()))
+        .build()
+        .unwrap()
+}
+
+/// A response that used `tokens` tokens in total.
+fn response(tokens: u64) -> ResponseTemplate {
+    ResponseTemplate::new(200).set_body_json(json!({
+        "candidates": [{ "content": { "role": "model", "parts": [{ "text": "ok" }] } }],
+        "usageMetadata": {
+            "promptTokenCount": tokens / 2,
+            "candidatesTokenCount": tokens - tokens / 2,
+            "totalTokenCount": tokens
+        }
+    }))
+}
+
+async fn mount_generate(server: &MockServer, tokens: u64) {
+    Mock::given(method("POST"))
+        .and(path("/v1beta/models/gemini-2.0-flash:generateContent"))
+        .respond_with(response(tokens))
+        .mount(server)
+        .await;
+}
+
+fn exceeded(result: gemini_rs::Result<impl std::fmt::Debug>) -> BudgetLimit {
+    match result {
+        Err(Error::BudgetExceeded { limit, .. }) => limit,
+        other => panic!("expected BudgetExceeded, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn test_max_tokens_per_request() {
+    let server = MockServer::start().await;
+    mount_generate(&server, 10).await;
+
+    let model = client(&server)
+        .model(Model::Gemini20Flash)
+        .with_budget(Budget::new().max_tokens_per_request(100));
+
+    // "x" * 200 is estimated at 50 tokens
+    model.generate_content("x".repeat(200)).await.unwrap();
+    assert_eq!(
+        exceeded(model.generate_content("x".repeat(800)).await),
+        BudgetLimit::TokensPerRequest(100)
+    );
+
+    // max_output_tokens counts towards the request
+    let capped = model
+        .clone()
+        .with_config(GenerationConfig::new().max_tokens(60));
+    assert!(matches!(
+        capped.generate_content("x".repeat(200)).await,
+        Err(Error::BudgetExceeded { used, .. }) if used == 110.0
+    ));
+}
+
+#[tokio::test]
+async fn test_rolling_hour_and_day_windows() {
+    let server = MockServer::start().await;
+    mount_generate(&server, 400).await;
+
+    let clock = ManualClock::new();
+    let budget = Budget::new()
+        .max_tokens_per_hour(1000)
+        .max_tokens_per_day(1500)
+        .with_clock(clock.clone());
+    let model = client(&server)
+        .model(Model::Gemini20Flash)
+        .with_budget(budget.clone());
+
+    model.generate_content("Hi").await.unwrap();
+    model.generate_content("Hi").await.unwrap();
+    // Reconciled with the actual usage, not the estimate
+    assert_eq!(budget.tokens_last_hour(), 800);
+
+    model.generate_content("Hi").await.unwrap();
+    assert_eq!(
+        exceeded(model.generate_content("Hi").await),
+        BudgetLimit::TokensPerHour(1000)
+    );
+
+    clock.advance(Duration::from_secs(61 * 60));
+    assert_eq!(budget.tokens_last_hour(), 0);
+    model.generate_content("Hi").await.unwrap();
+    assert_eq!(
+        exceeded(model.generate_content("Hi").await),
+        BudgetLimit::TokensPerDay(1500)
+    );
+
+    clock.advance(Duration::from_secs(24 * 60 * 60));
+    model.generate_content("Hi").await.unwrap();
+}
+
+#[tokio::test]
+async fn test_max_spend_shared_across_clients() {
+    let server = MockServer::start().await;
+    mount_generate(&server, 1_000_000).await;
+
+    // $1 per million tokens in and out
+    let prices = PriceTable::new().with(
+        "gemini-2.0-flash",
+        ModelPricing::new(TokenPrices::new(1.0, 1.0)),
+    );
+    let budget = Budget::new()
+        .max_spend(1.5)
+        .with_calculator(CostCalculator::new(prices));
+
+    let client = client(&server);
+    let first = client.clone().with_budget(budget.clone());
+    let mut chat = client
+        .model(Model::Gemini20Flash)
+        .start_chat()
+        .with_budget(budget.clone());
+
+    first
+        .model(Model::Gemini20Flash)
+        .generate_content("Hi")
+        .await
+        .unwrap();
+    assert!((budget.spent() - 1.0).abs() < 1e-9);
+
+    chat.send_message("Hi").await.unwrap();
+    let result = chat.send_message("Hi again").await;
+    assert!(matches!(
+        result,
+        Err(Error::BudgetExceeded { limit: BudgetLimit::Spend(_), used }) if (used - 2.0).abs() < 1e-9
+    ));
+    assert_eq!(chat.history().len(), 2);
+}
+
+#[tokio::test]
+async fn test_count_tokens_precheck_and_failures() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1beta/models/gemini-2.0-flash:countTokens"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "totalTokens": 500 })))
+        .expect(2)
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/v1beta/models/gemini-2.0-flash:generateContent"))
+        .respond_with(ResponseTemplate::new(500))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let budget = Budget::new().max_tokens_per_request(400).count_tokens(true);
+    let model = client(&server)
+        .model(Model::Gemini20Flash)
+        .with_budget(budget.clone());
+    // Short prompt, but countTokens says 500
+    assert_eq!(
+        exceeded(model.generate_content("Hi").await),
+        BudgetLimit::TokensPerRequest(400)
+    );
+
+    // Failed calls release their reservation
+    let budget = Budget::new().count_tokens(true);
+    let model = client(&server)
+        .model(Model::Gemini20Flash)
+        .with_budget(budget.clone());
+    assert!(matches!(
+        model.generate_content("Hi").await,
+        Err(Error::ApiError { .. })
+    ));
+    assert_eq!(budget.tokens_last_hour(), 0);
+}
+
+#[tokio::test]
+async fn test_streams_reconcile_when_finished() {
+    let server = MockServer::start().await;
+    let chunk = |tokens: u64| {
+        json!({
+            "candidates": [{ "content": { "role": "model", "parts": [{ "text": "a" }] } }],
+            "usageMetadata": { "promptTokenCount": 5, "candidatesTokenCount": tokens - 5, "totalTokenCount": tokens }
+        })
+    };
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(200).set_body_raw(
+            format!("data: {}\n\ndata: {}\n\n", chunk(10), chunk(250)),
+            "text/event-stream",
+        ))
+        .mount(&server)
+        .await;
+
+    let budget = Budget::new();
+    let model = client(&server)
+        .model(Model::Gemini20Flash)
+        .with_budget(budget.clone());
+
+    let stream = model.stream_generate_content("Hi").await.unwrap();
+    // The estimate is reserved while streaming
+    assert_eq!(budget.tokens_last_hour(), 1);
+    let chunks: Vec<_> = stream.collect().await;
+    assert_eq!(chunks.len(), 2);
+    assert_eq!(budget.tokens_last_hour(), 250);
+}
@@ -0,0 +1,127 @@
+//! ClientBuilder tests against a local mock server
+//! These tests don't require API keys
+
+use gemini_rs::backend::ApiVersion;
+use gemini_rs::{Client, Error, Model};
+use serde_json::json;
+use std::time::Duration;
+use wiremock::matchers::{header, header_regex, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+fn text_response(text: &str) -> ResponseTemplate {
+    ResponseTemplate::new(200).set_body_json(json!({
+        "candidates": [{ "content": { "role": "model", "parts": [{ "text": text }] } }]
+    }))
+}
+
+#[tokio::test]
+async fn test_builder_base_url_headers_and_user_agent() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path(
+            "/custom/v1beta/models/gemini-2.0-flash:generateContent",
+        ))
+        .and(header("x-goog-api-key", "test_api_key"))
+        .and(header("x-tenant", "acme"))
+        .and(header_regex("user-agent", r"^gemini-rs/\S+ billing/1\.0$"))
+        .respond_with(text_response("hello"))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let client = Client::builder()
+        .api_key("test_api_key")
+        .base_url(format!("{}/custom/v1beta", server.uri()))
+        .default_header("x-tenant", "acme")
+        .user_agent_suffix("billing/1.0")
+        .build()
+        .unwrap();
+
+    let response = client
+        .model(Model::Gemini20Flash)
+        .generate_content("Hi")
+        .await
+        .unwrap();
+    assert_eq!(response.text(), "hello");
+}
+
+#[tokio::test]
+async fn test_builder_request_timeout() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .respond_with(text_response("too late").set_delay(Duration::from_secs(5)))
+        .mount(&server)
+        .await;
+
+    let client = Client::builder()
+        .api_key("test_api_key")
+        .base_url(server.uri())
+        .timeout(Duration::from_millis(100))
+        .build()
+        .unwrap();
+
+    match client
+        .model(Model::Gemini25Flash)
+        .generate_content("Hi")
+        .await
+    {
+        Err(Error::Timeout(after)) => assert_eq!(after, Duration::from_millis(100)),
+        other => panic!("expected a timeout, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_builder_api_version() {
+    let client = Client::builder()
+        .api_key("test_api_key")
+        .api_version(ApiVersion::V1)
+        .build()
+        .unwrap();
+    assert_eq!(
+        client.backend().base_url(),
+        "https://generativelanguage.googleapis.com/v1"
+    );
+
+    // An explicit base URL wins over the API version
+    let client = Client::builder()
+        .api_key("test_api_key")
+        .api_version(ApiVersion::V1)
+        .base_url("http://localhost:9000/v1beta/")
+        .build()
+        .unwrap();
+    assert_eq!(client.backend().base_url(), "http://localhost:9000/v1beta");
+}
+
+#[test]
+fn test_builder_errors() {
+    assert!(matches!(
+        Client::builder().build(),
+        Err(Error::InvalidInput(_))
+    ));
+
+    assert!(matches!(
+        Client::builder()
+            .api_key("test_api_key")
+            .default_header("bad header", "value")
+            .build(),
+        Err(Error::InvalidInput(_))
+    ));
+
+    // Connection options belong on the injected client
+    assert!(matches!(
+        Client::builder()
+            .api_key("test_api_key")
+            .http_client(reqwest::Client::new())
+            .timeout(Duration::from_secs(1))
+            .build(),
+        Err(Error::InvalidInput(_))
+    ));
+
+    assert!(Client::builder()
+        .api_key("test_api_key")
+        .http_client(reqwest::Client::new())
+        .build()
+        .is_ok());
+}
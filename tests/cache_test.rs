@@ -0,0 +1,342 @@
+//! Response cache tests against a local mock server
+//! These tests don't require API keys
+
+use futures::StreamExt;
+use gemini_rs::cache::{
+    cache_key, CachePolicy, CacheStatus, FileCache, MemoryCache, ResponseCache,
+};
+use gemini_rs::clock::ManualClock;
+use gemini_rs::metrics::MemoryMetrics;
+use gemini_rs::middleware::Interceptor;
+use gemini_rs::rate_limit::{Quota, RateLimiter};
+use gemini_rs::types::{GenerateContentRequest, Part};
+use gemini_rs::{Client, Content, Error, GenerateContentResponse, GenerationConfig, Model, Result};
+use serde_json::json;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+fn client(server: &MockServer) -> Client {
+    Client::builder()
+        .api_key("test_api_key")
+        .base_url(format!("{}/v1beta", server.uri()))
+        .build()
+        .unwrap()
+}
+
+fn response(text: &str) -> GenerateContentResponse {
+    serde_json::from_value(json!({
+        "candidates": [{ "content": { "role": "model", "parts": [{ "text": text }] } }]
+    }))
+    .unwrap()
+}
+
+async fn mount_generate(server: &MockServer) {
+    Mock::given(method("POST"))
+        .and(path("/v1beta/models/gemini-2.0-flash:generateContent"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "candidates": [{ "content": { "role": "model", "parts": [{ "text": "fresh" }] } }],
+            "usageMetadata": { "promptTokenCount": 4, "candidatesTokenCount": 2, "totalTokenCount": 6 }
+        })))
+        .mount(server)
+        .await;
+}
+
+async fn api_calls(server: &MockServer) -> usize {
+    server.received_requests().await.unwrap().len()
+}
+
+#[test]
+fn test_cache_key_is_stable_and_content_based() {
+    let model = Client::new("key").model(Model::Gemini20Flash);
+    let request = model.build_request(vec![Content::text("Hello")]);
+
+    // Pinned so cache files stay valid across releases
+    assert_eq!(
+        cache_key(Model::Gemini20Flash, &request),
+        "f10838cd6dfe6eb90b872575ca77cd5d"
+    );
+
+    let other_prompt = model.build_request(vec![Content::text("Hello!")]);
+    let other_config = model
+        .clone()
+        .with_config(GenerationConfig::new().temperature(0.0))
+        .build_request(vec![Content::text("Hello")]);
+    let key = cache_key(Model::Gemini20Flash, &request);
+    assert_ne!(key, cache_key(Model::Gemini20Flash, &other_prompt));
+    assert_ne!(key, cache_key(Model::Gemini20Flash, &other_config));
+    assert_ne!(key, cache_key(Model::Gemini25Flash, &request));
+}
+
+#[tokio::test]
+async fn test_identical_requests_hit_the_cache() {
+    let server = MockServer::start().await;
+    mount_generate(&server).await;
+
+    let metrics = MemoryMetrics::new();
+    let cache = MemoryCache::new(10);
+    let model = client(&server)
+        .with_metrics(metrics.clone())
+        .model(Model::Gemini20Flash)
+        .with_cache(cache.clone());
+
+    let first = model.generate_content("Hi").await.unwrap();
+    let second = model.generate_content("Hi").await.unwrap();
+    let other = model.generate_content("Bye").await.unwrap();
+
+    assert_eq!(first.cache_status, Some(CacheStatus::Miss));
+    assert_eq!(second.cache_status, Some(CacheStatus::Hit));
+    assert_eq!(second.text(), "fresh");
+    assert_eq!(other.cache_status, Some(CacheStatus::Miss));
+    assert_eq!(api_calls(&server).await, 2);
+    assert_eq!(cache.len(), 2);
+
+    let calls = metrics.calls();
+    assert_eq!(calls.len(), 3);
+    assert_eq!(calls[0].cache, Some(CacheStatus::Miss));
+    assert_eq!(calls[0].usage.total_token_count, 6);
+    assert_eq!(calls[1].cache, Some(CacheStatus::Hit));
+    assert_eq!(calls[1].usage.total_token_count, 0);
+    assert_eq!(
+        metrics.count(|call| call.cache == Some(CacheStatus::Hit)),
+        1
+    );
+}
+
+/// Replaces email addresses with a placeholder and counts calls.
+#[derive(Default)]
+struct ScrubEmails {
+    requests: AtomicUsize,
+    responses: AtomicUsize,
+}
+
+impl Interceptor for ScrubEmails {
+    fn before_request(&self, request: &mut GenerateContentRequest) -> Result<()> {
+        self.requests.fetch_add(1, Ordering::SeqCst);
+        for part in request.contents.iter_mut().flat_map(|c| c.parts.iter_mut()) {
+            if let Part::Text { text } = part {
+                *text = text
+                    .split(' ')
+                    .map(|word| if word.contains('@') { "<email>" } else { word })
+                    .collect::<Vec<_>>()
+                    .join(" ");
+            }
+        }
+        Ok(())
+    }
+
+    fn after_response(&self, _response: &GenerateContentResponse) -> Result<()> {
+        self.responses.fetch_add(1, Ordering::SeqCst);
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn test_cache_keys_on_intercepted_requests_and_runs_hooks_on_hits() {
+    let server = MockServer::start().await;
+    mount_generate(&server).await;
+
+    let scrub = Arc::new(ScrubEmails::default());
+    let model = client(&server)
+        .with_interceptor(scrub.clone())
+        .with_cache(MemoryCache::new(10))
+        .model(Model::Gemini20Flash);
+
+    let first = model
+        .generate_content("Mail ann@example.com")
+        .await
+        .unwrap();
+    let second = model
+        .generate_content("Mail bob@example.com")
+        .await
+        .unwrap();
+
+    assert_eq!(first.cache_status, Some(CacheStatus::Miss));
+    assert_eq!(second.cache_status, Some(CacheStatus::Hit));
+    assert_eq!(scrub.requests.load(Ordering::SeqCst), 2);
+    assert_eq!(scrub.responses.load(Ordering::SeqCst), 2);
+    assert_eq!(api_calls(&server).await, 1);
+    // The API saw the scrubbed request
+    let sent = &server.received_requests().await.unwrap()[0];
+    assert!(String::from_utf8_lossy(&sent.body).contains(r#""text":"Mail <email>""#));
+}
+
+#[tokio::test]
+async fn test_blocked_responses_are_not_cached() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_json(json!({ "promptFeedback": { "blockReason": "SAFETY" } })),
+        )
+        .up_to_n_times(1)
+        .mount(&server)
+        .await;
+    mount_generate(&server).await;
+
+    let cache = MemoryCache::new(10);
+    let model = client(&server)
+        .with_cache(cache.clone())
+        .model(Model::Gemini20Flash);
+
+    assert!(matches!(
+        model.generate_content("Hi").await,
+        Err(Error::NoResponse)
+    ));
+    assert_eq!(cache.len(), 0);
+
+    let response = model.generate_content("Hi").await.unwrap();
+    assert_eq!(response.cache_status, Some(CacheStatus::Miss));
+    assert_eq!(response.text(), "fresh");
+    assert_eq!(cache.len(), 1);
+}
+
+#[tokio::test]
+async fn test_zero_temperature_policy() {
+    let server = MockServer::start().await;
+    mount_generate(&server).await;
+
+    let client = client(&server)
+        .with_cache(MemoryCache::new(10))
+        .with_cache_policy(CachePolicy::ZeroTemperature);
+    let creative = client
+        .model(Model::Gemini20Flash)
+        .with_config(GenerationConfig::new().temperature(0.7));
+    let deterministic = client
+        .model(Model::Gemini20Flash)
+        .with_config(GenerationConfig::new().temperature(0.0));
+
+    for _ in 0..2 {
+        let response = creative.generate_content("Hi").await.unwrap();
+        assert_eq!(response.cache_status, None);
+    }
+    assert_eq!(api_calls(&server).await, 2);
+
+    deterministic.generate_content("Hi").await.unwrap();
+    let response = deterministic.generate_content("Hi").await.unwrap();
+    assert_eq!(response.cache_status, Some(CacheStatus::Hit));
+    assert_eq!(api_calls(&server).await, 3);
+}
+
+#[tokio::test]
+async fn test_hits_skip_rate_limits_and_streams_are_not_cached() {
+    let server = MockServer::start().await;
+    mount_generate(&server).await;
+    Mock::given(method("POST"))
+        .and(path("/v1beta/models/gemini-2.0-flash:streamGenerateContent"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(
+            "data: {\"candidates\":[{\"content\":{\"role\":\"model\",\"parts\":[{\"text\":\"s\"}]}}]}\n\n",
+        ))
+        .mount(&server)
+        .await;
+
+    let limiter = RateLimiter::new()
+        .default_quota(Quota::requests_per_minute(2))
+        .fail_fast(true)
+        .with_clock(ManualClock::new());
+    let model = client(&server)
+        .with_cache(MemoryCache::new(10))
+        .with_rate_limiter(limiter)
+        .model(Model::Gemini20Flash);
+
+    model.generate_content("Hi").await.unwrap();
+    for _ in 0..5 {
+        model.generate_content("Hi").await.unwrap();
+    }
+
+    let mut stream = model.stream_generate_content("Hi").await.unwrap();
+    let chunk = stream.next().await.unwrap().unwrap();
+    assert_eq!(chunk.cache_status, None);
+    assert!(matches!(
+        model.generate_content("New").await,
+        Err(Error::RateLimitExceeded { .. })
+    ));
+    assert_eq!(api_calls(&server).await, 2);
+}
+
+#[test]
+fn test_memory_cache_evicts_least_recently_used_and_expires() {
+    let clock = ManualClock::new();
+    let cache = MemoryCache::new(2)
+        .with_ttl(Duration::from_secs(60))
+        .with_clock(clock.clone());
+
+    cache.put("a", &response("a")).unwrap();
+    cache.put("b", &response("b")).unwrap();
+    // Using "a" makes "b" the least recently used
+    assert!(cache.get("a").unwrap().is_some());
+    cache.put("c", &response("c")).unwrap();
+
+    assert_eq!(cache.len(), 2);
+    assert!(cache.get("b").unwrap().is_none());
+    assert_eq!(cache.get("a").unwrap().unwrap().text(), "a");
+
+    clock.advance(Duration::from_secs(30));
+    cache.put("a", &response("a2")).unwrap();
+    clock.advance(Duration::from_secs(30));
+    assert!(cache.get("c").unwrap().is_none());
+    assert_eq!(cache.get("a").unwrap().unwrap().text(), "a2");
+
+    cache.clear();
+    assert!(cache.is_empty());
+}
+
+#[tokio::test]
+async fn test_file_cache_survives_restarts_and_expires() {
+    let server = MockServer::start().await;
+    mount_generate(&server).await;
+    let dir = tempfile::tempdir().unwrap();
+
+    let first = client(&server)
+        .with_cache(FileCache::new(dir.path()).unwrap())
+        .model(Model::Gemini20Flash);
+    first.generate_content("Hi").await.unwrap();
+
+    // A new client, e.g. in the next CI run, reuses the stored response
+    let second = client(&server)
+        .with_cache(FileCache::new(dir.path()).unwrap())
+        .model(Model::Gemini20Flash);
+    let response = second.generate_content("Hi").await.unwrap();
+    assert_eq!(response.cache_status, Some(CacheStatus::Hit));
+    assert_eq!(response.text(), "fresh");
+    assert_eq!(api_calls(&server).await, 1);
+
+    let expired = FileCache::new(dir.path()).unwrap().with_ttl(Duration::ZERO);
+    let key = cache_key(
+        Model::Gemini20Flash,
+        &second.build_request(vec![Content::text("Hi")]),
+    );
+    assert!(expired.get(&key).unwrap().is_none());
+    assert!(FileCache::new(dir.path())
+        .unwrap()
+        .get(&key)
+        .unwrap()
+        .is_none());
+}
+
+#[tokio::test]
+async fn test_broken_cache_entries_are_misses() {
+    let server = MockServer::start().await;
+    mount_generate(&server).await;
+    let dir = tempfile::tempdir().unwrap();
+    let cache = FileCache::new(dir.path()).unwrap();
+    let model = client(&server)
+        .with_cache(cache.clone())
+        .model(Model::Gemini20Flash);
+
+    let key = cache_key(
+        Model::Gemini20Flash,
+        &model.build_request(vec![Content::text("Hi")]),
+    );
+    std::fs::write(dir.path().join(format!("{}.json", key)), "not json").unwrap();
+    assert!(cache.get(&key).is_err());
+
+    let response = model.generate_content("Hi").await.unwrap();
+    assert_eq!(response.cache_status, Some(CacheStatus::Miss));
+    assert!(cache.get(&key).unwrap().is_some());
+
+    cache.clear().unwrap();
+    assert!(cache.get(&key).unwrap().is_none());
+}
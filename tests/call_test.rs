@@ -0,0 +1,177 @@
+//! Per-call timeout, cancellation and hedging tests against a local mock server
+//! These tests don't require API keys
+
+use futures::StreamExt;
+use gemini_rs::call::{CallOptions, CancellationToken};
+use gemini_rs::{Client, Error, Model};
+use serde_json::{json, Value};
+use std::time::{Duration, Instant};
+use wiremock::matchers::method;
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+fn client(server: &MockServer) -> Client {
+    Client::builder()
+        .api_key("test_api_key")
+        .base_url(format!("{}/v1beta", server.uri()))
+        .build()
+        .unwrap()
+}
+
+fn text_response(text: &str) -> Value {
+    json!({ "candidates": [{ "content": { "role": "model", "parts": [{ "text": text }] } }] })
+}
+
+fn reply(text: &str, delay: Duration) -> ResponseTemplate {
+    ResponseTemplate::new(200)
+        .set_body_json(text_response(text))
+        .set_delay(delay)
+}
+
+async fn requests(server: &MockServer) -> usize {
+    server.received_requests().await.unwrap().len()
+}
+
+#[tokio::test]
+async fn test_call_timeout() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .respond_with(reply("slow", Duration::from_secs(5)))
+        .mount(&server)
+        .await;
+    let model = client(&server)
+        .model(Model::Gemini25Flash)
+        .with_call_options(CallOptions::new().timeout(Duration::from_millis(100)));
+
+    let start = Instant::now();
+    match model.generate_content("Hi").await {
+        Err(err @ Error::Timeout(after)) => {
+            assert_eq!(after, Duration::from_millis(100));
+            assert!(err.is_retryable());
+        }
+        other => panic!("expected a timeout, got {:?}", other),
+    }
+    assert!(start.elapsed() < Duration::from_secs(2));
+
+    assert!(matches!(
+        model
+            .count_tokens(vec![gemini_rs::Content::text("Hi")])
+            .await,
+        Err(Error::Timeout(_))
+    ));
+    assert!(matches!(
+        model.stream_generate_content("Hi").await,
+        Err(Error::Timeout(_))
+    ));
+}
+
+#[tokio::test]
+async fn test_cancellation() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .respond_with(reply("slow", Duration::from_secs(5)))
+        .mount(&server)
+        .await;
+    let token = CancellationToken::new();
+    let model = client(&server)
+        .model(Model::Gemini25Flash)
+        .with_call_options(CallOptions::new().cancel_on(token.clone()));
+
+    let canceller = token.clone();
+    tokio::spawn(async move {
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        canceller.cancel();
+    });
+    let start = Instant::now();
+    match model.generate_content("Hi").await {
+        Err(err @ Error::Cancelled) => {
+            assert!(!err.is_retryable());
+            assert_eq!(err.to_string(), "Call cancelled");
+        }
+        other => panic!("expected a cancellation, got {:?}", other),
+    }
+    assert!(start.elapsed() < Duration::from_secs(2));
+    assert_eq!(requests(&server).await, 1);
+
+    // Calls with a cancelled token are not sent
+    let mut chat = model.start_chat();
+    assert!(matches!(
+        chat.send_message("Hi").await,
+        Err(Error::Cancelled)
+    ));
+    assert_eq!(requests(&server).await, 1);
+}
+
+#[tokio::test]
+async fn test_cancelled_stream_ends() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(format!(
+            "data: {}\r\n\r\ndata: {}\r\n\r\n",
+            text_response("Hel"),
+            text_response("lo")
+        )))
+        .mount(&server)
+        .await;
+    let token = CancellationToken::new();
+    let model = client(&server)
+        .model(Model::Gemini25Flash)
+        .with_call_options(CallOptions::new().cancel_on(token.clone()));
+
+    let mut stream = model.stream_generate_content("Hi").await.unwrap();
+    assert_eq!(stream.next().await.unwrap().unwrap().text(), "Hel");
+    token.cancel();
+    assert!(matches!(stream.next().await, Some(Err(Error::Cancelled))));
+    assert!(stream.next().await.is_none());
+}
+
+#[tokio::test]
+async fn test_hedging() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .respond_with(reply("first", Duration::from_secs(5)))
+        .up_to_n_times(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .respond_with(reply("hedged", Duration::from_millis(50)))
+        .mount(&server)
+        .await;
+    let model = client(&server)
+        .model(Model::Gemini25Flash)
+        .with_call_options(CallOptions::new().hedge(Duration::from_millis(100)));
+
+    let start = Instant::now();
+    let response = model.generate_content("Hi").await.unwrap();
+    assert_eq!(response.text(), "hedged");
+    assert!(start.elapsed() < Duration::from_secs(2));
+    assert_eq!(requests(&server).await, 2);
+
+    // A fast answer is not hedged
+    assert_eq!(model.generate_content("Hi").await.unwrap().text(), "hedged");
+    assert_eq!(requests(&server).await, 3);
+}
+
+#[tokio::test]
+async fn test_hedging_takes_the_first_success() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .respond_with(
+            ResponseTemplate::new(503)
+                .set_body_json(json!({ "error": { "code": 503, "message": "overloaded" } }))
+                .set_delay(Duration::from_millis(200)),
+        )
+        .up_to_n_times(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .respond_with(reply("hedged", Duration::from_millis(400)))
+        .mount(&server)
+        .await;
+    let model = client(&server)
+        .model(Model::Gemini25Flash)
+        .with_call_options(CallOptions::new().hedge(Duration::from_millis(50)));
+
+    // The first request fails before the duplicate answers
+    assert_eq!(model.generate_content("Hi").await.unwrap().text(), "hedged");
+    assert_eq!(requests(&server).await, 2);
+}
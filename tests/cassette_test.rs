@@ -0,0 +1,268 @@
+#![cfg(feature = "testing")]
+//! Record/replay transport tests against a mock server
+//! These tests don't require API keys
+
+use futures::future::BoxFuture;
+use futures::StreamExt;
+use gemini_rs::testing::{
+    Cassette, CassetteTransport, Interaction, RecordedRequest, RecordedResponse,
+};
+use gemini_rs::transport::{HttpRequest, HttpResponse, ReqwestTransport, Transport};
+use gemini_rs::{Client, Error, Model, REDACTED};
+use reqwest::header::{HeaderValue, AUTHORIZATION};
+use reqwest::Method;
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+fn text_response(text: &str) -> Value {
+    json!({ "candidates": [{ "content": { "role": "model", "parts": [{ "text": text }] } }] })
+}
+
+async fn mount(server: &MockServer) {
+    Mock::given(method("POST"))
+        .and(path("/v1beta/models/gemini-2.0-flash:generateContent"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(text_response("recorded")))
+        .mount(server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path(
+            "/v1beta/models/gemini-2.0-flash:streamGenerateContent",
+        ))
+        .respond_with(ResponseTemplate::new(200).set_body_string(format!(
+            "data: {}\r\n\r\ndata: {}\r\n\r\n",
+            text_response("Hel"),
+            text_response("lo")
+        )))
+        .mount(server)
+        .await;
+}
+
+fn client(api_key: &str, base_url: Option<String>, transport: CassetteTransport) -> Client {
+    let builder = Client::builder().api_key(api_key).transport(transport);
+    match base_url {
+        Some(base_url) => builder.base_url(base_url),
+        None => builder,
+    }
+    .build()
+    .unwrap()
+}
+
+async fn stream_text(client: &Client, prompt: &str) -> Vec<String> {
+    let mut stream = client
+        .model(Model::Gemini20Flash)
+        .stream_generate_content(prompt)
+        .await
+        .unwrap();
+    let mut chunks = Vec::new();
+    while let Some(chunk) = stream.next().await {
+        chunks.push(chunk.unwrap().text());
+    }
+    chunks
+}
+
+#[tokio::test]
+async fn test_record_then_replay_offline() {
+    let dir = tempfile::tempdir().unwrap();
+    let file = dir.path().join("cassettes/flow.json");
+
+    {
+        let server = MockServer::start().await;
+        mount(&server).await;
+        let transport = CassetteTransport::record(&file, ReqwestTransport::default());
+        assert!(transport.is_recording());
+        let client = client(
+            "secret-api-key",
+            Some(format!("{}/v1beta", server.uri())),
+            transport.clone(),
+        );
+
+        let model = client.model(Model::Gemini20Flash);
+        assert_eq!(
+            model.generate_content("Hi").await.unwrap().text(),
+            "recorded"
+        );
+        assert_eq!(stream_text(&client, "Hi").await, vec!["Hel", "lo"]);
+        assert_eq!(transport.cassette().interactions.len(), 2);
+    }
+
+    let saved = std::fs::read_to_string(&file).unwrap();
+    assert!(!saved.contains("secret-api-key"));
+    assert!(!saved.contains("127.0.0.1"));
+    let cassette = Cassette::load(&file).unwrap();
+    let generate = &cassette.interactions[0];
+    assert_eq!(generate.request.method, "POST");
+    assert_eq!(
+        generate.request.url,
+        "/v1beta/models/gemini-2.0-flash:generateContent"
+    );
+    assert_eq!(
+        generate.request.body["contents"][0]["parts"][0]["text"],
+        "Hi"
+    );
+    assert_eq!(generate.response.body, text_response("recorded"));
+    let stream = &cassette.interactions[1];
+    assert_eq!(
+        stream.request.url,
+        "/v1beta/models/gemini-2.0-flash:streamGenerateContent?alt=sse"
+    );
+    assert_eq!(
+        stream.response.events,
+        Some(vec![text_response("Hel"), text_response("lo")])
+    );
+
+    // The server is gone and the key differs; the default base URL has the
+    // same path as the recorded one
+    let transport = CassetteTransport::replay(&file).unwrap();
+    assert!(!transport.is_recording());
+    let client = client("another-key", None, transport);
+    let model = client.model(Model::Gemini20Flash);
+    assert_eq!(
+        model.generate_content("Hi").await.unwrap().text(),
+        "recorded"
+    );
+    assert_eq!(stream_text(&client, "Hi").await, vec!["Hel", "lo"]);
+}
+
+struct Echo;
+
+impl Transport for Echo {
+    fn send<'a>(&'a self, request: HttpRequest) -> BoxFuture<'a, gemini_rs::Result<HttpResponse>> {
+        Box::pin(async move { Ok(HttpResponse::new(200, request.body)) })
+    }
+}
+
+#[tokio::test]
+async fn test_secrets_are_redacted() {
+    let dir = tempfile::tempdir().unwrap();
+    let file = dir.path().join("redacted.json");
+    let transport = CassetteTransport::record(&file, Echo).redact("my-project-42");
+
+    let mut request = HttpRequest::post_json(
+        "https://example.com/v1/projects/my-project-42/models/m:generateContent?key=query-key",
+        &json!({ "token": "bearer-token", "key": "query-key" }),
+    )
+    .unwrap();
+    let mut token = HeaderValue::from_static("Bearer bearer-token");
+    token.set_sensitive(true);
+    request.headers.insert(AUTHORIZATION, token);
+
+    let response = transport.send(request.clone()).await.unwrap();
+    assert!(response.text().contains("bearer-token"));
+
+    let saved = std::fs::read_to_string(&file).unwrap();
+    for secret in ["my-project-42", "bearer-token", "query-key"] {
+        assert!(!saved.contains(secret), "{} leaked: {}", secret, saved);
+    }
+    let interaction = &Cassette::load(&file).unwrap().interactions[0];
+    assert_eq!(
+        interaction.request.url,
+        format!(
+            "/v1/projects/{0}/models/m:generateContent?key={0}",
+            REDACTED
+        )
+    );
+    assert!(!interaction.request.headers.contains_key("authorization"));
+    assert_eq!(
+        interaction.response.body,
+        json!({ "token": REDACTED, "key": REDACTED })
+    );
+
+    // Requests are redacted before matching, so replay accepts them
+    let replay = CassetteTransport::replay(&file)
+        .unwrap()
+        .redact("my-project-42");
+    assert_eq!(replay.send(request).await.unwrap().status, 200);
+}
+
+fn interaction(url: &str, body: Value, text: &str) -> Interaction {
+    Interaction {
+        request: RecordedRequest {
+            method: "POST".into(),
+            url: url.into(),
+            headers: BTreeMap::new(),
+            body,
+        },
+        response: RecordedResponse {
+            status: 200,
+            headers: BTreeMap::new(),
+            body: text_response(text),
+            events: None,
+        },
+    }
+}
+
+#[tokio::test]
+async fn test_replay_order_and_mismatches() {
+    let dir = tempfile::tempdir().unwrap();
+    let file = dir.path().join("sequence.json");
+    Cassette {
+        interactions: vec![
+            interaction("/v1/a?x=1&y=2", json!({ "n": 1 }), "first"),
+            interaction("/v1/a?x=1&y=2", json!({ "n": 1 }), "second"),
+            interaction("/v1/b", json!({ "n": 1 }), "other"),
+        ],
+    }
+    .save(&file)
+    .unwrap();
+
+    let transport = CassetteTransport::replay(&file).unwrap();
+    let request = |url: &str, body: Value| {
+        HttpRequest::post_json(format!("http://host{}", url), &body).unwrap()
+    };
+    let text = |response: HttpResponse| {
+        serde_json::from_slice::<Value>(&response.body).unwrap()["candidates"][0]["content"]
+            ["parts"][0]["text"]
+            .clone()
+    };
+
+    // Recorded responses are used in order, then the last one repeats;
+    // query parameter order does not matter
+    let a = request("/v1/a?y=2&x=1", json!({ "n": 1 }));
+    for expected in ["first", "second", "second"] {
+        assert_eq!(text(transport.send(a.clone()).await.unwrap()), expected);
+    }
+
+    let different_body = request("/v1/b", json!({ "n": 2 }));
+    match transport.send(different_body.clone()).await {
+        Err(Error::TransportError(message)) => {
+            assert!(message.contains("POST /v1/b"), "{}", message);
+        }
+        other => panic!("expected a transport error, got {:?}", other),
+    }
+    let get = HttpRequest::new(Method::GET, "http://host/v1/b");
+    assert!(transport.send(get).await.is_err());
+
+    let lenient = CassetteTransport::replay(&file).unwrap().match_body(false);
+    assert_eq!(text(lenient.send(different_body).await.unwrap()), "other");
+}
+
+#[tokio::test]
+async fn test_once_records_then_replays() {
+    let dir = tempfile::tempdir().unwrap();
+    let file = dir.path().join("once.json");
+    assert!(matches!(
+        CassetteTransport::replay(&file),
+        Err(Error::IoError(_))
+    ));
+
+    let server = MockServer::start().await;
+    mount(&server).await;
+    let base_url = format!("{}/v1beta", server.uri());
+
+    let first = CassetteTransport::once(&file, ReqwestTransport::default()).unwrap();
+    assert!(first.is_recording());
+    let model = client("key", Some(base_url.clone()), first).model(Model::Gemini20Flash);
+    model.generate_content("Hi").await.unwrap();
+
+    let second = CassetteTransport::once(&file, ReqwestTransport::default()).unwrap();
+    assert!(!second.is_recording());
+    let model = client("key", Some(base_url), second).model(Model::Gemini20Flash);
+    assert_eq!(
+        model.generate_content("Hi").await.unwrap().text(),
+        "recorded"
+    );
+    assert!(model.generate_content("Bye").await.is_err());
+    assert_eq!(server.received_requests().await.unwrap().len(), 1);
+}
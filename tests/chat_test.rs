@@ -2,7 +2,10 @@
 //!
 //! Run with: cargo test --test chat_test -- --ignored
 
-use gemini_rs::{Client, Model};
+use gemini_rs::{Client, Content, Model};
+use serde_json::json;
+use wiremock::matchers::method;
+use wiremock::{Mock, MockServer, ResponseTemplate};
 
 #[tokio::test]
 #[ignore]
@@ -57,3 +60,123 @@ async fn test_chat_clear_history() {
     chat.clear_history();
     assert!(chat.history().is_empty());
 }
+
+#[tokio::test]
+async fn test_chat_failed_call_rolls_back_against_mock() {
+    let server = MockServer::start().await;
+    let client = Client::builder()
+        .api_key("test_api_key")
+        .base_url(format!("{}/v1beta", server.uri()))
+        .build()
+        .unwrap();
+    let mut chat = client.model(Model::Gemini25Flash).start_chat();
+
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(503))
+        .up_to_n_times(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "candidates": [{ "content": { "role": "model", "parts": [{ "text": "Hi!" }] } }]
+        })))
+        .mount(&server)
+        .await;
+
+    assert!(chat.send_message("Hello").await.is_err());
+    assert!(chat.history().is_empty());
+
+    let response = chat.retry_last().await.unwrap();
+    assert_eq!(response.text(), "Hi!");
+    assert_eq!(chat.history().len(), 2);
+
+    // The retried request carried a single user turn, not two
+    let requests = server.received_requests().await.unwrap();
+    let body: serde_json::Value = requests.last().unwrap().body_json().unwrap();
+    assert_eq!(body["contents"].as_array().unwrap().len(), 1);
+}
+
+/// Fail the next call, then answer `text`.
+async fn fail_once_then_reply(server: &MockServer, text: &str) {
+    server.reset().await;
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(503))
+        .up_to_n_times(1)
+        .mount(server)
+        .await;
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "candidates": [{ "content": { "role": "model", "parts": [{ "text": text }] } }]
+        })))
+        .mount(server)
+        .await;
+}
+
+#[tokio::test]
+async fn test_chat_retry_after_failed_regenerate() {
+    let server = MockServer::start().await;
+    let client = Client::builder()
+        .api_key("test_api_key")
+        .base_url(format!("{}/v1beta", server.uri()))
+        .build()
+        .unwrap();
+    let mut chat = client
+        .model(Model::Gemini25Flash)
+        .start_chat_with_history(vec![Content::user("Hello"), Content::model("Hi!")]);
+
+    fail_once_then_reply(&server, "Hey there!").await;
+    assert!(chat.regenerate().await.is_err());
+    assert_eq!(chat.history().len(), 2);
+
+    let response = chat.retry_last().await.unwrap();
+    assert_eq!(response.text(), "Hey there!");
+    assert_eq!(
+        serde_json::to_value(chat.history()).unwrap(),
+        json!([
+            { "role": "user", "parts": [{ "text": "Hello" }] },
+            { "role": "model", "parts": [{ "text": "Hey there!" }] }
+        ])
+    );
+
+    // The retry asked again for the same user turn, without a duplicate
+    let requests = server.received_requests().await.unwrap();
+    let body: serde_json::Value = requests.last().unwrap().body_json().unwrap();
+    assert_eq!(
+        body["contents"],
+        json!([{ "role": "user", "parts": [{ "text": "Hello" }] }])
+    );
+}
+
+#[tokio::test]
+async fn test_chat_retry_after_failed_edit() {
+    let server = MockServer::start().await;
+    let client = Client::builder()
+        .api_key("test_api_key")
+        .base_url(format!("{}/v1beta", server.uri()))
+        .build()
+        .unwrap();
+    let mut chat = client
+        .model(Model::Gemini25Flash)
+        .start_chat_with_history(vec![Content::user("Hello"), Content::model("Hi!")]);
+
+    fail_once_then_reply(&server, "Bonjour !").await;
+    assert!(chat.edit_last_user_message("Bonjour").await.is_err());
+    assert_eq!(chat.history().len(), 2);
+
+    chat.retry_last().await.unwrap();
+    assert_eq!(
+        serde_json::to_value(chat.history()).unwrap(),
+        json!([
+            { "role": "user", "parts": [{ "text": "Bonjour" }] },
+            { "role": "model", "parts": [{ "text": "Bonjour !" }] }
+        ])
+    );
+
+    // The edited message replaced the old one in the retried request
+    let requests = server.received_requests().await.unwrap();
+    let body: serde_json::Value = requests.last().unwrap().body_json().unwrap();
+    assert_eq!(
+        body["contents"],
+        json!([{ "role": "user", "parts": [{ "text": "Bonjour" }] }])
+    );
+}
@@ -0,0 +1,249 @@
+//! Concurrent generation tests against a local mock server
+//! These tests don't require API keys
+
+use futures::future::BoxFuture;
+use futures::StreamExt;
+use gemini_rs::concurrent::{ManyOptions, Progress};
+use gemini_rs::middleware::{Middleware, ModelRequest, ModelResponse, Next};
+use gemini_rs::{Client, Error, Model, Result};
+use serde::Deserialize;
+use serde_json::json;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use wiremock::matchers::{body_partial_json, method};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+fn client(server: &MockServer) -> Client {
+    Client::builder()
+        .api_key("test_api_key")
+        .base_url(format!("{}/v1beta", server.uri()))
+        .build()
+        .unwrap()
+}
+
+fn text_response(text: &str) -> ResponseTemplate {
+    ResponseTemplate::new(200).set_body_json(json!({
+        "candidates": [{ "content": { "role": "model", "parts": [{ "text": text }] } }]
+    }))
+}
+
+fn prompt(text: &str) -> serde_json::Value {
+    json!({ "contents": [{ "parts": [{ "text": text }] }] })
+}
+
+fn fast(concurrency: usize) -> ManyOptions {
+    ManyOptions::new(concurrency).with_backoff(Duration::from_millis(1), Duration::from_millis(5))
+}
+
+/// Tracks the most calls in flight at once.
+#[derive(Default)]
+struct InFlight {
+    current: AtomicUsize,
+    max: AtomicUsize,
+}
+
+impl Middleware for InFlight {
+    fn handle<'a>(
+        &'a self,
+        request: ModelRequest,
+        next: Next<'a>,
+    ) -> BoxFuture<'a, Result<ModelResponse>> {
+        Box::pin(async move {
+            let current = self.current.fetch_add(1, Ordering::SeqCst) + 1;
+            self.max.fetch_max(current, Ordering::SeqCst);
+            let response = next.run(request).await;
+            self.current.fetch_sub(1, Ordering::SeqCst);
+            response
+        })
+    }
+}
+
+#[tokio::test]
+async fn test_generate_many_in_order_with_bounded_concurrency() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(body_partial_json(prompt("bad")))
+        .respond_with(ResponseTemplate::new(400))
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .respond_with(text_response("ok").set_delay(Duration::from_millis(20)))
+        .mount(&server)
+        .await;
+
+    let in_flight = Arc::new(InFlight::default());
+    let model = client(&server)
+        .with_middleware(in_flight.clone())
+        .model(Model::Gemini20Flash);
+
+    let prompts: Vec<String> = (0..10)
+        .map(|i| {
+            if i == 3 {
+                "bad".to_string()
+            } else {
+                format!("p{}", i)
+            }
+        })
+        .collect();
+    let results = model.generate_many(prompts, 3).await;
+
+    assert_eq!(results.len(), 10);
+    for (i, result) in results.iter().enumerate() {
+        if i == 3 {
+            assert!(matches!(
+                result,
+                Err(Error::ApiError {
+                    code: Some(400),
+                    ..
+                })
+            ));
+        } else {
+            assert_eq!(result.as_ref().unwrap().text(), "ok");
+        }
+    }
+    assert_eq!(in_flight.max.load(Ordering::SeqCst), 3);
+}
+
+#[tokio::test]
+async fn test_retries_and_progress() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(429))
+        .up_to_n_times(2)
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .respond_with(text_response("ok"))
+        .mount(&server)
+        .await;
+
+    let updates = Arc::new(Mutex::new(Vec::new()));
+    let options = fast(1).on_progress({
+        let updates = updates.clone();
+        move |progress| updates.lock().unwrap().push(*progress)
+    });
+    let model = client(&server).model(Model::Gemini20Flash);
+    let results = model.generate_many(vec!["a", "b"], options).await;
+    assert!(results.iter().all(|result| result.is_ok()));
+
+    let last = *updates.lock().unwrap().last().unwrap();
+    assert_eq!(
+        last,
+        Progress {
+            total: 2,
+            completed: 2,
+            failed: 0,
+            retries: 2
+        }
+    );
+
+    // Retries are limited
+    server.reset().await;
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(503))
+        .expect(3)
+        .mount(&server)
+        .await;
+    let results = model.generate_many(vec!["a"], fast(1).max_retries(2)).await;
+    assert!(matches!(
+        results[0],
+        Err(Error::ApiError {
+            code: Some(503),
+            ..
+        })
+    ));
+}
+
+#[tokio::test]
+async fn test_retry_waits_for_the_server_delay() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(429).set_body_json(json!({
+            "error": {
+                "code": 429,
+                "message": "Resource has been exhausted",
+                "details": [{
+                    "@type": "type.googleapis.com/google.rpc.RetryInfo",
+                    "retryDelay": "0.3s"
+                }]
+            }
+        })))
+        .up_to_n_times(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .respond_with(text_response("ok"))
+        .mount(&server)
+        .await;
+
+    // The server's delay replaces the 5ms backoff, for every worker
+    let model = client(&server).model(Model::Gemini20Flash);
+    let start = Instant::now();
+    let results = model.generate_many(vec!["a", "b", "c"], fast(3)).await;
+    assert!(results.iter().all(|result| result.is_ok()));
+    assert!(start.elapsed() >= Duration::from_millis(300));
+}
+
+#[tokio::test]
+async fn test_stream_yields_in_completion_order() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(body_partial_json(prompt("slow")))
+        .respond_with(text_response("slow").set_delay(Duration::from_millis(200)))
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .respond_with(text_response("fast"))
+        .mount(&server)
+        .await;
+
+    let model = client(&server).model(Model::Gemini20Flash);
+    let order: Vec<usize> = model
+        .generate_many_stream(vec!["slow", "fast1", "fast2"], 3)
+        .map(|(index, result)| {
+            result.unwrap();
+            index
+        })
+        .collect()
+        .await;
+    assert_eq!(order.last(), Some(&0));
+    assert_eq!(order.len(), 3);
+}
+
+#[derive(Debug, Deserialize, PartialEq)]
+struct Label {
+    label: String,
+}
+
+#[tokio::test]
+async fn test_generate_json_many() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(body_partial_json(json!({
+            "generationConfig": { "responseMimeType": "application/json" }
+        })))
+        .and(body_partial_json(prompt("not json")))
+        .respond_with(text_response("nope"))
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(body_partial_json(json!({
+            "generationConfig": { "responseMimeType": "application/json" }
+        })))
+        .respond_with(text_response("```json\n{\"label\": \"food\"}\n```"))
+        .mount(&server)
+        .await;
+
+    let model = client(&server).model(Model::Gemini20Flash);
+    let results = model
+        .generate_json_many::<Label>(vec!["coffee", "not json"], 2)
+        .await;
+    assert_eq!(
+        results[0].as_ref().unwrap(),
+        &Label {
+            label: "food".to_string()
+        }
+    );
+    assert!(matches!(results[1], Err(Error::GenerationFailed(_))));
+}
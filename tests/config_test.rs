@@ -0,0 +1,209 @@
+//! Environment and file configuration tests
+//! These tests don't require API keys
+
+use gemini_rs::config::GeminiConfig;
+use gemini_rs::{Client, Error, Model};
+use serde_json::json;
+use wiremock::matchers::{body_partial_json, header, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+const ENV_VARS: [&str; 6] = [
+    "GOOGLE_API_KEY",
+    "GEMINI_API_KEY",
+    "GOOGLE_GENAI_USE_VERTEXAI",
+    "GOOGLE_CLOUD_PROJECT",
+    "GOOGLE_CLOUD_LOCATION",
+    "GEMINI_MODEL",
+];
+
+fn config_error(result: Result<Client, Error>) -> String {
+    match result {
+        Err(Error::ConfigError(message)) => message,
+        other => panic!("expected ConfigError, got {:?}", other),
+    }
+}
+
+fn configured_model(config: &GeminiConfig) -> Model {
+    config.model_client().unwrap().start_chat().snapshot().model
+}
+
+// The environment is process-wide, so every from_env case runs in one test.
+#[test]
+fn test_client_from_env() {
+    for name in ENV_VARS {
+        std::env::remove_var(name);
+    }
+
+    let message = config_error(Client::from_env());
+    assert!(message.contains("GOOGLE_API_KEY") && message.contains("GEMINI_API_KEY"));
+
+    // Empty values count as unset
+    std::env::set_var("GOOGLE_API_KEY", "");
+    std::env::set_var("GEMINI_API_KEY", "gemini_key");
+    let client = Client::from_env().unwrap();
+    assert!(!client.backend().is_vertex_ai());
+
+    std::env::set_var("GOOGLE_API_KEY", "google_key");
+    assert!(Client::from_env().is_ok());
+
+    std::env::set_var("GOOGLE_GENAI_USE_VERTEXAI", "true");
+    let message = config_error(Client::from_env());
+    assert!(message.contains("GOOGLE_CLOUD_PROJECT"), "{}", message);
+
+    std::env::set_var("GOOGLE_GENAI_USE_VERTEXAI", "false");
+    assert!(!Client::from_env().unwrap().backend().is_vertex_ai());
+
+    // GEMINI_MODEL picks the model when the configuration names none
+    let config = GeminiConfig::default();
+    assert_eq!(configured_model(&config), Model::default());
+    std::env::set_var("GEMINI_MODEL", "gemini-2.0-flash");
+    assert_eq!(configured_model(&config), Model::Gemini20Flash);
+    let config = GeminiConfig::from_json_str(r#"{ "model": "gemini-1.5-pro" }"#).unwrap();
+    assert_eq!(configured_model(&config), Model::Gemini15Pro);
+    std::env::set_var("GEMINI_MODEL", "gemini-unknown");
+    let message = match GeminiConfig::default().model_client() {
+        Err(Error::ConfigError(message)) => message,
+        other => panic!("expected ConfigError, got {:?}", other),
+    };
+    assert!(message.contains("GEMINI_MODEL"), "{}", message);
+
+    for name in ENV_VARS {
+        std::env::remove_var(name);
+    }
+}
+
+#[test]
+fn test_config_from_json() {
+    let config = GeminiConfig::from_json_str(
+        r#"{
+            "model": "gemini-2.0-flash",
+            "api_key": "test_api_key",
+            "generation_config": { "temperature": 0.2, "max_output_tokens": 256 },
+            "safety_settings": [
+                { "category": "HARM_CATEGORY_HARASSMENT", "threshold": "BLOCK_ONLY_HIGH" }
+            ],
+            "system_instruction": "Be brief.",
+            "client": { "timeout_secs": 30, "api_version": "v1" }
+        }"#,
+    )
+    .unwrap();
+
+    assert_eq!(config.model, Some(Model::Gemini20Flash));
+    let generation = config.generation_config.as_ref().unwrap();
+    assert_eq!(generation.temperature, Some(0.2));
+    assert_eq!(generation.max_output_tokens, Some(256));
+    assert_eq!(config.client.timeout_secs, Some(30));
+
+    assert!(config.model_client().is_ok());
+
+    // Unknown keys and invalid values are reported
+    assert!(matches!(
+        GeminiConfig::from_json_str(r#"{ "temprature": 0.2 }"#),
+        Err(Error::ConfigError(_))
+    ));
+    let config =
+        GeminiConfig::from_json_str(r#"{ "api_key": "k", "client": { "api_version": "v2" } }"#)
+            .unwrap();
+    assert!(config
+        .client()
+        .unwrap_err()
+        .to_string()
+        .contains("api_version"));
+}
+
+#[test]
+fn test_config_api_key_env_names_variable() {
+    std::env::remove_var("GEMINI_RS_TEST_MISSING_KEY");
+    let config =
+        GeminiConfig::from_json_str(r#"{ "api_key_env": "GEMINI_RS_TEST_MISSING_KEY" }"#).unwrap();
+    let message = config_error(config.client());
+    assert!(
+        message.contains("GEMINI_RS_TEST_MISSING_KEY"),
+        "{}",
+        message
+    );
+}
+
+#[cfg(feature = "toml")]
+#[test]
+fn test_config_from_toml_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("gemini.toml");
+    std::fs::write(
+        &path,
+        r#"
+model = "gemini-2.5-flash"
+api_key = "test_api_key"
+system_instruction = "Be brief."
+
+[generation_config]
+temperature = 0.5
+top_k = 40
+
+[client]
+connect_timeout_secs = 5
+"#,
+    )
+    .unwrap();
+
+    let config = GeminiConfig::from_file(&path).unwrap();
+    assert_eq!(config.model, Some(Model::Gemini25Flash));
+    assert_eq!(config.generation_config.unwrap().top_k, Some(40));
+    assert_eq!(config.client.connect_timeout_secs, Some(5));
+}
+
+#[test]
+fn test_config_from_file_rejects_unknown_extension() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("gemini.yaml");
+    std::fs::write(&path, "model: gemini-2.5-flash").unwrap();
+    assert!(matches!(
+        GeminiConfig::from_file(&path),
+        Err(Error::ConfigError(_))
+    ));
+}
+
+#[tokio::test]
+async fn test_config_model_client_sends_defaults() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/v1beta/models/gemini-2.0-flash:generateContent"))
+        .and(header("x-goog-api-key", "test_api_key"))
+        .and(body_partial_json(json!({
+            "generationConfig": { "temperature": 0.0, "maxOutputTokens": 64 },
+            "safetySettings": [
+                { "category": "HARM_CATEGORY_HARASSMENT", "threshold": "BLOCK_ONLY_HIGH" }
+            ],
+            "systemInstruction": { "parts": [{ "text": "Be brief." }] }
+        })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "candidates": [{ "content": { "role": "model", "parts": [{ "text": "ok" }] } }]
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let config = GeminiConfig::from_json_str(
+        &json!({
+            "model": "gemini-2.0-flash",
+            "api_key": "test_api_key",
+            "generation_config": { "temperature": 0.0, "maxOutputTokens": 64 },
+            "safety_settings": [
+                { "category": "HARM_CATEGORY_HARASSMENT", "threshold": "BLOCK_ONLY_HIGH" }
+            ],
+            "system_instruction": "Be brief.",
+            "client": { "base_url": format!("{}/v1beta", server.uri()) }
+        })
+        .to_string(),
+    )
+    .unwrap();
+
+    let response = config
+        .model_client()
+        .unwrap()
+        .generate_content("Hi")
+        .await
+        .unwrap();
+    assert_eq!(response.text(), "ok");
+}
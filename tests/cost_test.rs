@@ -0,0 +1,225 @@
+//! Cost accounting tests
+//! These tests don't require API keys
+
+use gemini_rs::cost::{CostCalculator, CostTracker, ModelPricing, PriceTable, TokenPrices};
+use gemini_rs::types::{GenerateContentResponse, UsageMetadata};
+use gemini_rs::{Client, Model};
+use serde_json::json;
+use wiremock::matchers::method;
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+fn usage(value: serde_json::Value) -> UsageMetadata {
+    serde_json::from_value(value).unwrap()
+}
+
+fn assert_close(actual: f64, expected: f64) {
+    assert!(
+        (actual - expected).abs() < 1e-12,
+        "expected {}, got {}",
+        expected,
+        actual
+    );
+}
+
+#[test]
+fn test_cost_by_token_kind() {
+    let pricing = ModelPricing::new(
+        TokenPrices::new(1.0, 10.0)
+            .cached_input(0.25)
+            .audio_input(3.0)
+            .thinking(20.0),
+    );
+    let cost = pricing.cost(&usage(json!({
+        "promptTokenCount": 1_000_000,
+        "cachedContentTokenCount": 200_000,
+        "candidatesTokenCount": 100_000,
+        "thoughtsTokenCount": 50_000,
+        "promptTokensDetails": [
+            { "modality": "TEXT", "tokenCount": 700_000 },
+            { "modality": "AUDIO", "tokenCount": 300_000 }
+        ],
+        "cacheTokensDetails": [{ "modality": "AUDIO", "tokenCount": 100_000 }]
+    })));
+
+    // 600k uncached text at $1, 200k uncached audio at $3
+    assert_close(cost.input, 0.6 + 0.6);
+    assert_close(cost.cached_input, 0.05);
+    assert_close(cost.output, 1.0);
+    assert_close(cost.thinking, 1.0);
+    assert_close(cost.total(), 3.25);
+}
+
+#[test]
+fn test_long_context_tier() {
+    let pricing =
+        ModelPricing::new(TokenPrices::new(1.0, 10.0)).above(200_000, TokenPrices::new(2.0, 15.0));
+
+    let short = pricing.cost(&usage(
+        json!({ "promptTokenCount": 200_000, "candidatesTokenCount": 1000 }),
+    ));
+    assert_close(short.input, 0.2);
+    assert_close(short.output, 0.01);
+
+    let long = pricing.cost(&usage(
+        json!({ "promptTokenCount": 200_001, "candidatesTokenCount": 1000 }),
+    ));
+    assert_close(long.input, 0.400002);
+    assert_close(long.output, 0.015);
+}
+
+#[test]
+fn test_price_table_lookup() {
+    let table = PriceTable::gemini();
+    assert!(table.get("gemini-2.5-flash").is_some());
+    assert!(table.get("models/gemini-2.5-flash").is_some());
+    assert_eq!(
+        table.get("gemini-2.5-flash-lite-preview-06-17"),
+        table.get("gemini-2.5-flash-lite")
+    );
+    assert_ne!(
+        table.get("gemini-2.5-flash-lite"),
+        table.get("gemini-2.5-flash")
+    );
+    assert!(table.get("unknown-model").is_none());
+
+    let custom = PriceTable::new().with("my-model", ModelPricing::new(TokenPrices::new(1.0, 2.0)));
+    let calculator = CostCalculator::new(custom);
+    let cost = calculator
+        .cost("my-model", &usage(json!({ "promptTokenCount": 1_000_000 })))
+        .unwrap();
+    assert_close(cost.total(), 1.0);
+    assert!(calculator
+        .cost("gemini-2.5-flash", &UsageMetadata::default())
+        .is_none());
+}
+
+#[test]
+fn test_response_estimated_cost() {
+    let response: GenerateContentResponse = serde_json::from_value(json!({
+        "candidates": [],
+        "usageMetadata": { "promptTokenCount": 1_000_000, "candidatesTokenCount": 1_000_000 },
+        "modelVersion": "gemini-2.0-flash-001"
+    }))
+    .unwrap();
+    assert_close(response.estimated_cost().unwrap().total(), 0.5);
+
+    let no_usage: GenerateContentResponse =
+        serde_json::from_value(json!({ "modelVersion": "gemini-2.0-flash" })).unwrap();
+    assert!(no_usage.estimated_cost().is_none());
+}
+
+#[tokio::test]
+async fn test_tracker_aggregates_by_model_and_label() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "candidates": [{ "content": { "role": "model", "parts": [{ "text": "ok" }] } }],
+            "usageMetadata": { "promptTokenCount": 1000, "candidatesTokenCount": 1000, "totalTokenCount": 2000 }
+        })))
+        .mount(&server)
+        .await;
+
+    let tracker = CostTracker::with_calculator(CostCalculator::new(PriceTable::new().with(
+        "gemini-2.0-flash",
+        ModelPricing::new(TokenPrices::new(1.0, 2.0)),
+    )));
+    let client = Client::builder()
+        .api_key("test_api_key")
+        .base_url(format!("{}/v1beta", server.uri()))
+        .build()
+        .unwrap()
+        .with_metrics(tracker.clone());
+
+    let search = client
+        .clone()
+        .with_metric_label("feature", "search")
+        .model(Model::Gemini20Flash);
+    let mut chat = client
+        .clone()
+        .with_metric_label("feature", "chat")
+        .model(Model::Gemini20Flash)
+        .start_chat();
+
+    search.generate_content("a").await.unwrap();
+    chat.send_message("b").await.unwrap();
+    chat.send_message("c").await.unwrap();
+    client
+        .model(Model::Gemini15Pro)
+        .generate_content("d")
+        .await
+        .unwrap();
+
+    assert_close(tracker.total().total(), 0.009);
+    assert_eq!(tracker.unpriced_calls(), 1);
+    assert_close(tracker.by_model()["gemini-2.0-flash"].total(), 0.009);
+    let by_feature = tracker.by_label("feature");
+    assert_close(by_feature["search"].total(), 0.003);
+    assert_close(by_feature["chat"].total(), 0.006);
+
+    tracker.reset();
+    assert_eq!(tracker.total().total(), 0.0);
+}
+
+#[tokio::test]
+async fn test_estimated_cost_falls_back_to_the_model_that_answered() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "candidates": [{ "content": { "role": "model", "parts": [{ "text": "ok" }] } }],
+            "usageMetadata": { "promptTokenCount": 1_000_000, "candidatesTokenCount": 1_000_000 }
+        })))
+        .mount(&server)
+        .await;
+
+    let client = Client::builder()
+        .api_key("test_api_key")
+        .base_url(format!("{}/v1beta", server.uri()))
+        .build()
+        .unwrap();
+    let response = client
+        .model(Model::Gemini20Flash)
+        .generate_content("a")
+        .await
+        .unwrap();
+
+    assert!(response.model_version.is_none());
+    assert_close(response.estimated_cost().unwrap().total(), 0.5);
+}
+
+#[tokio::test]
+async fn test_chat_session_tracks_only_its_own_calls() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "candidates": [{ "content": { "role": "model", "parts": [{ "text": "ok" }] } }],
+            "usageMetadata": { "promptTokenCount": 1000, "candidatesTokenCount": 1000, "totalTokenCount": 2000 }
+        })))
+        .mount(&server)
+        .await;
+
+    let calculator = CostCalculator::new(PriceTable::new().with(
+        "gemini-2.0-flash",
+        ModelPricing::new(TokenPrices::new(1.0, 2.0)),
+    ));
+    let client_tracker = CostTracker::with_calculator(calculator.clone());
+    let model = Client::builder()
+        .api_key("test_api_key")
+        .base_url(format!("{}/v1beta", server.uri()))
+        .build()
+        .unwrap()
+        .with_metrics(client_tracker.clone())
+        .model(Model::Gemini20Flash);
+    let mut chat = model
+        .start_chat()
+        .with_cost_tracker(CostTracker::with_calculator(calculator));
+    let mut other = model.start_chat();
+
+    chat.send_message("a").await.unwrap();
+    chat.send_message("b").await.unwrap();
+    other.send_message("c").await.unwrap();
+    model.generate_content("d").await.unwrap();
+
+    assert_close(chat.cost_tracker().unwrap().total().total(), 0.006);
+    assert!(other.cost_tracker().is_none());
+    assert_close(client_tracker.total().total(), 0.012);
+}
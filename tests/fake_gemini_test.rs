@@ -0,0 +1,292 @@
+#![cfg(feature = "testing")]
+//! FakeGemini tests
+//! These tests don't require API keys
+
+use futures::StreamExt;
+use gemini_rs::batch::BatchOutput;
+use gemini_rs::clock::{Clock, ManualClock};
+use gemini_rs::testing::{FakeGemini, Reply, Route};
+use gemini_rs::transport::{HttpRequest, Transport};
+use gemini_rs::{Content, Error, Model};
+use reqwest::Method;
+use serde_json::{json, Value};
+use std::time::Duration;
+
+const BASE: &str = "https://generativelanguage.googleapis.com";
+
+#[tokio::test]
+async fn test_queued_and_default_replies() {
+    let fake = FakeGemini::new();
+    fake.enqueue(Reply::text("first"));
+    fake.enqueue(Reply::text("second").with_finish_reason("MAX_TOKENS"));
+    let model = fake.client().model(Model::Gemini25Flash);
+
+    let first = model.generate_content("Hello there").await.unwrap();
+    assert_eq!(first.text(), "first");
+    let usage = first.usage_metadata.unwrap();
+    assert_eq!(usage.prompt_token_count, 3);
+    assert_eq!(usage.candidates_token_count, 2);
+    assert_eq!(first.model_version.as_deref(), Some("gemini-2.5-flash"));
+
+    let second = model.generate_content("Again").await.unwrap();
+    assert_eq!(
+        second.candidates.unwrap()[0].finish_reason.as_deref(),
+        Some("MAX_TOKENS")
+    );
+    assert_eq!(model.generate_content("More").await.unwrap().text(), "OK");
+
+    fake.set_default(Reply::text("default"));
+    assert_eq!(
+        model.generate_content("More").await.unwrap().text(),
+        "default"
+    );
+
+    let requests = fake.requests();
+    assert_eq!(requests.len(), 4);
+    assert_eq!(requests[0].route, Route::GenerateContent);
+    assert_eq!(requests[0].model.as_deref(), Some("gemini-2.5-flash"));
+    assert_eq!(requests[0].prompt(), "Hello there");
+    assert_eq!(
+        requests[0].headers["x-goog-api-key"].to_str().unwrap(),
+        "fake-api-key"
+    );
+    fake.clear_requests();
+    assert!(fake.requests().is_empty());
+}
+
+#[tokio::test]
+async fn test_streaming_and_count_tokens() {
+    let fake = FakeGemini::new();
+    fake.enqueue(Reply::chunks(["Hel", "lo", "!"]));
+    let model = fake.client().model(Model::Gemini20Flash);
+
+    let mut stream = model.stream_generate_content("Hi").await.unwrap();
+    let mut chunks = Vec::new();
+    while let Some(chunk) = stream.next().await {
+        chunks.push(chunk.unwrap());
+    }
+    let texts: Vec<String> = chunks.iter().map(|chunk| chunk.text()).collect();
+    assert_eq!(texts, vec!["Hel", "lo", "!"]);
+    assert!(chunks[0].usage_metadata.is_none());
+    let last = chunks[2].candidates.as_ref().unwrap();
+    assert_eq!(last[0].finish_reason.as_deref(), Some("STOP"));
+
+    let count = model
+        .count_tokens(vec![Content::text("12345678")])
+        .await
+        .unwrap();
+    assert_eq!(count.total_tokens, 2);
+
+    fake.enqueue_for(Route::CountTokens, Reply::server_error());
+    assert!(model.count_tokens(vec![Content::text("x")]).await.is_err());
+    assert_eq!(fake.requests_to(Route::StreamGenerateContent).len(), 1);
+    assert_eq!(fake.requests_to(Route::CountTokens).len(), 2);
+}
+
+#[tokio::test]
+async fn test_injected_errors_and_safety_blocks() {
+    let fake = FakeGemini::new();
+    let model = fake.client().model(Model::Gemini25Flash);
+
+    fake.enqueue(Reply::rate_limited(Duration::from_millis(1500)));
+    let err = model.generate_content("Hi").await.unwrap_err();
+    assert!(matches!(err, Error::RateLimitExceeded { .. }));
+    // Both the header and the RetryInfo detail round up to whole seconds
+    assert_eq!(err.retry_after(), Some(Duration::from_secs(2)));
+
+    fake.enqueue(Reply::rate_limited(Duration::from_secs(30)));
+    let raw = fake
+        .send(
+            HttpRequest::post_json(
+                format!("{}/v1beta/models/gemini-2.5-flash:generateContent", BASE),
+                &json!({ "contents": [] }),
+            )
+            .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(raw.status, 429);
+    assert_eq!(raw.headers["retry-after"], "30");
+    let body: Value = serde_json::from_slice(&raw.body).unwrap();
+    assert_eq!(body["error"]["status"], "RESOURCE_EXHAUSTED");
+    assert_eq!(body["error"]["details"][0]["retryDelay"], "30s");
+
+    fake.enqueue(Reply::server_error());
+    match model.generate_content("Hi").await {
+        Err(
+            err @ Error::ApiError {
+                code: Some(500), ..
+            },
+        ) => assert!(err.is_retryable()),
+        other => panic!("expected HTTP 500, got {:?}", other),
+    }
+
+    // A blocked prompt has no candidates
+    fake.enqueue(Reply::prompt_blocked());
+    assert!(matches!(
+        model.generate_content("Hi").await,
+        Err(Error::NoResponse)
+    ));
+    fake.enqueue(Reply::prompt_blocked());
+    let mut stream = model.stream_generate_content("Hi").await.unwrap();
+    let blocked = stream.next().await.unwrap().unwrap();
+    assert_eq!(
+        blocked.prompt_feedback.unwrap().block_reason.as_deref(),
+        Some("SAFETY")
+    );
+
+    fake.enqueue(Reply::safety_stop());
+    let stopped = model.generate_content("Hi").await.unwrap();
+    assert_eq!(stopped.text(), "");
+    assert_eq!(
+        stopped.candidates.unwrap()[0].finish_reason.as_deref(),
+        Some("SAFETY")
+    );
+
+    // Errors are not streamed
+    fake.enqueue(Reply::error(400, "bad request"));
+    assert!(matches!(
+        model.stream_generate_content("Hi").await,
+        Err(Error::ApiError {
+            code: Some(400),
+            ..
+        })
+    ));
+}
+
+#[tokio::test]
+async fn test_latency() {
+    let clock = ManualClock::new();
+    let start = clock.now();
+    let fake = FakeGemini::new().with_clock(clock.clone());
+    fake.set_latency(Duration::from_millis(200));
+    fake.enqueue(Reply::text("slow").with_latency(Duration::from_secs(2)));
+    let model = fake.client().model(Model::Gemini25Flash);
+
+    model.generate_content("Hi").await.unwrap();
+    assert_eq!(clock.now() - start, Duration::from_millis(2200));
+    model.generate_content("Hi").await.unwrap();
+    assert_eq!(clock.now() - start, Duration::from_millis(2400));
+}
+
+#[tokio::test]
+async fn test_closure_responses() {
+    let fake = FakeGemini::new();
+    let model = fake.client().model(Model::Gemini25Flash);
+    fake.respond_with(|request| {
+        let prompt = request.prompt();
+        if prompt.starts_with("echo ") {
+            Some(Reply::text(
+                prompt.trim_start_matches("echo ").to_uppercase(),
+            ))
+        } else {
+            None
+        }
+    });
+    fake.enqueue(Reply::text("queued"));
+
+    assert_eq!(
+        model.generate_content("echo hi").await.unwrap().text(),
+        "HI"
+    );
+    assert_eq!(
+        model.generate_content("other").await.unwrap().text(),
+        "queued"
+    );
+}
+
+#[tokio::test]
+async fn test_embeddings_are_deterministic_unit_vectors() {
+    let fake = FakeGemini::new();
+    let embed = |text: &str, dimensions: Option<u64>| {
+        let mut body = json!({ "content": { "parts": [{ "text": text }] } });
+        if let Some(dimensions) = dimensions {
+            body["outputDimensionality"] = dimensions.into();
+        }
+        HttpRequest::post_json(
+            format!("{}/v1beta/models/text-embedding-004:embedContent", BASE),
+            &body,
+        )
+        .unwrap()
+    };
+    let values = |body: &[u8]| -> Vec<f64> {
+        let body: Value = serde_json::from_slice(body).unwrap();
+        serde_json::from_value(body["embedding"]["values"].clone()).unwrap()
+    };
+
+    let a = values(&fake.send(embed("cat", None)).await.unwrap().body);
+    let again = values(&fake.send(embed("cat", None)).await.unwrap().body);
+    let b = values(&fake.send(embed("dog", Some(16))).await.unwrap().body);
+    assert_eq!(a.len(), 768);
+    assert_eq!(a, again);
+    assert_eq!(b.len(), 16);
+    let norm: f64 = b.iter().map(|value| value * value).sum::<f64>().sqrt();
+    assert!((norm - 1.0).abs() < 1e-9);
+
+    let batch = HttpRequest::post_json(
+        format!(
+            "{}/v1beta/models/text-embedding-004:batchEmbedContents",
+            BASE
+        ),
+        &json!({ "requests": [
+            { "content": { "parts": [{ "text": "cat" }] } },
+            { "content": { "parts": [{ "text": "dog" }] }, "outputDimensionality": 16 }
+        ] }),
+    )
+    .unwrap();
+    let body: Value = serde_json::from_slice(&fake.send(batch).await.unwrap().body).unwrap();
+    let first: Vec<f64> = serde_json::from_value(body["embeddings"][0]["values"].clone()).unwrap();
+    assert_eq!(first, a);
+    assert_eq!(fake.requests_to(Route::BatchEmbedContents).len(), 1);
+}
+
+#[tokio::test]
+async fn test_files_endpoints() {
+    let fake = FakeGemini::new();
+    let client = fake.client();
+    let batches = client.batches();
+
+    // Resumable upload through the batch client
+    let model = client.model(Model::Gemini25Flash);
+    let request =
+        gemini_rs::batch::BatchRequest::new("a", model.build_request(vec![Content::text("one")]));
+    let name = batches.upload_jsonl("input", &[request]).await.unwrap();
+    let uploaded = String::from_utf8(fake.file_data(&name).unwrap().to_vec()).unwrap();
+    assert!(uploaded.contains("\"key\":\"a\""));
+
+    let get = |path: &str| HttpRequest::new(Method::GET, format!("{}{}", BASE, path));
+    let file: Value = serde_json::from_slice(
+        &fake
+            .send(get(&format!("/v1beta/{}", name)))
+            .await
+            .unwrap()
+            .body,
+    )
+    .unwrap();
+    assert_eq!(file["displayName"], "input");
+    assert_eq!(file["mimeType"], "application/jsonl");
+    assert_eq!(file["sizeBytes"], uploaded.len().to_string());
+
+    // Seeded files can be downloaded, e.g. batch results
+    let lines = format!(
+        "{}\n",
+        json!({ "key": "a", "response": { "candidates": [{ "content": { "role": "model", "parts": [{ "text": "hi" }] } }] } })
+    );
+    let results = fake.add_file("results", "application/jsonl", lines);
+    let output: BatchOutput = serde_json::from_value(json!({ "responsesFile": results })).unwrap();
+    let results = batches.output_results(&output).await.unwrap();
+    assert_eq!(results["a"].as_ref().unwrap().text(), "hi");
+
+    let list: Value =
+        serde_json::from_slice(&fake.send(get("/v1beta/files")).await.unwrap().body).unwrap();
+    assert_eq!(list["files"].as_array().unwrap().len(), 2);
+
+    let delete = HttpRequest::new(Method::DELETE, format!("{}/v1beta/{}", BASE, name));
+    assert_eq!(fake.send(delete.clone()).await.unwrap().status, 200);
+    assert_eq!(fake.send(delete).await.unwrap().status, 404);
+    assert!(fake.file_data(&name).is_none());
+
+    let unknown = fake.send(get("/v1beta/tunedModels")).await.unwrap();
+    assert_eq!(unknown.status, 404);
+    assert_eq!(fake.requests_to(Route::Other).len(), 1);
+}
@@ -0,0 +1,404 @@
+//! Model fallback tests against a local mock server
+//! These tests don't require API keys
+
+use futures::StreamExt;
+use gemini_rs::fallback::FallbackPolicy;
+use gemini_rs::rate_limit::{Quota, RateLimiter};
+use gemini_rs::{Client, Error, Model};
+use serde_json::{json, Value};
+use std::time::{Duration, Instant};
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+const PRIMARY: &str = "/v1beta/models/gemini-2.5-flash:generateContent";
+const FALLBACK: &str = "/v1beta/models/gemini-2.0-flash:generateContent";
+
+fn client(server: &MockServer) -> Client {
+    Client::builder()
+        .api_key("test_api_key")
+        .base_url(format!("{}/v1beta", server.uri()))
+        .build()
+        .unwrap()
+}
+
+fn text_response(text: &str, finish_reason: &str) -> Value {
+    json!({ "candidates": [{
+        "content": { "role": "model", "parts": [{ "text": text }] },
+        "finishReason": finish_reason
+    }] })
+}
+
+async fn mount(server: &MockServer, endpoint: &str, response: ResponseTemplate) {
+    Mock::given(method("POST"))
+        .and(path(endpoint))
+        .respond_with(response)
+        .mount(server)
+        .await;
+}
+
+async fn bodies(server: &MockServer, endpoint: &str) -> Vec<Value> {
+    server
+        .received_requests()
+        .await
+        .unwrap()
+        .into_iter()
+        .filter(|request| request.url.path() == endpoint)
+        .map(|request| serde_json::from_slice(&request.body).unwrap())
+        .collect()
+}
+
+fn overloaded() -> ResponseTemplate {
+    ResponseTemplate::new(503).set_body_json(json!({
+        "error": { "code": 503, "message": "The model is overloaded.", "status": "UNAVAILABLE" }
+    }))
+}
+
+#[tokio::test]
+async fn test_overloaded_primary_falls_back() {
+    let server = MockServer::start().await;
+    mount(&server, PRIMARY, overloaded()).await;
+    mount(
+        &server,
+        FALLBACK,
+        ResponseTemplate::new(200).set_body_json(text_response("from 2.0", "STOP")),
+    )
+    .await;
+    let model = client(&server)
+        .model(Model::Gemini25Flash)
+        .with_system_instruction("Be brief")
+        .with_fallbacks([Model::Gemini20Flash]);
+
+    let response = model.generate_content("Hi").await.unwrap();
+    assert_eq!(response.text(), "from 2.0");
+    assert_eq!(response.answered_by, Some(Model::Gemini20Flash));
+
+    // Both models got the same request
+    let primary = bodies(&server, PRIMARY).await;
+    let fallback = bodies(&server, FALLBACK).await;
+    assert_eq!(primary.len(), 1);
+    assert_eq!(primary, fallback);
+    assert_eq!(
+        fallback[0]["systemInstruction"]["parts"][0]["text"],
+        "Be brief"
+    );
+
+    // Without fallbacks the primary reports itself
+    let server = MockServer::start().await;
+    mount(
+        &server,
+        PRIMARY,
+        ResponseTemplate::new(200).set_body_json(text_response("hi", "STOP")),
+    )
+    .await;
+    let response = client(&server)
+        .model(Model::Gemini25Flash)
+        .generate_content("Hi")
+        .await
+        .unwrap();
+    assert_eq!(response.answered_by, Some(Model::Gemini25Flash));
+}
+
+#[tokio::test]
+async fn test_error_triggers() {
+    let server = MockServer::start().await;
+    mount(
+        &server,
+        PRIMARY,
+        ResponseTemplate::new(400).set_body_json(json!({
+            "error": { "code": 400, "message": "bad request", "status": "INVALID_ARGUMENT" }
+        })),
+    )
+    .await;
+    mount(
+        &server,
+        FALLBACK,
+        ResponseTemplate::new(200).set_body_json(text_response("fallback", "STOP")),
+    )
+    .await;
+    let model = client(&server)
+        .model(Model::Gemini25Flash)
+        .with_fallbacks([Model::Gemini20Flash]);
+
+    // Non-retryable errors are returned by default
+    assert!(matches!(
+        model.generate_content("Hi").await,
+        Err(Error::ApiError {
+            code: Some(400),
+            ..
+        })
+    ));
+    assert!(bodies(&server, FALLBACK).await.is_empty());
+
+    let model = model.with_fallback_policy(FallbackPolicy::new().on_error(|err| {
+        matches!(
+            err,
+            Error::ApiError {
+                code: Some(400),
+                ..
+            }
+        )
+    }));
+    let response = model.generate_content("Hi").await.unwrap();
+    assert_eq!(response.answered_by, Some(Model::Gemini20Flash));
+}
+
+#[tokio::test]
+async fn test_last_error_is_returned() {
+    let server = MockServer::start().await;
+    mount(&server, PRIMARY, overloaded()).await;
+    mount(
+        &server,
+        FALLBACK,
+        ResponseTemplate::new(500).set_body_json(json!({
+            "error": { "code": 500, "message": "internal", "status": "INTERNAL" }
+        })),
+    )
+    .await;
+    let model = client(&server)
+        .model(Model::Gemini25Flash)
+        .with_fallbacks([Model::Gemini20Flash]);
+
+    assert!(matches!(
+        model.generate_content("Hi").await,
+        Err(Error::ApiError {
+            code: Some(500),
+            ..
+        })
+    ));
+}
+
+#[tokio::test]
+async fn test_exhausted_quota_falls_back() {
+    let server = MockServer::start().await;
+    mount(
+        &server,
+        PRIMARY,
+        ResponseTemplate::new(200).set_body_json(text_response("primary", "STOP")),
+    )
+    .await;
+    mount(
+        &server,
+        FALLBACK,
+        ResponseTemplate::new(200).set_body_json(text_response("fallback", "STOP")),
+    )
+    .await;
+    let limiter = RateLimiter::new()
+        .limit(Model::Gemini25Flash, Quota::requests_per_minute(1))
+        .fail_fast(true);
+    let model = client(&server)
+        .with_rate_limiter(limiter)
+        .model(Model::Gemini25Flash)
+        .with_fallbacks([Model::Gemini20Flash]);
+
+    let first = model.generate_content("Hi").await.unwrap();
+    assert_eq!(first.answered_by, Some(Model::Gemini25Flash));
+    let second = model.generate_content("Hi").await.unwrap();
+    assert_eq!(second.answered_by, Some(Model::Gemini20Flash));
+    assert_eq!(bodies(&server, PRIMARY).await.len(), 1);
+}
+
+#[tokio::test]
+async fn test_finish_reason_triggers() {
+    let server = MockServer::start().await;
+    mount(
+        &server,
+        PRIMARY,
+        ResponseTemplate::new(200).set_body_json(text_response("", "SAFETY")),
+    )
+    .await;
+    mount(
+        &server,
+        FALLBACK,
+        ResponseTemplate::new(200).set_body_json(text_response("cut", "MAX_TOKENS")),
+    )
+    .await;
+    let model = client(&server)
+        .model(Model::Gemini25Flash)
+        .with_fallbacks([Model::Gemini20Flash, Model::Gemini15Flash]);
+
+    // Finish reasons are not checked by default
+    let response = model.generate_content("Hi").await.unwrap();
+    assert_eq!(response.answered_by, Some(Model::Gemini25Flash));
+
+    let model = model.with_fallback_policy(FallbackPolicy::new().on_blocked());
+    let response = model.generate_content("Hi").await.unwrap();
+    assert_eq!(response.text(), "cut");
+    assert_eq!(response.answered_by, Some(Model::Gemini20Flash));
+
+    mount(
+        &server,
+        "/v1beta/models/gemini-1.5-flash:generateContent",
+        ResponseTemplate::new(200).set_body_json(text_response("complete", "STOP")),
+    )
+    .await;
+    let model = model.with_fallback_policy(
+        FallbackPolicy::new()
+            .on_blocked()
+            .on_finish_reason("MAX_TOKENS"),
+    );
+    let response = model.generate_content("Hi").await.unwrap();
+    assert_eq!(response.text(), "complete");
+    assert_eq!(response.answered_by, Some(Model::Gemini15Flash));
+}
+
+#[tokio::test]
+async fn test_blocked_prompt_falls_back() {
+    let server = MockServer::start().await;
+    mount(
+        &server,
+        PRIMARY,
+        ResponseTemplate::new(200)
+            .set_body_json(json!({ "promptFeedback": { "blockReason": "SAFETY" } })),
+    )
+    .await;
+    mount(
+        &server,
+        FALLBACK,
+        ResponseTemplate::new(200).set_body_json(text_response("answered", "STOP")),
+    )
+    .await;
+    let model = client(&server)
+        .model(Model::Gemini25Flash)
+        .with_fallbacks([Model::Gemini20Flash]);
+
+    assert!(matches!(
+        model.generate_content("Hi").await,
+        Err(Error::NoResponse)
+    ));
+    let model = model.with_fallback_policy(FallbackPolicy::new().on_blocked());
+    let response = model.generate_content("Hi").await.unwrap();
+    assert_eq!(response.answered_by, Some(Model::Gemini20Flash));
+}
+
+#[tokio::test]
+async fn test_slow_model_times_out() {
+    let server = MockServer::start().await;
+    mount(
+        &server,
+        PRIMARY,
+        ResponseTemplate::new(200)
+            .set_body_json(text_response("slow", "STOP"))
+            .set_delay(Duration::from_secs(5)),
+    )
+    .await;
+    mount(
+        &server,
+        FALLBACK,
+        ResponseTemplate::new(200)
+            .set_body_json(text_response("fast", "STOP"))
+            .set_delay(Duration::from_millis(200)),
+    )
+    .await;
+    let model = client(&server)
+        .model(Model::Gemini25Flash)
+        .with_fallbacks([Model::Gemini20Flash])
+        .with_fallback_policy(FallbackPolicy::new().timeout(Duration::from_millis(100)));
+
+    // The last model is not timed out
+    let start = Instant::now();
+    let response = model.generate_content("Hi").await.unwrap();
+    assert_eq!(response.text(), "fast");
+    assert_eq!(response.answered_by, Some(Model::Gemini20Flash));
+    assert!(start.elapsed() < Duration::from_secs(2));
+
+    let model = model.with_fallback_policy(
+        FallbackPolicy::new()
+            .timeout(Duration::from_millis(100))
+            .on_error(|_| false),
+    );
+    match model.generate_content("Hi").await {
+        Err(err @ Error::Timeout(_)) => assert!(err.is_retryable()),
+        other => panic!("expected a timeout, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn test_chat_carries_history_across_fallbacks() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path(PRIMARY))
+        .respond_with(overloaded())
+        .up_to_n_times(1)
+        .mount(&server)
+        .await;
+    mount(
+        &server,
+        PRIMARY,
+        ResponseTemplate::new(200).set_body_json(text_response("from 2.5", "STOP")),
+    )
+    .await;
+    mount(
+        &server,
+        FALLBACK,
+        ResponseTemplate::new(200).set_body_json(text_response("from 2.0", "STOP")),
+    )
+    .await;
+    let model = client(&server)
+        .model(Model::Gemini25Flash)
+        .with_system_instruction("Be brief")
+        .with_fallbacks([Model::Gemini20Flash]);
+    let mut chat = model.start_chat();
+
+    let first = chat.send_message("One").await.unwrap();
+    assert_eq!(first.answered_by, Some(Model::Gemini20Flash));
+    assert_eq!(chat.history().len(), 2);
+
+    // The next message goes to the primary again, with the fallback's reply
+    // in its history
+    let second = chat.send_message("Two").await.unwrap();
+    assert_eq!(second.answered_by, Some(Model::Gemini25Flash));
+    let primary = bodies(&server, PRIMARY).await;
+    let last = &primary[1];
+    assert_eq!(last["contents"].as_array().unwrap().len(), 3);
+    assert_eq!(last["contents"][1]["parts"][0]["text"], "from 2.0");
+    assert_eq!(last["systemInstruction"]["parts"][0]["text"], "Be brief");
+
+    // The snapshot names the primary; resuming keeps the fallbacks
+    let snapshot = chat.snapshot();
+    assert_eq!(snapshot.model, Model::Gemini25Flash);
+    server.reset().await;
+    mount(&server, PRIMARY, overloaded()).await;
+    mount(
+        &server,
+        FALLBACK,
+        ResponseTemplate::new(200).set_body_json(text_response("resumed", "STOP")),
+    )
+    .await;
+    let mut resumed = model.resume_chat(snapshot);
+    let third = resumed.send_message("Three").await.unwrap();
+    assert_eq!(third.answered_by, Some(Model::Gemini20Flash));
+    assert_eq!(resumed.history().len(), 6);
+}
+
+#[tokio::test]
+async fn test_stream_falls_back() {
+    let server = MockServer::start().await;
+    mount(
+        &server,
+        "/v1beta/models/gemini-2.5-flash:streamGenerateContent",
+        overloaded(),
+    )
+    .await;
+    mount(
+        &server,
+        "/v1beta/models/gemini-2.0-flash:streamGenerateContent",
+        ResponseTemplate::new(200).set_body_string(format!(
+            "data: {}\r\n\r\ndata: {}\r\n\r\n",
+            text_response("Hel", "STOP"),
+            text_response("lo", "STOP")
+        )),
+    )
+    .await;
+    let model = client(&server)
+        .model(Model::Gemini25Flash)
+        .with_fallbacks([Model::Gemini20Flash]);
+
+    let mut stream = model.stream_generate_content("Hi").await.unwrap();
+    let mut texts = Vec::new();
+    while let Some(chunk) = stream.next().await {
+        let chunk = chunk.unwrap();
+        assert_eq!(chunk.answered_by, Some(Model::Gemini20Flash));
+        texts.push(chunk.text());
+    }
+    assert_eq!(texts, vec!["Hel", "lo"]);
+}
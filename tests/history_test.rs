@@ -0,0 +1,232 @@
+//! History strategy tests
+//! These tests don't require API keys
+
+use gemini_rs::history::{
+    HistoryStrategy, LastTurns, PinFirst, Summarize, TokenBudget, SUMMARY_PREFIX,
+};
+use gemini_rs::{Client, Content, Conversation, Model, Role};
+use serde_json::{json, Value};
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+fn conversation(exchanges: usize) -> Vec<Content> {
+    let mut turns = Vec::new();
+    for i in 0..exchanges {
+        turns.push(Content::user(format!("question {}", i)));
+        turns.push(Content::model(format!("answer {}", i)));
+    }
+    turns.push(Content::user("latest question"));
+    turns
+}
+
+fn text(content: &Content) -> String {
+    serde_json::to_value(&content.parts[0]).unwrap()["text"]
+        .as_str()
+        .unwrap()
+        .to_string()
+}
+
+#[tokio::test]
+async fn test_last_turns_starts_with_user() {
+    let model = Client::new("test_api_key").model(Default::default());
+
+    // 4 turns would start on a model turn, so the window shrinks to 3
+    let turns = LastTurns::new(4)
+        .compact(conversation(5), &model)
+        .await
+        .unwrap();
+    assert_eq!(turns.len(), 3);
+    assert_eq!(turns[0].role, Some(Role::User));
+    assert_eq!(text(turns.last().unwrap()), "latest question");
+    assert!(Conversation::new(&turns).validate().is_ok());
+
+    // The message being sent is always kept
+    let turns = LastTurns::new(0)
+        .compact(conversation(2), &model)
+        .await
+        .unwrap();
+    assert_eq!(turns.len(), 1);
+}
+
+#[tokio::test]
+async fn test_token_budget_drops_oldest_turns() {
+    let model = Client::new("test_api_key").model(Default::default());
+    let turns = conversation(10);
+    let total: u64 = turns.iter().map(Content::estimate_tokens).sum();
+
+    let kept = TokenBudget::estimated(total)
+        .compact(turns.clone(), &model)
+        .await
+        .unwrap();
+    assert_eq!(kept.len(), turns.len());
+
+    let kept = TokenBudget::estimated(total / 2)
+        .compact(turns, &model)
+        .await
+        .unwrap();
+    assert!(kept.len() < 21);
+    assert!(kept.iter().map(Content::estimate_tokens).sum::<u64>() <= total / 2);
+    assert!(Conversation::new(&kept).validate().is_ok());
+}
+
+#[tokio::test]
+async fn test_pin_first_keeps_opening_exchange() {
+    let model = Client::new("test_api_key").model(Default::default());
+
+    let kept = PinFirst::new(2, LastTurns::new(3))
+        .compact(conversation(6), &model)
+        .await
+        .unwrap();
+    assert_eq!(kept.len(), 5);
+    assert_eq!(text(&kept[0]), "question 0");
+    assert_eq!(text(&kept[1]), "answer 0");
+    assert_eq!(text(&kept[2]), "question 5");
+    assert!(Conversation::new(&kept).validate().is_ok());
+}
+
+async fn request_bodies(server: &MockServer) -> Vec<Value> {
+    server
+        .received_requests()
+        .await
+        .unwrap()
+        .iter()
+        .map(|request| request.body_json().unwrap())
+        .collect()
+}
+
+#[tokio::test]
+async fn test_token_budget_counted_scales_estimates() {
+    let server = MockServer::start().await;
+    let client = Client::builder()
+        .api_key("test_api_key")
+        .base_url(server.uri())
+        .build()
+        .unwrap();
+    let model = client.model(Model::Gemini25Flash);
+    let turns = conversation(3);
+    let estimates: Vec<u64> = turns.iter().map(Content::estimate_tokens).collect();
+    let estimated: u64 = estimates.iter().sum();
+    let last_exchange: u64 = estimates[4..].iter().sum();
+
+    // The real count is ten times the estimate
+    Mock::given(method("POST"))
+        .and(path("/models/gemini-2.5-flash:countTokens"))
+        .respond_with(
+            ResponseTemplate::new(200).set_body_json(json!({ "totalTokens": estimated * 10 })),
+        )
+        .mount(&server)
+        .await;
+
+    // Within budget: nothing is dropped
+    let kept = TokenBudget::counted(estimated * 10)
+        .compact(turns.clone(), &model)
+        .await
+        .unwrap();
+    assert_eq!(kept.len(), 7);
+    let bodies = request_bodies(&server).await;
+    assert_eq!(bodies.len(), 1);
+    assert_eq!(
+        bodies[0],
+        json!({ "generateContentRequest": {
+            "model": "models/gemini-2.5-flash",
+            "contents": serde_json::to_value(&turns).unwrap(),
+        } })
+    );
+
+    // Over budget: the estimates are scaled to the count, so only the last
+    // exchange fits, where the raw estimates would have kept everything
+    assert!(estimated <= last_exchange * 10);
+    let kept = TokenBudget::counted(last_exchange * 10)
+        .compact(turns, &model)
+        .await
+        .unwrap();
+    let kept: Vec<String> = kept.iter().map(text).collect();
+    assert_eq!(kept, vec!["question 2", "answer 2", "latest question"]);
+    assert_eq!(request_bodies(&server).await.len(), 2);
+}
+
+#[tokio::test]
+async fn test_summarize_replaces_older_turns() {
+    let server = MockServer::start().await;
+    let client = Client::builder()
+        .api_key("test_api_key")
+        .base_url(server.uri())
+        .build()
+        .unwrap();
+    let model = client.model(Model::Gemini25Flash);
+    let summarizer = client.model(Model::Gemini15Flash8B);
+    Mock::given(method("POST"))
+        .and(path("/models/gemini-1.5-flash-8b:generateContent"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "candidates": [{ "content": { "role": "model", "parts": [{ "text": " first summary \n" }] } }]
+        })))
+        .up_to_n_times(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/models/gemini-1.5-flash-8b:generateContent"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "candidates": [{ "content": { "role": "model", "parts": [{ "text": "second summary" }] } }]
+        })))
+        .mount(&server)
+        .await;
+    let strategy = Summarize::new(summarizer, 4, 3).with_prompt("Summarize this.");
+
+    // Within max_turns: no summary is requested
+    let kept = strategy.compact(conversation(1), &model).await.unwrap();
+    assert_eq!(kept.len(), 3);
+    assert!(request_bodies(&server).await.is_empty());
+
+    // The split falls on the user turn opening the last 3 turns
+    let turns = strategy.compact(conversation(3), &model).await.unwrap();
+    let texts: Vec<String> = turns.iter().map(text).collect();
+    assert_eq!(
+        texts,
+        vec![
+            format!("{}\nfirst summary", SUMMARY_PREFIX),
+            "Understood. I'll keep that context in mind.".to_string(),
+            "question 2".to_string(),
+            "answer 2".to_string(),
+            "latest question".to_string(),
+        ]
+    );
+    assert_eq!(turns[0].role, Some(Role::User));
+    assert_eq!(turns[1].role, Some(Role::Model));
+    assert!(Conversation::new(&turns).validate().is_ok());
+    assert_eq!(
+        request_bodies(&server).await[0]["contents"],
+        json!([{ "parts": [{ "text": "Summarize this.\n\n\
+User: question 0\n\
+Assistant: answer 0\n\
+User: question 1\n\
+Assistant: answer 1" }] }])
+    );
+
+    // An earlier summary is folded into the next one
+    let mut turns = turns;
+    turns.push(Content::model("latest answer"));
+    turns.push(Content::user("newest question"));
+    let turns = strategy.compact(turns, &model).await.unwrap();
+    let texts: Vec<String> = turns.iter().map(text).collect();
+    assert_eq!(
+        texts,
+        vec![
+            format!("{}\nsecond summary", SUMMARY_PREFIX),
+            "Understood. I'll keep that context in mind.".to_string(),
+            "latest question".to_string(),
+            "latest answer".to_string(),
+            "newest question".to_string(),
+        ]
+    );
+    assert_eq!(
+        request_bodies(&server).await[1]["contents"][0]["parts"][0]["text"],
+        format!(
+            "Summarize this.\n\n\
+User: {}\nfirst summary\n\
+Assistant: Understood. I'll keep that context in mind.\n\
+User: question 2\n\
+Assistant: answer 2",
+            SUMMARY_PREFIX
+        )
+    );
+}
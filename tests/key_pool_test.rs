@@ -0,0 +1,324 @@
+//! API key pool tests with a manual clock and a local mock server
+//! These tests don't require API keys
+
+use futures::future::BoxFuture;
+use futures::StreamExt;
+use gemini_rs::call::{CallOptions, CancellationToken};
+use gemini_rs::clock::ManualClock;
+use gemini_rs::key_pool::{KeyPool, KeySelection};
+use gemini_rs::middleware::{Middleware, ModelRequest, ModelResponse, Next};
+use gemini_rs::{Client, Content, Error, Model, Result};
+use serde_json::json;
+use std::time::Duration;
+use wiremock::matchers::{header, method};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+const KEYS: [&str; 3] = ["KEY-A-secret", "KEY-B-secret", "KEY-C-secret"];
+
+fn client(server: &MockServer, pool: KeyPool) -> Client {
+    Client::builder()
+        .api_key("backend-key")
+        .base_url(format!("{}/v1beta", server.uri()))
+        .build()
+        .unwrap()
+        .with_key_pool(pool)
+}
+
+fn ok() -> ResponseTemplate {
+    ResponseTemplate::new(200).set_body_json(json!({
+        "candidates": [{ "content": { "role": "model", "parts": [{ "text": "ok" }] } }]
+    }))
+}
+
+/// Answer requests made with `key` with `response`.
+async fn mount_key(server: &MockServer, key: &str, response: ResponseTemplate) {
+    Mock::given(method("POST"))
+        .and(header("x-goog-api-key", key))
+        .respond_with(response)
+        .mount(server)
+        .await;
+}
+
+/// The keys of the requests received so far, by index in `KEYS`.
+async fn used_keys(server: &MockServer) -> Vec<usize> {
+    server
+        .received_requests()
+        .await
+        .unwrap()
+        .iter()
+        .map(|request| {
+            let key = request.headers["x-goog-api-key"].to_str().unwrap();
+            KEYS.iter().position(|k| *k == key).unwrap()
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn test_round_robin_shared_across_clones() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .respond_with(ok())
+        .mount(&server)
+        .await;
+    let pool = KeyPool::new(KEYS);
+    let client = client(&server, pool.clone());
+    let model = client.model(Model::Gemini25Flash);
+    let other = client.clone().model(Model::Gemini20Flash);
+
+    for _ in 0..3 {
+        model.generate_content("Hi").await.unwrap();
+        other.generate_content("Hi").await.unwrap();
+    }
+
+    assert_eq!(used_keys(&server).await, vec![0, 1, 2, 0, 1, 2]);
+    assert_eq!(pool.ids(), vec!["key-0", "key-1", "key-2"]);
+    for health in pool.health() {
+        assert_eq!(health.requests, 2);
+        assert_eq!(health.throttled, 0);
+        assert_eq!(health.quarantined_for, None);
+    }
+}
+
+#[tokio::test]
+async fn test_throttled_keys_are_quarantined() {
+    let server = MockServer::start().await;
+    mount_key(&server, KEYS[0], ResponseTemplate::new(429)).await;
+    mount_key(
+        &server,
+        KEYS[1],
+        ResponseTemplate::new(403).insert_header("retry-after", "5"),
+    )
+    .await;
+    mount_key(&server, KEYS[2], ok()).await;
+    let clock = ManualClock::new();
+    let pool = KeyPool::new(KEYS)
+        .quarantine(Duration::from_secs(30))
+        .with_clock(clock.clone());
+    let model = client(&server, pool.clone()).model(Model::Gemini25Flash);
+
+    let err = model.generate_content("Hi").await.unwrap_err();
+    assert_eq!(err.key_id(), Some("key-0"));
+    assert!(matches!(err.without_key(), Error::RateLimitExceeded { .. }));
+    assert!(err.is_retryable());
+    assert_eq!(err.to_string(), "Rate limit exceeded (API key key-0)");
+
+    let err = model.generate_content("Hi").await.unwrap_err();
+    assert_eq!(err.key_id(), Some("key-1"));
+    assert!(matches!(
+        err.without_key(),
+        Error::ApiError {
+            code: Some(403),
+            ..
+        }
+    ));
+
+    // Only the healthy key is used while the others are quarantined
+    for _ in 0..3 {
+        model.generate_content("Hi").await.unwrap();
+    }
+    let health = pool.health();
+    assert_eq!(health[0].throttled, 1);
+    assert_eq!(health[0].quarantined_for, Some(Duration::from_secs(30)));
+    assert_eq!(health[1].quarantined_for, Some(Duration::from_secs(5)));
+    assert_eq!(health[2].requests, 3);
+
+    // Retry-After releases key-1 before key-0
+    clock.advance(Duration::from_secs(5));
+    assert!(model.generate_content("Hi").await.is_err());
+    clock.advance(Duration::from_secs(25));
+    assert_eq!(pool.health()[0].quarantined_for, None);
+    model.generate_content("Hi").await.unwrap();
+    assert!(model.generate_content("Hi").await.is_err());
+    assert_eq!(used_keys(&server).await, vec![0, 1, 2, 2, 2, 1, 2, 0]);
+
+    // With every key quarantined, the one released first is used
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(429))
+        .mount(&server)
+        .await;
+    let pool = KeyPool::new(KEYS).with_clock(clock.clone());
+    let model = client(&server, pool).model(Model::Gemini25Flash);
+    for _ in 0..3 {
+        model.generate_content("Hi").await.unwrap_err();
+        clock.advance(Duration::from_secs(1));
+    }
+    let err = model.generate_content("Hi").await.unwrap_err();
+    assert_eq!(err.key_id(), Some("key-0"));
+}
+
+#[tokio::test]
+async fn test_least_recently_throttled() {
+    let server = MockServer::start().await;
+    mount_key(&server, KEYS[0], ResponseTemplate::new(429)).await;
+    mount_key(&server, KEYS[1], ResponseTemplate::new(429)).await;
+    mount_key(&server, KEYS[2], ok()).await;
+    let clock = ManualClock::new();
+    let pool = KeyPool::new(KEYS)
+        .selection(KeySelection::LeastRecentlyThrottled)
+        .quarantine(Duration::from_secs(10))
+        .with_clock(clock.clone());
+    let model = client(&server, pool).model(Model::Gemini25Flash);
+
+    // Untried keys are taken in turn
+    model.generate_content("Hi").await.unwrap_err();
+    clock.advance(Duration::from_secs(1));
+    model.generate_content("Hi").await.unwrap_err();
+    clock.advance(Duration::from_secs(20));
+
+    // After the quarantine, the key never throttled is preferred, then the
+    // one throttled longest ago
+    model.generate_content("Hi").await.unwrap();
+    model.generate_content("Hi").await.unwrap();
+    server.reset().await;
+    mount_key(&server, KEYS[0], ok()).await;
+    mount_key(&server, KEYS[2], ResponseTemplate::new(429)).await;
+    model.generate_content("Hi").await.unwrap_err();
+    model.generate_content("Hi").await.unwrap();
+    assert_eq!(used_keys(&server).await, vec![2, 0]);
+}
+
+#[tokio::test]
+async fn test_errors_name_the_key_not_its_value() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(400).set_body_json(json!({
+            "error": { "code": 400, "message": format!("API key {} not valid", KEYS[0]) }
+        })))
+        .up_to_n_times(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(format!(
+            "data: {}\r\n\r\ndata: {}\r\n\r\n",
+            json!({ "candidates": [{ "content": { "role": "model", "parts": [{ "text": "Hel" }] } }] }),
+            json!({ "error": { "code": 500, "message": format!("leaked {}", KEYS[1]) } })
+        )))
+        .mount(&server)
+        .await;
+    let pool = KeyPool::named([("team-a", KEYS[0]), ("team-b", KEYS[1])]);
+    let client = client(&server, pool);
+    let model = client.model(Model::Gemini25Flash);
+
+    let err = model.generate_content("Hi").await.unwrap_err();
+    assert_eq!(err.key_id(), Some("team-a"));
+    let message = err.to_string();
+    assert!(message.ends_with("(API key team-a)"), "{}", message);
+    assert!(!message.contains(KEYS[0]), "{}", message);
+
+    // Errors in the middle of a stream name the key too
+    let mut stream = model.stream_generate_content("Hi").await.unwrap();
+    assert_eq!(stream.next().await.unwrap().unwrap().text(), "Hel");
+    let err = stream.next().await.unwrap().unwrap_err();
+    assert_eq!(err.key_id(), Some("team-b"));
+    assert!(!err.to_string().contains(KEYS[1]), "{}", err);
+
+    let debug = format!("{:?}", client);
+    assert!(debug.contains("team-a"), "{}", debug);
+    for key in KEYS {
+        assert!(!debug.contains(key), "{}", debug);
+    }
+}
+
+/// Retries a failed call once.
+struct RetryOnce;
+
+impl Middleware for RetryOnce {
+    fn handle<'a>(
+        &'a self,
+        mut request: ModelRequest,
+        next: Next<'a>,
+    ) -> BoxFuture<'a, Result<ModelResponse>> {
+        Box::pin(async move {
+            match next.run(request.clone()).await {
+                Err(_) => {
+                    request.attempt += 1;
+                    next.run(request).await
+                }
+                response => response,
+            }
+        })
+    }
+}
+
+/// Fails every call without sending it.
+struct Reject;
+
+impl Middleware for Reject {
+    fn handle<'a>(
+        &'a self,
+        _request: ModelRequest,
+        _next: Next<'a>,
+    ) -> BoxFuture<'a, Result<ModelResponse>> {
+        Box::pin(async { Err(Error::InvalidInput("rejected".to_string())) })
+    }
+}
+
+#[tokio::test]
+async fn test_every_error_of_a_pooled_call_names_the_key() {
+    let server = MockServer::start().await;
+    mount_key(&server, KEYS[0], ResponseTemplate::new(400)).await;
+    mount_key(
+        &server,
+        KEYS[1],
+        ResponseTemplate::new(200).set_body_json(json!({})),
+    )
+    .await;
+    let pool = KeyPool::new(KEYS);
+
+    // A retry takes a fresh key, and the error names the last key used
+    let model = client(&server, pool.clone())
+        .with_middleware(RetryOnce)
+        .model(Model::Gemini25Flash);
+    let err = model.generate_content("Hi").await.unwrap_err();
+    assert!(matches!(err.without_key(), Error::NoResponse), "{:?}", err);
+    assert_eq!(err.key_id(), Some("key-1"));
+    assert_eq!(used_keys(&server).await, vec![0, 1]);
+
+    // Errors of middleware that never reaches the API name the call's key
+    let model = client(&server, pool.clone())
+        .with_middleware(Reject)
+        .model(Model::Gemini25Flash);
+    let err = model.generate_content("Hi").await.unwrap_err();
+    assert!(
+        matches!(err.without_key(), Error::InvalidInput(_)),
+        "{:?}",
+        err
+    );
+    assert_eq!(err.key_id(), Some("key-2"));
+    assert_eq!(used_keys(&server).await, vec![0, 1]);
+
+    // Errors raised around the call are not wrapped
+    let model = client(&server, pool).model(Model::Gemini25Flash);
+    let err = model
+        .generate_content_from_parts(vec![Content::model("Hi")])
+        .await
+        .unwrap_err();
+    assert!(matches!(err, Error::InvalidInput(_)), "{:?}", err);
+    let cancel = CancellationToken::new();
+    cancel.cancel();
+    let err = model
+        .with_call_options(CallOptions::new().cancel_on(cancel))
+        .generate_content("Hi")
+        .await
+        .unwrap_err();
+    assert!(matches!(err, Error::Cancelled), "{:?}", err);
+}
+
+#[tokio::test]
+async fn test_project_resources_use_the_first_key() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(header("x-goog-api-key", KEYS[0]))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "name": "batches/1" })))
+        .mount(&server)
+        .await;
+    let pool = KeyPool::new(KEYS);
+    let batches = client(&server, pool.clone()).batches();
+
+    for _ in 0..2 {
+        batches.get("batches/1").await.unwrap();
+    }
+    assert_eq!(used_keys(&server).await, vec![0, 0]);
+    assert_eq!(pool.health()[0].requests, 2);
+}
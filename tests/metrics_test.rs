@@ -0,0 +1,214 @@
+//! Metrics sink tests against a local mock server
+//! These tests don't require API keys
+
+use futures::StreamExt;
+use gemini_rs::call::CallOptions;
+use gemini_rs::clock::ManualClock;
+use gemini_rs::metrics::{MemoryMetrics, Outcome};
+use gemini_rs::middleware::Endpoint;
+use gemini_rs::rate_limit::{Quota, RateLimiter};
+use gemini_rs::{Client, Content, Error, Model};
+use serde_json::json;
+use std::time::Duration;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+fn client(server: &MockServer) -> Client {
+    Client::builder()
+        .api_key("test_api_key")
+        .base_url(format!("{}/v1beta", server.uri()))
+        .build()
+        .unwrap()
+}
+
+fn response_body(text: &str, output_tokens: u64) -> serde_json::Value {
+    json!({
+        "candidates": [{ "content": { "role": "model", "parts": [{ "text": text }] } }],
+        "usageMetadata": {
+            "promptTokenCount": 10,
+            "candidatesTokenCount": output_tokens,
+            "thoughtsTokenCount": 5,
+            "cachedContentTokenCount": 4,
+            "totalTokenCount": 15 + output_tokens
+        }
+    })
+}
+
+#[tokio::test]
+async fn test_records_calls_with_usage_and_labels() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1beta/models/gemini-2.0-flash:generateContent"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(response_body("ok", 3)))
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/v1beta/models/gemini-2.0-flash:countTokens"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "totalTokens": 3 })))
+        .mount(&server)
+        .await;
+
+    let metrics = MemoryMetrics::new();
+    let model = client(&server)
+        .with_metrics(metrics.clone())
+        .with_metric_label("tenant", "acme")
+        .model(Model::Gemini20Flash);
+
+    model.generate_content("Hi").await.unwrap();
+    model.generate_content("Hi again").await.unwrap();
+    model.count_tokens(vec![Content::text("Hi")]).await.unwrap();
+
+    let calls = metrics.calls();
+    assert_eq!(calls.len(), 3);
+    assert_eq!(calls[0].model, "gemini-2.0-flash");
+    assert_eq!(calls[0].endpoint, Endpoint::GenerateContent);
+    assert_eq!(calls[0].outcome, Outcome::Success);
+    assert_eq!(calls[0].label("tenant"), Some("acme"));
+    assert_eq!(calls[0].attempt, 0);
+    assert!(calls[0].time_to_first_token.is_none());
+    assert_eq!(calls[2].endpoint, Endpoint::CountTokens);
+    assert_eq!(calls[2].usage.total_token_count, 0);
+
+    let usage = metrics.total_usage();
+    assert_eq!(usage.prompt_token_count, 20);
+    assert_eq!(usage.candidates_token_count, 6);
+    assert_eq!(usage.thoughts_token_count, 10);
+    assert_eq!(usage.cached_content_token_count, 8);
+    assert_eq!(usage.total_token_count, 36);
+    assert_eq!(
+        metrics.count(|call| call.endpoint == Endpoint::GenerateContent),
+        2
+    );
+}
+
+#[tokio::test]
+async fn test_records_errors() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(429))
+        .mount(&server)
+        .await;
+
+    let metrics = MemoryMetrics::new();
+    let model = client(&server)
+        .with_metrics(metrics.clone())
+        .model(Model::Gemini20Flash);
+    assert!(model.generate_content("Hi").await.is_err());
+
+    let calls = metrics.calls();
+    assert_eq!(calls.len(), 1);
+    assert_eq!(
+        calls[0].outcome,
+        Outcome::Error {
+            error_type: "rate_limit_exceeded".to_string()
+        }
+    );
+    assert_eq!(calls[0].outcome.as_str(), "error");
+}
+
+#[tokio::test]
+async fn test_records_calls_that_never_reach_the_api() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(response_body("ok", 1)))
+        .mount(&server)
+        .await;
+
+    let metrics = MemoryMetrics::new();
+    let limiter = RateLimiter::new()
+        .default_quota(Quota::requests_per_minute(1))
+        .fail_fast(true)
+        .with_clock(ManualClock::new());
+    let model = client(&server)
+        .with_metrics(metrics.clone())
+        .with_rate_limiter(limiter)
+        .model(Model::Gemini20Flash);
+
+    model.generate_content("Hi").await.unwrap();
+    assert!(matches!(
+        model.generate_content("Hi").await,
+        Err(Error::RateLimitExceeded { .. })
+    ));
+
+    let calls = metrics.calls();
+    assert_eq!(calls.len(), 2);
+    assert!(calls[0].outcome.is_success());
+    assert_eq!(
+        calls[1].outcome,
+        Outcome::Error {
+            error_type: "rate_limit_exceeded".to_string()
+        }
+    );
+    assert_eq!(calls[1].usage.total_token_count, 0);
+    assert_eq!(server.received_requests().await.unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn test_records_abandoned_calls_as_cancelled() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_json(response_body("late", 1))
+                .set_delay(Duration::from_secs(5)),
+        )
+        .mount(&server)
+        .await;
+
+    let metrics = MemoryMetrics::new();
+    let model = client(&server)
+        .with_metrics(metrics.clone())
+        .model(Model::Gemini20Flash)
+        .with_call_options(CallOptions::new().timeout(Duration::from_millis(50)));
+
+    assert!(matches!(
+        model.generate_content("Hi").await,
+        Err(Error::Timeout(_))
+    ));
+
+    let calls = metrics.calls();
+    assert_eq!(calls.len(), 1);
+    assert_eq!(
+        calls[0].outcome,
+        Outcome::Error {
+            error_type: "cancelled".to_string()
+        }
+    );
+}
+
+#[tokio::test]
+async fn test_records_streams_when_finished() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path(
+            "/v1beta/models/gemini-2.0-flash:streamGenerateContent",
+        ))
+        .respond_with(ResponseTemplate::new(200).set_body_raw(
+            format!(
+                "data: {}\n\ndata: {}\n\n",
+                response_body("a", 1),
+                response_body("b", 2)
+            ),
+            "text/event-stream",
+        ))
+        .mount(&server)
+        .await;
+
+    let metrics = MemoryMetrics::new();
+    let model = client(&server)
+        .with_metrics(metrics.clone())
+        .model(Model::Gemini20Flash);
+
+    let mut stream = model.stream_generate_content("Hi").await.unwrap();
+    stream.next().await.unwrap().unwrap();
+    assert!(metrics.calls().is_empty());
+    while stream.next().await.is_some() {}
+    drop(stream);
+
+    let calls = metrics.calls();
+    assert_eq!(calls.len(), 1);
+    assert_eq!(calls[0].endpoint, Endpoint::StreamGenerateContent);
+    assert!(calls[0].time_to_first_token.unwrap() <= calls[0].latency);
+    // Chunks carry running totals; the last one counts
+    assert_eq!(calls[0].usage.candidates_token_count, 2);
+}
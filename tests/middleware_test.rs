@@ -0,0 +1,255 @@
+//! Interceptor and middleware tests against a local mock server
+//! These tests don't require API keys
+
+use futures::future::BoxFuture;
+use futures::StreamExt;
+use gemini_rs::middleware::{Endpoint, Interceptor, Middleware, ModelRequest, ModelResponse, Next};
+use gemini_rs::types::{GenerateContentRequest, GenerateContentResponse, Part};
+use gemini_rs::{Client, Content, Error, Model, Result};
+use serde_json::json;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use wiremock::matchers::{body_partial_json, header, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+fn text_response(text: &str) -> ResponseTemplate {
+    ResponseTemplate::new(200).set_body_json(json!({
+        "candidates": [{ "content": { "role": "model", "parts": [{ "text": text }] } }]
+    }))
+}
+
+fn client(server: &MockServer) -> Client {
+    Client::builder()
+        .api_key("test_api_key")
+        .base_url(format!("{}/v1beta", server.uri()))
+        .build()
+        .unwrap()
+}
+
+/// Replaces a secret in every text part and counts responses.
+#[derive(Default)]
+struct Scrub {
+    responses: AtomicUsize,
+}
+
+impl Interceptor for Scrub {
+    fn before_request(&self, request: &mut GenerateContentRequest) -> Result<()> {
+        for part in request.contents.iter_mut().flat_map(|c| c.parts.iter_mut()) {
+            if let Part::Text { text } = part {
+                *text = text.replace("s3cret", "<redacted>");
+            }
+        }
+        Ok(())
+    }
+
+    fn after_response(&self, _response: &GenerateContentResponse) -> Result<()> {
+        self.responses.fetch_add(1, Ordering::SeqCst);
+        Ok(())
+    }
+}
+
+/// Records the order layers run in and adds a header.
+struct Record {
+    name: &'static str,
+    log: Arc<Mutex<Vec<String>>>,
+}
+
+impl Middleware for Record {
+    fn handle<'a>(
+        &'a self,
+        mut request: ModelRequest,
+        next: Next<'a>,
+    ) -> BoxFuture<'a, Result<ModelResponse>> {
+        Box::pin(async move {
+            self.log
+                .lock()
+                .unwrap()
+                .push(format!("{} {}", self.name, request.endpoint));
+            request
+                .headers
+                .insert("x-trace-id", self.name.parse().unwrap());
+            let response = next.run(request).await;
+            self.log.lock().unwrap().push(format!("{} done", self.name));
+            response
+        })
+    }
+}
+
+#[tokio::test]
+async fn test_layers_run_in_order_and_modify_requests() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/v1beta/models/gemini-2.0-flash:generateContent"))
+        .and(header("x-trace-id", "inner"))
+        .and(body_partial_json(json!({
+            "contents": [{ "parts": [{ "text": "my password is <redacted>" }] }]
+        })))
+        .respond_with(text_response("ok"))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let log = Arc::new(Mutex::new(Vec::new()));
+    let scrub = Arc::new(Scrub::default());
+    let client = client(&server)
+        .with_middleware(Record {
+            name: "outer",
+            log: log.clone(),
+        })
+        .with_interceptor(scrub.clone())
+        .with_middleware(Record {
+            name: "inner",
+            log: log.clone(),
+        });
+
+    let response = client
+        .model(Model::Gemini20Flash)
+        .generate_content("my password is s3cret")
+        .await
+        .unwrap();
+    assert_eq!(response.text(), "ok");
+    assert_eq!(scrub.responses.load(Ordering::SeqCst), 1);
+    assert_eq!(
+        *log.lock().unwrap(),
+        vec![
+            "outer generateContent",
+            "inner generateContent",
+            "inner done",
+            "outer done"
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_interceptor_sees_every_stream_chunk_and_count_tokens() {
+    let server = MockServer::start().await;
+    let chunk = |text: &str| json!({ "candidates": [{ "content": { "role": "model", "parts": [{ "text": text }] } }] });
+
+    Mock::given(method("POST"))
+        .and(path(
+            "/v1beta/models/gemini-2.0-flash:streamGenerateContent",
+        ))
+        .and(body_partial_json(json!({
+            "contents": [{ "parts": [{ "text": "<redacted>" }] }]
+        })))
+        .respond_with(ResponseTemplate::new(200).set_body_raw(
+            format!("data: {}\n\ndata: {}\n\n", chunk("a"), chunk("b")),
+            "text/event-stream",
+        ))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/v1beta/models/gemini-2.0-flash:countTokens"))
+        .and(body_partial_json(json!({
+            "generateContentRequest": { "contents": [{ "parts": [{ "text": "<redacted>" }] }] }
+        })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "totalTokens": 3 })))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let scrub = Arc::new(Scrub::default());
+    let model = client(&server)
+        .with_interceptor(scrub.clone())
+        .model(Model::Gemini20Flash);
+
+    let chunks: Vec<String> = model
+        .stream_generate_content("s3cret")
+        .await
+        .unwrap()
+        .map(|chunk| chunk.unwrap().text())
+        .collect()
+        .await;
+    assert_eq!(chunks, vec!["a", "b"]);
+    assert_eq!(scrub.responses.load(Ordering::SeqCst), 2);
+
+    let count = model
+        .count_tokens(vec![Content::text("s3cret")])
+        .await
+        .unwrap();
+    assert_eq!(count.total_tokens, 3);
+    assert_eq!(scrub.responses.load(Ordering::SeqCst), 2);
+}
+
+/// Rejects prompts over a length limit.
+struct MaxPromptChars(usize);
+
+impl Interceptor for MaxPromptChars {
+    fn before_request(&self, request: &mut GenerateContentRequest) -> Result<()> {
+        let chars: usize = request
+            .contents
+            .iter()
+            .flat_map(|content| &content.parts)
+            .map(|part| match part {
+                Part::Text { text } => text.len(),
+                _ => 0,
+            })
+            .sum();
+        if chars > self.0 {
+            return Err(Error::InvalidInput("prompt too long".to_string()));
+        }
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn test_interceptor_error_aborts_chat_send() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .respond_with(text_response("ok"))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let mut chat = client(&server)
+        .with_interceptor(MaxPromptChars(20))
+        .model(Model::Gemini20Flash)
+        .start_chat();
+
+    chat.send_message("short").await.unwrap();
+    let result = chat
+        .send_message("this message is far too long to send")
+        .await;
+    assert!(matches!(result, Err(Error::InvalidInput(_))));
+    assert_eq!(chat.history().len(), 2);
+}
+
+/// Answers generateContent without calling the API.
+struct Canned;
+
+impl Middleware for Canned {
+    fn handle<'a>(
+        &'a self,
+        request: ModelRequest,
+        next: Next<'a>,
+    ) -> BoxFuture<'a, Result<ModelResponse>> {
+        if request.endpoint != Endpoint::GenerateContent {
+            return next.run(request);
+        }
+        Box::pin(async {
+            let response = serde_json::from_value(json!({
+                "candidates": [{ "content": { "role": "model", "parts": [{ "text": "cached" }] } }]
+            }))?;
+            Ok(ModelResponse::Generate(response))
+        })
+    }
+}
+
+#[tokio::test]
+async fn test_middleware_can_short_circuit() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .respond_with(text_response("live"))
+        .expect(0)
+        .mount(&server)
+        .await;
+
+    let model = client(&server)
+        .with_middleware(Canned)
+        .model(Model::Gemini20Flash);
+    assert_eq!(model.generate_content("Hi").await.unwrap().text(), "cached");
+}
@@ -0,0 +1,266 @@
+//! Long-running operation tests against a mock server
+//! These tests don't require API keys
+
+use gemini_rs::batch::{BatchMetadata, BatchOutput, BatchState};
+use gemini_rs::clock::{Clock, ManualClock};
+use gemini_rs::operation::Operation;
+use gemini_rs::{Client, Error};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::time::Duration;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+#[derive(Debug, Deserialize, PartialEq)]
+struct Video {
+    uri: String,
+}
+
+#[derive(Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+struct Progress {
+    progress_percent: u32,
+}
+
+fn client(server: &MockServer) -> Client {
+    Client::builder()
+        .api_key("test-key")
+        .base_url(format!("{}/v1beta", server.uri()))
+        .build()
+        .unwrap()
+}
+
+fn pending(name: &str, percent: u32) -> ResponseTemplate {
+    ResponseTemplate::new(200).set_body_json(json!({
+        "name": name,
+        "metadata": { "progressPercent": percent }
+    }))
+}
+
+fn finished(name: &str) -> ResponseTemplate {
+    ResponseTemplate::new(200).set_body_json(json!({
+        "name": name,
+        "done": true,
+        "metadata": { "progressPercent": 100 },
+        "response": { "@type": "type.googleapis.com/Video", "uri": "files/video" }
+    }))
+}
+
+#[tokio::test]
+async fn test_poll_metadata_and_result() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/v1beta/operations/op1"))
+        .respond_with(pending("operations/op1", 40))
+        .up_to_n_times(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/v1beta/operations/op1"))
+        .respond_with(finished("operations/op1"))
+        .mount(&server)
+        .await;
+
+    let mut operation = client(&server).operation::<Video, Progress>("operations/op1");
+    assert_eq!(operation.name(), "operations/op1");
+    assert!(operation.result().is_none());
+
+    assert!(!operation.poll().await.unwrap());
+    assert_eq!(
+        operation.metadata().unwrap(),
+        Some(Progress {
+            progress_percent: 40
+        })
+    );
+    assert!(operation.result().is_none());
+
+    assert!(operation.poll().await.unwrap());
+    assert!(operation.is_done());
+    assert_eq!(
+        operation.result().unwrap().unwrap(),
+        Video {
+            uri: "files/video".into()
+        }
+    );
+}
+
+#[tokio::test]
+async fn test_wait_backs_off_and_retries() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/v1beta/operations/op2"))
+        .respond_with(pending("operations/op2", 10))
+        .up_to_n_times(2)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/v1beta/operations/op2"))
+        .respond_with(ResponseTemplate::new(503).set_body_string("overloaded"))
+        .up_to_n_times(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/v1beta/operations/op2"))
+        .respond_with(finished("operations/op2"))
+        .mount(&server)
+        .await;
+
+    let clock = ManualClock::new();
+    let start = clock.now();
+    let mut operation = client(&server)
+        .operation::<Video, Value>("operations/op2")
+        .with_clock(clock.clone());
+    let video = operation
+        .wait(Duration::from_secs(2), Duration::from_secs(60))
+        .await
+        .unwrap();
+
+    assert_eq!(video.uri, "files/video");
+    // 2s, then 3s, then 4.5s between the four polls
+    assert_eq!(clock.now() - start, Duration::from_millis(9500));
+    assert_eq!(server.received_requests().await.unwrap().len(), 4);
+}
+
+#[tokio::test]
+async fn test_wait_times_out() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/v1beta/operations/slow"))
+        .respond_with(pending("operations/slow", 1))
+        .mount(&server)
+        .await;
+
+    let clock = ManualClock::new();
+    let start = clock.now();
+    let mut operation = client(&server)
+        .operation::<Video, Progress>("operations/slow")
+        .with_clock(clock.clone());
+    let result = operation
+        .wait(Duration::from_secs(1), Duration::from_secs(5))
+        .await;
+
+    assert!(matches!(result, Err(Error::Timeout(timeout)) if timeout == Duration::from_secs(5)));
+    assert_eq!(clock.now() - start, Duration::from_secs(5));
+}
+
+#[tokio::test]
+async fn test_failed_operation_and_fatal_poll_errors() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/v1beta/operations/failed"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "name": "operations/failed",
+            "done": true,
+            "error": { "code": 13, "message": "internal failure" }
+        })))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/v1beta/operations/missing"))
+        .respond_with(ResponseTemplate::new(404).set_body_string("not found"))
+        .mount(&server)
+        .await;
+
+    let client = client(&server);
+    let mut failed = client.operation::<Video, Value>("operations/failed");
+    let result = failed
+        .wait(Duration::from_millis(1), Duration::from_secs(1))
+        .await;
+    match result {
+        Err(Error::ApiError { code, message }) => {
+            assert_eq!(code, Some(13));
+            assert_eq!(message, "internal failure");
+        }
+        other => panic!("expected an API error, got {:?}", other),
+    }
+
+    let mut missing = client.operation::<Video, Value>("operations/missing");
+    let result = missing
+        .wait(Duration::from_millis(1), Duration::from_secs(1))
+        .await;
+    assert!(matches!(
+        result,
+        Err(Error::ApiError {
+            code: Some(404),
+            ..
+        })
+    ));
+}
+
+#[tokio::test]
+async fn test_serialize_and_resume() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/v1beta/operations/op3"))
+        .respond_with(pending("operations/op3", 50))
+        .up_to_n_times(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/v1beta/operations/op3"))
+        .respond_with(finished("operations/op3"))
+        .mount(&server)
+        .await;
+
+    let mut operation = client(&server).operation::<Video, Progress>("operations/op3");
+    operation.poll().await.unwrap();
+    let saved = serde_json::to_string(&operation).unwrap();
+    assert!(!saved.contains("test-key"));
+
+    let restored: Operation<Video, Progress> = serde_json::from_str(&saved).unwrap();
+    assert_eq!(restored.name(), "operations/op3");
+    assert_eq!(restored.metadata().unwrap().unwrap().progress_percent, 50);
+
+    let mut detached = restored.clone();
+    assert!(matches!(detached.poll().await, Err(Error::ConfigError(_))));
+
+    let mut resumed = client(&server).resume_operation(restored);
+    assert!(resumed.poll().await.unwrap());
+    assert_eq!(resumed.result().unwrap().unwrap().uri, "files/video");
+}
+
+#[tokio::test]
+async fn test_cancel() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1beta/operations/op4:cancel"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({})))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let operation = client(&server).operation::<Video, Value>("operations/op4");
+    operation.cancel().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_batch_job_as_operation() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/v1beta/batches/7"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "name": "batches/7",
+            "done": true,
+            "metadata": { "state": "BATCH_STATE_SUCCEEDED" },
+            "response": {
+                "inlinedResponses": { "inlinedResponses": [{
+                    "response": { "candidates": [{ "content": { "role": "model", "parts": [{ "text": "hi" }] } }] },
+                    "metadata": { "key": "a" }
+                }] }
+            }
+        })))
+        .mount(&server)
+        .await;
+
+    let batches = client(&server).batches();
+    let mut operation = batches.operation("7");
+    let output: BatchOutput = operation
+        .wait(Duration::from_secs(1), Duration::from_secs(10))
+        .await
+        .unwrap();
+    let metadata: BatchMetadata = operation.metadata().unwrap().unwrap();
+    assert_eq!(metadata.state, BatchState::Succeeded);
+
+    let results = batches.output_results(&output).await.unwrap();
+    assert_eq!(results["a"].as_ref().unwrap().text(), "hi");
+}
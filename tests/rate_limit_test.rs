@@ -0,0 +1,208 @@
+//! Rate limiter tests with a manual clock and a local mock server
+//! These tests don't require API keys
+
+use gemini_rs::clock::{Clock, ManualClock};
+use gemini_rs::rate_limit::{Quota, RateLimiter};
+use gemini_rs::{Client, Error, Model};
+use serde_json::json;
+use std::time::Duration;
+use wiremock::matchers::method;
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+fn client(server: &MockServer) -> Client {
+    Client::builder()
+        .api_key("test_api_key")
+        .base_url(format!("{}/v1beta", server.uri()))
+        .build()
+        .unwrap()
+}
+
+async fn mount_response(server: &MockServer, total_tokens: u64) {
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "candidates": [{ "content": { "role": "model", "parts": [{ "text": "ok" }] } }],
+            "usageMetadata": { "totalTokenCount": total_tokens }
+        })))
+        .mount(server)
+        .await;
+}
+
+#[tokio::test]
+async fn test_requests_per_minute_waits() {
+    let clock = ManualClock::new();
+    let start = clock.now();
+    let limiter = RateLimiter::new()
+        .limit(Model::Gemini20Flash, Quota::requests_per_minute(2))
+        .with_clock(clock.clone());
+
+    limiter.acquire(Model::Gemini20Flash, 0).await.unwrap();
+    limiter.acquire(Model::Gemini20Flash, 0).await.unwrap();
+    assert_eq!(clock.now(), start);
+
+    // The bucket refills at one request per 30s
+    limiter.acquire(Model::Gemini20Flash, 0).await.unwrap();
+    assert_eq!(clock.now() - start, Duration::from_secs(30));
+
+    // Other models are not limited
+    limiter.acquire(Model::Gemini25Flash, 0).await.unwrap();
+    assert_eq!(clock.now() - start, Duration::from_secs(30));
+}
+
+#[tokio::test]
+async fn test_tokens_per_minute_and_default_quota() {
+    let clock = ManualClock::new();
+    let start = clock.now();
+    let limiter = RateLimiter::new()
+        .default_quota(Quota::tokens_only(600))
+        .with_clock(clock.clone());
+
+    limiter.acquire(Model::Gemini25Flash, 500).await.unwrap();
+    // 400 more tokens need 300 refilled at 10 per second
+    limiter.acquire(Model::Gemini25Flash, 400).await.unwrap();
+    assert_eq!(clock.now() - start, Duration::from_secs(30));
+
+    // Requests above the quota only wait for a full bucket
+    limiter.acquire(Model::Gemini25Flash, 1000).await.unwrap();
+    assert_eq!(clock.now() - start, Duration::from_secs(90));
+}
+
+#[tokio::test]
+async fn test_fail_fast() {
+    let limiter = RateLimiter::new()
+        .limit(Model::Gemini20Flash, Quota::requests_per_minute(1))
+        .with_clock(ManualClock::new())
+        .fail_fast(true);
+
+    limiter.acquire(Model::Gemini20Flash, 0).await.unwrap();
+    match limiter.acquire(Model::Gemini20Flash, 0).await {
+        Err(Error::RateLimitExceeded {
+            retry_after,
+            message: Some(message),
+        }) => {
+            // One request refills in a minute
+            assert_eq!(retry_after, Some(Duration::from_secs(60)));
+            assert!(message.contains("client-side"), "{}", message);
+        }
+        other => panic!("expected RateLimitExceeded, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn test_client_reconciles_actual_usage() {
+    let server = MockServer::start().await;
+    mount_response(&server, 1200).await;
+
+    let clock = ManualClock::new();
+    let start = clock.now();
+    let limiter = RateLimiter::new()
+        .limit(Model::Gemini20Flash, Quota::tokens_only(1200))
+        .with_clock(clock.clone());
+    let client = client(&server).with_rate_limiter(limiter);
+
+    // Estimated at 1 token, but the response reports 1200
+    client
+        .model(Model::Gemini20Flash)
+        .generate_content("Hi")
+        .await
+        .unwrap();
+    assert_eq!(clock.now(), start);
+
+    // A clone shares the bucket and waits for it to refill
+    client
+        .clone()
+        .model(Model::Gemini20Flash)
+        .generate_content("Hi")
+        .await
+        .unwrap();
+    // The bucket is empty; one token refills in 50ms
+    let waited = clock.now() - start;
+    assert!(
+        waited > Duration::from_millis(49) && waited < Duration::from_millis(51),
+        "{:?}",
+        waited
+    );
+}
+
+#[tokio::test]
+async fn test_server_429_is_classified() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .respond_with(
+            ResponseTemplate::new(429)
+                .insert_header("retry-after", "30")
+                .set_body_json(json!({
+                    "error": {
+                        "code": 429,
+                        "message": "Resource has been exhausted",
+                        "details": [{
+                            "@type": "type.googleapis.com/google.rpc.RetryInfo",
+                            "retryDelay": "12.5s"
+                        }]
+                    }
+                })),
+        )
+        .up_to_n_times(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(429).insert_header("retry-after", "30"))
+        .mount(&server)
+        .await;
+    let model = client(&server).model(Model::Gemini20Flash);
+
+    // RetryInfo is preferred to the rounded Retry-After header
+    let err = model.generate_content("Hi").await.unwrap_err();
+    assert!(matches!(
+        &err,
+        Error::RateLimitExceeded { message: Some(message), .. }
+            if message == "Resource has been exhausted"
+    ));
+    assert_eq!(err.retry_after(), Some(Duration::from_millis(12_500)));
+    assert_eq!(
+        err.to_string(),
+        "Rate limit exceeded: Resource has been exhausted"
+    );
+
+    let err = model.generate_content("Hi").await.unwrap_err();
+    assert!(matches!(
+        err,
+        Error::RateLimitExceeded { message: None, .. }
+    ));
+    assert_eq!(err.retry_after(), Some(Duration::from_secs(30)));
+}
+
+#[tokio::test]
+async fn test_server_429_pauses_the_shared_bucket() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(429).insert_header("retry-after", "20"))
+        .up_to_n_times(1)
+        .mount(&server)
+        .await;
+    mount_response(&server, 1).await;
+
+    let clock = ManualClock::new();
+    let start = clock.now();
+    let limiter = RateLimiter::new()
+        .limit(Model::Gemini20Flash, Quota::requests_per_minute(60))
+        .with_clock(clock.clone());
+    let client = client(&server).with_rate_limiter(limiter);
+
+    let err = client
+        .model(Model::Gemini20Flash)
+        .generate_content("Hi")
+        .await
+        .unwrap_err();
+    assert_eq!(err.retry_after(), Some(Duration::from_secs(20)));
+    assert_eq!(clock.now(), start);
+
+    // A clone waits out the server's delay instead of getting its own 429
+    client
+        .clone()
+        .model(Model::Gemini20Flash)
+        .generate_content("Hi")
+        .await
+        .unwrap();
+    assert_eq!(clock.now() - start, Duration::from_secs(20));
+    assert_eq!(server.received_requests().await.unwrap().len(), 2);
+}
@@ -0,0 +1,104 @@
+//! Regression tests: the API key never leaks into URLs, errors or Debug output
+//! These tests don't require API keys
+
+use gemini_rs::backend::Backend;
+use gemini_rs::{Client, Error, Model, REDACTED};
+use serde_json::json;
+use wiremock::matchers::{header, method, path, query_param_is_missing};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+const SECRET: &str = "AIzaSy-super-secret-test-key";
+
+fn mock_client(base_url: &str) -> Client {
+    Client::with_backend(Backend::google_ai(SECRET).with_base_url(base_url))
+}
+
+fn assert_redacted(err: &Error) {
+    assert!(!err.to_string().contains(SECRET), "Display leaked: {}", err);
+    assert!(
+        !format!("{:?}", err).contains(SECRET),
+        "Debug leaked: {:?}",
+        err
+    );
+}
+
+#[tokio::test]
+async fn test_api_key_sent_in_header() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/v1beta/models/gemini-2.5-flash:generateContent"))
+        .and(header("x-goog-api-key", SECRET))
+        .and(query_param_is_missing("key"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "candidates": [{ "content": { "role": "model", "parts": [{ "text": "hi" }] } }]
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let client = mock_client(&format!("{}/v1beta", server.uri()));
+    let response = client
+        .model(Model::Gemini25Flash)
+        .generate_content("Hello")
+        .await
+        .unwrap();
+    assert_eq!(response.text(), "hi");
+}
+
+#[tokio::test]
+async fn test_api_error_does_not_leak_key() {
+    let server = MockServer::start().await;
+
+    // A server echoing the key back must not get it into our errors
+    Mock::given(method("POST"))
+        .respond_with(
+            ResponseTemplate::new(400).set_body_string(format!("API key not valid: {}", SECRET)),
+        )
+        .mount(&server)
+        .await;
+
+    let client = mock_client(&format!("{}/v1beta", server.uri()));
+    let err = client
+        .model(Model::Gemini25Flash)
+        .generate_content("Hello")
+        .await
+        .unwrap_err();
+    assert!(matches!(
+        err,
+        Error::ApiError {
+            code: Some(400),
+            ..
+        }
+    ));
+    assert_redacted(&err);
+}
+
+#[tokio::test]
+async fn test_http_error_does_not_leak_key() {
+    // Nothing listens on port 1, and the key is smuggled into the base URL
+    let client = mock_client(&format!("http://127.0.0.1:1/v1beta?key={}", SECRET));
+    let err = client
+        .model(Model::Gemini25Flash)
+        .generate_content("Hello")
+        .await
+        .unwrap_err();
+    assert!(matches!(err, Error::HttpError(_)));
+    assert_redacted(&err);
+}
+
+#[test]
+fn test_debug_output_redacts_key() {
+    let client = Client::new(SECRET);
+    let model = client.model(Model::Gemini25Flash);
+    let chat = model.start_chat();
+
+    for debug in [
+        format!("{:?}", client),
+        format!("{:?}", model),
+        format!("{:?}", chat),
+    ] {
+        assert!(!debug.contains(SECRET), "Debug leaked: {}", debug);
+        assert!(debug.contains(REDACTED));
+    }
+}
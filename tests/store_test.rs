@@ -0,0 +1,88 @@
+//! Chat persistence tests
+//! These tests don't require API keys
+
+use gemini_rs::store::{ChatStore, FileChatStore, MemoryChatStore};
+use gemini_rs::{ChatSnapshot, Client, Content, Error, GenerationConfig, Model};
+
+fn sample_session() -> gemini_rs::ChatSession {
+    Client::new("test_api_key")
+        .model(Model::Gemini15Pro)
+        .with_config(GenerationConfig::new().temperature(0.2))
+        .with_system_instruction("Be brief")
+        .start_chat_with_history(vec![Content::user("Hi"), Content::model("Hello!")])
+}
+
+#[test]
+fn test_snapshot_roundtrip() {
+    let snapshot = sample_session().snapshot();
+    let json = serde_json::to_string(&snapshot).unwrap();
+    assert!(json.contains("\"model\":\"gemini-1.5-pro\""));
+
+    let restored: ChatSnapshot = serde_json::from_str(&json).unwrap();
+    assert_eq!(restored.model, Model::Gemini15Pro);
+    assert_eq!(restored.history.len(), 2);
+    assert_eq!(restored.generation_config.unwrap().temperature, Some(0.2));
+
+    let chat = Client::new("test_api_key")
+        .model(Model::default())
+        .resume_chat(serde_json::from_str(&json).unwrap());
+    assert_eq!(chat.history().len(), 2);
+    assert_eq!(chat.snapshot().model, Model::Gemini15Pro);
+}
+
+#[test]
+fn test_snapshot_rejects_unknown_model() {
+    let result = serde_json::from_str::<ChatSnapshot>(r#"{"model": "gpt-4", "history": []}"#);
+    assert!(result.is_err());
+}
+
+#[test]
+fn test_memory_store() {
+    let store = MemoryChatStore::new();
+    let shared = store.clone();
+    store.save("a", &sample_session().snapshot()).unwrap();
+
+    assert_eq!(shared.list().unwrap(), vec!["a".to_string()]);
+    assert!(shared.load("a").unwrap().is_some());
+    assert!(shared.load("b").unwrap().is_none());
+
+    shared.delete("a").unwrap();
+    assert!(store.list().unwrap().is_empty());
+}
+
+#[test]
+fn test_file_store_json_and_jsonl() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut session = sample_session();
+
+    for store in [
+        FileChatStore::json(dir.path().join("json")).unwrap(),
+        FileChatStore::jsonl(dir.path().join("jsonl")).unwrap(),
+    ] {
+        store.save("user-1", &session.snapshot()).unwrap();
+        session.undo();
+        store.save("user-1", &session.snapshot()).unwrap();
+
+        // The latest save wins
+        let loaded = store.load("user-1").unwrap().unwrap();
+        assert!(loaded.history.is_empty());
+        assert_eq!(store.list().unwrap(), vec!["user-1".to_string()]);
+
+        store.delete("user-1").unwrap();
+        store.delete("user-1").unwrap();
+        assert!(store.load("user-1").unwrap().is_none());
+
+        session = sample_session();
+    }
+}
+
+#[test]
+fn test_store_rejects_path_traversal() {
+    let dir = tempfile::tempdir().unwrap();
+    let store = FileChatStore::json(dir.path()).unwrap();
+    assert!(matches!(
+        store.save("../escape", &sample_session().snapshot()),
+        Err(Error::InvalidInput(_))
+    ));
+    assert!(matches!(store.load(""), Err(Error::InvalidInput(_))));
+}
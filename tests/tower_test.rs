@@ -0,0 +1,185 @@
+//! tower::Service integration tests against a local mock server
+//! These tests don't require API keys
+#![cfg(feature = "tower")]
+
+use gemini_rs::metrics::MemoryMetrics;
+use gemini_rs::service::{ModelService, RetryPolicy, ServiceRequest};
+use gemini_rs::{Client, Content, Error, Model};
+use serde_json::json;
+use std::time::Duration;
+use tower::{Service, ServiceBuilder, ServiceExt};
+use wiremock::matchers::{body_partial_json, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+fn text_response(text: &str) -> ResponseTemplate {
+    ResponseTemplate::new(200).set_body_json(json!({
+        "candidates": [{ "content": { "role": "model", "parts": [{ "text": text }] } }]
+    }))
+}
+
+fn client(server: &MockServer) -> Client {
+    Client::builder()
+        .api_key("test_api_key")
+        .base_url(format!("{}/v1beta", server.uri()))
+        .build()
+        .unwrap()
+}
+
+fn fast_retries(max_retries: usize) -> RetryPolicy {
+    RetryPolicy::new(max_retries).with_backoff(Duration::from_millis(1), Duration::from_millis(5))
+}
+
+#[tokio::test]
+async fn test_model_client_as_service() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/v1beta/models/gemini-2.0-flash:generateContent"))
+        .and(body_partial_json(
+            json!({ "generationConfig": { "temperature": 0.1 } }),
+        ))
+        .respond_with(text_response("hello"))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let model = client(&server)
+        .model(Model::Gemini20Flash)
+        .with_config(gemini_rs::GenerationConfig::new().temperature(0.1));
+    let request = model.build_request(vec![Content::text("Hi")]);
+
+    let response = model.oneshot(request).await.unwrap();
+    assert_eq!(response.text(), "hello");
+}
+
+#[tokio::test]
+async fn test_retry_policy_retries_server_errors() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(503))
+        .up_to_n_times(2)
+        .expect(2)
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .respond_with(text_response("recovered"))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let model = client(&server).model(Model::Gemini20Flash);
+    let request = model.build_request(vec![Content::text("Hi")]);
+    let mut service = ServiceBuilder::new()
+        .retry(fast_retries(3))
+        .concurrency_limit(4)
+        .service(model);
+
+    let response = service.ready().await.unwrap().call(request).await.unwrap();
+    assert_eq!(response.text(), "recovered");
+}
+
+#[tokio::test]
+async fn test_retry_policy_gives_up() {
+    let server = MockServer::start().await;
+
+    // Client errors are not retried
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(400))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let model = client(&server).model(Model::Gemini20Flash);
+    let request = model.build_request(vec![Content::text("Hi")]);
+    let service = ServiceBuilder::new()
+        .retry(fast_retries(3))
+        .service(model.clone());
+    let result = service.oneshot(request.clone()).await;
+    assert!(matches!(
+        result,
+        Err(Error::ApiError {
+            code: Some(400),
+            ..
+        })
+    ));
+    server.verify().await;
+
+    // Retryable errors stop after max_retries
+    server.reset().await;
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(429))
+        .expect(3)
+        .mount(&server)
+        .await;
+
+    let service = ServiceBuilder::new().retry(fast_retries(2)).service(model);
+    let result = service.oneshot(request).await;
+    assert!(matches!(result, Err(Error::RateLimitExceeded { .. })));
+}
+
+#[tokio::test]
+async fn test_retry_policy_waits_for_the_server_delay() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(429).set_body_json(json!({
+            "error": {
+                "code": 429,
+                "message": "Quota exceeded",
+                "details": [{
+                    "@type": "type.googleapis.com/google.rpc.RetryInfo",
+                    "retryDelay": "0.05s"
+                }]
+            }
+        })))
+        .up_to_n_times(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .respond_with(text_response("recovered"))
+        .mount(&server)
+        .await;
+
+    let model = client(&server).model(Model::Gemini20Flash);
+    let request = model.build_request(vec![Content::text("Hi")]);
+    // A backoff this long would time the call out
+    let service = ServiceBuilder::new()
+        .retry(RetryPolicy::new(1).with_backoff(Duration::from_secs(60), Duration::from_secs(60)))
+        .service(model);
+
+    let response = tokio::time::timeout(Duration::from_secs(5), service.oneshot(request))
+        .await
+        .expect("the retry waited for the backoff")
+        .unwrap();
+    assert_eq!(response.text(), "recovered");
+}
+
+#[tokio::test]
+async fn test_model_service_reports_retry_attempts() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(503))
+        .up_to_n_times(2)
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .respond_with(text_response("recovered"))
+        .mount(&server)
+        .await;
+
+    let metrics = MemoryMetrics::new();
+    let model = client(&server)
+        .with_metrics(metrics.clone())
+        .model(Model::Gemini20Flash);
+    let request = ServiceRequest::from(model.build_request(vec![Content::text("Hi")]));
+    let mut service = ServiceBuilder::new()
+        .retry(fast_retries(3))
+        .service(ModelService::new(model));
+
+    let response = service.ready().await.unwrap().call(request).await.unwrap();
+    assert_eq!(response.text(), "recovered");
+    let attempts: Vec<u32> = metrics.calls().iter().map(|call| call.attempt).collect();
+    assert_eq!(attempts, vec![0, 1, 2]);
+}
@@ -0,0 +1,220 @@
+//! Tracing span tests against a local mock server
+//! These tests don't require API keys
+#![cfg(feature = "tracing")]
+
+use futures::StreamExt;
+use gemini_rs::{Client, Error, GenerationConfig, Model};
+use serde_json::json;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing::{Event, Subscriber};
+use tracing_subscriber::layer::{Context, Layer, SubscriberExt};
+use tracing_subscriber::registry::LookupSpan;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+type Fields = HashMap<String, String>;
+
+/// Collects the fields of closed `gemini_rs` spans and of events.
+#[derive(Clone, Default)]
+struct Capture {
+    spans: Arc<Mutex<Vec<Fields>>>,
+    events: Arc<Mutex<Vec<Fields>>>,
+}
+
+struct Visitor<'a>(&'a mut Fields);
+
+impl Visit for Visitor<'_> {
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.0
+            .insert(field.name().to_string(), format!("{:?}", value));
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.0.insert(field.name().to_string(), value.to_string());
+    }
+}
+
+impl<S: Subscriber + for<'a> LookupSpan<'a>> Layer<S> for Capture {
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        if attrs.metadata().target() != "gemini_rs" {
+            return;
+        }
+        let mut fields = Fields::new();
+        attrs.record(&mut Visitor(&mut fields));
+        ctx.span(id).unwrap().extensions_mut().insert(fields);
+    }
+
+    fn on_record(&self, id: &Id, values: &Record<'_>, ctx: Context<'_, S>) {
+        let span = ctx.span(id).unwrap();
+        let mut extensions = span.extensions_mut();
+        if let Some(fields) = extensions.get_mut::<Fields>() {
+            values.record(&mut Visitor(fields));
+        }
+    }
+
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        if event.metadata().target() != "gemini_rs" {
+            return;
+        }
+        let mut fields = Fields::new();
+        event.record(&mut Visitor(&mut fields));
+        self.events.lock().unwrap().push(fields);
+    }
+
+    fn on_close(&self, id: Id, ctx: Context<'_, S>) {
+        let span = ctx.span(&id).unwrap();
+        let fields = span.extensions_mut().remove::<Fields>();
+        if let Some(fields) = fields {
+            self.spans.lock().unwrap().push(fields);
+        }
+    }
+}
+
+fn capture() -> (Capture, tracing::subscriber::DefaultGuard) {
+    let capture = Capture::default();
+    let subscriber = tracing_subscriber::registry().with(capture.clone());
+    (capture, tracing::subscriber::set_default(subscriber))
+}
+
+fn client(server: &MockServer) -> Client {
+    Client::builder()
+        .api_key("test_api_key")
+        .base_url(format!("{}/v1beta", server.uri()))
+        .build()
+        .unwrap()
+}
+
+fn response_body(text: &str) -> serde_json::Value {
+    json!({
+        "candidates": [{
+            "content": { "role": "model", "parts": [{ "text": text }] },
+            "finishReason": "STOP"
+        }],
+        "usageMetadata": {
+            "promptTokenCount": 4,
+            "candidatesTokenCount": 2,
+            "thoughtsTokenCount": 1,
+            "totalTokenCount": 7
+        },
+        "modelVersion": "gemini-2.0-flash-001",
+        "responseId": "resp-1"
+    })
+}
+
+#[tokio::test]
+async fn test_generate_content_span() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1beta/models/gemini-2.0-flash:generateContent"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(response_body("secret answer")))
+        .mount(&server)
+        .await;
+
+    let (capture, _guard) = capture();
+    let response = client(&server)
+        .model(Model::Gemini20Flash)
+        .with_config(GenerationConfig::new().temperature(0.5).max_tokens(64))
+        .generate_content("secret prompt")
+        .await
+        .unwrap();
+    assert_eq!(response.text(), "secret answer");
+
+    let spans = capture.spans.lock().unwrap();
+    assert_eq!(spans.len(), 1);
+    let span = &spans[0];
+    assert_eq!(span["otel.name"], "generate_content gemini-2.0-flash");
+    assert_eq!(span["gen_ai.operation.name"], "generate_content");
+    assert_eq!(span["gen_ai.provider.name"], "gcp.gemini");
+    assert_eq!(span["gen_ai.request.model"], "gemini-2.0-flash");
+    assert_eq!(span["gen_ai.request.temperature"], "0.5");
+    assert_eq!(span["gen_ai.request.max_tokens"], "64");
+    assert_eq!(span["gen_ai.response.model"], "gemini-2.0-flash-001");
+    assert_eq!(span["gen_ai.response.id"], "resp-1");
+    assert_eq!(span["gen_ai.response.finish_reasons"], r#"["STOP"]"#);
+    assert_eq!(span["gen_ai.usage.input_tokens"], "4");
+    assert_eq!(span["gen_ai.usage.output_tokens"], "3");
+    assert_eq!(span["gemini.endpoint"], "generateContent");
+    assert_eq!(span["server.address"], "127.0.0.1");
+    assert_eq!(span["http.response.status_code"], "200");
+    assert_eq!(span["http.request.resend_count"], "0");
+    assert!(span.contains_key("latency_ms"));
+    assert!(!span.contains_key("error.type"));
+
+    // Content is not recorded without opting in
+    let events = capture.events.lock().unwrap();
+    assert!(events.iter().all(|event| !event.contains_key("content")));
+}
+
+#[tokio::test]
+async fn test_content_tracing_opt_in() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(response_body("the answer")))
+        .mount(&server)
+        .await;
+
+    let (capture, _guard) = capture();
+    client(&server)
+        .with_content_tracing(true)
+        .model(Model::Gemini20Flash)
+        .generate_content("the question")
+        .await
+        .unwrap();
+
+    let events = capture.events.lock().unwrap();
+    let contents: Vec<&str> = events
+        .iter()
+        .filter_map(|event| event.get("content").map(String::as_str))
+        .collect();
+    assert_eq!(contents.len(), 2);
+    assert!(contents[0].contains("the question"));
+    assert_eq!(contents[1], "the answer");
+}
+
+#[tokio::test]
+async fn test_error_and_stream_spans() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1beta/models/gemini-2.0-flash:generateContent"))
+        .respond_with(ResponseTemplate::new(503))
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path(
+            "/v1beta/models/gemini-2.0-flash:streamGenerateContent",
+        ))
+        .respond_with(ResponseTemplate::new(200).set_body_raw(
+            format!("data: {}\n\n", response_body("streamed")),
+            "text/event-stream",
+        ))
+        .mount(&server)
+        .await;
+
+    let (capture, _guard) = capture();
+    let model = client(&server).model(Model::Gemini20Flash);
+
+    let result = model.generate_content("Hi").await;
+    assert!(matches!(result, Err(Error::ApiError { .. })));
+
+    let chunks: Vec<_> = model
+        .stream_generate_content("Hi")
+        .await
+        .unwrap()
+        .collect()
+        .await;
+    assert_eq!(chunks.len(), 1);
+
+    let spans = capture.spans.lock().unwrap();
+    assert_eq!(spans.len(), 2);
+    assert_eq!(spans[0]["error.type"], "503");
+    assert_eq!(spans[0]["otel.status_code"], "ERROR");
+    assert_eq!(spans[0]["http.response.status_code"], "503");
+
+    assert_eq!(spans[1]["gemini.endpoint"], "streamGenerateContent");
+    assert_eq!(spans[1]["gen_ai.usage.output_tokens"], "3");
+    assert!(spans[1].contains_key("latency_ms"));
+}
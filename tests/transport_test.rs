@@ -0,0 +1,253 @@
+//! Transport and streaming tests
+//! These tests don't require API keys
+
+use bytes::Bytes;
+use futures::future::BoxFuture;
+use futures::stream::{self, StreamExt};
+use gemini_rs::transport::{HttpRequest, HttpResponse, StreamingResponse, Transport};
+use gemini_rs::{Client, Content, Error, Model};
+use serde_json::{json, Value};
+use std::sync::{Arc, Mutex};
+use wiremock::matchers::{header, method, path, query_param};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+/// Records requests and answers them in-process.
+#[derive(Clone, Default)]
+struct FakeTransport {
+    requests: Arc<Mutex<Vec<HttpRequest>>>,
+    chunks: Vec<String>,
+    status: u16,
+}
+
+impl FakeTransport {
+    fn new(status: u16, chunks: &[&str]) -> Self {
+        Self {
+            requests: Arc::default(),
+            chunks: chunks.iter().map(|chunk| chunk.to_string()).collect(),
+            status,
+        }
+    }
+
+    fn requests(&self) -> Vec<HttpRequest> {
+        self.requests.lock().unwrap().clone()
+    }
+}
+
+impl Transport for FakeTransport {
+    fn send<'a>(&'a self, request: HttpRequest) -> BoxFuture<'a, gemini_rs::Result<HttpResponse>> {
+        self.requests.lock().unwrap().push(request);
+        let body = self.chunks.concat();
+        Box::pin(async move { Ok(HttpResponse::new(self.status, body)) })
+    }
+
+    fn send_streaming<'a>(
+        &'a self,
+        request: HttpRequest,
+    ) -> BoxFuture<'a, gemini_rs::Result<StreamingResponse>> {
+        self.requests.lock().unwrap().push(request);
+        let chunks: Vec<gemini_rs::Result<Bytes>> = self
+            .chunks
+            .iter()
+            .map(|chunk| Ok(Bytes::from(chunk.clone())))
+            .collect();
+        Box::pin(async move {
+            let mut response: StreamingResponse = HttpResponse::new(self.status, "").into();
+            response.body = stream::iter(chunks).boxed();
+            Ok(response)
+        })
+    }
+}
+
+fn text_body(text: &str) -> String {
+    json!({ "candidates": [{ "content": { "role": "model", "parts": [{ "text": text }] } }] })
+        .to_string()
+}
+
+#[tokio::test]
+async fn test_fake_transport_receives_authorized_request() {
+    let transport = FakeTransport::new(200, &[&text_body("hello")]);
+    let client = Client::builder()
+        .api_key("test_api_key")
+        .transport(transport.clone())
+        .build()
+        .unwrap();
+
+    let response = client
+        .model(Model::Gemini20Flash)
+        .generate_content("Hi")
+        .await
+        .unwrap();
+    assert_eq!(response.text(), "hello");
+
+    let requests = transport.requests();
+    assert_eq!(requests.len(), 1);
+    let request = &requests[0];
+    assert_eq!(request.method, reqwest::Method::POST);
+    assert_eq!(
+        request.url,
+        "https://generativelanguage.googleapis.com/v1beta/models/gemini-2.0-flash:generateContent"
+    );
+    assert_eq!(request.headers["x-goog-api-key"], "test_api_key");
+    assert!(request.headers["x-goog-api-key"].is_sensitive());
+
+    let sent: Value = serde_json::from_slice(&request.body).unwrap();
+    assert_eq!(sent["contents"][0]["parts"][0]["text"], "Hi");
+}
+
+#[tokio::test]
+async fn test_fake_transport_error_status_and_failure() {
+    let transport = FakeTransport::new(400, &[r#"{"error":{"message":"bad"}}"#]);
+    let client = Client::builder()
+        .api_key("test_api_key")
+        .transport(transport)
+        .build()
+        .unwrap();
+    let model = client.model(Model::Gemini20Flash);
+
+    match model.generate_content("Hi").await {
+        Err(Error::ApiError { code, message }) => {
+            assert_eq!(code, Some(400));
+            assert!(message.contains("bad"));
+        }
+        other => panic!("expected ApiError, got {:?}", other),
+    }
+    assert!(matches!(
+        model.stream_generate_content("Hi").await,
+        Err(Error::ApiError {
+            code: Some(400),
+            ..
+        })
+    ));
+
+    struct Unreachable;
+    impl Transport for Unreachable {
+        fn send<'a>(
+            &'a self,
+            _request: HttpRequest,
+        ) -> BoxFuture<'a, gemini_rs::Result<HttpResponse>> {
+            Box::pin(async { Err(Error::TransportError("connection refused".into())) })
+        }
+    }
+    let client = Client::builder()
+        .api_key("test_api_key")
+        .transport(Unreachable)
+        .build()
+        .unwrap();
+    assert!(matches!(
+        client
+            .model(Model::Gemini20Flash)
+            .count_tokens(vec![Content::text("Hi")])
+            .await,
+        Err(Error::TransportError(_))
+    ));
+}
+
+#[tokio::test]
+async fn test_stream_splits_events_across_chunks() {
+    // Events split mid-line, CRLF line endings and a trailing event without
+    // a blank line
+    let transport = FakeTransport::new(
+        200,
+        &[
+            "data: {\"candidates\":[{\"content\":{\"role\":\"model\",\"parts\":[{\"te",
+            "xt\":\"Hel\"}]}}]}\r\n\r\n",
+            ": keep-alive\n\ndata: {\"candidates\":[{\"content\":{\"role\":\"model\",",
+            "\"parts\":[{\"text\":\"lo\"}]}}]}",
+        ],
+    );
+    let client = Client::builder()
+        .api_key("test_api_key")
+        .transport(transport.clone())
+        .build()
+        .unwrap();
+
+    let chunks: Vec<String> = client
+        .model(Model::Gemini20Flash)
+        .stream_generate_content("Hi")
+        .await
+        .unwrap()
+        .map(|chunk| chunk.unwrap().text())
+        .collect()
+        .await;
+    assert_eq!(chunks, vec!["Hel", "lo"]);
+    assert!(transport.requests()[0]
+        .url
+        .ends_with("gemini-2.0-flash:streamGenerateContent?alt=sse"));
+}
+
+#[tokio::test]
+async fn test_stream_reports_error_events() {
+    let transport = FakeTransport::new(
+        200,
+        &[
+            "data: {\"candidates\":[{\"content\":{\"parts\":[{\"text\":\"a\"}]}}]}\n\n",
+            "data: {\"error\":{\"code\":503,\"message\":\"overloaded\"}}\n\n",
+        ],
+    );
+    let client = Client::builder()
+        .api_key("test_api_key")
+        .transport(transport)
+        .build()
+        .unwrap();
+
+    let mut stream = client
+        .model(Model::Gemini20Flash)
+        .stream_generate_content("Hi")
+        .await
+        .unwrap();
+    assert_eq!(stream.next().await.unwrap().unwrap().text(), "a");
+    match stream.next().await.unwrap() {
+        Err(Error::ApiError { code, message }) => {
+            assert_eq!(code, Some(503));
+            assert_eq!(message, "overloaded");
+        }
+        other => panic!("expected ApiError, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn test_reqwest_transport_streams_sse() {
+    let server = MockServer::start().await;
+    let body = format!(
+        "data: {}\n\ndata: {}\n\n",
+        text_body("one"),
+        text_body("two")
+    );
+
+    Mock::given(method("POST"))
+        .and(path(
+            "/v1beta/models/gemini-2.0-flash:streamGenerateContent",
+        ))
+        .and(query_param("alt", "sse"))
+        .and(header("x-goog-api-key", "test_api_key"))
+        .respond_with(ResponseTemplate::new(200).set_body_raw(body, "text/event-stream"))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let client = Client::builder()
+        .api_key("test_api_key")
+        .base_url(format!("{}/v1beta", server.uri()))
+        .build()
+        .unwrap();
+
+    let text: Vec<String> = client
+        .model(Model::Gemini20Flash)
+        .stream_generate_content("Hi")
+        .await
+        .unwrap()
+        .map(|chunk| chunk.unwrap().text())
+        .collect()
+        .await;
+    assert_eq!(text, vec!["one", "two"]);
+}
+
+#[test]
+fn test_transport_cannot_combine_with_connection_options() {
+    let result = Client::builder()
+        .api_key("test_api_key")
+        .transport(FakeTransport::default())
+        .timeout(std::time::Duration::from_secs(1))
+        .build();
+    assert!(matches!(result, Err(Error::InvalidInput(_))));
+}
@@ -1,7 +1,8 @@
 //! Unit tests for gemini-rs crate
 //! These tests don't require API keys and test the structure/types
 
-use gemini_rs::{Client, GenerationConfig, Model};
+use gemini_rs::{Client, Content, Conversation, Error, GenerationConfig, Model, Part, Role};
+use serde_json::json;
 
 #[test]
 fn test_model_enum() {
@@ -59,3 +60,166 @@ fn test_model_full_name() {
     assert_eq!(Model::Gemini20Flash.full_name(), "models/gemini-2.0-flash");
     assert_eq!(Model::Gemini15Flash.full_name(), "models/gemini-1.5-flash");
 }
+
+#[test]
+fn test_role_serialization() {
+    let content = Content::user("Hi");
+    let value = serde_json::to_value(&content).unwrap();
+    assert_eq!(value["role"], "user");
+
+    let parsed: Content =
+        serde_json::from_value(json!({ "role": "model", "parts": [{ "text": "Hello" }] })).unwrap();
+    assert_eq!(parsed.role, Some(Role::Model));
+
+    // Role-less content omits the field entirely
+    let value = serde_json::to_value(Content::text("Hi")).unwrap();
+    assert!(value.get("role").is_none());
+}
+
+#[test]
+fn test_function_call_part_deserialization() {
+    let parsed: Content = serde_json::from_value(json!({
+        "role": "model",
+        "parts": [{ "functionCall": { "name": "get_weather", "args": { "city": "Paris" } } }]
+    }))
+    .unwrap();
+    assert!(matches!(parsed.parts[0], Part::FunctionCall { .. }));
+    assert_eq!(parsed.function_calls().next().unwrap().name, "get_weather");
+}
+
+fn invalid_input_message(turns: &[Content]) -> String {
+    match Conversation::new(turns).validate() {
+        Err(Error::InvalidInput(message)) => message,
+        other => panic!("expected InvalidInput, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_conversation_valid() {
+    let turns = vec![
+        Content::user("What's the weather in Paris?"),
+        Content {
+            role: Some(Role::Model),
+            parts: vec![Part::FunctionCall {
+                function_call: gemini_rs::FunctionCall {
+                    name: "get_weather".to_string(),
+                    args: json!({ "city": "Paris" }),
+                },
+            }],
+        },
+        Content::function_response("get_weather", json!({ "temp": 21 })),
+    ];
+    assert!(Conversation::new(&turns).validate().is_ok());
+
+    // A single role-less prompt is fine
+    assert!(Conversation::new(&[Content::text("Hi")]).validate().is_ok());
+}
+
+#[test]
+fn test_conversation_invalid() {
+    assert!(invalid_input_message(&[]).contains("at least one turn"));
+
+    let message = invalid_input_message(&[Content::user("Hi"), Content::user("Hello?")]);
+    assert!(message.contains("contents[0] and contents[1]"));
+    assert!(message.contains("consecutive `user` turns"));
+
+    let message = invalid_input_message(&[Content::user("Hi"), Content::model("Hello")]);
+    assert!(message.contains("contents[1] is a `model` turn"));
+
+    let message = invalid_input_message(&[Content::model("Hello"), Content::user("Hi")]);
+    assert!(message.contains("must start with a `user` turn"));
+
+    let message = invalid_input_message(&[
+        Content::user("Hi"),
+        Content::model("Hello"),
+        Content::text("Role-less"),
+    ]);
+    assert!(message.contains("contents[2] has no role"));
+
+    let message = invalid_input_message(&[
+        Content::user("Hi"),
+        Content::model("Hello"),
+        Content::function_response("get_weather", json!({})),
+    ]);
+    assert!(message.contains("function `get_weather` without a matching call"));
+}
+
+#[test]
+fn test_chat_undo() {
+    let client = Client::new("test_api_key");
+    let mut chat = client
+        .model(Model::Gemini25Flash)
+        .start_chat_with_history(vec![
+            Content::user("Hi"),
+            Content::model("Hello!"),
+            Content::user("How are you?"),
+            Content::model("Great."),
+        ]);
+
+    let removed = chat.undo();
+    assert_eq!(removed.len(), 2);
+    assert_eq!(chat.history().len(), 2);
+
+    chat.undo();
+    assert!(chat.history().is_empty());
+    assert!(chat.undo().is_empty());
+}
+
+#[tokio::test]
+async fn test_chat_failed_send_keeps_history() {
+    let client = Client::new("test_api_key");
+    // A dangling user turn makes the next send fail validation before any request
+    let mut chat = client
+        .model(Model::Gemini25Flash)
+        .start_chat_with_history(vec![Content::user("Hi")]);
+
+    assert!(matches!(
+        chat.send_message("Hello?").await,
+        Err(Error::InvalidInput(_))
+    ));
+    assert_eq!(chat.history().len(), 1);
+
+    // The failed message is kept for a retry, which fails the same way
+    assert!(matches!(
+        chat.retry_last().await,
+        Err(Error::InvalidInput(_))
+    ));
+    assert_eq!(chat.history().len(), 1);
+}
+
+#[tokio::test]
+async fn test_chat_edit_operations_require_history() {
+    let client = Client::new("test_api_key");
+    let mut chat = client.model(Model::Gemini25Flash).start_chat();
+
+    assert!(matches!(
+        chat.retry_last().await,
+        Err(Error::InvalidInput(_))
+    ));
+    assert!(matches!(
+        chat.regenerate().await,
+        Err(Error::InvalidInput(_))
+    ));
+    assert!(matches!(
+        chat.edit_last_user_message("Hi").await,
+        Err(Error::InvalidInput(_))
+    ));
+}
+
+#[test]
+fn test_error_is_retryable() {
+    let api_error = |code| Error::ApiError {
+        message: String::new(),
+        code: Some(code),
+    };
+    assert!(api_error(429).is_retryable());
+    assert!(api_error(503).is_retryable());
+    assert!(!api_error(400).is_retryable());
+    assert!(!api_error(403).is_retryable());
+    assert!(Error::RateLimitExceeded {
+        retry_after: None,
+        message: None
+    }
+    .is_retryable());
+    assert!(!Error::InvalidInput("bad".to_string()).is_retryable());
+}
@@ -0,0 +1,237 @@
+//! Vertex AI backend tests against a local mock server
+//! These tests don't require API keys
+
+use gemini_rs::auth::Credentials;
+use gemini_rs::backend::Backend;
+use gemini_rs::clock::ManualClock;
+use gemini_rs::types::SafetySettings;
+use gemini_rs::{Client, Content, Error, Model};
+use serde_json::json;
+use std::time::Duration;
+use wiremock::matchers::{body_json, body_string_contains, header, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+const GENERATE_PATH: &str =
+    "/v1/projects/test-project/locations/us-central1/publishers/google/models/gemini-2.5-flash:generateContent";
+
+fn service_account(token_uri: &str) -> Credentials {
+    let mut key: serde_json::Value =
+        serde_json::from_str(include_str!("fixtures/service_account.json")).unwrap();
+    key["token_uri"] = token_uri.into();
+    Credentials::from_json(&key.to_string()).unwrap()
+}
+
+fn vertex_client(server: &MockServer, credentials: Credentials) -> Client {
+    Client::with_backend(
+        Backend::vertex_ai("test-project", "us-central1", credentials)
+            .with_base_url(format!("{}/v1", server.uri())),
+    )
+}
+
+fn text_response(text: &str) -> ResponseTemplate {
+    ResponseTemplate::new(200).set_body_json(json!({
+        "candidates": [{ "content": { "role": "model", "parts": [{ "text": text }] } }]
+    }))
+}
+
+#[test]
+fn test_vertex_url_building() {
+    let credentials = Credentials::from_token("token");
+    let regional = Backend::vertex_ai("p", "europe-west4", credentials.clone());
+    assert_eq!(
+        regional.model_url(Model::Gemini20Flash, "countTokens"),
+        "https://europe-west4-aiplatform.googleapis.com/v1/projects/p/locations/europe-west4/publishers/google/models/gemini-2.0-flash:countTokens"
+    );
+
+    let global = Backend::vertex_ai("p", "global", credentials);
+    assert!(global
+        .model_url(Model::Gemini25Flash, "generateContent")
+        .starts_with("https://aiplatform.googleapis.com/v1/projects/p/locations/global/"));
+    assert!(global.is_vertex_ai());
+    assert!(!Backend::google_ai("key").is_vertex_ai());
+}
+
+#[tokio::test]
+async fn test_service_account_token_is_signed_and_cached() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/token"))
+        .and(body_string_contains(
+            "grant_type=urn%3Aietf%3Aparams%3Aoauth%3Agrant-type%3Ajwt-bearer",
+        ))
+        .and(body_string_contains("assertion=ey"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_json(json!({ "access_token": "ya29.test", "expires_in": 3600 })),
+        )
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    Mock::given(method("POST"))
+        .and(path(GENERATE_PATH))
+        .and(header("authorization", "Bearer ya29.test"))
+        .respond_with(text_response("Hello from Vertex"))
+        .expect(2)
+        .mount(&server)
+        .await;
+
+    let client = vertex_client(&server, service_account(&format!("{}/token", server.uri())));
+    let model = client.model(Model::Gemini25Flash);
+
+    for _ in 0..2 {
+        let response = model.generate_content("Hi").await.unwrap();
+        assert_eq!(response.text(), "Hello from Vertex");
+    }
+}
+
+#[tokio::test]
+async fn test_vertex_count_tokens_sends_the_prompt_at_the_top_level() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path(
+            "/v1/projects/test-project/locations/us-central1/publishers/google/models/gemini-2.5-flash:countTokens",
+        ))
+        .and(header("authorization", "Bearer ya29.test"))
+        .and(body_json(json!({
+            "contents": [{ "parts": [{ "text": "Hi" }] }],
+            "systemInstruction": { "parts": [{ "text": "Be brief" }] },
+        })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "totalTokens": 7 })))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let count = vertex_client(&server, Credentials::from_token("ya29.test"))
+        .model(Model::Gemini25Flash)
+        .with_system_instruction("Be brief")
+        .with_safety(SafetySettings::block_none())
+        .count_tokens(vec![Content::text("Hi")])
+        .await
+        .unwrap();
+    assert_eq!(count.total_tokens, 7);
+}
+
+#[tokio::test]
+async fn test_authorized_user_refresh() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/token"))
+        .and(body_string_contains("grant_type=refresh_token"))
+        .and(body_string_contains("refresh_token=refresh-me"))
+        .respond_with(
+            ResponseTemplate::new(200).set_body_json(json!({ "access_token": "ya29.user" })),
+        )
+        .mount(&server)
+        .await;
+
+    Mock::given(method("POST"))
+        .and(path(GENERATE_PATH))
+        .and(header("authorization", "Bearer ya29.user"))
+        .respond_with(text_response("ok"))
+        .mount(&server)
+        .await;
+
+    let credentials = Credentials::from_json(
+        &json!({
+            "type": "authorized_user",
+            "client_id": "id",
+            "client_secret": "secret",
+            "refresh_token": "refresh-me",
+            "token_uri": format!("{}/token", server.uri()),
+        })
+        .to_string(),
+    )
+    .unwrap();
+
+    let response = vertex_client(&server, credentials)
+        .model(Model::Gemini25Flash)
+        .generate_content("Hi")
+        .await
+        .unwrap();
+    assert_eq!(response.text(), "ok");
+}
+
+#[tokio::test]
+async fn test_short_lived_token_is_cached_until_it_nears_expiry() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/token"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_json(json!({ "access_token": "ya29.short", "expires_in": 40 })),
+        )
+        .mount(&server)
+        .await;
+
+    Mock::given(method("POST"))
+        .and(path(GENERATE_PATH))
+        .and(header("authorization", "Bearer ya29.short"))
+        .respond_with(text_response("ok"))
+        .mount(&server)
+        .await;
+
+    let clock = ManualClock::new();
+    let credentials = service_account(&format!("{}/token", server.uri())).with_clock(clock.clone());
+    let model = vertex_client(&server, credentials).model(Model::Gemini25Flash);
+    let token_fetches = || async {
+        server
+            .received_requests()
+            .await
+            .unwrap()
+            .iter()
+            .filter(|request| request.url.path() == "/token")
+            .count()
+    };
+
+    model.generate_content("Hi").await.unwrap();
+    model.generate_content("Hi").await.unwrap();
+    assert_eq!(token_fetches().await, 1);
+
+    // A 40s token is refreshed 10s before it expires
+    clock.advance(Duration::from_secs(25));
+    model.generate_content("Hi").await.unwrap();
+    assert_eq!(token_fetches().await, 1);
+
+    clock.advance(Duration::from_secs(10));
+    model.generate_content("Hi").await.unwrap();
+    model.generate_content("Hi").await.unwrap();
+    assert_eq!(token_fetches().await, 2);
+}
+
+#[tokio::test]
+async fn test_token_endpoint_failure_is_auth_error() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/token"))
+        .respond_with(ResponseTemplate::new(400).set_body_string("invalid_grant"))
+        .mount(&server)
+        .await;
+
+    let client = vertex_client(&server, service_account(&format!("{}/token", server.uri())));
+    match client
+        .model(Model::Gemini25Flash)
+        .generate_content("Hi")
+        .await
+    {
+        Err(Error::AuthError(message)) => assert!(message.contains("invalid_grant")),
+        other => panic!("expected AuthError, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_invalid_credentials_json() {
+    assert!(matches!(
+        Credentials::from_json(r#"{"type": "external_account"}"#),
+        Err(Error::AuthError(_))
+    ));
+    assert!(matches!(
+        Credentials::service_account_file("does/not/exist.json"),
+        Err(Error::AuthError(_))
+    ));
+}